@@ -0,0 +1,362 @@
+//! Syntax trees built in code, for hosts generating programs without
+//! going through source text:
+//!
+//! ```
+//! # use rlox::ast_builder::{Expr, Stmt};
+//! # use rlox::interpreter::Interpreter;
+//! # let mut interpreter = Interpreter::new();
+//! let program = vec![
+//!     Stmt::var("x", Expr::number(2.)),
+//!     Stmt::print(Expr::number(1.).add(Expr::var("x"))),
+//! ];
+//! interpreter.execute_tree(&program)?;
+//! # Ok::<(), rlox::error::Error>(())
+//! ```
+//!
+//! Every identifier built gets an id of its own, so a tree can be
+//! executed once, and a node should be built again rather than cloned
+//! into a second place of the same tree. Built nodes have no place in the
+//! source, errors in them are reported at line 0.
+
+use std::cell::Cell;
+
+use crate::environment::BUILT_IDENTIFIERS;
+use crate::expression::{
+    Assignment, Binary, BinaryOperator, Call, DebugInfo, Expression, Get, Identifier, Index, List,
+    Literal, LiteralValue, Logical, LogicalOperator, Unary, UnaryOperator,
+};
+use crate::statement::{Block, Statement};
+
+pub type Expr = Expression;
+pub type Stmt = Statement;
+
+thread_local! {
+    static NEXT_IDENTIFIER: Cell<usize> = const { Cell::new(BUILT_IDENTIFIERS) };
+}
+
+fn debug_info(lexeme: &str) -> DebugInfo {
+    DebugInfo {
+        line: 0,
+        position: 0,
+        lexeme: lexeme.to_owned(),
+        offset: 0,
+    }
+}
+
+fn identifier(name: &str) -> Identifier {
+    let id = NEXT_IDENTIFIER.replace(NEXT_IDENTIFIER.get() + 1);
+    Identifier::from(name.to_owned(), id, debug_info(name))
+}
+
+fn block(statements: impl IntoIterator<Item = Statement>) -> Block {
+    Block {
+        statements: statements.into_iter().collect(),
+        debug_info: debug_info("{"),
+    }
+}
+
+impl Expression {
+    pub fn number(value: f64) -> Self {
+        let lexeme = value.to_string();
+        Expression::literal(LiteralValue::Number(value, debug_info(&lexeme)))
+    }
+
+    pub fn string(value: &str) -> Self {
+        let lexeme = format!("{value:?}");
+        Expression::literal(LiteralValue::String(value.to_owned(), debug_info(&lexeme)))
+    }
+
+    pub fn bool(value: bool) -> Self {
+        Expression::literal(match value {
+            true => LiteralValue::True(debug_info("true")),
+            false => LiteralValue::False(debug_info("false")),
+        })
+    }
+
+    pub fn nil() -> Self {
+        Expression::literal(LiteralValue::Nil(debug_info("nil")))
+    }
+
+    fn literal(value: LiteralValue) -> Self {
+        Expression::from(Literal { value })
+    }
+
+    /// The variable `name`.
+    pub fn var(name: &str) -> Self {
+        Expression::from(identifier(name))
+    }
+
+    /// `name = value`.
+    pub fn assign(name: &str, value: Expression) -> Self {
+        Expression::from(Assignment {
+            target: identifier(name),
+            value,
+        })
+    }
+
+    /// `[elements...]`.
+    pub fn list(elements: impl IntoIterator<Item = Expression>) -> Self {
+        Expression::from(List {
+            elements: elements.into_iter().collect(),
+        })
+    }
+
+    /// `self(args...)`.
+    pub fn call(self, args: impl IntoIterator<Item = Expression>) -> Self {
+        Expression::from(Call {
+            calle: self,
+            debug_info: debug_info("("),
+            args: args.into_iter().collect(),
+        })
+    }
+
+    /// `self.name`.
+    pub fn get(self, name: &str) -> Self {
+        Expression::from(Get {
+            object: self,
+            name: name.to_owned(),
+            debug_info: debug_info(name),
+            optional: false,
+        })
+    }
+
+    /// `self[index]`.
+    pub fn index(self, index: Expression) -> Self {
+        Expression::from(Index {
+            object: self,
+            index,
+            debug_info: debug_info("["),
+        })
+    }
+
+    fn binary(self, operator: fn(DebugInfo) -> BinaryOperator, lexeme: &str, right: Self) -> Self {
+        Expression::from(Binary {
+            left: self,
+            operator: operator(debug_info(lexeme)),
+            right,
+        })
+    }
+
+    // a method like the other builders, `ops::Add` would have to be
+    // imported to call it
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(self, right: Expression) -> Self {
+        self.binary(BinaryOperator::Add, "+", right)
+    }
+
+    pub fn subtract(self, right: Expression) -> Self {
+        self.binary(BinaryOperator::Subtract, "-", right)
+    }
+
+    pub fn multiply(self, right: Expression) -> Self {
+        self.binary(BinaryOperator::Multiply, "*", right)
+    }
+
+    pub fn divide(self, right: Expression) -> Self {
+        self.binary(BinaryOperator::Divide, "/", right)
+    }
+
+    pub fn equal(self, right: Expression) -> Self {
+        self.binary(BinaryOperator::Equal, "==", right)
+    }
+
+    pub fn not_equal(self, right: Expression) -> Self {
+        self.binary(BinaryOperator::NotEqual, "!=", right)
+    }
+
+    pub fn less(self, right: Expression) -> Self {
+        self.binary(BinaryOperator::Less, "<", right)
+    }
+
+    pub fn less_equal(self, right: Expression) -> Self {
+        self.binary(BinaryOperator::LessEqual, "<=", right)
+    }
+
+    pub fn greater(self, right: Expression) -> Self {
+        self.binary(BinaryOperator::Greater, ">", right)
+    }
+
+    pub fn greater_equal(self, right: Expression) -> Self {
+        self.binary(BinaryOperator::GreaterEqual, ">=", right)
+    }
+
+    fn logical(
+        self,
+        operator: fn(DebugInfo) -> LogicalOperator,
+        lexeme: &str,
+        right: Self,
+    ) -> Self {
+        Expression::from(Logical {
+            left: self,
+            operator: operator(debug_info(lexeme)),
+            right,
+        })
+    }
+
+    pub fn and(self, right: Expression) -> Self {
+        self.logical(LogicalOperator::And, "and", right)
+    }
+
+    pub fn or(self, right: Expression) -> Self {
+        self.logical(LogicalOperator::Or, "or", right)
+    }
+
+    /// `!self`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        Expression::from(Unary {
+            operator: UnaryOperator::Not(debug_info("!")),
+            right: self,
+        })
+    }
+
+    /// `-self`.
+    pub fn negate(self) -> Self {
+        Expression::from(Unary {
+            operator: UnaryOperator::Negative(debug_info("-")),
+            right: self,
+        })
+    }
+}
+
+impl Statement {
+    /// `expression;`.
+    pub fn expression(expression: Expression) -> Self {
+        Statement::Expression(expression)
+    }
+
+    /// `print value;`.
+    pub fn print(value: Expression) -> Self {
+        Statement::Print(value)
+    }
+
+    /// `var name = value;`.
+    pub fn var(name: &str, value: Expression) -> Self {
+        Statement::Variable {
+            name: identifier(name),
+            initializer: Some(value),
+            constant: false,
+        }
+    }
+
+    /// `const name = value;`.
+    pub fn constant(name: &str, value: Expression) -> Self {
+        Statement::Variable {
+            name: identifier(name),
+            initializer: Some(value),
+            constant: true,
+        }
+    }
+
+    /// `{ statements... }`.
+    pub fn block(statements: impl IntoIterator<Item = Statement>) -> Self {
+        Statement::Block(block(statements))
+    }
+
+    /// `if condition { then_branch... }`.
+    pub fn if_then(
+        condition: Expression,
+        then_branch: impl IntoIterator<Item = Statement>,
+    ) -> Self {
+        Statement::If {
+            condition,
+            then_branch: block(then_branch),
+            else_branch: None,
+        }
+    }
+
+    /// `if condition { then_branch... } else { else_branch... }`.
+    pub fn if_else(
+        condition: Expression,
+        then_branch: impl IntoIterator<Item = Statement>,
+        else_branch: impl IntoIterator<Item = Statement>,
+    ) -> Self {
+        Statement::If {
+            condition,
+            then_branch: block(then_branch),
+            else_branch: Some(block(else_branch)),
+        }
+    }
+
+    /// `while condition { body... }`.
+    pub fn while_loop(condition: Expression, body: impl IntoIterator<Item = Statement>) -> Self {
+        Statement::While {
+            condition,
+            body: block(body),
+            increment: None,
+        }
+    }
+
+    /// `fun name(args...) { body... }`.
+    pub fn function(name: &str, args: &[&str], body: impl IntoIterator<Item = Statement>) -> Self {
+        Statement::Function {
+            name: identifier(name),
+            args: args.iter().map(|arg| identifier(arg)).collect(),
+            body: block(body),
+            variadic: false,
+        }
+    }
+
+    /// `return value;`.
+    pub fn return_value(value: Expression) -> Self {
+        Statement::Return {
+            value: Some(value),
+            debug_info: debug_info("return"),
+        }
+    }
+}
+
+#[test]
+fn test_ast_builder() {
+    use crate::error::Error;
+    use crate::interpreter::Interpreter;
+    use crate::output::Captured;
+
+    let output = Captured::default();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(output.clone());
+
+    let program = vec![
+        Stmt::function(
+            "fib",
+            &["n"],
+            [
+                Stmt::if_then(
+                    Expr::var("n").less(Expr::number(2.)),
+                    [Stmt::return_value(Expr::var("n"))],
+                ),
+                Stmt::return_value(
+                    Expr::var("fib")
+                        .call([Expr::var("n").subtract(Expr::number(1.))])
+                        .add(Expr::var("fib").call([Expr::var("n").subtract(Expr::number(2.))])),
+                ),
+            ],
+        ),
+        Stmt::var("i", Expr::number(0.)),
+        Stmt::while_loop(
+            Expr::var("i").less(Expr::number(5.)),
+            [
+                Stmt::print(Expr::var("fib").call([Expr::var("i")])),
+                Stmt::expression(Expr::assign("i", Expr::var("i").add(Expr::number(1.)))),
+            ],
+        ),
+        Stmt::constant(
+            "result",
+            Expr::list([Expr::string("a"), Expr::bool(true).and(Expr::nil()).not()]),
+        ),
+    ];
+    interpreter.execute_tree(&program).unwrap();
+    assert_eq!(output.contents(), "0\n1\n1\n2\n3\n");
+    assert_eq!(
+        interpreter
+            .get_global_value("result")
+            .map(|v| v.to_string()),
+        Some(r#"["a", true]"#.to_owned())
+    );
+
+    let program = vec![Stmt::print(Expr::number(1.).add(Expr::string("x")))];
+    match interpreter.execute_tree(&program) {
+        Err(Error::RuntimeError { line, .. }) => assert_eq!(line, 0),
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+}
@@ -0,0 +1,38 @@
+//! Natives reaching outside of the interpreter.
+//!
+//! Scripts cannot touch the host unless the embedder, or the command line
+//! with `--allow`, grants them a capability. Each capability defines a
+//! group of natives, which are missing from the globals otherwise.
+
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Capability {
+    /// `getEnv()`, `setEnv()` and `platform()`.
+    Env,
+    /// `exec()`.
+    Process,
+}
+
+impl Capability {
+    pub const ALL: &'static [Capability] = &[Capability::Env, Capability::Process];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Env => "env",
+            Capability::Process => "process",
+        }
+    }
+}
+
+impl FromStr for Capability {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Capability::ALL
+            .iter()
+            .find(|capability| capability.name() == name)
+            .copied()
+            .ok_or(())
+    }
+}
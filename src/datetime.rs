@@ -0,0 +1,313 @@
+//! Calendar dates behind `formatTime()` and `parseTime()`.
+//!
+//! Times are seconds since the Unix epoch, always in UTC. Formats use a
+//! subset of `strftime` directives:
+//!
+//! | directive | meaning                   | example      |
+//! |-----------|---------------------------|--------------|
+//! | `%Y`      | year                      | `2026`       |
+//! | `%y`      | year without the century  | `26`         |
+//! | `%m`      | month                     | `03`         |
+//! | `%d`      | day of the month          | `09`         |
+//! | `%j`      | day of the year           | `068`        |
+//! | `%H`      | hour                      | `14`         |
+//! | `%M`      | minute                    | `05`         |
+//! | `%S`      | second                    | `00`         |
+//! | `%b`      | abbreviated month name    | `Mar`        |
+//! | `%B`      | month name                | `March`      |
+//! | `%a`      | abbreviated weekday name  | `Mon`        |
+//! | `%A`      | weekday name              | `Monday`     |
+//! | `%F`      | same as `%Y-%m-%d`        | `2026-03-09` |
+//! | `%T`      | same as `%H:%M:%S`        | `14:05:00`   |
+//! | `%%`      | a literal `%`             | `%`          |
+//!
+//! Parsing accepts all of them except `%y`, `%j` and weekday names.
+
+use crate::messages::{message, Message};
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// A moment split into calendar fields, months and days counted from 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct DateTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+}
+
+impl DateTime {
+    fn from_epoch(epoch: i64) -> Self {
+        let (days, seconds) = (
+            epoch.div_euclid(SECONDS_PER_DAY),
+            epoch.rem_euclid(SECONDS_PER_DAY) as u32,
+        );
+        let (year, month, day) = civil_from_days(days);
+        DateTime {
+            year,
+            month,
+            day,
+            hour: seconds / 3600,
+            minute: seconds / 60 % 60,
+            second: seconds % 60,
+        }
+    }
+
+    fn to_epoch(self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * SECONDS_PER_DAY
+            + i64::from(self.hour * 3600 + self.minute * 60 + self.second)
+    }
+
+    fn days(self) -> i64 {
+        days_from_civil(self.year, self.month, self.day)
+    }
+}
+
+/// Year, month and day of the `days`th day since 1970-01-01, from
+/// Howard Hinnant's `chrono`-compatible date algorithms.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Inverse of [`civil_from_days`].
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let shifted_month = i64::from((month + 9) % 12);
+    let day_of_year = (153 * shifted_month + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Formats the time `epoch` seconds after the Unix epoch.
+pub fn format(epoch: i64, format: &str) -> Result<String, Message> {
+    let time = DateTime::from_epoch(epoch);
+    let mut formatted = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            formatted.push(c);
+            continue;
+        }
+        let month = MONTHS[time.month as usize - 1];
+        // 1970-01-01 was a Thursday
+        let weekday = WEEKDAYS[(time.days() + 3).rem_euclid(7) as usize];
+        match chars.next() {
+            Some('Y') => formatted += &time.year.to_string(),
+            Some('y') => formatted += &format!("{:02}", time.year.rem_euclid(100)),
+            Some('m') => formatted += &format!("{:02}", time.month),
+            Some('d') => formatted += &format!("{:02}", time.day),
+            Some('j') => {
+                let day_of_year = time.days() - days_from_civil(time.year, 1, 1) + 1;
+                formatted += &format!("{day_of_year:03}");
+            }
+            Some('H') => formatted += &format!("{:02}", time.hour),
+            Some('M') => formatted += &format!("{:02}", time.minute),
+            Some('S') => formatted += &format!("{:02}", time.second),
+            Some('b') => formatted += &month[..3],
+            Some('B') => formatted += month,
+            Some('a') => formatted += &weekday[..3],
+            Some('A') => formatted += weekday,
+            Some('F') => formatted += &self::format(epoch, "%Y-%m-%d")?,
+            Some('T') => formatted += &self::format(epoch, "%H:%M:%S")?,
+            Some('%') => formatted.push('%'),
+            directive => return Err(unknown_directive(directive)),
+        }
+    }
+    Ok(formatted)
+}
+
+/// Reads a time written in `format`, returning seconds since the Unix
+/// epoch. Fields missing from the format default to the epoch's.
+pub fn parse(input: &str, format: &str) -> Result<i64, Message> {
+    let mut time = DateTime::from_epoch(0);
+    match parse_into(input, format, &mut time) {
+        Ok("") => {}
+        Ok(_) | Err(ParseFailure::Mismatch) => {
+            return Err(message!(TimeDoesNotMatch, input = input, format = format))
+        }
+        Err(ParseFailure::UnknownDirective(directive)) => return Err(unknown_directive(directive)),
+    }
+
+    let valid = (1..=12).contains(&time.month)
+        && (1..=days_in_month(time.year, time.month)).contains(&time.day)
+        && time.hour < 24
+        && time.minute < 60
+        && time.second < 60;
+    match valid {
+        true => Ok(time.to_epoch()),
+        false => Err(message!(InvalidDate, input = input)),
+    }
+}
+
+enum ParseFailure {
+    Mismatch,
+    UnknownDirective(Option<char>),
+}
+
+/// Reads the fields of `format` from the start of `input` into `time`,
+/// returning the rest of `input`.
+fn parse_into<'a>(
+    input: &'a str,
+    format: &str,
+    time: &mut DateTime,
+) -> Result<&'a str, ParseFailure> {
+    let mut rest = input;
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            rest = rest.strip_prefix(c).ok_or(ParseFailure::Mismatch)?;
+            continue;
+        }
+        rest = match chars.next() {
+            Some('Y') => {
+                let negative = rest.starts_with('-');
+                let (year, rest) = number(&rest[usize::from(negative)..], 4)?;
+                time.year = if negative { -year } else { year };
+                rest
+            }
+            Some(field @ ('m' | 'd' | 'H' | 'M' | 'S')) => {
+                let (value, rest) = number(rest, 2)?;
+                let value = value as u32;
+                match field {
+                    'm' => time.month = value,
+                    'd' => time.day = value,
+                    'H' => time.hour = value,
+                    'M' => time.minute = value,
+                    _ => time.second = value,
+                }
+                rest
+            }
+            Some(directive @ ('b' | 'B')) => {
+                let (index, name) = MONTHS
+                    .iter()
+                    .map(|month| if directive == 'b' { &month[..3] } else { month })
+                    .enumerate()
+                    .find(|(_, name)| {
+                        rest.get(..name.len())
+                            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(name))
+                    })
+                    .ok_or(ParseFailure::Mismatch)?;
+                time.month = index as u32 + 1;
+                &rest[name.len()..]
+            }
+            Some('F') => parse_into(rest, "%Y-%m-%d", time)?,
+            Some('T') => parse_into(rest, "%H:%M:%S", time)?,
+            Some('%') => rest.strip_prefix('%').ok_or(ParseFailure::Mismatch)?,
+            directive => return Err(ParseFailure::UnknownDirective(directive)),
+        };
+    }
+    Ok(rest)
+}
+
+/// Reads up to `width` leading digits of `input`, at least one.
+fn number(input: &str, width: usize) -> Result<(i64, &str), ParseFailure> {
+    let length = input
+        .bytes()
+        .take(width)
+        .take_while(u8::is_ascii_digit)
+        .count();
+    let value = input[..length]
+        .parse()
+        .map_err(|_| ParseFailure::Mismatch)?;
+    Ok((value, &input[length..]))
+}
+
+fn unknown_directive(directive: Option<char>) -> Message {
+    let directive = directive.map_or(String::new(), String::from);
+    message!(UnknownTimeDirective, directive = directive)
+}
+
+#[test]
+fn test_datetime() {
+    assert_eq!(format(0, "%F %T").unwrap(), "1970-01-01 00:00:00");
+    assert_eq!(
+        format(1_772_978_700, "%A %d %B %Y, %H:%M (%a %b %y, day %j) 100%%").unwrap(),
+        "Sunday 08 March 2026, 14:05 (Sun Mar 26, day 067) 100%"
+    );
+    assert_eq!(format(-1, "%F %T").unwrap(), "1969-12-31 23:59:59");
+    assert_eq!(format(951_782_400, "%F").unwrap(), "2000-02-29");
+
+    for epoch in [0, -1, 951_782_400, 1_772_978_700, 4_102_444_799] {
+        let formatted = format(epoch, "%Y-%m-%dT%H:%M:%S").unwrap();
+        assert_eq!(parse(&formatted, "%Y-%m-%dT%H:%M:%S"), Ok(epoch));
+    }
+    assert_eq!(
+        parse("8 mar 2026 14:05:00", "%d %b %Y %T"),
+        Ok(1_772_978_700)
+    );
+    assert_eq!(parse("March 2026", "%B %Y"), Ok(1_772_323_200));
+    assert_eq!(parse("2026-03-08", "%F"), Ok(1_772_928_000));
+
+    assert_eq!(
+        parse("2026-02-30", "%F").map_err(String::from),
+        Err("Invalid date '2026-02-30'.".to_owned())
+    );
+    assert_eq!(
+        parse("2026/03/08", "%F").map_err(String::from),
+        Err("'2026/03/08' does not match the time format '%F'.".to_owned())
+    );
+    assert_eq!(
+        parse("2026-03-08 extra", "%F").map_err(String::from),
+        Err("'2026-03-08 extra' does not match the time format '%F'.".to_owned())
+    );
+    assert_eq!(
+        format(0, "%Q").map_err(String::from),
+        Err("Unknown directive '%Q' in time format.".to_owned())
+    );
+}
@@ -0,0 +1,234 @@
+//! Problems found in a program, by any stage from the scanner to the
+//! interpreter, in one shape for whatever reports them.
+//!
+//! An error stops the stage which found it and comes out of it as an
+//! [`Error`], [`Error::into_diagnostic`] turns it into a diagnostic. A
+//! warning leaves the program running, stages collect warnings next to
+//! their result, like the resolver does for `rlox lint`.
+
+use crate::error::Error;
+use crate::interpreter::CallFrame;
+use crate::messages::{message, Message};
+use crate::source_map::advance_column;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A stretch of the source within a line, from column `position` up to
+/// column `end`, which is not part of it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub position: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// The single character at `line:position`.
+    pub fn new(line: usize, position: usize) -> Self {
+        Span::with_end(line, position, position + 1)
+    }
+
+    /// Covers at least the character at `line:position`.
+    pub fn with_end(line: usize, position: usize, end: usize) -> Self {
+        Span {
+            line,
+            position,
+            end: end.max(position + 1),
+        }
+    }
+
+    /// The span of `text` starting at `line:position`, up to the end of
+    /// its first line.
+    pub fn of_text(line: usize, position: usize, text: &str) -> Self {
+        let first_line = text.lines().next().unwrap_or_default();
+        Span::with_end(line, position, advance_column(position, first_line))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// the code of the error, e.g. `E0001`
+    pub code: Option<&'static str>,
+    pub span: Span,
+    pub message: String,
+    /// explanations shown after the message, each on a line of its own
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    /// A diagnostic with the code of the catalog entry `message` was
+    /// rendered from, if any.
+    pub fn error(span: Span, message: impl Into<Message>) -> Self {
+        let message = message.into();
+        Diagnostic {
+            severity: Severity::Error,
+            code: message.code(),
+            span,
+            message: message.text,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn warning(span: Span, message: impl Into<Message>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(span, message)
+        }
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+}
+
+/// Notes following a runtime error at `line:position` through the calls
+/// in `trace`, e.g. `in fib at 3:10`, `called from <script> at 12:1`.
+fn backtrace(trace: &[CallFrame], line: usize, position: usize) -> Vec<String> {
+    let Some(innermost) = trace.first() else {
+        return Vec::new();
+    };
+    let mut notes = vec![String::from(message!(
+        TraceIn,
+        function = innermost.function,
+        line = line,
+        position = position
+    ))];
+    for (i, frame) in trace.iter().enumerate() {
+        let caller = match trace.get(i + 1) {
+            Some(caller) => caller.function.clone(),
+            None => message!(TraceTopLevel).into(),
+        };
+        notes.push(
+            message!(
+                TraceCalledFrom,
+                function = caller,
+                line = frame.line,
+                position = frame.position
+            )
+            .into(),
+        );
+    }
+    notes
+}
+
+impl Error {
+    /// Turns an error tied to a place in the source into a diagnostic,
+    /// with the code of its message, other errors are returned as they
+    /// are.
+    pub fn into_diagnostic(self) -> Result<Diagnostic, Error> {
+        match self {
+            Error::SyntaxError {
+                line,
+                position,
+                end,
+                message,
+            }
+            | Error::ParsingError {
+                line,
+                position,
+                end,
+                message,
+            }
+            | Error::UnknownBinaryOperator {
+                line,
+                position,
+                end,
+                message,
+            }
+            | Error::UnknownUnaryOperator {
+                line,
+                position,
+                end,
+                message,
+            }
+            | Error::UnknownLiteral {
+                line,
+                position,
+                end,
+                message,
+            }
+            | Error::ResolverError {
+                line,
+                position,
+                end,
+                message,
+            } => Ok(Diagnostic::error(
+                Span::with_end(line, position, end),
+                message,
+            )),
+            Error::RuntimeError {
+                line,
+                position,
+                end,
+                message,
+                trace,
+            } => {
+                let diagnostic = Diagnostic::error(Span::with_end(line, position, end), message);
+                Ok(backtrace(&trace, line, position)
+                    .into_iter()
+                    .fold(diagnostic, Diagnostic::with_note))
+            }
+            error @ (Error::InternalRuntimeError { .. }
+            | Error::Thrown { .. }
+            | Error::Exit { .. }) => Err(error),
+        }
+    }
+}
+
+#[test]
+fn test_diagnostics_of_errors() {
+    use crate::edition::Edition;
+    use crate::interpreter::Interpreter;
+    use crate::messages::{set_locale, Locale, MessageId};
+    use crate::parser::Parser;
+
+    let mut interpreter = Interpreter::new();
+    let diagnostic = interpreter
+        .eval("var a = 1;\nprint a +;")
+        .unwrap_err()
+        .into_diagnostic()
+        .unwrap();
+    assert_eq!(diagnostic.severity, Severity::Error);
+    assert_eq!(diagnostic.code, Some("E0107"));
+    assert_eq!(diagnostic.span, Span::new(2, 10));
+    assert!(diagnostic.notes.is_empty());
+
+    assert!(matches!(
+        interpreter.eval("exit(2);").unwrap_err().into_diagnostic(),
+        Err(Error::Exit { code: 2 })
+    ));
+
+    // the code is the one of the message the error was created with,
+    // whatever locale it is reported in
+    set_locale(Locale::Polish);
+    let error = interpreter.eval("print -nil;").unwrap_err();
+    set_locale(Locale::English);
+    let code = error.into_diagnostic().unwrap().code;
+    assert_eq!(code, MessageId::OperandMustBeNumber.code());
+
+    // a runtime error caught and thrown again keeps its code
+    let tokens = crate::scanner::scan_tokens("try { print -nil; } catch e { throw e; }").unwrap();
+    let program = Parser::with_edition(Edition::Lox2026)
+        .parse(tokens)
+        .unwrap();
+    let access_table = crate::resolver::resolve(&program).unwrap();
+    let mut interpreter = Interpreter::with_edition(Edition::Lox2026);
+    let error = interpreter.execute(&program, access_table).unwrap_err();
+    assert_eq!(error.into_diagnostic().unwrap().code, code);
+
+    // text which only reads like a message of the catalog has no code
+    let error = Error::RuntimeError {
+        line: 1,
+        position: 1,
+        end: 2,
+        message: "Undefined variable 'x'.".into(),
+        trace: Vec::new(),
+    };
+    assert_eq!(error.into_diagnostic().unwrap().code, None);
+}
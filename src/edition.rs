@@ -0,0 +1,105 @@
+//! Language editions and contextual keywords.
+//!
+//! New keywords would break programs using them as identifiers, so they are
+//! never reserved by the scanner. Instead the parser recognizes them only in
+//! editions that opted into them, and only at the start of a statement,
+//! followed by what has to follow the keyword.
+
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Edition {
+    /// The language as described in the book.
+    #[default]
+    Lox2023,
+    /// Adds `break`, `continue`, `const`, `match`, exceptions and imports.
+    Lox2026,
+}
+
+impl Edition {
+    pub const ALL: &'static [Edition] = &[Edition::Lox2023, Edition::Lox2026];
+
+    pub fn year(self) -> &'static str {
+        match self {
+            Edition::Lox2023 => "2023",
+            Edition::Lox2026 => "2026",
+        }
+    }
+}
+
+impl FromStr for Edition {
+    type Err = ();
+
+    fn from_str(year: &str) -> Result<Self, Self::Err> {
+        Edition::ALL
+            .iter()
+            .find(|edition| edition.year() == year)
+            .copied()
+            .ok_or(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContextualKeyword {
+    Break,
+    Continue,
+    Const,
+    Match,
+    Try,
+    Catch,
+    Finally,
+    Throw,
+    Import,
+    From,
+}
+
+impl ContextualKeyword {
+    pub fn lexeme(self) -> &'static str {
+        match self {
+            ContextualKeyword::Break => "break",
+            ContextualKeyword::Continue => "continue",
+            ContextualKeyword::Const => "const",
+            ContextualKeyword::Match => "match",
+            ContextualKeyword::Try => "try",
+            ContextualKeyword::Catch => "catch",
+            ContextualKeyword::Finally => "finally",
+            ContextualKeyword::Throw => "throw",
+            ContextualKeyword::Import => "import",
+            ContextualKeyword::From => "from",
+        }
+    }
+
+    /// First edition in which the keyword is recognized.
+    pub fn since(self) -> Edition {
+        match self {
+            ContextualKeyword::Break
+            | ContextualKeyword::Continue
+            | ContextualKeyword::Const
+            | ContextualKeyword::Match
+            | ContextualKeyword::Try
+            | ContextualKeyword::Catch
+            | ContextualKeyword::Finally
+            | ContextualKeyword::Throw
+            | ContextualKeyword::Import
+            | ContextualKeyword::From => Edition::Lox2026,
+        }
+    }
+
+    /// Returns the keyword spelled `name`, if `edition` recognizes it.
+    pub fn from_name(name: &str, edition: Edition) -> Option<ContextualKeyword> {
+        [
+            ContextualKeyword::Break,
+            ContextualKeyword::Continue,
+            ContextualKeyword::Const,
+            ContextualKeyword::Match,
+            ContextualKeyword::Try,
+            ContextualKeyword::Catch,
+            ContextualKeyword::Finally,
+            ContextualKeyword::Throw,
+            ContextualKeyword::Import,
+            ContextualKeyword::From,
+        ]
+        .into_iter()
+        .find(|keyword| keyword.lexeme() == name && keyword.since() <= edition)
+    }
+}
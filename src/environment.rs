@@ -1,17 +1,92 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::{Deref, DerefMut};
-use std::rc::Rc;
 
 use crate::error::Error;
 use crate::expression::{DebugInfo, Identifier, IdentifierId};
+use crate::lox_function::LoxFun;
 use crate::lox_value::LoxValue;
+use crate::messages::message;
 use crate::resolver::AccessTable;
+use crate::shared::{Rc, RefCell, Weak};
 
 #[derive(Debug)]
 pub struct Variable {
     value: LoxValue,
     defined_at: DebugInfo,
+    /// declared with `const`, assigning to it is an error
+    constant: bool,
+}
+
+impl Variable {
+    fn assign(&mut self, name: &str, value: LoxValue) -> Result<LoxValue, Error> {
+        if self.constant {
+            return Err(Error::InternalRuntimeError {
+                message: message!(AssignToConstant, name = name),
+            });
+        }
+        self.value = value;
+        Ok(self.value.clone())
+    }
+}
+
+/// Variables are shared between the frame defining them and the capture
+/// frames of closures using them.
+type VariableRef = Rc<RefCell<Variable>>;
+
+#[derive(Debug, Clone)]
+enum Slot {
+    Variable(VariableRef),
+    /// A function referring to itself from its own capture frame.
+    ///
+    /// Capturing the variable holding the function would create a cycle
+    /// (`variable -> function -> capture frame -> variable`) and leak
+    /// every recursive function, so the function is referenced weakly.
+    /// It is alive whenever its body runs, as the caller holds it.
+    /// Only used when the resolver finds no assignment to the name.
+    /// Assigning to the slot turns it into a regular variable, private
+    /// to the capture frame.
+    Function(Weak<LoxFun>),
+}
+
+impl Slot {
+    fn value(&self) -> LoxValue {
+        match self {
+            Slot::Variable(variable) => variable.borrow().value.clone(),
+            Slot::Function(function) => function.upgrade().map_or(LoxValue::Nil, LoxValue::LoxFun),
+        }
+    }
+
+    fn cell(&self) -> VariableRef {
+        match self {
+            Slot::Variable(variable) => variable.clone(),
+            Slot::Function(_) => Rc::new(RefCell::new(Variable {
+                value: self.value(),
+                defined_at: DebugInfo::default(),
+                constant: false,
+            })),
+        }
+    }
+}
+
+/// Variables captured by a function, see `Environment::capture`.
+pub struct Captures {
+    values: HashMap<String, Slot>,
+    captures_itself: Option<String>,
+}
+
+impl Captures {
+    /// Builds the capture frame for `function`, which
+    /// becomes the parent of the function's parameters frame.
+    pub fn into_frame(mut self, function: &Weak<LoxFun>) -> FrameRef {
+        if let Some(name) = self.captures_itself {
+            self.values.insert(name, Slot::Function(function.clone()));
+        }
+
+        FrameRef(Rc::new(RefCell::new(Frame {
+            values: self.values,
+            parent: None,
+        })))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -35,38 +110,86 @@ impl FrameRef {
         self.0.as_ref().borrow().parent.clone()
     }
 
-    fn get(&self, name: &String) -> Option<LoxValue> {
-        self.0
-            .as_ref()
-            .borrow()
-            .values
+    /// Returns every binding visible from this frame, innermost
+    /// definitions shadow outer ones, sorted by name.
+    pub fn bindings(&self) -> Vec<(String, LoxValue)> {
+        let mut bindings: HashMap<String, LoxValue> = HashMap::new();
+        let mut frame = Some(self.clone());
+
+        while let Some(current) = frame {
+            for (name, variable) in current.0.as_ref().borrow().values.iter() {
+                bindings
+                    .entry(name.clone())
+                    .or_insert_with(|| variable.value());
+            }
+            frame = current.get_parent();
+        }
+
+        let mut bindings: Vec<_> = bindings.into_iter().collect();
+        bindings.sort_by(|(a, _), (b, _)| a.cmp(b));
+        bindings
+    }
+
+    /// Copies a capture frame, giving `name` a new cell holding `value`,
+    /// also when the frame did not capture it. The remaining variables
+    /// stay shared with the original frame.
+    pub fn rebind(&self, name: &str, value: LoxValue) -> FrameRef {
+        let frame = self.0.as_ref().borrow();
+        let mut values = frame.values.clone();
+        let defined_at = values
             .get(name)
-            .map(|v| v.value.clone())
+            .map(|slot| slot.cell().borrow().defined_at.clone())
+            .unwrap_or_default();
+        values.insert(
+            name.to_owned(),
+            Slot::Variable(Rc::new(RefCell::new(Variable {
+                value,
+                defined_at,
+                constant: false,
+            }))),
+        );
+
+        FrameRef(Rc::new(RefCell::new(Frame {
+            values,
+            parent: frame.parent.clone(),
+        })))
     }
 
-    fn assign(&self, name: &String, value: LoxValue) -> Option<LoxValue> {
-        let mut frame = self.0.as_ref().borrow_mut();
-        let variable = frame.values.get_mut(name);
+    fn cell(&self, name: &String) -> Option<VariableRef> {
+        self.0.as_ref().borrow().values.get(name).map(Slot::cell)
+    }
 
-        if let Some(variable) = variable {
-            variable.value = value;
-            Some(variable.value.clone())
-        } else {
-            None
+    pub fn get(&self, name: &String) -> Option<LoxValue> {
+        self.0.as_ref().borrow().values.get(name).map(Slot::value)
+    }
+
+    fn assign(&self, name: &String, value: LoxValue) -> Option<Result<LoxValue, Error>> {
+        let mut frame = self.0.as_ref().borrow_mut();
+        let slot = frame.values.get_mut(name)?;
+
+        match slot {
+            Slot::Variable(variable) => Some(variable.borrow_mut().assign(name, value)),
+            Slot::Function(_) => {
+                *slot = Slot::Variable(Rc::new(RefCell::new(Variable {
+                    value: value.clone(),
+                    defined_at: DebugInfo::default(),
+                    constant: false,
+                })));
+                Some(Ok(value))
+            }
         }
     }
 
     fn define(&self, name: &String, variable: Variable) -> Result<(), DebugInfo> {
         let mut frame = self.0.as_ref().borrow_mut();
 
-        if let Some(Variable {
-            value: _,
-            defined_at,
-        }) = frame.values.get(name)
-        {
-            Err(defined_at.clone())
+        if let Some(Slot::Variable(variable)) = frame.values.get(name) {
+            Err(variable.borrow().defined_at.clone())
         } else {
-            frame.values.insert(name.clone(), variable);
+            frame.values.insert(
+                name.clone(),
+                Slot::Variable(Rc::new(RefCell::new(variable))),
+            );
             Ok(())
         }
     }
@@ -86,18 +209,144 @@ impl DerefMut for FrameRef {
     }
 }
 
+/// First identifier id given to code passed to `eval`, see `Globals`.
+pub const EVAL_IDENTIFIERS: IdentifierId = IdentifierId::MAX / 2;
+
+/// First identifier id given to syntax trees built in code, see `Globals`.
+pub const BUILT_IDENTIFIERS: IdentifierId = IdentifierId::MAX / 4 * 3;
+
+/// Global variables are kept in a flat table of slots instead of a frame.
+///
+/// Every identifier that resolves to a global remembers its slot in
+/// `slot_cache` (indexed by `IdentifierId`), so only the first access
+/// pays for hashing the name, later ones are plain indexing. This relies
+/// on identifier ids being unique for the lifetime of an interpreter,
+/// which holds as long as all code is parsed by the same `Parser`, or by
+/// `parser::parse` on the same thread.
+/// Code passed to `eval` is numbered from `EVAL_IDENTIFIERS` on instead,
+/// and trees built with `ast_builder` from `BUILT_IDENTIFIERS` on, their
+/// identifiers are always looked up by name.
+#[derive(Debug, Default)]
+struct Globals {
+    slots: Vec<(String, Variable)>,
+    names: HashMap<String, usize>,
+    slot_cache: Vec<Option<usize>>,
+    /// slots still holding the value the interpreter defined them with,
+    /// programs may reuse their names, e.g. `var name = "x";`
+    builtins: HashSet<usize>,
+}
+
+impl Globals {
+    fn slot(&mut self, name: &String, id: IdentifierId) -> Option<usize> {
+        if let Some(Some(slot)) = self.slot_cache.get(id) {
+            debug_assert_eq!(&self.slots[*slot].0, name, "identifier id reused");
+            return Some(*slot);
+        }
+
+        let slot = *self.names.get(name)?;
+        if id >= EVAL_IDENTIFIERS {
+            return Some(slot);
+        }
+        if self.slot_cache.len() <= id {
+            self.slot_cache.resize(id + 1, None);
+        }
+        self.slot_cache[id] = Some(slot);
+        Some(slot)
+    }
+
+    fn get(&mut self, name: &String, id: IdentifierId) -> Option<LoxValue> {
+        let slot = self.slot(name, id)?;
+        Some(self.slots[slot].1.value.clone())
+    }
+
+    fn get_by_name(&self, name: &String) -> Option<LoxValue> {
+        let slot = *self.names.get(name)?;
+        Some(self.slots[slot].1.value.clone())
+    }
+
+    fn assign(
+        &mut self,
+        name: &String,
+        id: IdentifierId,
+        value: LoxValue,
+    ) -> Option<Result<LoxValue, Error>> {
+        let slot = self.slot(name, id)?;
+        Some(self.slots[slot].1.assign(name, value))
+    }
+
+    /// Assigns `value` to the global `name`, defining it if there is none.
+    fn set(&mut self, name: &String, value: LoxValue) -> Result<(), Error> {
+        if let Some(&slot) = self.names.get(name) {
+            return self.slots[slot].1.assign(name, value).map(drop);
+        }
+        let variable = Variable {
+            value,
+            defined_at: DebugInfo::default(),
+            constant: false,
+        };
+        self.names.insert(name.clone(), self.slots.len());
+        self.slots.push((name.clone(), variable));
+        Ok(())
+    }
+
+    /// Defines a new global, or replaces a built-in one that no global of
+    /// the program has replaced yet.
+    fn define(&mut self, name: &String, variable: Variable) -> Result<(), DebugInfo> {
+        if let Some(&slot) = self.names.get(name) {
+            if !self.builtins.remove(&slot) {
+                return Err(self.slots[slot].1.defined_at.clone());
+            }
+            self.slots[slot].1 = variable;
+            return Ok(());
+        }
+
+        self.names.insert(name.clone(), self.slots.len());
+        self.slots.push((name.clone(), variable));
+        Ok(())
+    }
+
+    fn define_builtin(&mut self, name: &String, value: LoxValue) {
+        let variable = Variable {
+            value,
+            defined_at: DebugInfo::default(),
+            constant: false,
+        };
+        self.define(name, variable)
+            .unwrap_or_else(|_| panic!("built-in '{name}' defined twice"));
+        self.builtins.insert(self.names[name]);
+    }
+}
+
+/// Top level of the program or of an imported module: its global
+/// variables and the scopes its identifiers resolve to. Functions keep
+/// the module they were defined in and switch to it when called.
+#[derive(Debug)]
+pub struct Module {
+    access_table: AccessTable,
+    globals: Globals,
+    /// globals defined by `import`, with the module they came from
+    imports: HashMap<String, ModuleRef>,
+}
+
+pub type ModuleRef = Rc<RefCell<Module>>;
+
 #[derive(Debug)]
 pub struct Environment {
     closure_stack: Vec<FrameRef>,
-    pub access_table: AccessTable,
     // head: FrameId,
     head: FrameRef,
+    /// root frame, marks the top level of the program,
+    /// its variables are stored in the globals of `module`
     global: FrameRef,
+    /// module of the code being executed
+    module: ModuleRef,
+    /// built-in globals every module starts with, the natives
+    prelude: Vec<(String, LoxValue)>,
 }
 
 #[derive(Debug)]
 pub struct Frame {
-    values: HashMap<String, Variable>,
+    values: HashMap<String, Slot>,
     // parent: Option<FrameId>,
     parent: Option<FrameRef>,
 }
@@ -108,23 +357,116 @@ pub struct Frame {
 //     }
 // }
 
+impl Default for Environment {
+    fn default() -> Self {
+        Environment::new()
+    }
+}
+
 impl Environment {
     pub fn new() -> Self {
         let global = FrameRef::global();
         Environment {
             closure_stack: Vec::new(),
-            access_table: AccessTable::empty(),
             head: global.clone(),
             global,
+            module: Rc::new(RefCell::new(Module {
+                access_table: AccessTable::empty(),
+                globals: Globals::default(),
+                imports: HashMap::new(),
+            })),
+            prelude: Vec::new(),
         }
     }
 
-    pub fn get_current_frame(&self) -> FrameRef {
-        self.head.clone()
+    /// Defines a global of the interpreter, which a global of the program
+    /// may replace.
+    pub fn define_builtin(&mut self, name: &str, value: LoxValue) {
+        self.module
+            .borrow_mut()
+            .globals
+            .define_builtin(&name.to_owned(), value);
+    }
+
+    /// Marks the built-ins defined so far as the prelude, defined in every
+    /// module created later.
+    pub fn end_prelude(&mut self) {
+        self.prelude = self.globals();
+    }
+
+    /// Creates an empty module, holding only the prelude.
+    pub fn new_module(&self) -> ModuleRef {
+        let mut globals = Globals::default();
+        for (name, value) in &self.prelude {
+            globals.define_builtin(name, value.clone());
+        }
+
+        Rc::new(RefCell::new(Module {
+            access_table: AccessTable::empty(),
+            globals,
+            imports: HashMap::new(),
+        }))
+    }
+
+    pub fn module(&self) -> ModuleRef {
+        self.module.clone()
+    }
+
+    /// Makes `module` the current one, returns the previous module.
+    pub fn enter_module(&mut self, module: ModuleRef) -> ModuleRef {
+        std::mem::replace(&mut self.module, module)
+    }
+
+    /// Globals defined by the code of `module`, leaving out the built-ins.
+    pub fn exports(&self, module: &ModuleRef) -> Vec<(String, LoxValue)> {
+        let module = module.borrow();
+        module
+            .globals
+            .slots
+            .iter()
+            .enumerate()
+            .filter(|(slot, _)| !module.globals.builtins.contains(slot))
+            .map(|(_, (name, variable))| (name.clone(), variable.value.clone()))
+            .collect()
+    }
+
+    /// Collects the variables `function`, defined in the current scope,
+    /// uses from its enclosing scopes. Only those are shared with the
+    /// closure instead of the whole chain of frames.
+    pub fn capture(&mut self, function: &Identifier) -> Result<Captures, Error> {
+        let mut values = HashMap::new();
+        let mut captures_itself = None;
+
+        let module = self.module.borrow();
+        let captures = module.access_table.captures(&function.id);
+        // a name assigned somewhere has to be shared like any other variable
+        let weak_self = module.access_table.captures_itself(&function.id);
+        for capture in captures.iter() {
+            if weak_self && capture.depth == 0 && capture.name == function.name {
+                captures_itself = Some(capture.name.clone());
+                continue;
+            }
+
+            let cell = self
+                .get_nth_scope(capture.depth)
+                .cell(&capture.name)
+                .ok_or_else(|| Error::InternalRuntimeError {
+                    message: message!(CapturedUndefined, name = capture.name),
+                })?;
+            values.insert(capture.name.clone(), Slot::Variable(cell));
+        }
+
+        Ok(Captures {
+            values,
+            captures_itself,
+        })
     }
 
-    pub fn extend_access_table(&mut self, access_table: AccessTable) -> Result<(), ()> {
-        self.access_table.add_all(access_table)?;
+    pub(crate) fn extend_access_table(&mut self, access_table: AccessTable) -> Result<(), ()> {
+        self.module
+            .borrow_mut()
+            .access_table
+            .add_all(access_table)?;
         Ok(())
     }
 
@@ -139,6 +481,12 @@ impl Environment {
         self.closure_stack.push(parent);
     }
 
+    /// Makes the top level the current scope, until `pop_closure`.
+    pub fn push_global(&mut self) {
+        let parent = std::mem::replace(&mut self.head, self.global.clone());
+        self.closure_stack.push(parent);
+    }
+
     pub fn pop(&mut self) {
         let head = self.head.get_parent();
         self.head = head.expect("tried to get parent of global scope");
@@ -151,7 +499,7 @@ impl Environment {
             .expect("tried to pop closure scope, when no closure scope was pushed before");
     }
 
-    fn get_nth_scope(&mut self, n: usize) -> FrameRef {
+    fn get_nth_scope(&self, n: usize) -> FrameRef {
         let mut nth_scope = self.head.clone();
 
         for _ in 0..n {
@@ -159,10 +507,60 @@ impl Environment {
             nth_scope = tmp.expect("tried to get parent scope of global scope");
         }
 
-        return nth_scope;
+        nth_scope
+    }
+
+    pub fn define(&mut self, identifier: &Identifier, value: LoxValue) -> Result<(), Error> {
+        self.define_variable(identifier, value, false)
+            .map_err(|previous| already_defined(&identifier.name, &previous, &previous))
+    }
+
+    pub fn define_constant(
+        &mut self,
+        identifier: &Identifier,
+        value: LoxValue,
+    ) -> Result<(), Error> {
+        self.define_variable(identifier, value, true)
+            .map_err(|previous| already_defined(&identifier.name, &previous, &previous))
+    }
+
+    /// Sets the value of `identifier`, just defined in the current scope.
+    pub fn initialize(&mut self, identifier: &Identifier, value: LoxValue) -> Result<(), Error> {
+        let name = &identifier.name;
+        if Rc::ptr_eq(&self.head, &self.global) {
+            self.module.borrow_mut().globals.set(name, value)
+        } else {
+            let assigned = self.head.assign(name, value);
+            assigned.expect("variable to be defined").map(drop)
+        }
+    }
+
+    /// Defines the global `identifier` imported from `module`. Importing
+    /// it from the same module again keeps the value it was bound to, a
+    /// name defined otherwise is reported at the import.
+    pub fn import(
+        &mut self,
+        identifier: &Identifier,
+        module: &ModuleRef,
+        value: LoxValue,
+    ) -> Result<(), Error> {
+        let name = &identifier.name;
+        if let Some(imported) = self.module.borrow().imports.get(name) {
+            if Rc::ptr_eq(imported, module) {
+                return Ok(());
+            }
+        }
+
+        self.define_variable(identifier, value, false)
+            .map_err(|previous| already_defined(name, &previous, &identifier.debug_info))?;
+        self.module
+            .borrow_mut()
+            .imports
+            .insert(name.clone(), module.clone());
+        Ok(())
     }
 
-    pub fn define(
+    fn define_variable(
         &mut self,
         Identifier {
             name,
@@ -170,62 +568,115 @@ impl Environment {
             ..
         }: &Identifier,
         value: LoxValue,
-    ) -> Result<(), Error> {
-        match self.head.define(
-            name,
-            Variable {
-                value,
-                defined_at: debug.clone(),
-            },
-        ) {
-            Ok(_) => Ok(()),
-            Err(DebugInfo {
-                line,
-                position,
-                lexeme: _,
-            }) => Err(Error::RuntimeError {
-                line,
-                position,
-                message: format!("Variable {name} already defined at {line}:{position}!"),
-            }),
+        constant: bool,
+    ) -> Result<(), DebugInfo> {
+        let variable = Variable {
+            value,
+            defined_at: debug.clone(),
+            constant,
+        };
+        if Rc::ptr_eq(&self.head, &self.global) {
+            self.module.borrow_mut().globals.define(name, variable)
+        } else {
+            self.head.define(name, variable)
         }
     }
 
     pub fn get(&mut self, name: &String, id: &IdentifierId) -> Option<LoxValue> {
-        if let Some(depth) = self.access_table.get(id) {
+        let depth = self.module.borrow().access_table.get(id);
+        if let Some(depth) = depth {
             self.get_nth_scope(depth.get()).get(name)
         } else {
-            self.global.get(name)
+            self.module.borrow_mut().globals.get(name, *id)
+        }
+    }
+
+    /// Names of all variables visible from the current scope, including globals.
+    pub fn visible_names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut frame = Some(self.head.clone());
+
+        while let Some(current) = frame {
+            names.extend(current.0.as_ref().borrow().values.keys().cloned());
+            frame = current.get_parent();
         }
+        names.extend(self.module.borrow().globals.names.keys().cloned());
+
+        names
+    }
+
+    /// Local variables visible from the current scope, sorted by name.
+    pub fn locals(&self) -> Vec<(String, LoxValue)> {
+        self.head.bindings()
+    }
+
+    /// Global variables in the order they were defined.
+    pub fn globals(&self) -> Vec<(String, LoxValue)> {
+        self.module
+            .borrow()
+            .globals
+            .slots
+            .iter()
+            .map(|(name, variable)| (name.clone(), variable.value.clone()))
+            .collect()
     }
 
-    #[allow(dead_code)]
-    pub fn get_global(&mut self, name: &String) -> Option<LoxValue> {
-        self.global.get(name)
+    pub fn get_global(&self, name: &String) -> Option<LoxValue> {
+        self.module.borrow().globals.get_by_name(name)
     }
 
+    /// Assigns `value` to the global `name` of the current module, or
+    /// defines it if there is no such global.
+    pub fn set_global(&mut self, name: &str, value: LoxValue) -> Result<(), Error> {
+        self.module
+            .borrow_mut()
+            .globals
+            .set(&name.to_owned(), value)
+    }
+
+    /// Assigns `value` to an existing variable, returns `None` when there
+    /// is no variable named `target`.
     pub fn assign(
         &mut self,
         target: &String,
         id: &IdentifierId,
         value: LoxValue,
-    ) -> Option<LoxValue> {
-        if let Some(depth) = self.access_table.get(id) {
+    ) -> Result<Option<LoxValue>, Error> {
+        let depth = self.module.borrow().access_table.get(id);
+        if let Some(depth) = depth {
             self.get_nth_scope(depth.get()).assign(target, value)
         } else {
-            self.global.assign(target, value)
+            self.module.borrow_mut().globals.assign(target, *id, value)
         }
+        .transpose()
+    }
+}
+
+/// Error for `name` defined again at `at`, after its definition at
+/// `previous`.
+fn already_defined(name: &str, previous: &DebugInfo, at: &DebugInfo) -> Error {
+    Error::RuntimeError {
+        line: at.line,
+        position: at.position,
+        end: at.span().end,
+        message: message!(
+            AlreadyDefined,
+            name = name,
+            line = previous.line,
+            position = previous.position
+        ),
+        trace: Vec::new(),
     }
 }
 
 #[test]
 fn test_function_call() {
     use crate::interpreter::Interpreter;
-    use crate::lox_function::ForeinFun;
+    use crate::lox_function::{ForeinFun, NativeContext};
     use crate::parser::Parser;
     use crate::resolver;
     use crate::scanner;
-    let source = concat!("var a = test(123);",).to_string();
+    let source = "var a = test(123);".to_string();
     let tokens = scanner::scan_tokens(&source).unwrap();
     let tree = Parser::new().parse(tokens).unwrap();
     let access_table = resolver::resolve(&tree).unwrap();
@@ -238,12 +689,13 @@ fn test_function_call() {
             line: 0,
             position: 0,
             lexeme: "<native test>".to_owned(),
+            offset: 0,
         },
     };
 
-    fn test(_env: &mut Interpreter, args: Box<[LoxValue]>) -> Result<LoxValue, Error> {
-        println!("Woo, called a native function!! args: {args:?}");
-        let a = args.get(0).unwrap();
+    fn test(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+        println!("Woo, called a native function!! args: {:?}", ctx.args);
+        let a = ctx.arg(0);
 
         let str = format!("({})", LoxValue::to_string(a));
 
@@ -273,7 +725,7 @@ fn test_closure_capturing() {
     use crate::parser::Parser;
     use crate::resolver;
     use crate::scanner;
-    let source = vec![
+    let source = [
         "fun funkcja() {",
         "    var a = 123;",
         "    fun local_fun() {",
@@ -301,3 +753,120 @@ fn test_closure_capturing() {
     // TODO: fix when return statements implemented
     assert_eq!(val, LoxValue::Number(123.));
 }
+
+#[test]
+fn test_global_slot_cache() {
+    use crate::interpreter::Interpreter;
+    use crate::lox_value::LoxValue;
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let mut parser = Parser::new();
+    let mut interp = Interpreter::new();
+
+    // `counter` is looked up before it is defined, a miss must not be cached
+    let source = "fun bump() { counter = counter + 1; return counter; }";
+    let tree = parser.parse(scanner::scan_tokens(source).unwrap()).unwrap();
+    interp
+        .execute(&tree, resolver::resolve(&tree).unwrap())
+        .unwrap();
+    let source = "bump();";
+    let tree = parser.parse(scanner::scan_tokens(source).unwrap()).unwrap();
+    interp
+        .execute(&tree, resolver::resolve(&tree).unwrap())
+        .unwrap_err();
+
+    let source = "var counter = 0; for (var i = 0; i < 10; i = i + 1) { bump(); }";
+    let tree = parser.parse(scanner::scan_tokens(source).unwrap()).unwrap();
+    interp
+        .execute(&tree, resolver::resolve(&tree).unwrap())
+        .unwrap();
+
+    assert_eq!(
+        interp.environment.get_global(&"counter".to_string()),
+        Some(LoxValue::Number(10.))
+    );
+}
+
+#[test]
+fn test_closures_capture_only_used_variables() {
+    use crate::interpreter::Interpreter;
+    use crate::lox_value::LoxValue;
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source = [
+        "fun outer() {",
+        "    var unused = \"large value\";",
+        "    var x = 1;",
+        "    fun middle() {",
+        "        fun inner() { x = x + 1; return x; }",
+        "        return inner;",
+        "    }",
+        "    var f = middle();",
+        "    f();",
+        "    return f;",
+        "}",
+        "var inner = outer();",
+        "var result = inner();",
+    ]
+    .join("\n");
+
+    let tokens = scanner::scan_tokens(&source).unwrap();
+    let tree = Parser::new().parse(tokens).unwrap();
+    let access_table = resolver::resolve(&tree).unwrap();
+    let mut interp = Interpreter::new();
+    interp.execute(&tree, access_table).unwrap();
+
+    let result = interp.environment.get_global(&"result".to_string());
+    assert_eq!(result, Some(LoxValue::Number(3.)));
+
+    let Some(LoxValue::LoxFun(inner)) = interp.environment.get_global(&"inner".to_string()) else {
+        panic!("Expected variable `inner` to be a function.");
+    };
+    let captured = inner.captured_scope.bindings();
+    assert_eq!(captured, [("x".to_owned(), LoxValue::Number(3.))]);
+}
+
+#[test]
+fn test_globals_and_locals() {
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source = "
+        var b = 1;
+        var a = \"x\";
+        fun f(z) {
+            var y = z;
+            { var x = 3; return locals(); }
+        }
+        var l = f(2);
+        var g = globals();
+    ";
+    let tree = Parser::new()
+        .parse(scanner::scan_tokens(source).unwrap())
+        .unwrap();
+    let mut interp = Interpreter::new();
+    interp
+        .execute(&tree, resolver::resolve(&tree).unwrap())
+        .unwrap();
+
+    let to_string = |name: &str| {
+        interp
+            .environment
+            .globals()
+            .into_iter()
+            .find(|(n, _)| n == name)
+            .unwrap()
+            .1
+            .to_string()
+    };
+    assert_eq!(to_string("l"), "{\"x\": 3, \"y\": 2, \"z\": 2}");
+    // natives come first, followed by globals in definition order
+    let globals = to_string("g");
+    assert!(globals.starts_with("{\"toString\": <native fn toString>, "));
+    assert!(globals.ends_with(
+        ", \"b\": 1, \"a\": \"x\", \"f\": <fn f>, \"l\": {\"x\": 3, \"y\": 2, \"z\": 2}}"
+    ));
+}
@@ -1,41 +1,86 @@
+use crate::interpreter::CallFrame;
+use crate::lox_value::LoxValue;
+use crate::messages::Message;
+
+// `line` and `position` are where the offending text starts, `end` is
+// the column just past it on the same line
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum Error {
     SyntaxError {
         line: usize,
         position: usize,
-        message: String,
+        end: usize,
+        message: Message,
     },
     ParsingError {
         line: usize,
         position: usize,
-        message: String,
+        end: usize,
+        message: Message,
     },
     UnknownBinaryOperator {
         line: usize,
         position: usize,
-        message: String,
+        end: usize,
+        message: Message,
     },
     UnknownUnaryOperator {
         line: usize,
         position: usize,
-        message: String,
+        end: usize,
+        message: Message,
     },
     UnknownLiteral {
         line: usize,
         position: usize,
-        message: String,
+        end: usize,
+        message: Message,
     },
     InternalRuntimeError {
-        message: String,
+        message: Message,
     },
     RuntimeError {
         line: usize,
         position: usize,
-        message: String,
+        end: usize,
+        message: Message,
+        /// calls of Lox functions the error left, innermost first
+        trace: Vec<CallFrame>,
     },
     ResolverError {
         line: usize,
         position: usize,
-        message: String,
+        end: usize,
+        message: Message,
     },
+    /// A value thrown by `throw` leaving a function call, it is turned
+    /// back into `LoxResult::Thrown` by the statement containing the call.
+    Thrown {
+        value: LoxValue,
+        line: usize,
+        position: usize,
+    },
+    /// `exit()` was called, the program ends with the status `code`
+    /// without being caught by `catch` blocks.
+    Exit {
+        code: u8,
+    },
+}
+
+/// The first of the errors a parse failed with, for callers which stop at
+/// one error, e.g. `eval()` or an import.
+impl From<Vec<Error>> for Error {
+    fn from(errors: Vec<Error>) -> Self {
+        errors
+            .into_iter()
+            .next()
+            .expect("a failed parse has at least one error")
+    }
+}
+
+impl From<Error> for Vec<Error> {
+    fn from(error: Error) -> Self {
+        vec![error]
+    }
 }
@@ -1,6 +1,8 @@
 use core::fmt;
 use std::fmt::Formatter;
 
+use crate::diagnostic::Span;
+use crate::messages::message;
 use crate::*;
 
 #[derive(Clone, Default)]
@@ -8,6 +10,8 @@ pub struct DebugInfo {
     pub line: usize,
     pub position: usize,
     pub lexeme: String,
+    /// byte offset of the lexeme in the source
+    pub offset: usize,
 }
 
 impl std::fmt::Debug for DebugInfo {
@@ -16,6 +20,7 @@ impl std::fmt::Debug for DebugInfo {
             line,
             position,
             lexeme,
+            ..
         } = self;
         f.write_fmt(format_args!(
             "DebugInfo {{ line: {line}, position: {position}, lexeme: \"{lexeme}\" }}"
@@ -23,12 +28,19 @@ impl std::fmt::Debug for DebugInfo {
     }
 }
 
+impl DebugInfo {
+    pub fn span(&self) -> Span {
+        Span::of_text(self.line, self.position, &self.lexeme)
+    }
+}
+
 impl From<Token> for DebugInfo {
     fn from(token: Token) -> Self {
         Self {
             line: token.line,
             position: token.position,
-            lexeme: token.lexeme,
+            lexeme: token.lexeme.to_string(),
+            offset: token.offset,
         }
     }
 }
@@ -39,6 +51,12 @@ pub enum BinaryOperator {
     Subtract(DebugInfo),
     Multiply(DebugInfo),
     Divide(DebugInfo),
+    FloorDivide(DebugInfo),
+    BitAnd(DebugInfo),
+    BitOr(DebugInfo),
+    BitXor(DebugInfo),
+    ShiftLeft(DebugInfo),
+    ShiftRight(DebugInfo),
     Equal(DebugInfo),
     NotEqual(DebugInfo),
     Less(DebugInfo),
@@ -54,6 +72,12 @@ impl fmt::Debug for BinaryOperator {
             BinaryOperator::Subtract(dbg) => write!(f, "Subtract({:?})", dbg),
             BinaryOperator::Multiply(dbg) => write!(f, "Multiply({:?})", dbg),
             BinaryOperator::Divide(dbg) => write!(f, "Divide({:?})", dbg),
+            BinaryOperator::FloorDivide(dbg) => write!(f, "FloorDivide({:?})", dbg),
+            BinaryOperator::BitAnd(dbg) => write!(f, "BitAnd({:?})", dbg),
+            BinaryOperator::BitOr(dbg) => write!(f, "BitOr({:?})", dbg),
+            BinaryOperator::BitXor(dbg) => write!(f, "BitXor({:?})", dbg),
+            BinaryOperator::ShiftLeft(dbg) => write!(f, "ShiftLeft({:?})", dbg),
+            BinaryOperator::ShiftRight(dbg) => write!(f, "ShiftRight({:?})", dbg),
             BinaryOperator::Equal(dbg) => write!(f, "Equal({:?})", dbg),
             BinaryOperator::NotEqual(dbg) => write!(f, "NotEqual({:?})", dbg),
             BinaryOperator::Less(dbg) => write!(f, "Less({:?})", dbg),
@@ -70,6 +94,12 @@ impl BinaryOperator {
             TokenType::Plus => Ok(Self::Add(DebugInfo::from(token))),
             TokenType::Minus => Ok(Self::Subtract(DebugInfo::from(token))),
             TokenType::Slash => Ok(Self::Divide(DebugInfo::from(token))),
+            TokenType::TildeSlash => Ok(Self::FloorDivide(DebugInfo::from(token))),
+            TokenType::Ampersand => Ok(Self::BitAnd(DebugInfo::from(token))),
+            TokenType::Pipe => Ok(Self::BitOr(DebugInfo::from(token))),
+            TokenType::Caret => Ok(Self::BitXor(DebugInfo::from(token))),
+            TokenType::LessLess => Ok(Self::ShiftLeft(DebugInfo::from(token))),
+            TokenType::GreaterGreater => Ok(Self::ShiftRight(DebugInfo::from(token))),
             TokenType::Star => Ok(Self::Multiply(DebugInfo::from(token))),
             TokenType::BangEqual => Ok(Self::NotEqual(DebugInfo::from(token))),
             TokenType::EqualEqual => Ok(Self::Equal(DebugInfo::from(token))),
@@ -80,7 +110,8 @@ impl BinaryOperator {
             _ => Err(Error::UnknownBinaryOperator {
                 line: token.line,
                 position: token.position,
-                message: format!("Unknown Binary Operator \"{:?}\".", token.lexeme),
+                end: token.span().end,
+                message: message!(UnknownBinaryOperator, lexeme = token.lexeme),
             }),
         }
     }
@@ -98,6 +129,52 @@ pub struct Grouping {
     pub expression: Expression,
 }
 
+/// List literal, `[a, b, c]`.
+#[derive(Debug, Clone)]
+pub struct List {
+    pub elements: Vec<Expression>,
+}
+
+/// Map literal, `{key: value, ...}`, `debug_info` points at the opening brace.
+#[derive(Debug, Clone)]
+pub struct Map {
+    pub entries: Vec<(Expression, Expression)>,
+    pub debug_info: DebugInfo,
+}
+
+/// `object[index]`, `debug_info` points at the opening bracket.
+#[derive(Debug, Clone)]
+pub struct Index {
+    pub object: Expression,
+    pub index: Expression,
+    pub debug_info: DebugInfo,
+}
+
+/// `object[start:end]`, either bound may be left out.
+#[derive(Debug, Clone)]
+pub struct Slice {
+    pub object: Expression,
+    pub start: Option<Expression>,
+    pub end: Option<Expression>,
+    pub debug_info: DebugInfo,
+}
+
+/// `object[index] = value`.
+#[derive(Debug, Clone)]
+pub struct IndexSet {
+    pub object: Expression,
+    pub index: Expression,
+    pub value: Expression,
+    pub debug_info: DebugInfo,
+}
+
+/// String literal with embedded expressions, the values of `parts` are
+/// converted to strings and concatenated.
+#[derive(Debug, Clone)]
+pub struct Interpolation {
+    pub parts: Vec<Expression>,
+}
+
 #[derive(Clone)]
 pub enum LiteralValue {
     String(String, DebugInfo),
@@ -130,7 +207,8 @@ impl LiteralValue {
             _ => Err(Error::UnknownLiteral {
                 line: token.line,
                 position: token.position,
-                message: format!("Unknown Literal \"{:?}\".", token.lexeme),
+                end: token.span().end,
+                message: message!(UnknownLiteral, lexeme = token.lexeme),
             }),
         }
     }
@@ -145,6 +223,8 @@ pub struct Literal {
 pub enum LogicalOperator {
     And(DebugInfo),
     Or(DebugInfo),
+    /// `??`, the right operand is only evaluated when the left one is nil
+    Coalesce(DebugInfo),
 }
 
 impl fmt::Debug for LogicalOperator {
@@ -152,6 +232,7 @@ impl fmt::Debug for LogicalOperator {
         match self {
             LogicalOperator::And(dbg) => write!(f, "And({:?})", dbg),
             LogicalOperator::Or(dbg) => write!(f, "Or({:?})", dbg),
+            LogicalOperator::Coalesce(dbg) => write!(f, "Coalesce({:?})", dbg),
         }
     }
 }
@@ -161,10 +242,12 @@ impl LogicalOperator {
         match token.token_type {
             TokenType::And => Ok(Self::And(DebugInfo::from(token))),
             TokenType::Or => Ok(Self::Or(DebugInfo::from(token))),
+            TokenType::QuestionQuestion => Ok(Self::Coalesce(DebugInfo::from(token))),
             _ => Err(Error::ParsingError {
                 line: token.line,
                 position: token.position,
-                message: format!("Unknown logical operator \"{:?}\".", token.lexeme),
+                end: token.span().end,
+                message: message!(UnknownLogicalOperator, lexeme = token.lexeme),
             }),
         }
     }
@@ -177,6 +260,13 @@ pub struct Logical {
     pub right: Expression,
 }
 
+/// `left, right`, evaluating both and giving the value of `right`.
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    pub left: Expression,
+    pub right: Expression,
+}
+
 #[derive(Debug, Clone)]
 pub enum UnaryOperator {
     Not(DebugInfo),
@@ -191,7 +281,8 @@ impl UnaryOperator {
             _ => Err(Error::UnknownUnaryOperator {
                 line: token.line,
                 position: token.position,
-                message: format!("Unknown Unary Operator \"{:?}\".", token.lexeme),
+                end: token.span().end,
+                message: message!(UnknownUnaryOperator, lexeme = token.lexeme),
             }),
         }
     }
@@ -235,16 +326,56 @@ pub struct Call {
     pub args: Vec<Expression>,
 }
 
+/// Reads a property of an instance, `object.name`.
+#[derive(Debug, Clone)]
+pub struct Get {
+    pub object: Expression,
+    pub name: String,
+    pub debug_info: DebugInfo,
+    /// `object?.name`, when the object is nil the chain of reads, indexing
+    /// and calls it starts evaluates to nil
+    pub optional: bool,
+}
+
+/// Assigns a property of an instance, `object.name = value`.
+#[derive(Debug, Clone)]
+pub struct Set {
+    pub object: Expression,
+    pub name: String,
+    pub value: Expression,
+    pub debug_info: DebugInfo,
+}
+
+/// Looks a method up in the superclass, `super.method`, `keyword` and
+/// `this` are resolved like variables captured by the enclosing method.
+#[derive(Debug, Clone)]
+pub struct Super {
+    pub keyword: Identifier,
+    pub this: Identifier,
+    pub method: String,
+    pub debug_info: DebugInfo,
+}
+
 #[derive(Clone)]
 pub enum Expression {
     Binary(Box<Binary>),
     Grouping(Box<Grouping>),
+    Interpolation(Box<Interpolation>),
     Literal(Box<Literal>),
     Unary(Box<Unary>),
     Identifier(Box<Identifier>),
     Assignment(Box<Assignment>),
     Logical(Box<Logical>),
+    Sequence(Box<Sequence>),
     Call(Box<Call>),
+    Get(Box<Get>),
+    Set(Box<Set>),
+    Super(Box<Super>),
+    List(Box<List>),
+    Map(Box<Map>),
+    Index(Box<Index>),
+    Slice(Box<Slice>),
+    IndexSet(Box<IndexSet>),
 }
 
 impl core::fmt::Debug for Expression {
@@ -252,61 +383,131 @@ impl core::fmt::Debug for Expression {
         match self {
             Expression::Binary(e) => fmt::Debug::fmt(e, f),
             Expression::Grouping(e) => fmt::Debug::fmt(e, f),
+            Expression::Interpolation(e) => fmt::Debug::fmt(e, f),
             Expression::Literal(e) => fmt::Debug::fmt(e, f),
             Expression::Unary(e) => fmt::Debug::fmt(e, f),
             Expression::Identifier(e) => fmt::Debug::fmt(e, f),
             Expression::Assignment(e) => fmt::Debug::fmt(e, f),
             Expression::Logical(e) => fmt::Debug::fmt(e, f),
+            Expression::Sequence(e) => fmt::Debug::fmt(e, f),
             Expression::Call(e) => fmt::Debug::fmt(e, f),
+            Expression::Get(e) => fmt::Debug::fmt(e, f),
+            Expression::Set(e) => fmt::Debug::fmt(e, f),
+            Expression::Super(e) => fmt::Debug::fmt(e, f),
+            Expression::List(e) => fmt::Debug::fmt(e, f),
+            Expression::Map(e) => fmt::Debug::fmt(e, f),
+            Expression::Index(e) => fmt::Debug::fmt(e, f),
+            Expression::Slice(e) => fmt::Debug::fmt(e, f),
+            Expression::IndexSet(e) => fmt::Debug::fmt(e, f),
         }
     }
 }
 
 impl From<Binary> for Expression {
     fn from(g: Binary) -> Self {
-        return Self::Binary(Box::new(g));
+        Self::Binary(Box::new(g))
     }
 }
 
 impl From<Grouping> for Expression {
     fn from(g: Grouping) -> Self {
-        return Self::Grouping(Box::new(g));
+        Self::Grouping(Box::new(g))
+    }
+}
+
+impl From<Interpolation> for Expression {
+    fn from(i: Interpolation) -> Self {
+        Self::Interpolation(Box::new(i))
     }
 }
 
 impl From<Literal> for Expression {
     fn from(g: Literal) -> Self {
-        return Self::Literal(Box::new(g));
+        Self::Literal(Box::new(g))
     }
 }
 
 impl From<Unary> for Expression {
     fn from(g: Unary) -> Self {
-        return Self::Unary(Box::new(g));
+        Self::Unary(Box::new(g))
     }
 }
 
 impl From<Identifier> for Expression {
     fn from(i: Identifier) -> Self {
-        return Self::Identifier(Box::new(i));
+        Self::Identifier(Box::new(i))
     }
 }
 
 impl From<Assignment> for Expression {
     fn from(i: Assignment) -> Self {
-        return Self::Assignment(Box::new(i));
+        Self::Assignment(Box::new(i))
     }
 }
 
 impl From<Logical> for Expression {
     fn from(i: Logical) -> Self {
-        return Self::Logical(Box::new(i));
+        Self::Logical(Box::new(i))
+    }
+}
+
+impl From<Sequence> for Expression {
+    fn from(i: Sequence) -> Self {
+        Self::Sequence(Box::new(i))
     }
 }
 
 impl From<Call> for Expression {
     fn from(i: Call) -> Self {
-        return Self::Call(Box::new(i));
+        Self::Call(Box::new(i))
+    }
+}
+
+impl From<Get> for Expression {
+    fn from(i: Get) -> Self {
+        Self::Get(Box::new(i))
+    }
+}
+
+impl From<Set> for Expression {
+    fn from(i: Set) -> Self {
+        Self::Set(Box::new(i))
+    }
+}
+
+impl From<Super> for Expression {
+    fn from(i: Super) -> Self {
+        Self::Super(Box::new(i))
+    }
+}
+
+impl From<List> for Expression {
+    fn from(i: List) -> Self {
+        Self::List(Box::new(i))
+    }
+}
+
+impl From<Map> for Expression {
+    fn from(i: Map) -> Self {
+        Self::Map(Box::new(i))
+    }
+}
+
+impl From<Index> for Expression {
+    fn from(i: Index) -> Self {
+        Self::Index(Box::new(i))
+    }
+}
+
+impl From<Slice> for Expression {
+    fn from(i: Slice) -> Self {
+        Self::Slice(Box::new(i))
+    }
+}
+
+impl From<IndexSet> for Expression {
+    fn from(i: IndexSet) -> Self {
+        Self::IndexSet(Box::new(i))
     }
 }
 
@@ -315,26 +516,29 @@ fn expression_test() {
     let e = Expression::from(Binary {
         operator: BinaryOperator::new(Token {
             token_type: TokenType::Minus,
-            lexeme: String::new(),
+            lexeme: "".into(),
             line: 0,
             position: 0,
+            offset: 0,
         })
         .unwrap(),
         left: Expression::from(Literal {
             value: LiteralValue::new(Token {
                 token_type: TokenType::Number(10.),
-                lexeme: String::new(),
+                lexeme: "".into(),
                 line: 0,
                 position: 0,
+                offset: 0,
             })
             .unwrap(),
         }),
         right: Expression::from(Literal {
             value: LiteralValue::new(Token {
                 token_type: TokenType::Number(10.),
-                lexeme: String::new(),
+                lexeme: "".into(),
                 line: 0,
                 position: 0,
+                offset: 0,
             })
             .unwrap(),
         }),
@@ -343,9 +547,10 @@ fn expression_test() {
     let unary = Expression::from(Unary {
         operator: UnaryOperator::new(Token {
             token_type: TokenType::Minus,
-            lexeme: String::new(),
+            lexeme: "".into(),
             line: 0,
             position: 0,
+            offset: 0,
         })
         .unwrap(),
         right: e,
@@ -0,0 +1,220 @@
+//! Natives loaded from shared libraries, `rlox --load ./libext.so`.
+//!
+//! An extension is a C-compatible library exporting `rlox_register`,
+//! which defines its functions through the callback it is given:
+//!
+//! ```c
+//! #include <stdbool.h>
+//! #include <stddef.h>
+//!
+//! enum { RLOX_NIL, RLOX_BOOL, RLOX_NUMBER, RLOX_STRING };
+//!
+//! typedef struct {
+//!     unsigned int type;
+//!     bool boolean;
+//!     double number;
+//!     const char *string; /* NUL-terminated UTF-8 */
+//! } rlox_value;
+//!
+//! /* returns 0 on success, or else an error message in `result->string` */
+//! typedef int (*rlox_native)(const rlox_value *args, size_t count, rlox_value *result);
+//! /* a negative arity accepts any number of arguments */
+//! typedef void (*rlox_define)(void *registry, const char *name, int arity, rlox_native native);
+//!
+//! static int twice(const rlox_value *args, size_t count, rlox_value *result) {
+//!     result->type = RLOX_NUMBER;
+//!     result->number = args[0].number * 2;
+//!     return 0;
+//! }
+//!
+//! void rlox_register(void *registry, rlox_define define) {
+//!     define(registry, "twice", 1, twice);
+//! }
+//! ```
+//!
+//! Only nil, booleans, numbers and strings cross the boundary. Strings
+//! passed to a native are valid until it returns, a string it returns
+//! has to stay valid until it is called again.
+
+use std::ffi::{c_char, c_int, c_uint, c_void, CStr, CString};
+use std::path::Path;
+
+use libloading::Library;
+
+use crate::lox_function::Arity;
+use crate::lox_value::LoxValue;
+use crate::messages::{message, Message};
+
+pub const RLOX_NIL: c_uint = 0;
+pub const RLOX_BOOL: c_uint = 1;
+pub const RLOX_NUMBER: c_uint = 2;
+pub const RLOX_STRING: c_uint = 3;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RloxValue {
+    pub kind: c_uint,
+    pub boolean: bool,
+    pub number: f64,
+    pub string: *const c_char,
+}
+
+pub type RloxNative = unsafe extern "C" fn(*const RloxValue, usize, *mut RloxValue) -> c_int;
+pub type RloxDefine = unsafe extern "C" fn(*mut c_void, *const c_char, c_int, RloxNative);
+pub type RloxRegister = unsafe extern "C" fn(*mut c_void, RloxDefine);
+
+const ENTRY_POINT: &[u8] = b"rlox_register\0";
+
+/// Loaded libraries, kept open for as long as their natives can be called.
+#[derive(Default)]
+pub struct Extensions {
+    libraries: Vec<Library>,
+}
+
+impl Extensions {
+    /// Opens the library at `path` and returns its entry point, the
+    /// library has to be [`keep`](Self::keep)t while it is in use.
+    pub fn open(path: &Path) -> Result<(Library, RloxRegister), Message> {
+        let failed = |error: libloading::Error| {
+            message!(CannotLoadExtension, path = path.display(), error = error)
+        };
+        // SAFETY: loading a library runs its initializers, extensions are
+        // trusted the same way as the interpreter binary itself
+        let library = unsafe { Library::new(path) }.map_err(failed)?;
+        // SAFETY: the signature of the entry point is part of the
+        // documented extension interface
+        let register = unsafe { library.get::<RloxRegister>(ENTRY_POINT) }.map_err(failed)?;
+        let register = *register;
+        Ok((library, register))
+    }
+
+    pub fn keep(&mut self, library: Library) {
+        self.libraries.push(library);
+    }
+
+    /// Calls the entry point of an extension, returning the natives it
+    /// defined along with their arity.
+    pub fn register(register: RloxRegister) -> Result<Vec<(String, Arity, RloxNative)>, Message> {
+        let mut registry = Registry::default();
+        // SAFETY: `define` only lives as long as the call, the registry
+        // pointer is only given back to it
+        unsafe { register(&mut registry as *mut Registry as *mut c_void, define) };
+        match registry.error {
+            Some(error) => Err(error),
+            None => Ok(registry.natives),
+        }
+    }
+}
+
+/// Calls the `native` of an extension, registered as `name`.
+pub fn call(native: RloxNative, name: &str, args: &[LoxValue]) -> Result<LoxValue, Message> {
+    // the strings have to outlive the call
+    let strings = args
+        .iter()
+        .enumerate()
+        .map(|(index, arg)| match arg {
+            LoxValue::String(string) => CString::new(string.as_str())
+                .map(Some)
+                .map_err(|_| (index, arg)),
+            LoxValue::Nil | LoxValue::Bool(_) | LoxValue::Number(_) => Ok(None),
+            _ => Err((index, arg)),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|(index, arg)| {
+            message!(
+                ExtensionArgument,
+                index = index + 1,
+                function = name,
+                value = arg.type_name()
+            )
+        })?;
+    let values: Vec<RloxValue> = args
+        .iter()
+        .zip(&strings)
+        .map(|(arg, string)| {
+            let mut value = RloxValue::NIL;
+            match arg {
+                LoxValue::Bool(boolean) => {
+                    value.kind = RLOX_BOOL;
+                    value.boolean = *boolean;
+                }
+                LoxValue::Number(number) => {
+                    value.kind = RLOX_NUMBER;
+                    value.number = *number;
+                }
+                LoxValue::String(_) => {
+                    value.kind = RLOX_STRING;
+                    value.string = string.as_ref().map_or(std::ptr::null(), |s| s.as_ptr());
+                }
+                _ => {}
+            }
+            value
+        })
+        .collect();
+
+    let mut result = RloxValue::NIL;
+    // SAFETY: the arguments and the strings they point to are alive
+    // for the duration of the call
+    let status = unsafe { native(values.as_ptr(), values.len(), &mut result) };
+
+    let string = || match result.string.is_null() {
+        true => None,
+        // SAFETY: extensions return NUL-terminated strings that stay
+        // valid until their next call
+        false => Some(unsafe { CStr::from_ptr(result.string) }.to_string_lossy()),
+    };
+    if status != 0 {
+        let error = string().unwrap_or_default();
+        return Err(message!(ExtensionFailed, function = name, message = error));
+    }
+    match result.kind {
+        RLOX_NIL => Ok(LoxValue::Nil),
+        RLOX_BOOL => Ok(LoxValue::Bool(result.boolean)),
+        RLOX_NUMBER => Ok(LoxValue::Number(result.number)),
+        RLOX_STRING => match string() {
+            Some(string) => Ok(LoxValue::String(string.into_owned())),
+            None => Err(message!(ExtensionInvalidResult, function = name)),
+        },
+        _ => Err(message!(ExtensionInvalidResult, function = name)),
+    }
+}
+
+impl RloxValue {
+    pub const NIL: RloxValue = RloxValue {
+        kind: RLOX_NIL,
+        boolean: false,
+        number: 0.,
+        string: std::ptr::null(),
+    };
+}
+
+#[derive(Default)]
+struct Registry {
+    natives: Vec<(String, Arity, RloxNative)>,
+    /// the first invalid definition
+    error: Option<Message>,
+}
+
+unsafe extern "C" fn define(
+    registry: *mut c_void,
+    name: *const c_char,
+    arity: c_int,
+    native: RloxNative,
+) {
+    // SAFETY: the pointer was created from a `&mut Registry` by `register`
+    let registry = unsafe { &mut *(registry as *mut Registry) };
+    if name.is_null() {
+        registry.error.get_or_insert(message!(ExtensionInvalidName));
+        return;
+    }
+    // SAFETY: names are NUL-terminated strings
+    let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+        registry.error.get_or_insert(message!(ExtensionInvalidName));
+        return;
+    };
+    let arity = match usize::try_from(arity) {
+        Ok(arity) => Arity::exactly(arity),
+        Err(_) => Arity::at_least(0),
+    };
+    registry.natives.push((name.to_owned(), arity, native));
+}
@@ -0,0 +1,147 @@
+//! C API for embedding the interpreter, built into `librlox` with
+//! `cargo build --release --features ffi`:
+//!
+//! ```c
+//! typedef struct rlox rlox;
+//!
+//! rlox *rlox_new(void);
+//! /* 0 on success, -1 on an error, or the status passed to exit() */
+//! int rlox_run(rlox *lox, const char *source);
+//! /* NUL-terminated UTF-8, valid until the next call with `lox` */
+//! const char *rlox_result(const rlox *lox);
+//! void rlox_free(rlox *lox);
+//! ```
+//!
+//! Every run continues at the top level of the same program, so globals
+//! defined by one are seen by the next. The result of a run is the value
+//! of its last statement, if that is an expression, or the message of the
+//! error it failed with.
+
+use std::ffi::{c_char, c_int, CStr, CString};
+
+use crate::error::Error;
+use crate::interpreter::Interpreter;
+use crate::messages::message;
+
+/// An interpreter handed out to C, along with the result of its last run.
+pub struct Rlox {
+    interpreter: Interpreter,
+    result: CString,
+}
+
+/// Creates an interpreter, to be released with [`rlox_free`].
+#[no_mangle]
+pub extern "C" fn rlox_new() -> *mut Rlox {
+    let lox = Rlox {
+        interpreter: Interpreter::new(),
+        result: CString::default(),
+    };
+    Box::into_raw(Box::new(lox))
+}
+
+/// Runs `source`, returning 0 on success, -1 on an error, or the status
+/// the script passed to `exit()`.
+///
+/// # Safety
+///
+/// `lox` has to come from [`rlox_new`] and `source` has to be a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rlox_run(lox: *mut Rlox, source: *const c_char) -> c_int {
+    // SAFETY: guaranteed by the caller
+    let (Some(lox), false) = (unsafe { lox.as_mut() }, source.is_null()) else {
+        return -1;
+    };
+    // SAFETY: guaranteed by the caller
+    let Ok(source) = unsafe { CStr::from_ptr(source) }.to_str() else {
+        lox.set_result(message!(FfiInvalidSource));
+        return -1;
+    };
+
+    match lox.interpreter.eval(source) {
+        Ok(value) => {
+            lox.set_result(value.to_string());
+            0
+        }
+        Err(Error::Exit { code }) => {
+            lox.set_result(String::new());
+            code.into()
+        }
+        Err(error) => {
+            let message = match error.into_diagnostic() {
+                Ok(diagnostic) => message!(
+                    FfiErrorAt,
+                    line = diagnostic.span.line,
+                    position = diagnostic.span.position,
+                    message = diagnostic.message
+                ),
+                Err(Error::InternalRuntimeError { message }) => message,
+                Err(error) => format!("{error:?}").into(),
+            };
+            lox.set_result(message);
+            -1
+        }
+    }
+}
+
+/// The result of the last run of `lox`, empty before the first one.
+///
+/// # Safety
+///
+/// `lox` has to come from [`rlox_new`].
+#[no_mangle]
+pub unsafe extern "C" fn rlox_result(lox: *const Rlox) -> *const c_char {
+    // SAFETY: guaranteed by the caller
+    match unsafe { lox.as_ref() } {
+        Some(lox) => lox.result.as_ptr(),
+        None => std::ptr::null(),
+    }
+}
+
+/// Releases an interpreter, null is ignored.
+///
+/// # Safety
+///
+/// `lox` has to come from [`rlox_new`] and can not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rlox_free(lox: *mut Rlox) {
+    if !lox.is_null() {
+        // SAFETY: guaranteed by the caller
+        drop(unsafe { Box::from_raw(lox) });
+    }
+}
+
+impl Rlox {
+    fn set_result(&mut self, result: impl Into<String>) {
+        let result: String = result.into();
+        // C strings end at the first NUL
+        self.result = CString::new(result.replace('\0', "")).unwrap_or_default();
+    }
+}
+
+#[test]
+fn test_ffi() {
+    let run = |lox, source: &CStr| {
+        // SAFETY: `lox` is alive and the source is NUL-terminated
+        let status = unsafe { rlox_run(lox, source.as_ptr()) };
+        // SAFETY: the result is valid until the next run
+        let result = unsafe { CStr::from_ptr(rlox_result(lox)) };
+        (status, result.to_str().unwrap().to_owned())
+    };
+
+    let lox = rlox_new();
+    assert_eq!(run(lox, c"var x = 20;"), (0, "nil".to_owned()));
+    assert_eq!(run(lox, c"x * 2 + 2;"), (0, "42".to_owned()));
+    assert_eq!(
+        run(lox, c"\"a\" + 1;"),
+        (
+            -1,
+            "Error at line 1, position 5: Operands of '+' must be two numbers or two strings, \
+             got string and number."
+                .to_owned()
+        )
+    );
+    assert_eq!(run(lox, c"exit(3);"), (3, String::new()));
+    // SAFETY: `lox` is not used afterwards
+    unsafe { rlox_free(lox) };
+}
@@ -0,0 +1,199 @@
+//! Format strings of `format()`.
+//!
+//! `{}` is replaced with the next argument and `{1}` with the argument at
+//! that index, counted from 0. After a colon, a placeholder can specify
+//! how the value is laid out, `[[fill]align][0][width][.precision]`:
+//!
+//! - `{:>8}`, `{:<8}` and `{:^8}` pad the value to 8 characters,
+//!   aligning it right, left or in the center; `{:*^8}` pads with `*`,
+//! - `{:08}` pads a number with zeros after its sign,
+//! - `{:.2}` writes a number with 2 decimals, or keeps the first 2
+//!   characters of any other value.
+//!
+//! Numbers are aligned right by default, other values left. `{{` and
+//! `}}` stand for literal braces.
+
+use crate::lox_value::LoxValue;
+use crate::messages::{message, Message};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Spec {
+    fill: Option<char>,
+    align: Option<Align>,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Result<Spec, Message> {
+        let invalid = || message!(InvalidFormatSpec, spec = spec);
+        let align = |c| match c {
+            '<' => Some(Align::Left),
+            '^' => Some(Align::Center),
+            '>' => Some(Align::Right),
+            _ => None,
+        };
+
+        let mut parsed = Spec::default();
+        let mut rest = spec;
+        let mut chars = rest.chars();
+        match (chars.next(), chars.next().and_then(align)) {
+            (Some(fill), Some(alignment)) => {
+                parsed.fill = Some(fill);
+                parsed.align = Some(alignment);
+                rest = &rest[fill.len_utf8() + 1..];
+            }
+            (Some(c), _) if align(c).is_some() => {
+                parsed.align = align(c);
+                rest = &rest[1..];
+            }
+            _ => {}
+        }
+        if let Some(after) = rest.strip_prefix('0') {
+            parsed.zero = true;
+            rest = after;
+        }
+
+        let (width, precision) = match rest.split_once('.') {
+            Some((width, precision)) => (width, Some(precision)),
+            None => (rest, None),
+        };
+        let number = |digits: &str| match digits.bytes().all(|b| b.is_ascii_digit()) {
+            true => digits.parse().map_err(|_| invalid()),
+            false => Err(invalid()),
+        };
+        if !width.is_empty() {
+            parsed.width = number(width)?;
+        }
+        if let Some(precision) = precision {
+            parsed.precision = Some(number(precision)?);
+        }
+        Ok(parsed)
+    }
+
+    fn apply(&self, value: &LoxValue) -> String {
+        let (mut text, number) = match (value, self.precision) {
+            (LoxValue::Number(n), Some(precision)) => (format!("{n:.precision$}"), true),
+            (LoxValue::Number(_), None) => (LoxValue::to_string(value), true),
+            (_, Some(precision)) => (
+                LoxValue::to_string(value).chars().take(precision).collect(),
+                false,
+            ),
+            (_, None) => (LoxValue::to_string(value), false),
+        };
+
+        let padding = self.width.saturating_sub(text.chars().count());
+        if padding == 0 {
+            return text;
+        }
+        if number && self.zero && self.align.is_none() {
+            let sign = if text.starts_with('-') { "-" } else { "" };
+            text.insert_str(sign.len(), &"0".repeat(padding));
+            return text;
+        }
+
+        let default = if number { Align::Right } else { Align::Left };
+        let (before, after) = match self.align.unwrap_or(default) {
+            Align::Left => (0, padding),
+            Align::Center => (padding / 2, padding - padding / 2),
+            Align::Right => (padding, 0),
+        };
+        let fill = self.fill.unwrap_or(' ').to_string();
+        fill.repeat(before) + &text + &fill.repeat(after)
+    }
+}
+
+/// Replaces the placeholders of `template` with the `args`.
+pub fn format(template: &str, args: &[LoxValue]) -> Result<String, Message> {
+    let mut formatted = String::new();
+    let mut next = 0;
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        formatted += &rest[..start];
+        let brace = &rest[start..];
+        if let Some(after) = brace.strip_prefix("{{").or(brace.strip_prefix("}}")) {
+            formatted.push(brace.as_bytes()[0] as char);
+            rest = after;
+            continue;
+        }
+        if brace.starts_with('}') {
+            return Err(message!(UnmatchedFormatBrace));
+        }
+
+        let end = brace
+            .find('}')
+            .ok_or_else(|| message!(UnclosedFormatPlaceholder))?;
+        let placeholder = &brace[1..end];
+        let (index, spec) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+        let index = match index {
+            "" => {
+                next += 1;
+                next - 1
+            }
+            index => index
+                .parse()
+                .map_err(|_| message!(InvalidFormatPlaceholder, placeholder = placeholder))?,
+        };
+        let value = args
+            .get(index)
+            .ok_or_else(|| message!(MissingFormatArgument, index = index, count = args.len()))?;
+        formatted += &Spec::parse(spec)?.apply(value);
+        rest = &brace[end + 1..];
+    }
+    formatted += rest;
+    Ok(formatted)
+}
+
+#[test]
+fn test_format() {
+    let args = [
+        LoxValue::Number(3.),
+        LoxValue::String("ab".to_owned()),
+        LoxValue::Number(-2.5),
+        LoxValue::Nil,
+    ];
+    let format = |template| format(template, &args);
+
+    assert_eq!(format("x={}, y={}!").unwrap(), "x=3, y=ab!");
+    assert_eq!(format("{1}{0}{1} {} {3}").unwrap(), "ab3ab 3 nil");
+    assert_eq!(format("{{{}}} }}").unwrap(), "{3} }");
+    assert_eq!(
+        format("[{:4}|{1:4}|{2:<6}|{1:^5}]").unwrap(),
+        "[   3|ab  |-2.5  | ab  ]"
+    );
+    assert_eq!(
+        format("[{2:07.2}|{0:*>4}|{1:-^6}|{3:.1}]").unwrap(),
+        "[-002.50|***3|--ab--|n]"
+    );
+    assert_eq!(format("{2:.0} {0:.3} {1:1}").unwrap(), "-2 3.000 ab");
+    assert_eq!(format("{:ż<3}").unwrap(), "3żż");
+
+    assert_eq!(
+        format("{} {} {} {} {}").map_err(String::from),
+        Err("Format string refers to argument 4, but only 4 arguments were given.".to_owned())
+    );
+    assert_eq!(
+        format("{").map_err(String::from),
+        Err("Unclosed '{' in format string.".to_owned())
+    );
+    assert_eq!(
+        format("}").map_err(String::from),
+        Err("Unmatched '}' in format string.".to_owned())
+    );
+    assert_eq!(
+        format("{x}").map_err(String::from),
+        Err("Invalid placeholder '{x}' in format string.".to_owned())
+    );
+    assert_eq!(
+        format("{:5x}").map_err(String::from),
+        Err("Invalid format specifier '5x'.".to_owned())
+    );
+}
@@ -0,0 +1,292 @@
+//! `rlox fmt`, rewriting a program in the canonical layout:
+//!
+//! - every statement on a line of its own, indented by two spaces for
+//!   every block it is in,
+//! - `{` of a block at the end of the line opening it, `}` on a line of its
+//!   own, followed by `else`, `catch` or `finally` on the same line,
+//! - binary operators surrounded by spaces, none inside brackets or
+//!   between a function and its arguments,
+//! - at most one blank line where the source had some.
+//!
+//! Comments are kept, at the end of the line they were on or on a line of
+//! their own. Literals are written exactly as they were, an interpolated
+//! string with the expressions embedded in it.
+
+use crate::edition::Edition;
+use crate::parser::Parser;
+use crate::scanner;
+use crate::source_map::BYTE_ORDER_MARK;
+use crate::{Error, LosslessToken, Token, TokenType, Trivia};
+
+const INDENT: &str = "  ";
+
+/// Returns `source` in the canonical layout, or the errors it fails to
+/// parse with.
+pub fn format(source: &str, edition: Edition) -> Result<String, Vec<Error>> {
+    let tokens = scanner::scan_lossless(source)?;
+    // only a valid program can be laid out by its brackets
+    let syntax = tokens
+        .iter()
+        .map(|lossless| lossless.token.clone())
+        .collect();
+    Parser::with_edition(edition).parse_syntax(syntax)?;
+
+    let mut formatter = Formatter::default();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(LosslessToken { trivia, token }) = tokens.next() {
+        for trivia in trivia {
+            match trivia {
+                Trivia::Whitespace(text) => formatter.line_breaks += text.matches('\n').count(),
+                Trivia::Comment(text) => formatter.comment(&text),
+                Trivia::Preamble(text) => formatter.preamble(&text),
+            }
+        }
+        if matches!(token.token_type, TokenType::Eof) {
+            break;
+        }
+        formatter.token(token, tokens.peek().map(|next| &next.token));
+    }
+
+    let mut output = formatter.output;
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+/// Bracket the formatter is inside of.
+#[derive(Clone, Copy, PartialEq)]
+enum Bracket {
+    Block,
+    Map,
+    Paren,
+    Square,
+}
+
+#[derive(Default)]
+struct Formatter {
+    output: String,
+    open: Vec<Bracket>,
+    previous: Option<Token>,
+    /// the previous token was a `-` or `...` applied to the next one
+    previous_prefix: bool,
+    /// the previous token closed a block rather than a map
+    previous_closed_block: bool,
+    /// line breaks since the previous token or comment
+    line_breaks: usize,
+    newline_pending: bool,
+    /// nothing was written since the `{` of a block
+    block_opened: bool,
+}
+
+impl Formatter {
+    /// Keeps the `#!` line of a script, a byte order mark is dropped.
+    fn preamble(&mut self, text: &str) {
+        let shebang = text.trim_start_matches(BYTE_ORDER_MARK).trim_end();
+        if !shebang.is_empty() {
+            self.output.push_str(shebang);
+            self.newline_pending = true;
+        }
+    }
+
+    fn comment(&mut self, text: &str) {
+        if self.line_breaks == 0 && !self.output.is_empty() {
+            self.output.push(' ');
+        } else {
+            self.newline(!self.block_opened);
+        }
+        self.output.push_str(text);
+        self.line_breaks = 0;
+        self.newline_pending = true;
+        self.block_opened = false;
+    }
+
+    fn token(&mut self, token: Token, next: Option<&Token>) {
+        use TokenType as T;
+
+        let closing_block =
+            matches!(token.token_type, T::RightBrace) && self.open.last() == Some(&Bracket::Block);
+        if closing_block {
+            self.open.pop();
+            // an empty block stays on the line it was opened on
+            self.newline_pending = !matches!(self.previous_type(), Some(T::LeftBrace));
+        }
+
+        let continues_block = self.previous_closed_block
+            && (matches!(
+                token.token_type,
+                T::Else | T::Semicolon | T::Comma | T::RightParen | T::RightBracket
+            ) || is_block_continuation(&token, next));
+        if self.newline_pending && !continues_block {
+            // blocks neither start nor end with a blank line
+            self.newline(!self.block_opened && !closing_block);
+        } else if self.space_before(&token) {
+            self.output.push(' ');
+        }
+        self.newline_pending = false;
+        self.block_opened = false;
+        self.output.push_str(&token.lexeme);
+        self.line_breaks = 0;
+
+        self.previous_prefix = matches!(token.token_type, T::DotDotDot)
+            || (matches!(token.token_type, T::Minus) && !self.ends_operand());
+        self.previous_closed_block = closing_block;
+        match token.token_type {
+            T::LeftBrace if self.opens_block() => {
+                self.open.push(Bracket::Block);
+                self.newline_pending = true;
+                self.block_opened = true;
+            }
+            T::LeftBrace => self.open.push(Bracket::Map),
+            T::LeftParen => self.open.push(Bracket::Paren),
+            T::LeftBracket => self.open.push(Bracket::Square),
+            T::RightBrace if closing_block => self.newline_pending = true,
+            T::RightBrace | T::RightParen | T::RightBracket => {
+                self.open.pop();
+            }
+            T::Semicolon if matches!(self.open.last(), None | Some(Bracket::Block)) => {
+                self.newline_pending = true;
+            }
+            _ => {}
+        }
+        self.previous = Some(token);
+    }
+
+    /// Ends the current line, with a blank line after it if the source
+    /// had one before `line` and `blank` allows it, and indents the next.
+    fn newline(&mut self, blank: bool) {
+        if self.output.is_empty() {
+            return;
+        }
+        self.output.push('\n');
+        if blank && self.line_breaks > 1 {
+            self.output.push('\n');
+        }
+        let depth = self.open.iter().filter(|b| **b == Bracket::Block).count();
+        self.output.push_str(&INDENT.repeat(depth));
+    }
+
+    fn previous_type(&self) -> Option<&TokenType> {
+        self.previous.as_ref().map(|token| &token.token_type)
+    }
+
+    /// Whether the previous token ends an operand, making a following `-`
+    /// a subtraction and `(` or `[` a call or an index.
+    fn ends_operand(&self) -> bool {
+        use TokenType as T;
+        match self.previous_type() {
+            Some(T::RightBrace) => !self.previous_closed_block,
+            Some(
+                T::Identifier(_)
+                | T::String(_)
+                | T::Interpolation(_)
+                | T::Number(_)
+                | T::True
+                | T::False
+                | T::Nil
+                | T::This
+                | T::Super
+                | T::RightParen
+                | T::RightBracket,
+            ) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether a `{` after the previous token opens a block, a map literal
+    /// can only follow an operator or an opening bracket.
+    fn opens_block(&self) -> bool {
+        use TokenType as T;
+        self.ends_operand()
+            || matches!(
+                self.previous_type(),
+                None | Some(T::Semicolon | T::LeftBrace | T::RightBrace | T::FatArrow | T::Else)
+            )
+    }
+
+    fn space_before(&self, token: &Token) -> bool {
+        use TokenType as T;
+        let Some(previous) = self.previous_type() else {
+            return false;
+        };
+        let in_brackets = self.open.last() == Some(&Bracket::Square);
+
+        match (previous, &token.token_type) {
+            (_, T::Comma | T::Semicolon | T::RightParen | T::RightBracket) => false,
+            (_, T::Dot | T::QuestionDot) => false,
+            (T::LeftParen | T::LeftBracket | T::Dot | T::QuestionDot | T::Bang, _) => false,
+            (_, T::Colon) => false,
+            (T::Colon, _) => !in_brackets,
+            (T::LeftBrace, _) | (_, T::RightBrace) => false,
+            (_, T::LeftParen | T::LeftBracket) if self.ends_operand() => false,
+            _ => !self.previous_prefix,
+        }
+    }
+}
+
+/// Whether `token` is a `catch` or `finally` continuing a `try` statement
+/// after the `}` of its block.
+fn is_block_continuation(token: &Token, next: Option<&Token>) -> bool {
+    let TokenType::Identifier(name) = &token.token_type else {
+        return false;
+    };
+    (&**name == "catch" || &**name == "finally")
+        && matches!(
+            next.map(|next| &next.token_type),
+            Some(TokenType::LeftBrace | TokenType::Identifier(_))
+        )
+}
+
+#[test]
+fn test_format() {
+    let source = "#!/usr/bin/env rlox
+// greets people
+fun greet(names,...rest){for(var i=0;i<len(names);i=i+1){print \"hi ${names[i]}\";}
+
+  if (-rest[0:1] == nil) { return {\"a\" : 1, \"b\":[ ] }; } else { return; }}
+
+
+fun f(x) { return x * -1; } // negates
+print f(1) ;
+try { greet([\"a\"]); } catch e {} finally { f(2)?.x; }
+{}
+";
+    let formatted = format(source, Edition::Lox2026).unwrap();
+    assert_eq!(
+        formatted,
+        "#!/usr/bin/env rlox
+// greets people
+fun greet(names, ...rest) {
+  for (var i = 0; i < len(names); i = i + 1) {
+    print \"hi ${names[i]}\";
+  }
+
+  if (-rest[0:1] == nil) {
+    return {\"a\": 1, \"b\": []};
+  } else {
+    return;
+  }
+}
+
+fun f(x) {
+  return x * -1;
+} // negates
+print f(1);
+try {
+  greet([\"a\"]);
+} catch e {} finally {
+  f(2)?.x;
+}
+{}
+"
+    );
+    assert_eq!(format(&formatted, Edition::Lox2026).unwrap(), formatted);
+
+    assert!(format("print (1;", Edition::Lox2026).is_err());
+
+    let windows = "\u{feff}#!/usr/bin/env rlox\r\nprint 1;\r\n\r\n\r\nprint 2; // two\r\n";
+    assert_eq!(
+        format(windows, Edition::Lox2026).unwrap(),
+        "#!/usr/bin/env rlox\nprint 1;\n\nprint 2; // two\n"
+    );
+}
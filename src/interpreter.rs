@@ -1,95 +1,1018 @@
-use crate::environment::Environment;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::capability::Capability;
+use crate::datetime;
+use crate::edition::Edition;
+use crate::environment::{Environment, ModuleRef, EVAL_IDENTIFIERS};
 use crate::error::Error;
 use crate::expression::Binary;
 use crate::expression::BinaryOperator;
 use crate::expression::Call;
 use crate::expression::DebugInfo;
 use crate::expression::Expression;
+use crate::expression::Get;
 use crate::expression::Grouping;
 use crate::expression::Identifier;
+use crate::expression::Index;
+use crate::expression::IndexSet;
+use crate::expression::Interpolation;
+use crate::expression::List;
 use crate::expression::LiteralValue;
 use crate::expression::Logical;
 use crate::expression::LogicalOperator;
+use crate::expression::Map;
+use crate::expression::Sequence;
+use crate::expression::Set;
+use crate::expression::Slice;
+use crate::expression::Super;
 use crate::expression::Unary;
 use crate::expression::UnaryOperator;
+use crate::extension::{self, Extensions, RloxRegister};
+use crate::format;
+use crate::lower;
+use crate::lox_function::Arity;
+use crate::lox_function::BoundFun;
 use crate::lox_function::ForeinFun;
 use crate::lox_function::LoxFun;
+use crate::lox_function::NativeContext;
+use crate::lox_key::LoxKey;
+use crate::lox_map::LoxMap;
+use crate::lox_object::{LoxClass, LoxObject};
+use crate::lox_range::LoxRange;
 use crate::lox_value::LoxValue;
-use crate::resolver::AccessTable;
+use crate::messages::{message, Message, MessageId};
+use crate::module_loader::ModuleLoader;
+use crate::native_module::NativeModule;
+use crate::output::Sink;
+use crate::parser::Parser;
+use crate::random::Random;
+use crate::resolver::{self, AccessTable};
+use crate::scanner;
+use crate::shared::{Rc, RefCell, Shareable};
 use crate::statement::Block;
+use crate::statement::FunctionDeclaration;
 use crate::statement::Statement;
+use crate::suggestions::did_you_mean;
+use crate::typed_native::IntoLox;
 
 pub struct Interpreter {
     pub line: usize,
     pub position: usize,
+    pub end: usize,
     pub environment: Environment,
+    /// Scratch stack holding evaluated call arguments, reused between calls
+    /// so that calling a function does not allocate a fresh argument list.
+    arg_stack: Vec<LoxValue>,
+    /// Lox functions that are currently executing, innermost last.
+    pub call_stack: Vec<CallFrame>,
+    /// Class of the objects runtime errors become when a `catch` block
+    /// receives them.
+    error_class: Rc<LoxClass>,
+    /// Methods of built-in types by type name, e.g. `"abc".length()`.
+    methods: HashMap<&'static str, HashMap<String, Rc<ForeinFun>>>,
+    /// files imported so far
+    modules: ModuleLoader,
+    /// last identifier id given to code passed to `eval`
+    eval_identifiers: usize,
+    /// `monotonicMillis()` counts from here
+    started: Instant,
+    /// generator of `random()`, seeded with the time until `seedRandom()`
+    random: Random,
+    /// capabilities granted with [`allow`](Self::allow)
+    capabilities: Vec<Capability>,
+    /// natives of shared libraries loaded with
+    /// [`load_extension`](Self::load_extension)
+    extensions: Extensions,
+    /// where `print` writes to
+    output: Box<dyn Sink>,
+    /// where `eprint()` writes to
+    errors: Box<dyn Sink>,
+}
+
+/// A call of a Lox function that has not returned yet.
+#[derive(Clone, Debug)]
+pub struct CallFrame {
+    pub function: String,
+    /// location of the call expression
+    pub line: usize,
+    pub position: usize,
 }
 
 #[derive(Debug)]
 pub enum LoxResult {
     Return(LoxValue),
+    Break,
+    Continue,
+    /// a value thrown by `throw` at `line:position`, unwinding until a
+    /// `try` statement catches it
+    Thrown {
+        value: LoxValue,
+        line: usize,
+        position: usize,
+    },
     None,
 }
 
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
 impl Interpreter {
     pub fn new() -> Self {
+        Interpreter::with_edition(Edition::default())
+    }
+
+    /// An interpreter parsing imported modules in `edition`.
+    pub fn with_edition(edition: Edition) -> Self {
         let mut interpreter = Interpreter {
             line: 0,
             position: 0,
+            end: 0,
             environment: Environment::new(),
+            arg_stack: Vec::new(),
+            call_stack: Vec::new(),
+            error_class: Rc::new(LoxClass {
+                name: "RuntimeError".to_owned(),
+                superclass: None,
+                methods: HashMap::new(),
+            }),
+            methods: HashMap::new(),
+            modules: ModuleLoader::new(edition),
+            eval_identifiers: EVAL_IDENTIFIERS,
+            started: Instant::now(),
+            random: Random::from_time(),
+            capabilities: Vec::new(),
+            extensions: Extensions::default(),
+            output: Box::new(io::stdout()),
+            errors: Box::new(io::stderr()),
         };
 
         interpreter.init();
 
-        return interpreter;
+        interpreter
     }
 
     fn init(&mut self) {
-        let native_identifier = Identifier {
-            name: "toString".to_owned(),
-            id: 0,
-            debug_info: DebugInfo {
-                line: 0,
-                position: 0,
-                lexeme: "<native identifier>".to_owned(),
-            },
-        };
-
-        fn to_string(_env: &mut Interpreter, args: Box<[LoxValue]>) -> Result<LoxValue, Error> {
-            let value = args.get(0).unwrap();
+        fn to_string(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let value = ctx.arg(0);
 
             let str = LoxValue::to_string(value);
 
             Ok(LoxValue::String(str))
         }
 
-        let fun = ForeinFun::new("toString".to_owned(), 1, to_string);
-        self.environment
-            .define(&native_identifier, LoxValue::ForeinFun(fun.into()))
-            .expect("Failed to initialize function toString");
+        fn repr(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            Ok(LoxValue::String(ctx.arg(0).repr()))
+        }
+
+        fn globals(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let globals = ctx
+                .interpreter
+                .environment
+                .globals()
+                .into_iter()
+                .map(|(name, value)| (LoxKey::String(name), value))
+                .collect();
+            Ok(LoxValue::Map(Rc::new(RefCell::new(globals))))
+        }
+
+        fn locals(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let locals = ctx
+                .interpreter
+                .environment
+                .locals()
+                .into_iter()
+                .map(|(name, value)| (LoxKey::String(name), value))
+                .collect();
+            Ok(LoxValue::Map(Rc::new(RefCell::new(locals))))
+        }
+
+        fn stack_trace(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let frames = ctx
+                .interpreter
+                .call_stack
+                .iter()
+                .rev()
+                .map(|frame| {
+                    let frame: LoxMap = [
+                        ("function", LoxValue::String(frame.function.clone())),
+                        ("line", LoxValue::Number(frame.line as f64)),
+                        ("position", LoxValue::Number(frame.position as f64)),
+                    ]
+                    .into_iter()
+                    .map(|(key, value)| (LoxKey::String(key.to_owned()), value))
+                    .collect();
+                    LoxValue::Map(Rc::new(RefCell::new(frame)))
+                })
+                .collect();
+            Ok(LoxValue::List(Rc::new(RefCell::new(frames))))
+        }
+
+        /// Variadic functions report the least number of arguments they accept.
+        fn arity(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let arity = ctx.arg_callable(0)?.arity().expect("callable");
+            Ok(LoxValue::Number(arity.min as f64))
+        }
+
+        /// Seconds since the Unix epoch, with a fractional part.
+        fn clock(_ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            Ok(LoxValue::Number(now.as_secs_f64()))
+        }
+
+        /// Whole seconds since the Unix epoch.
+        fn now(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            clock(ctx).map(|now| match now {
+                LoxValue::Number(seconds) => LoxValue::Number(seconds.floor()),
+                _ => unreachable!("clock() returns a number"),
+            })
+        }
+
+        /// Writes a time given in seconds since the Unix epoch, in UTC.
+        fn format_time(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let epoch = ctx.arg_number(0)?.floor() as i64;
+            datetime::format(epoch, ctx.arg_string(1)?)
+                .map(LoxValue::String)
+                .map_err(|message| ctx.error(message))
+        }
+
+        fn parse_time(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            datetime::parse(ctx.arg_string(0)?, ctx.arg_string(1)?)
+                .map(|epoch| LoxValue::Number(epoch as f64))
+                .map_err(|message| ctx.error(message))
+        }
+
+        /// Body of the response to a GET request, responses other than
+        /// 2xx are runtime errors.
+        #[cfg(feature = "http")]
+        fn http_get(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let url = ctx.arg_string(0)?;
+            let failed = |error: &dyn std::fmt::Display| {
+                ctx.error(message!(HttpRequestFailed, url = url, error = error))
+            };
+            match ureq::get(url).call() {
+                Ok(response) => response
+                    .into_string()
+                    .map(LoxValue::String)
+                    .map_err(|error| failed(&error)),
+                Err(ureq::Error::Status(status, _)) => {
+                    Err(ctx.error(message!(HttpStatus, url = url, status = status)))
+                }
+                Err(error) => Err(failed(&error)),
+            }
+        }
+
+        /// Milliseconds since the interpreter started, never going back
+        /// when the system clock is adjusted.
+        fn monotonic_millis(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let elapsed = ctx.interpreter.started.elapsed();
+            Ok(LoxValue::Number(elapsed.as_secs_f64() * 1000.))
+        }
+
+        fn random(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            Ok(LoxValue::Number(ctx.interpreter.random.next_f64()))
+        }
+
+        /// An integer between the bounds, both included.
+        fn random_int(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let (low, high) = (ctx.arg_integer(0)?, ctx.arg_integer(1)?);
+            if low > high {
+                return Err(ctx.error(message!(EmptyRandomRange, low = low, high = high)));
+            }
+            let n = ctx.interpreter.random.next_in(low, high);
+            Ok(LoxValue::Number(n as f64))
+        }
+
+        fn seed_random(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let seed = ctx.arg_integer(0)?;
+            ctx.interpreter.random = Random::new(seed as u64);
+            Ok(LoxValue::Nil)
+        }
+
+        fn type_of(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            Ok(LoxValue::String(ctx.arg(0).type_name().to_owned()))
+        }
+
+        fn name_of(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let name = ctx.arg_callable(0)?.callable_name().unwrap_or_default();
+            Ok(LoxValue::String(name.to_owned()))
+        }
+
+        /// Errors of the evaluated code are reported at the call.
+        fn eval(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let source = ctx.arg_string(0)?.to_owned();
+            ctx.interpreter
+                .eval(&source)
+                .map_err(|error| match error.into_diagnostic() {
+                    Ok(diagnostic) => ctx.error(message!(
+                        ErrorInEval,
+                        line = diagnostic.span.line,
+                        position = diagnostic.span.position,
+                        message = diagnostic.message
+                    )),
+                    Err(Error::InternalRuntimeError { message }) => ctx.error(message),
+                    Err(error) => error,
+                })
+        }
+
+        /// Writes the value to the standard error, the way `print` writes
+        /// to the standard output.
+        fn eprint(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            LoxValue::print(&ctx.args[0], &mut ctx.interpreter.errors)
+                .map_err(|error| ctx.error(message!(CannotWriteOutput, error = error)))?;
+            Ok(LoxValue::Nil)
+        }
+
+        /// `format("x={}, y={:.2}", x, y)`, see [`format`] for placeholders.
+        fn format(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            format::format(ctx.arg_string(0)?, &ctx.args[1..])
+                .map(LoxValue::String)
+                .map_err(|message| ctx.error(message))
+        }
+
+        /// Fails with the message, or a generic one, when the condition
+        /// is falsy. The error can be caught like any runtime error.
+        fn assert(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            if LoxValue::is_truthy(ctx.arg(0)) {
+                return Ok(LoxValue::Nil);
+            }
+            Err(ctx.error(match ctx.args.get(1) {
+                Some(message) => message!(AssertionFailedWith, message = message),
+                None => message!(AssertionFailed),
+            }))
+        }
+
+        fn panic(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            Err(ctx.error(message!(Panic, message = ctx.arg(0))))
+        }
+
+        /// Ends the program with the status `code`, 0 by default.
+        fn exit(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let code = match ctx.args.is_empty() {
+                true => 0,
+                false => ctx.arg_integer(0)?,
+            };
+            match u8::try_from(code) {
+                Ok(code) => Err(Error::Exit { code }),
+                Err(_) => Err(ctx.error(message!(ExitCodeOutOfRange, code = code))),
+            }
+        }
+
+        fn bind(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let target = ctx.arg_callable(0)?.clone();
+            Ok(LoxValue::Bound(Rc::new(BoundFun {
+                target,
+                args: ctx.args[1..].into(),
+            })))
+        }
+
+        fn len(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let len = match ctx.arg(0) {
+                LoxValue::List(list) => list.borrow().len(),
+                LoxValue::String(s) => s.chars().count(),
+                LoxValue::Map(map) => map.borrow().len(),
+                _ => return Err(ctx.argument_error(0, "list, map or string")),
+            };
+            Ok(LoxValue::Number(len as f64))
+        }
+
+        fn keys(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let keys = ctx
+                .arg_map(0)?
+                .borrow()
+                .iter()
+                .map(|(key, _)| key.clone().into())
+                .collect();
+            Ok(LoxValue::List(Rc::new(RefCell::new(keys))))
+        }
+
+        fn values(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let values = ctx
+                .arg_map(0)?
+                .borrow()
+                .iter()
+                .map(|(_, value)| value.clone())
+                .collect();
+            Ok(LoxValue::List(Rc::new(RefCell::new(values))))
+        }
+
+        fn has(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let key = ctx.arg_key(1)?;
+            Ok(LoxValue::Bool(ctx.arg_map(0)?.borrow().contains_key(&key)))
+        }
+
+        /// Returns the removed value, `nil` if the key was not present.
+        fn remove(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let key = ctx.arg_key(1)?;
+            let removed = ctx.arg_map(0)?.borrow_mut().remove(&key);
+            Ok(removed.unwrap_or(LoxValue::Nil))
+        }
+
+        /// `range(end)`, `range(start, end)` or `range(start, end, step)`.
+        fn range(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let numbers = (0..ctx.args.len())
+                .map(|i| ctx.arg_number(i))
+                .collect::<Result<Vec<_>, _>>()?;
+            let (start, end, step) = match numbers[..] {
+                [end] => (0., end, 1.),
+                [start, end] => (start, end, 1.),
+                [start, end, step] => (start, end, step),
+                _ => unreachable!("arity is checked by the caller"),
+            };
+            LoxRange::new(start, end, step)
+                .map(LoxValue::Range)
+                .ok_or_else(|| ctx.error(message!(ZeroRangeStep)))
+        }
+
+        let natives = NativeModule::global()
+            .function("toString", 1, to_string)
+            .function("repr", 1, repr)
+            .function("globals", 0, globals)
+            .function("locals", 0, locals)
+            .function("stackTrace", 0, stack_trace)
+            .function("arity", 1, arity)
+            .function("nameOf", 1, name_of)
+            .function("name", 1, name_of)
+            .function("type", 1, type_of)
+            .function("clock", 0, clock)
+            .function("monotonicMillis", 0, monotonic_millis)
+            .function("now", 0, now)
+            .function("formatTime", 2, format_time)
+            .function("parseTime", 2, parse_time)
+            .function("random", 0, random)
+            .function("randomInt", 2, random_int)
+            .function("seedRandom", 1, seed_random)
+            .function(
+                "assert",
+                Arity {
+                    min: 1,
+                    max: Some(2),
+                },
+                assert,
+            )
+            .function("panic", 1, panic)
+            .function("format", Arity::at_least(1), format)
+            .function("eprint", 1, eprint)
+            .function(
+                "exit",
+                Arity {
+                    min: 0,
+                    max: Some(1),
+                },
+                exit,
+            )
+            .function("bind", Arity::at_least(1), bind)
+            .function("eval", 1, eval)
+            .function("len", 1, len)
+            .function("keys", 1, keys)
+            .function("values", 1, values)
+            .function("has", 2, has)
+            .function("remove", 2, remove)
+            .function(
+                "range",
+                Arity {
+                    min: 1,
+                    max: Some(3),
+                },
+                range,
+            );
+        #[cfg(feature = "http")]
+        let natives = natives.function("httpGet", 1, http_get);
+        self.install(natives)
+            .expect("built-in natives have unique names");
+
+        self.install(
+            NativeModule::namespace("math")
+                .typed("sqrt", f64::sqrt)
+                .typed("pow", f64::powf)
+                .typed("abs", f64::abs)
+                .typed("floor", f64::floor)
+                .typed("ceil", f64::ceil)
+                // halves are rounded away from zero
+                .typed("round", f64::round)
+                .typed("min", f64::min)
+                .typed("max", f64::max),
+        )
+        .expect("built-in natives have unique names");
+
+        fn string<'a>(ctx: &'a NativeContext) -> &'a str {
+            match ctx.arg(0) {
+                LoxValue::String(s) => s,
+                _ => unreachable!("string methods are only looked up on strings"),
+            }
+        }
+
+        fn string_length(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            Ok(LoxValue::Number(string(ctx).chars().count() as f64))
+        }
+
+        fn string_upper(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            Ok(LoxValue::String(string(ctx).to_uppercase()))
+        }
+
+        fn string_lower(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            Ok(LoxValue::String(string(ctx).to_lowercase()))
+        }
+
+        fn string_trim(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            Ok(LoxValue::String(string(ctx).trim().to_owned()))
+        }
+
+        fn string_contains(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let needle = ctx.arg_string(1)?;
+            Ok(LoxValue::Bool(string(ctx).contains(needle)))
+        }
+
+        /// An empty separator splits the string into characters.
+        fn string_split(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let separator = ctx.arg_string(1)?;
+            let s = string(ctx);
+            let parts: Vec<LoxValue> = match separator {
+                "" => s.chars().map(|c| LoxValue::String(c.to_string())).collect(),
+                separator => s
+                    .split(separator)
+                    .map(|part| LoxValue::String(part.to_owned()))
+                    .collect(),
+            };
+            Ok(LoxValue::List(Rc::new(RefCell::new(parts))))
+        }
+
+        fn number_abs(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            Ok(LoxValue::Number(ctx.arg_number(0)?.abs()))
+        }
+
+        fn number_floor(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            Ok(LoxValue::Number(ctx.arg_number(0)?.floor()))
+        }
+
+        fn number_ceil(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            Ok(LoxValue::Number(ctx.arg_number(0)?.ceil()))
+        }
+
+        fn number_round(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            Ok(LoxValue::Number(ctx.arg_number(0)?.round()))
+        }
+
+        fn list(ctx: &NativeContext) -> Rc<RefCell<Vec<LoxValue>>> {
+            match ctx.arg(0) {
+                LoxValue::List(list) => list.clone(),
+                _ => unreachable!("list methods are only looked up on lists"),
+            }
+        }
+
+        fn list_length(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            Ok(LoxValue::Number(list(ctx).borrow().len() as f64))
+        }
+
+        fn list_push(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            list(ctx).borrow_mut().push(ctx.arg(1).clone());
+            Ok(LoxValue::Nil)
+        }
+
+        /// Returns the removed element, `nil` if the list was empty.
+        fn list_pop(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            Ok(list(ctx).borrow_mut().pop().unwrap_or(LoxValue::Nil))
+        }
+
+        fn list_contains(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let found = list(ctx).borrow().contains(ctx.arg(1));
+            Ok(LoxValue::Bool(found))
+        }
+
+        fn list_join(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let separator = ctx.arg_string(1)?;
+            let joined = list(ctx)
+                .borrow()
+                .iter()
+                .map(LoxValue::to_string)
+                .collect::<Vec<_>>()
+                .join(separator);
+            Ok(LoxValue::String(joined))
+        }
+
+        self.define_method("string", "length", 0, string_length);
+        self.define_method("string", "upper", 0, string_upper);
+        self.define_method("string", "lower", 0, string_lower);
+        self.define_method("string", "trim", 0, string_trim);
+        self.define_method("string", "contains", 1, string_contains);
+        self.define_method("string", "split", 1, string_split);
+        self.define_method("number", "abs", 0, number_abs);
+        self.define_method("number", "floor", 0, number_floor);
+        self.define_method("number", "ceil", 0, number_ceil);
+        self.define_method("number", "round", 0, number_round);
+        self.define_method("list", "length", 0, list_length);
+        self.define_method("list", "push", 1, list_push);
+        self.define_method("list", "pop", 0, list_pop);
+        self.define_method("list", "contains", 1, list_contains);
+        self.define_method("list", "join", 1, list_join);
+    }
+
+    /// Registers a method of the built-in type named `kind`.
+    fn define_method(
+        &mut self,
+        kind: &'static str,
+        name: &str,
+        arity: impl Into<Arity>,
+        fun: impl Fn(&mut NativeContext) -> Result<LoxValue, Error> + Shareable + 'static,
+    ) {
+        let fun = ForeinFun::method(name.to_owned(), arity, fun);
+        self.methods
+            .entry(kind)
+            .or_default()
+            .insert(name.to_owned(), Rc::new(fun));
+    }
+
+    /// Defines the natives of `capability`, in every module. Capabilities
+    /// have to be granted before any code is executed.
+    pub fn allow(&mut self, capability: Capability) {
+        if self.capabilities.contains(&capability) {
+            return;
+        }
+        self.capabilities.push(capability);
+
+        /// `nil` for a variable that is not set.
+        fn get_env(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let name = ctx.arg_string(0)?;
+            Ok(std::env::var(name).map_or(LoxValue::Nil, LoxValue::String))
+        }
+
+        /// Setting a variable to `nil` removes it.
+        fn set_env(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let name = ctx.arg_string(0)?;
+            if name.is_empty() || name.contains(['=', '\0']) {
+                return Err(ctx.error(message!(InvalidEnvName, name = name)));
+            }
+            match ctx.arg(1) {
+                LoxValue::Nil => std::env::remove_var(name),
+                LoxValue::String(value) if !value.contains('\0') => std::env::set_var(name, value),
+                LoxValue::String(_) => {
+                    return Err(ctx.error(message!(InvalidEnvValue, name = name)))
+                }
+                _ => return Err(ctx.argument_error(1, "string")),
+            }
+            Ok(LoxValue::Nil)
+        }
+
+        /// The operating system, e.g. `linux`, `macos` or `windows`.
+        fn platform(_ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            Ok(LoxValue::String(std::env::consts::OS.to_owned()))
+        }
+
+        /// Runs the program `command` with the arguments in the list and
+        /// waits for it to finish. Evaluates to a map of its exit `status`,
+        /// `nil` when it was killed by a signal, and its `stdout` and
+        /// `stderr` output.
+        fn exec(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            let command = ctx.arg_string(0)?;
+            let mut args = Vec::new();
+            if ctx.args.len() > 1 {
+                for arg in ctx.arg_list(1)?.borrow().iter() {
+                    match arg {
+                        LoxValue::String(arg) => args.push(arg.clone()),
+                        _ => return Err(ctx.argument_error(1, "list of strings")),
+                    }
+                }
+            }
+
+            let output = std::process::Command::new(command)
+                .args(args)
+                .output()
+                .map_err(|error| {
+                    ctx.error(message!(CannotRunCommand, command = command, error = error))
+                })?;
+
+            let mut result = LoxMap::new();
+            let status = output
+                .status
+                .code()
+                .map_or(LoxValue::Nil, |code| LoxValue::Number(f64::from(code)));
+            for (key, value) in [
+                ("status", status),
+                (
+                    "stdout",
+                    LoxValue::String(String::from_utf8_lossy(&output.stdout).into_owned()),
+                ),
+                (
+                    "stderr",
+                    LoxValue::String(String::from_utf8_lossy(&output.stderr).into_owned()),
+                ),
+            ] {
+                result.insert(LoxKey::String(key.to_owned()), value);
+            }
+            Ok(LoxValue::Map(Rc::new(RefCell::new(result))))
+        }
+
+        self.install(match capability {
+            Capability::Env => NativeModule::global()
+                .function("getEnv", 1, get_env)
+                .function("setEnv", 2, set_env)
+                .function("platform", 0, platform),
+            Capability::Process => NativeModule::global().function(
+                "exec",
+                Arity {
+                    min: 1,
+                    max: Some(2),
+                },
+                exec,
+            ),
+        })
+        .expect("capabilities define distinct natives");
+    }
+
+    /// Installs the natives of the shared library at `path`, see
+    /// [`extension`](crate::extension) for the interface it implements.
+    pub fn load_extension(&mut self, path: &Path) -> Result<(), Error> {
+        let (library, register) = Extensions::open(path).map_err(|message| self.error(message))?;
+        self.register_extension(register)?;
+        self.extensions.keep(library);
+        Ok(())
+    }
+
+    /// Installs the natives defined by the entry point of an extension.
+    fn register_extension(&mut self, register: RloxRegister) -> Result<(), Error> {
+        let natives = Extensions::register(register).map_err(|message| self.error(message))?;
+        let module =
+            natives
+                .into_iter()
+                .fold(NativeModule::global(), |module, (name, arity, native)| {
+                    module.function(&name, arity, move |ctx| {
+                        extension::call(native, ctx.name, ctx.args)
+                            .map_err(|message| ctx.error(message))
+                    })
+                });
+        self.install(module)
+    }
+
+    /// Defines the native `name` as a global, in every module. Unlike
+    /// other natives it can be a closure, keeping state of the host.
+    pub fn register_native(
+        &mut self,
+        name: &str,
+        arity: impl Into<Arity>,
+        fun: impl Fn(&mut NativeContext) -> Result<LoxValue, Error> + Shareable + 'static,
+    ) -> Result<(), Error> {
+        self.install(NativeModule::global().function(name, arity, fun))
+    }
+
+    /// Defines the natives of `module` in every module. Like capabilities,
+    /// native modules have to be installed before any code is executed.
+    /// Nothing is defined if any of the names is taken already.
+    pub fn install(&mut self, module: NativeModule) -> Result<(), Error> {
+        let globals = module.into_globals();
+        for (i, (name, _)) in globals.iter().enumerate() {
+            let taken = self.environment.get_global(name).is_some()
+                || globals[..i].iter().any(|(other, _)| other == name);
+            if taken {
+                return Err(self.error(message!(NativeAlreadyDefined, name = name)));
+            }
+        }
+
+        for (name, value) in globals {
+            self.environment.define_builtin(&name, value);
+        }
+        self.environment.end_prelude();
+        Ok(())
+    }
+
+    /// Sends the output of `print` to `output` instead of the standard
+    /// output.
+    pub fn set_output(&mut self, output: impl Sink + 'static) {
+        self.output = Box::new(output);
+    }
+
+    /// Sends the output of `eprint()` to `errors` instead of the standard
+    /// error.
+    pub fn set_error_output(&mut self, errors: impl Sink + 'static) {
+        self.errors = Box::new(errors);
+    }
+
+    /// Makes imports relative to the directory of the script at `path`.
+    pub fn set_script(&mut self, path: &Path) {
+        self.modules
+            .set_base_dir(path.parent().unwrap_or(Path::new("")));
+    }
+
+    /// Directories searched for imports not found next to the importer.
+    pub fn set_source_roots(&mut self, roots: Vec<PathBuf>) {
+        self.modules.roots = roots;
     }
 
-    fn set_debug(self: &mut Self, debug: &DebugInfo) {
+    fn set_debug(&mut self, debug: &DebugInfo) {
         self.line = debug.line;
         self.position = debug.position;
+        self.end = debug.span().end;
     }
 
     pub fn execute(
         &mut self,
-        statements: &Vec<Statement>,
+        statements: &[Statement],
         access_table: AccessTable,
     ) -> Result<LoxResult, Error> {
         self.environment
             .extend_access_table(access_table)
-            .map_err(|_| self.error("Error while updating access_table"))?;
+            .map_err(|_| self.error(message!(AccessTableConflict)))?;
+
+        match self.run(statements)? {
+            LoxResult::Thrown {
+                value,
+                line,
+                position,
+            } => Err(self.uncaught(value, line, position)),
+            result => Ok(result),
+        }
+    }
+
+    /// Runs a line of the REPL like `execute`, evaluating to the value of
+    /// its last statement if it is an expression, so that it can be shown.
+    pub fn execute_line(
+        &mut self,
+        statements: &[Statement],
+        access_table: AccessTable,
+    ) -> Result<Option<LoxValue>, Error> {
+        let (statements, last) = match statements.split_last() {
+            Some((Statement::Expression(expression), statements)) => (statements, Some(expression)),
+            _ => (statements, None),
+        };
+        match (self.execute(statements, access_table)?, last) {
+            (LoxResult::None, Some(expression)) => match self.visit_expression(expression) {
+                Err(Error::Thrown {
+                    value,
+                    line,
+                    position,
+                }) => Err(self.uncaught(value, line, position)),
+                value => value.map(Some),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// Resolves and runs a syntax tree built in code, see `ast_builder`.
+    pub fn execute_tree(&mut self, statements: &[Statement]) -> Result<LoxResult, Error> {
+        let statements = lower::program(statements.to_vec());
+        let access_table = resolver::resolve(&statements)?;
+        self.execute(&statements, access_table)
+    }
+
+    /// Calls the global `name` of the current module with `args`, e.g. a
+    /// callback defined by a script. A value thrown out of the function
+    /// is reported like an uncaught exception.
+    pub fn call_function(&mut self, name: &str, args: &[LoxValue]) -> Result<LoxValue, Error> {
+        // the call has no place in the source
+        let call = DebugInfo::default();
+        self.set_debug(&call);
+        let Some(function) = self.environment.get_global(&name.to_owned()) else {
+            return Err(self.error(message!(UndefinedVariable, name = name)));
+        };
+
+        let base = self.arg_stack.len();
+        self.arg_stack.extend_from_slice(args);
+        let result = self.call_value(&function, base, &call);
+        self.arg_stack.truncate(base);
+        match result {
+            Err(Error::Thrown {
+                value,
+                line,
+                position,
+            }) => Err(self.uncaught(value, line, position)),
+            result => result,
+        }
+    }
+
+    /// The value of the global `name` of the current module, e.g. a result
+    /// left by a script after `execute`.
+    pub fn get_global_value(&self, name: &str) -> Option<LoxValue> {
+        self.environment.get_global(&name.to_owned())
+    }
+
+    /// Sets the global `name` of the current module to `value`, defining
+    /// it if the module has no such global. Meant for handing
+    /// configuration to a script before it is executed, constants can not
+    /// be set.
+    pub fn set_global(&mut self, name: &str, value: impl IntoLox) -> Result<(), Error> {
+        self.set_debug(&DebugInfo::default());
+        self.environment
+            .set_global(name, value.into_lox())
+            .map_err(|error| match error {
+                Error::InternalRuntimeError { message } => self.error(message),
+                error => error,
+            })
+    }
+
+    /// Runs `source` at the top level of the current module, wherever it
+    /// is called from. Evaluates to the value of the last statement if it
+    /// is an expression, or of a `return` ending the code, `nil` otherwise.
+    pub fn eval(&mut self, source: &str) -> Result<LoxValue, Error> {
+        let tokens = scanner::scan_tokens(source)?;
+        let mut parser = Parser::with_first_identifier(self.modules.edition, self.eval_identifiers);
+        let program = parser.parse(tokens);
+        self.eval_identifiers = parser.identifier_counter();
+        let program = program?;
+        let access_table = resolver::resolve(&program)?;
+        self.environment
+            .extend_access_table(access_table)
+            .expect("evaluated code is numbered apart from the rest");
 
-        self.run(statements)
+        let (statements, last) = match program.split_last() {
+            Some((Statement::Expression(expression), statements)) => (statements, Some(expression)),
+            _ => (&program[..], None),
+        };
+        self.environment.push_global();
+        let value = match self.run(statements) {
+            Ok(LoxResult::Thrown {
+                value,
+                line,
+                position,
+            }) => Err(Error::Thrown {
+                value,
+                line,
+                position,
+            }),
+            Ok(LoxResult::Return(value)) => Ok(value),
+            Ok(_) => match last {
+                Some(expression) => self.visit_expression(expression),
+                None => Ok(LoxValue::Nil),
+            },
+            Err(error) => Err(error),
+        };
+        self.environment.pop_closure();
+        value
+    }
+
+    /// Wraps a runtime error into an object, so that it can be handled
+    /// like any other thrown value.
+    fn error_object(&self, message: &Message, line: usize, position: usize) -> LoxValue {
+        let mut object = LoxObject::new(self.error_class.clone());
+        object.set("message", LoxValue::String(message.text.clone()));
+        let code = message.code().map(|code| LoxValue::String(code.to_owned()));
+        object.set("code", code.unwrap_or(LoxValue::Nil));
+        object.set("line", LoxValue::Number(line as f64));
+        object.set("position", LoxValue::Number(position as f64));
+        LoxValue::Object(Rc::new(RefCell::new(object)))
+    }
+
+    /// Error ending the program when nothing caught `value`. A rethrown
+    /// runtime error is reported the way it would have been originally.
+    fn uncaught(&self, value: LoxValue, line: usize, position: usize) -> Error {
+        if let LoxValue::Object(object) = &value {
+            let object = object.borrow();
+            if Rc::ptr_eq(&object.class, &self.error_class) {
+                if let (
+                    Some(LoxValue::String(message)),
+                    Some(LoxValue::Number(line)),
+                    Some(LoxValue::Number(position)),
+                ) = (
+                    object.get("message"),
+                    object.get("line"),
+                    object.get("position"),
+                ) {
+                    let id = match object.get("code") {
+                        Some(LoxValue::String(code)) => MessageId::of_code(&code),
+                        _ => None,
+                    };
+                    return Error::RuntimeError {
+                        line: line as usize,
+                        position: position as usize,
+                        end: position as usize + 1,
+                        message: Message { id, text: message },
+                        trace: Vec::new(),
+                    };
+                }
+            }
+        }
+
+        Error::RuntimeError {
+            line,
+            position,
+            end: position + 1,
+            message: message!(UncaughtException, value = value),
+            trace: Vec::new(),
+        }
     }
 
-    fn run(self: &mut Self, statements: &Vec<Statement>) -> Result<LoxResult, Error> {
+    fn run(&mut self, statements: &[Statement]) -> Result<LoxResult, Error> {
         for stmt in statements {
-            let result = self.visit_statement(stmt)?;
-            if let LoxResult::Return(_) = result {
+            // values thrown inside of a function call unwind as errors
+            // out of the expression, from here they become a signal again
+            let result = match self.visit_statement(stmt) {
+                Err(Error::Thrown {
+                    value,
+                    line,
+                    position,
+                }) => LoxResult::Thrown {
+                    value,
+                    line,
+                    position,
+                },
+                result => result?,
+            };
+            if !matches!(result, LoxResult::None) {
                 return Ok(result);
             }
         }
@@ -98,31 +1021,36 @@ impl Interpreter {
 
     fn visit_statement(&mut self, statement: &Statement) -> Result<LoxResult, Error> {
         match statement {
-            Statement::Nop => {}
             Statement::Expression(expr) => {
                 self.visit_expression(expr)?;
             }
             Statement::Print(expr) => {
                 let value = self.visit_expression(expr)?;
-                LoxValue::print(&value);
+                LoxValue::print(&value, &mut self.output)
+                    .map_err(|error| self.error(message!(CannotWriteOutput, error = error)))?;
             }
             Statement::Variable {
                 name,
                 initializer: Some(initializer),
+                constant,
             } => {
                 let value = self.visit_expression(initializer)?;
-                self.environment.define(name, value.clone())?;
+                match constant {
+                    true => self.environment.define_constant(name, value)?,
+                    false => self.environment.define(name, value)?,
+                }
             }
             Statement::Variable {
                 name,
                 initializer: None,
+                ..
             } => {
                 self.environment.define(name, LoxValue::Nil)?;
             }
             Statement::Block(block) => {
                 let result = self.run_block(block)?;
 
-                if let LoxResult::Return(_) = result {
+                if !matches!(result, LoxResult::None) {
                     return Ok(result);
                 }
             }
@@ -132,72 +1060,425 @@ impl Interpreter {
                 else_branch,
             } => {
                 let result = if LoxValue::is_truthy(&self.visit_expression(condition)?) {
-                    self.run_block(&then_branch)?
+                    self.run_block(then_branch)?
                 } else {
                     if let Some(else_branch) = else_branch {
-                        self.run_block(&else_branch)?
+                        self.run_block(else_branch)?
                     } else {
                         LoxResult::None
                     }
                 };
 
-                if let LoxResult::Return(_) = result {
+                if !matches!(result, LoxResult::None) {
                     return Ok(result);
                 }
             }
-            Statement::While { condition, body } => {
+            Statement::While {
+                condition,
+                body,
+                increment,
+            } => {
                 while LoxValue::is_truthy(&self.visit_expression(condition)?) {
-                    let result = self.run_block(body)?;
+                    match self.run_block(body)? {
+                        LoxResult::Break => break,
+                        result @ (LoxResult::Return(_) | LoxResult::Thrown { .. }) => {
+                            return Ok(result)
+                        }
+                        LoxResult::Continue | LoxResult::None => {}
+                    }
+                    if let Some(increment) = increment {
+                        self.visit_expression(increment)?;
+                    }
+                }
+            }
+            Statement::For { .. } => unreachable!("`for` loops are lowered before resolution"),
+            Statement::ForIn {
+                variable,
+                iterable,
+                body,
+                debug_info,
+            } => {
+                let iterable = self.visit_expression(iterable)?;
+                self.set_debug(debug_info);
+                for value in self.iterate(&iterable)? {
+                    self.environment.push();
+                    let result = self
+                        .environment
+                        .define(variable, value)
+                        .and_then(|_| self.run_block(body));
+                    self.environment.pop();
+                    match result? {
+                        LoxResult::Break => break,
+                        result @ (LoxResult::Return(_) | LoxResult::Thrown { .. }) => {
+                            return Ok(result)
+                        }
+                        LoxResult::Continue | LoxResult::None => {}
+                    }
+                }
+            }
+            Statement::Match {
+                value,
+                arms,
+                default,
+            } => {
+                let value = self.visit_expression(value)?;
+                let arm = arms
+                    .iter()
+                    .find(|arm| {
+                        arm.patterns
+                            .iter()
+                            .any(|pattern| self.visit_literal(pattern) == value)
+                    })
+                    .map(|arm| &arm.body)
+                    .or(default.as_ref());
 
-                    if let LoxResult::Return(_) = result {
+                if let Some(body) = arm {
+                    let result = self.run_block(body)?;
+                    if !matches!(result, LoxResult::None) {
                         return Ok(result);
                     }
                 }
             }
-            Statement::Function { name, args, body } => {
-                self.define_function(name, args, body)?;
+            Statement::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                let mut result = self.run_block(body);
+                if let Some(catch) = catch {
+                    // runtime errors are caught as well, as error objects
+                    let thrown = match &result {
+                        Ok(LoxResult::Thrown { value, .. }) => Some(value.clone()),
+                        Err(Error::RuntimeError {
+                            line,
+                            position,
+                            message,
+                            ..
+                        }) => Some(self.error_object(message, *line, *position)),
+                        _ => None,
+                    };
+                    if let Some(value) = thrown {
+                        self.environment.push();
+                        result = match &catch.variable {
+                            Some(variable) => self.environment.define(variable, value),
+                            None => Ok(()),
+                        }
+                        .and_then(|_| self.run_block(&catch.body));
+                        self.environment.pop();
+                    }
+                }
+
+                // runs even when the try or catch block failed, a finally
+                // block ending in return, break, continue or throw replaces
+                // their outcome
+                if let Some(finally) = finally {
+                    let finally = self.run_block(finally)?;
+                    if !matches!(finally, LoxResult::None) {
+                        return Ok(finally);
+                    }
+                }
+
+                let result = result?;
+                if !matches!(result, LoxResult::None) {
+                    return Ok(result);
+                }
+            }
+            Statement::Throw { value, debug_info } => {
+                let value = self.visit_expression(value)?;
+                return Ok(LoxResult::Thrown {
+                    value,
+                    line: debug_info.line,
+                    position: debug_info.position,
+                });
+            }
+            Statement::Import {
+                path,
+                names,
+                debug_info,
+            } => {
+                let module = self.import(path, debug_info)?;
+                let exports = self.environment.exports(&module);
+
+                if names.is_empty() {
+                    for (name, value) in exports {
+                        let identifier = Identifier {
+                            name,
+                            id: 0,
+                            debug_info: debug_info.clone(),
+                        };
+                        self.environment.import(&identifier, &module, value)?;
+                    }
+                } else {
+                    for name in names {
+                        let Some((_, value)) =
+                            exports.iter().find(|(export, _)| *export == name.name)
+                        else {
+                            self.set_debug(&name.debug_info);
+                            return Err(self.error(message!(
+                                NotExported,
+                                path = path,
+                                name = name.name
+                            )));
+                        };
+                        self.environment.import(name, &module, value.clone())?;
+                    }
+                }
+            }
+            Statement::Function {
+                name,
+                args,
+                body,
+                variadic,
+            } => {
+                self.define_function(name, args, body, *variadic)?;
+            }
+            Statement::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                self.define_class(name, superclass.as_ref(), methods)?;
             }
-            Statement::Return { value: Some(value) } => {
+            Statement::Return {
+                value: Some(value), ..
+            } => {
                 let value = self.visit_expression(value)?;
 
                 return Ok(LoxResult::Return(value));
             }
-            Statement::Return { value: None } => {
+            Statement::Return { value: None, .. } => {
                 return Ok(LoxResult::Return(LoxValue::Nil));
             }
+            Statement::Break { .. } => {
+                return Ok(LoxResult::Break);
+            }
+            Statement::Continue { .. } => {
+                return Ok(LoxResult::Continue);
+            }
+            // the resolver already refuses these
+            Statement::Error { debug_info, .. } => {
+                self.set_debug(debug_info);
+                return Err(self.error(message!(UnparsedStatement)));
+            }
         };
         Ok(LoxResult::None)
     }
 
-    pub fn run_block(&mut self, block: &Block) -> Result<LoxResult, Error> {
-        self.environment.push();
-        let result = self.run(&block.statements);
-        self.environment.pop();
-        result
+    /// Executes the module at `path` the first time it is imported,
+    /// returns the module holding its globals.
+    fn import(&mut self, path: &str, debug_info: &DebugInfo) -> Result<ModuleRef, Error> {
+        self.set_debug(debug_info);
+        let located = self
+            .modules
+            .locate(path)
+            .map_err(|error| self.error(message!(CannotImport, path = path, error = error)))?;
+        if let Some(module) = self.modules.cached(&located) {
+            return Ok(module);
+        }
+        if let Some(cycle) = self.modules.cycle(&located) {
+            return Err(self.error(message!(CircularImport, cycle = cycle)));
+        }
+
+        let result = self
+            .modules
+            .load(&located)
+            .and_then(|(program, access_table)| self.run_module(&located, &program, access_table));
+        self.modules.finish(&located, result.as_ref().ok().cloned());
+
+        self.set_debug(debug_info);
+        result.map_err(|error| self.module_error(path, error))
+    }
+
+    /// Runs the top level of a module in a scope of its own, imports
+    /// made by it are relative to its directory.
+    fn run_module(
+        &mut self,
+        path: &Path,
+        program: &[Statement],
+        access_table: AccessTable,
+    ) -> Result<ModuleRef, Error> {
+        let module = self.environment.new_module();
+        let importer = self.environment.enter_module(module.clone());
+        let base_dir = std::mem::replace(
+            &mut self.modules.base_dir,
+            path.parent().unwrap_or(Path::new("")).to_owned(),
+        );
+
+        self.environment
+            .extend_access_table(access_table)
+            .expect("new modules have an empty access table");
+        let result = self.run(program);
+
+        self.modules.base_dir = base_dir;
+        self.environment.enter_module(importer);
+
+        // imports are not allowed inside of `try`, nothing can catch
+        // a value thrown out of a module
+        match result? {
+            LoxResult::Thrown {
+                value,
+                line,
+                position,
+            } => Err(self.uncaught(value, line, position)),
+            _ => Ok(module),
+        }
+    }
+
+    /// Reports an error of an imported module at the `import` statement.
+    fn module_error(&self, path: &str, error: Error) -> Error {
+        match error.into_diagnostic() {
+            Ok(diagnostic) => self.error(message!(
+                ErrorInModule,
+                path = path,
+                line = diagnostic.span.line,
+                position = diagnostic.span.position,
+                message = diagnostic.message
+            )),
+            Err(Error::InternalRuntimeError { message }) => self.error(message),
+            Err(error) => error,
+        }
+    }
+
+    /// Values visited by a for-in loop over `iterable`: elements of a
+    /// list, keys of a map, characters of a string or numbers of a range.
+    /// Collections are copied first, so modifying them in the loop body
+    /// is safe, ranges produce their numbers lazily.
+    fn iterate(&self, iterable: &LoxValue) -> Result<Box<dyn Iterator<Item = LoxValue>>, Error> {
+        match iterable {
+            LoxValue::List(list) => Ok(Box::new(list.borrow().clone().into_iter())),
+            LoxValue::Map(map) => {
+                let keys: Vec<LoxValue> = map
+                    .borrow()
+                    .iter()
+                    .map(|(key, _)| key.clone().into())
+                    .collect();
+                Ok(Box::new(keys.into_iter()))
+            }
+            LoxValue::Range(range) => Ok(Box::new(range.iter().map(LoxValue::Number))),
+            LoxValue::String(s) => {
+                let chars: Vec<LoxValue> =
+                    s.chars().map(|c| LoxValue::String(c.to_string())).collect();
+                Ok(Box::new(chars.into_iter()))
+            }
+            value => Err(self.error(message!(NotIterable, kind = value.type_name()))),
+        }
+    }
+
+    pub fn run_block(&mut self, block: &Block) -> Result<LoxResult, Error> {
+        self.environment.push();
+        let result = self.run(&block.statements);
+        self.environment.pop();
+        result
     }
 
     pub fn define_function(
         &mut self,
         name: &Identifier,
-        args: &Vec<Identifier>,
+        args: &[Identifier],
         body: &Block,
+        variadic: bool,
     ) -> Result<(), Error> {
-        let frame = self.environment.get_current_frame();
-        let lox_function = LoxFun::new(
-            name.clone(),
-            frame,
-            args.clone().into_boxed_slice(),
-            body.clone(),
-        );
+        // the function may capture the variable holding it, so it is defined first
+        self.environment.define(name, LoxValue::Nil)?;
+        let lox_function = self.create_function(name, args, body, false, false, variadic)?;
         self.environment
-            .define(name, LoxValue::LoxFun(lox_function.into()))?;
-        Ok(())
+            .initialize(name, LoxValue::LoxFun(lox_function))
     }
 
-    pub fn visit_expression(self: &mut Self, expr: &Expression) -> Result<LoxValue, Error> {
+    fn create_function(
+        &mut self,
+        name: &Identifier,
+        args: &[Identifier],
+        body: &Block,
+        getter: bool,
+        initializer: bool,
+        variadic: bool,
+    ) -> Result<Rc<LoxFun>, Error> {
+        let captures = self.environment.capture(name)?;
+        let module = Rc::downgrade(&self.environment.module());
+        Ok(Rc::new_cyclic(|function| LoxFun {
+            getter,
+            initializer,
+            variadic,
+            ..LoxFun::new(
+                name.clone(),
+                captures.into_frame(function),
+                module,
+                args.into(),
+                Rc::new(body.clone()),
+            )
+        }))
+    }
+
+    /// Methods are created in a frame holding a placeholder `this`, which
+    /// they capture and which gets replaced whenever a method is bound.
+    /// Subclass methods also capture `super` from a frame around that one.
+    fn define_class(
+        &mut self,
+        name: &Identifier,
+        superclass: Option<&Identifier>,
+        methods: &[FunctionDeclaration],
+    ) -> Result<(), Error> {
+        let superclass = match superclass {
+            Some(identifier) => match self.visit_identifier(identifier)? {
+                LoxValue::Class(class) => Some(class),
+                _ => {
+                    self.set_debug(&identifier.debug_info);
+                    return Err(self.error(message!(SuperclassMustBeClass)));
+                }
+            },
+            None => None,
+        };
+        if let Some(superclass) = &superclass {
+            let keyword = Identifier {
+                name: "super".to_owned(),
+                ..name.clone()
+            };
+            self.environment.push();
+            self.environment
+                .define(&keyword, LoxValue::Class(superclass.clone()))?;
+        }
+
+        let this = Identifier {
+            name: "this".to_owned(),
+            ..name.clone()
+        };
+        self.environment.push();
+        let methods = self.environment.define(&this, LoxValue::Nil).and_then(|_| {
+            methods
+                .iter()
+                .map(|method| {
+                    let function = self.create_function(
+                        &method.name,
+                        &method.args,
+                        &method.body,
+                        method.getter,
+                        method.name.name == "init",
+                        method.variadic,
+                    )?;
+                    Ok((method.name.name.clone(), function))
+                })
+                .collect()
+        });
+        self.environment.pop();
+        if superclass.is_some() {
+            self.environment.pop();
+        }
+
+        let class = LoxClass {
+            name: name.name.clone(),
+            superclass,
+            methods: methods?,
+        };
+        self.environment
+            .define(name, LoxValue::Class(Rc::new(class)))
+    }
+
+    pub fn visit_expression(&mut self, expr: &Expression) -> Result<LoxValue, Error> {
         let result = match expr {
             Expression::Binary(binary) => self.visit_binary(binary),
             Expression::Grouping(grouping) => self.visit_grouping(grouping),
+            Expression::Interpolation(interpolation) => self.visit_interpolation(interpolation),
             Expression::Literal(literal) => Ok(self.visit_literal(&literal.value)),
             Expression::Unary(unary) => self.visit_unary(unary),
             Expression::Identifier(identifier) => self.visit_identifier(identifier),
@@ -205,252 +1486,765 @@ impl Interpreter {
                 self.visit_assignment(&assignment.target, &assignment.value)
             }
             Expression::Logical(logical) => self.visit_logical(logical),
-            Expression::Call(call) => self.visit_call(call),
+            Expression::Sequence(sequence) => self.visit_sequence(sequence),
+            Expression::Call(_)
+            | Expression::Get(_)
+            | Expression::Index(_)
+            | Expression::Slice(_) => self
+                .visit_chain(expr)
+                .map(|value| value.unwrap_or(LoxValue::Nil)),
+            Expression::Set(set) => self.visit_set(set),
+            Expression::Super(sup) => self.visit_super(sup),
+            Expression::List(list) => self.visit_list(list),
+            Expression::Map(map) => self.visit_map(map),
+            Expression::IndexSet(set) => self.visit_index_set(set),
         };
         match result {
             Ok(value) => Ok(value),
             Err(Error::InternalRuntimeError { message }) => Err(Error::RuntimeError {
                 line: self.line,
                 position: self.position,
+                end: self.end,
                 message,
+                trace: Vec::new(),
             }),
             Err(error) => Err(error),
         }
     }
 
-    fn visit_binary(self: &mut Self, binary: &Binary) -> Result<LoxValue, Error> {
+    fn visit_binary(&mut self, binary: &Binary) -> Result<LoxValue, Error> {
         let left = self.visit_expression(&binary.left)?;
         let right = self.visit_expression(&binary.right)?;
 
+        // instances on the left may overload the operator
+        if let (LoxValue::Object(_), Some((name, debug))) =
+            (&left, operator_method(&binary.operator))
+        {
+            self.set_debug(debug);
+            if let Some(result) =
+                self.call_operator(&left, name, std::slice::from_ref(&right), debug)?
+            {
+                return Ok(match binary.operator {
+                    BinaryOperator::Equal(_) => LoxValue::Bool(LoxValue::is_truthy(&result)),
+                    BinaryOperator::NotEqual(_) => LoxValue::Bool(!LoxValue::is_truthy(&result)),
+                    _ => result,
+                });
+            }
+        }
+
         match binary {
             Binary {
                 operator: BinaryOperator::Add(debug),
                 ..
             } => {
-                self.set_debug(&debug);
+                self.set_debug(debug);
                 LoxValue::add(left, right)
             }
             Binary {
                 operator: BinaryOperator::Subtract(debug),
                 ..
             } => {
-                self.set_debug(&debug);
+                self.set_debug(debug);
                 LoxValue::subtract(left, right)
             }
             Binary {
                 operator: BinaryOperator::Multiply(debug),
                 ..
             } => {
-                self.set_debug(&debug);
+                self.set_debug(debug);
                 LoxValue::multiply(left, right)
             }
             Binary {
                 operator: BinaryOperator::Divide(debug),
                 ..
             } => {
-                self.set_debug(&debug);
+                self.set_debug(debug);
                 LoxValue::divide(left, right)
             }
+            Binary {
+                operator: BinaryOperator::FloorDivide(debug),
+                ..
+            } => {
+                self.set_debug(debug);
+                LoxValue::floor_divide(left, right)
+            }
+            Binary {
+                operator: BinaryOperator::BitAnd(debug),
+                ..
+            } => {
+                self.set_debug(debug);
+                LoxValue::bit_and(left, right)
+            }
+            Binary {
+                operator: BinaryOperator::BitOr(debug),
+                ..
+            } => {
+                self.set_debug(debug);
+                LoxValue::bit_or(left, right)
+            }
+            Binary {
+                operator: BinaryOperator::BitXor(debug),
+                ..
+            } => {
+                self.set_debug(debug);
+                LoxValue::bit_xor(left, right)
+            }
+            Binary {
+                operator: BinaryOperator::ShiftLeft(debug),
+                ..
+            } => {
+                self.set_debug(debug);
+                LoxValue::shift_left(left, right)
+            }
+            Binary {
+                operator: BinaryOperator::ShiftRight(debug),
+                ..
+            } => {
+                self.set_debug(debug);
+                LoxValue::shift_right(left, right)
+            }
             Binary {
                 operator: BinaryOperator::Equal(debug),
                 ..
             } => {
-                self.set_debug(&debug);
+                self.set_debug(debug);
                 LoxValue::equal(left, right)
             }
             Binary {
                 operator: BinaryOperator::NotEqual(debug),
                 ..
             } => {
-                self.set_debug(&debug);
+                self.set_debug(debug);
                 LoxValue::not_equal(left, right)
             }
             Binary {
                 operator: BinaryOperator::Less(debug),
                 ..
             } => {
-                self.set_debug(&debug);
+                self.set_debug(debug);
                 LoxValue::less(left, right)
             }
             Binary {
                 operator: BinaryOperator::LessEqual(debug),
                 ..
             } => {
-                self.set_debug(&debug);
+                self.set_debug(debug);
                 LoxValue::less_equal(left, right)
             }
             Binary {
                 operator: BinaryOperator::Greater(debug),
                 ..
             } => {
-                self.set_debug(&debug);
+                self.set_debug(debug);
                 LoxValue::greater(left, right)
             }
             Binary {
                 operator: BinaryOperator::GreaterEqual(debug),
                 ..
             } => {
-                self.set_debug(&debug);
+                self.set_debug(debug);
                 LoxValue::greater_equal(left, right)
             }
         }
     }
 
-    fn visit_grouping(self: &mut Self, grouping: &Grouping) -> Result<LoxValue, Error> {
+    /// Calls the method `name` of an instance overloading an operator,
+    /// `None` if `object` is not an instance or its class lacks the method.
+    fn call_operator(
+        &mut self,
+        object: &LoxValue,
+        name: &str,
+        args: &[LoxValue],
+        debug_info: &DebugInfo,
+    ) -> Result<Option<LoxValue>, Error> {
+        let LoxValue::Object(instance) = object else {
+            return Ok(None);
+        };
+        let Some(method) = instance.borrow().class.find_method(name).cloned() else {
+            return Ok(None);
+        };
+
+        let method = LoxValue::LoxFun(Rc::new(method.bind(object.clone())));
+        let base = self.arg_stack.len();
+        self.arg_stack.extend_from_slice(args);
+        let result = self.call_value(&method, base, debug_info);
+        self.arg_stack.truncate(base);
+        result.map(Some)
+    }
+
+    fn visit_grouping(&mut self, grouping: &Grouping) -> Result<LoxValue, Error> {
         self.visit_expression(&grouping.expression)
     }
 
-    fn visit_literal(self: &mut Self, literal: &LiteralValue) -> LoxValue {
+    fn visit_interpolation(&mut self, interpolation: &Interpolation) -> Result<LoxValue, Error> {
+        let mut value = String::new();
+        for part in &interpolation.parts {
+            value += &self.visit_expression(part)?.to_string();
+        }
+        Ok(LoxValue::String(value))
+    }
+
+    fn visit_literal(&mut self, literal: &LiteralValue) -> LoxValue {
         match literal {
             LiteralValue::String(s, _) => LoxValue::String(s.clone()),
-            LiteralValue::Number(n, _) => LoxValue::Number(n.clone()),
+            LiteralValue::Number(n, _) => LoxValue::Number(*n),
             LiteralValue::True(_) => LoxValue::Bool(true),
             LiteralValue::False(_) => LoxValue::Bool(false),
             LiteralValue::Nil(_) => LoxValue::Nil,
         }
     }
 
-    fn visit_unary(self: &mut Self, unary: &Unary) -> Result<LoxValue, Error> {
+    fn visit_unary(&mut self, unary: &Unary) -> Result<LoxValue, Error> {
         let right = self.visit_expression(&unary.right)?;
         match unary {
             Unary {
                 operator: UnaryOperator::Negative(debug),
                 ..
             } => {
-                self.set_debug(&debug);
+                self.set_debug(debug);
                 LoxValue::negative(right)
             }
             Unary {
                 operator: UnaryOperator::Not(debug),
                 ..
             } => {
-                self.set_debug(&debug);
+                self.set_debug(debug);
                 let b = LoxValue::is_truthy(&right);
                 Ok(LoxValue::Bool(!b))
             }
         }
     }
 
-    fn visit_identifier(self: &mut Self, identifier: &Identifier) -> Result<LoxValue, Error> {
-        let Identifier {
-            name,
-            debug_info: DebugInfo { line, position, .. },
-            id,
-        } = identifier;
+    fn visit_identifier(&mut self, identifier: &Identifier) -> Result<LoxValue, Error> {
+        let Identifier { name, id, .. } = identifier;
         self.environment
             .get(name, id)
-            .ok_or_else(|| Error::RuntimeError {
-                line: *line,
-                position: *position,
-                message: format!("Variable {name} not defined!"),
-            })
+            .ok_or_else(|| self.undefined_variable(identifier))
+    }
+
+    /// Evaluates a chain of property reads, indexing and calls, such as
+    /// `a?.b.c(1)[0]`. `None` when a `?.` in it found nil, which skips the
+    /// rest of the chain up to the end of the expression or a parenthesis.
+    fn visit_chain(&mut self, expr: &Expression) -> Result<Option<LoxValue>, Error> {
+        let object = match expr {
+            Expression::Get(get) => &get.object,
+            Expression::Index(index) => &index.object,
+            Expression::Slice(slice) => &slice.object,
+            Expression::Call(call) => &call.calle,
+            expr => return self.visit_expression(expr).map(Some),
+        };
+        let Some(object) = self.visit_chain(object)? else {
+            return Ok(None);
+        };
+        match expr {
+            Expression::Get(get) if get.optional && object == LoxValue::Nil => Ok(None),
+            Expression::Get(get) => self.get_property(object, get).map(Some),
+            Expression::Index(index) => self.visit_index(object, index).map(Some),
+            Expression::Slice(slice) => self.visit_slice(object, slice).map(Some),
+            Expression::Call(call) => self.visit_call(object, call).map(Some),
+            _ => unreachable!("not a link of a chain"),
+        }
+    }
+
+    fn get_property(&mut self, object: LoxValue, get: &Get) -> Result<LoxValue, Error> {
+        self.set_debug(&get.debug_info);
+
+        // `map.name` reads the entry under the string key "name"
+        if let LoxValue::Map(map) = &object {
+            let key = LoxKey::String(get.name.clone());
+            return match map.borrow().get(&key) {
+                Some(value) => Ok(value.clone()),
+                None => Err(self.error(message!(UndefinedProperty, name = get.name))),
+            };
+        }
+        // methods of built-in types, bound to the value they are looked up on
+        if let Some(methods) = self.methods.get(object.type_name()) {
+            let Some(method) = methods.get(&get.name) else {
+                return Err(self.error(message!(UndefinedProperty, name = get.name)));
+            };
+            return Ok(LoxValue::Bound(Rc::new(BoundFun {
+                target: LoxValue::ForeinFun(method.clone()),
+                args: Box::new([object]),
+            })));
+        }
+        let LoxValue::Object(object) = object else {
+            return Err(self.error(message!(OnlyInstancesHaveProperties)));
+        };
+
+        if let Some(value) = object.borrow().get(&get.name) {
+            return Ok(value);
+        }
+        let method = object.borrow().class.find_method(&get.name).cloned();
+        match method {
+            Some(method) => self.bind_method(&method, LoxValue::Object(object), &get.debug_info),
+            None => Err(self.error(message!(UndefinedProperty, name = get.name))),
+        }
+    }
+
+    /// Binds `method` to `this`, getters are called right away.
+    fn bind_method(
+        &mut self,
+        method: &LoxFun,
+        this: LoxValue,
+        debug_info: &DebugInfo,
+    ) -> Result<LoxValue, Error> {
+        let bound = LoxValue::LoxFun(Rc::new(method.bind(this)));
+        if !method.getter {
+            return Ok(bound);
+        }
+        let base = self.arg_stack.len();
+        let result = self.call_value(&bound, base, debug_info);
+        self.arg_stack.truncate(base);
+        result
+    }
+
+    fn visit_set(&mut self, set: &Set) -> Result<LoxValue, Error> {
+        match self.visit_expression(&set.object)? {
+            LoxValue::Object(object) => {
+                let value = self.visit_expression(&set.value)?;
+                object.borrow_mut().set(&set.name, value.clone());
+                Ok(value)
+            }
+            // `map.name = value` writes the entry under the string key "name"
+            LoxValue::Map(map) => {
+                let value = self.visit_expression(&set.value)?;
+                map.borrow_mut()
+                    .insert(LoxKey::String(set.name.clone()), value.clone());
+                Ok(value)
+            }
+            _ => {
+                self.set_debug(&set.debug_info);
+                Err(self.error(message!(OnlyInstancesHaveFields)))
+            }
+        }
+    }
+
+    fn visit_super(&mut self, sup: &Super) -> Result<LoxValue, Error> {
+        let superclass = self.visit_identifier(&sup.keyword)?;
+        let this = self.visit_identifier(&sup.this)?;
+        self.set_debug(&sup.debug_info);
+
+        let LoxValue::Class(superclass) = superclass else {
+            return Err(self.error(message!(SuperclassMustBeClass)));
+        };
+        match superclass.find_method(&sup.method).cloned() {
+            Some(method) => self.bind_method(&method, this, &sup.debug_info),
+            None => Err(self.error(message!(UndefinedProperty, name = sup.method))),
+        }
+    }
+
+    fn visit_list(&mut self, list: &List) -> Result<LoxValue, Error> {
+        let elements = list
+            .elements
+            .iter()
+            .map(|element| self.visit_expression(element))
+            .collect::<Result<_, _>>()?;
+        Ok(LoxValue::List(Rc::new(RefCell::new(elements))))
+    }
+
+    fn visit_map(&mut self, map: &Map) -> Result<LoxValue, Error> {
+        let mut entries = LoxMap::new();
+        for (key, value) in &map.entries {
+            let key = self.visit_expression(key)?;
+            let value = self.visit_expression(value)?;
+            self.set_debug(&map.debug_info);
+            entries.insert(LoxKey::try_from(&key)?, value);
+        }
+        Ok(LoxValue::Map(Rc::new(RefCell::new(entries))))
+    }
+
+    fn visit_index(&mut self, object: LoxValue, index: &Index) -> Result<LoxValue, Error> {
+        let position = self.visit_expression(&index.index)?;
+        self.set_debug(&index.debug_info);
+
+        match object {
+            LoxValue::List(list) => {
+                let list = list.borrow();
+                let i = self.list_index(&position, list.len())?;
+                Ok(list[i].clone())
+            }
+            LoxValue::Map(map) => {
+                let key = LoxKey::try_from(&position)?;
+                map.borrow()
+                    .get(&key)
+                    .cloned()
+                    .ok_or_else(|| self.error(message!(UndefinedKey, key = position.repr())))
+            }
+            object => match self.call_operator(&object, "get", &[position], &index.debug_info)? {
+                Some(value) => Ok(value),
+                None => Err(self.error(message!(NotIndexable, kind = object.type_name()))),
+            },
+        }
+    }
+
+    fn visit_slice(&mut self, object: LoxValue, slice: &Slice) -> Result<LoxValue, Error> {
+        let mut bounds = [None, None];
+        for (bound, expression) in bounds.iter_mut().zip([&slice.start, &slice.end]) {
+            if let Some(expression) = expression {
+                *bound = Some(self.visit_expression(expression)?);
+            }
+        }
+        self.set_debug(&slice.debug_info);
+
+        let LoxValue::List(list) = object else {
+            return Err(self.error(message!(NotIndexable, kind = object.type_name())));
+        };
+        let list = list.borrow();
+        let [start, end] =
+            bounds.map(|bound| bound.map(|bound| self.slice_bound(&bound, list.len())));
+        let start = start.transpose()?.unwrap_or(0);
+        let end = end.transpose()?.unwrap_or(list.len());
+
+        let elements = list.get(start..end).unwrap_or_default().to_vec();
+        Ok(LoxValue::List(Rc::new(RefCell::new(elements))))
+    }
+
+    fn visit_index_set(&mut self, set: &IndexSet) -> Result<LoxValue, Error> {
+        let object = self.visit_expression(&set.object)?;
+        let position = self.visit_expression(&set.index)?;
+        let value = self.visit_expression(&set.value)?;
+        self.set_debug(&set.debug_info);
+
+        match object {
+            LoxValue::List(list) => {
+                let mut list = list.borrow_mut();
+                let i = self.list_index(&position, list.len())?;
+                list[i] = value.clone();
+                Ok(value)
+            }
+            LoxValue::Map(map) => {
+                let key = LoxKey::try_from(&position)?;
+                map.borrow_mut().insert(key, value.clone());
+                Ok(value)
+            }
+            // like any assignment, evaluates to the assigned value whatever
+            // `set` returns
+            object => {
+                let args = [position, value.clone()];
+                match self.call_operator(&object, "set", &args, &set.debug_info)? {
+                    Some(_) => Ok(value),
+                    None => Err(self.error(message!(NotIndexable, kind = object.type_name()))),
+                }
+            }
+        }
+    }
+
+    /// Checks that `index` is an integer pointing into a list of length
+    /// `len`, negative indexes count from the end of the list.
+    fn list_index(&self, index: &LoxValue, len: usize) -> Result<usize, Error> {
+        let LoxValue::Number(n) = *index else {
+            return Err(self.error(message!(IndexMustBeInteger, value = index.repr())));
+        };
+        if n.fract() != 0. {
+            return Err(self.error(message!(IndexMustBeInteger, value = n)));
+        }
+        let i = if n < 0. { n + len as f64 } else { n };
+        if i < 0. || i >= len as f64 {
+            return Err(self.error(message!(IndexOutOfRange, index = n, len = len)));
+        }
+        Ok(i as usize)
+    }
+
+    /// Like [`Self::list_index`], but a slice may also start or end just
+    /// past the last element.
+    fn slice_bound(&self, bound: &LoxValue, len: usize) -> Result<usize, Error> {
+        match *bound {
+            LoxValue::Number(n) if n == len as f64 => Ok(len),
+            _ => self.list_index(bound, len),
+        }
     }
 
     fn visit_assignment(
-        self: &mut Self,
+        &mut self,
         target: &Identifier,
         value: &Expression,
     ) -> Result<LoxValue, Error> {
-        let value = self.visit_expression(&value)?;
+        let value = self.visit_expression(value)?;
 
         let Identifier {
             name,
-            debug_info: DebugInfo { line, position, .. },
             id,
+            debug_info,
         } = target;
 
+        self.set_debug(debug_info);
         self.environment
-            .assign(&name, id, value)
-            .ok_or_else(|| Error::RuntimeError {
-                line: *line,
-                position: *position,
-                message: format!("Variable {name} already declared at {line}:{position}!"),
-            })
+            .assign(name, id, value)?
+            .ok_or_else(|| self.undefined_variable(target))
+    }
+
+    fn undefined_variable(&self, identifier: &Identifier) -> Error {
+        let Identifier {
+            name, debug_info, ..
+        } = identifier;
+
+        let visible = self.environment.visible_names();
+        let mut message = message!(UndefinedVariable, name = name);
+        if let Some(suggestion) = did_you_mean(name, visible.iter().map(String::as_str)) {
+            message.text = format!("{message} {}", message!(DidYouMean, name = suggestion));
+        }
+
+        Error::RuntimeError {
+            line: debug_info.line,
+            position: debug_info.position,
+            end: debug_info.span().end,
+            message,
+            trace: Vec::new(),
+        }
     }
 
-    fn visit_logical(self: &mut Self, logical: &Logical) -> Result<LoxValue, Error> {
+    fn visit_logical(&mut self, logical: &Logical) -> Result<LoxValue, Error> {
         let left = self.visit_expression(&logical.left)?;
         match &logical.operator {
             LogicalOperator::Or(debug) => {
-                self.set_debug(&debug);
+                self.set_debug(debug);
                 if LoxValue::is_truthy(&left) {
                     return Ok(left);
                 }
             }
             LogicalOperator::And(debug) => {
-                self.set_debug(&debug);
+                self.set_debug(debug);
                 if !LoxValue::is_truthy(&left) {
                     return Ok(left);
                 }
             }
+            LogicalOperator::Coalesce(debug) => {
+                self.set_debug(debug);
+                if left != LoxValue::Nil {
+                    return Ok(left);
+                }
+            }
         }
         let right = self.visit_expression(&logical.right)?;
         Ok(right)
     }
 
-    fn visit_call(self: &mut Self, call: &Call) -> Result<LoxValue, Error> {
-        let Call { calle, args, .. } = call;
-
-        let calle = self.visit_expression(calle)?;
+    fn visit_sequence(&mut self, sequence: &Sequence) -> Result<LoxValue, Error> {
+        self.visit_expression(&sequence.left)?;
+        self.visit_expression(&sequence.right)
+    }
 
-        let mut arg_values: Vec<LoxValue> = Vec::new();
+    fn visit_call(&mut self, calle: LoxValue, call: &Call) -> Result<LoxValue, Error> {
+        let Call {
+            args, debug_info, ..
+        } = call;
 
+        let base = self.arg_stack.len();
         for exp in args {
-            arg_values.push(self.visit_expression(exp)?);
+            match self.visit_expression(exp) {
+                Ok(value) => self.arg_stack.push(value),
+                Err(error) => {
+                    self.arg_stack.truncate(base);
+                    return Err(error);
+                }
+            }
         }
 
+        self.set_debug(debug_info);
+        let result = self.call_value(&calle, base, debug_info);
+        self.arg_stack.truncate(base);
+        result
+    }
+
+    /// Calls `calle` with the arguments stored on the argument stack above `base`.
+    ///
+    /// The caller is responsible for truncating the stack back to `base`
+    /// afterwards, arguments may or may not have been consumed.
+    fn call_value(
+        &mut self,
+        calle: &LoxValue,
+        base: usize,
+        call: &DebugInfo,
+    ) -> Result<LoxValue, Error> {
+        let arg_count = self.arg_stack.len() - base;
+
         match calle {
             LoxValue::LoxFun(fun) => {
-                if fun.arity() != args.len() {
-                    return Err(self.error(format!(
-                        "Expected {} arguments, got {}.",
-                        fun.arity(),
-                        args.len()
+                if !fun.arity().accepts(arg_count) {
+                    return Err(self.error(message!(
+                        ArityMismatch,
+                        expected = fun.arity(),
+                        got = arg_count
                     )));
                 }
 
+                if fun.variadic {
+                    // the rest parameter takes whatever is left after the
+                    // fixed ones, possibly nothing
+                    let rest = self.arg_stack.split_off(base + fun.args.len() - 1);
+                    self.arg_stack
+                        .push(LoxValue::List(Rc::new(RefCell::new(rest))));
+                }
+
                 self.environment.push_closure(fun.captured_scope.clone());
+                let module = fun
+                    .module
+                    .upgrade()
+                    .expect("modules outlive their functions");
+                let caller = self.environment.enter_module(module);
                 for (identifier, value) in
-                    std::iter::zip(fun.args.into_iter(), arg_values.into_iter())
+                    std::iter::zip(fun.args.iter(), self.arg_stack.drain(base..))
                 {
-                    self.environment.define(identifier, value.clone())?;
+                    if let Err(error) = self.environment.define(identifier, value) {
+                        self.environment.enter_module(caller);
+                        self.environment.pop_closure();
+                        return Err(error);
+                    }
                 }
-                let ret_value = match self.run(&fun.body.statements) {
+                self.call_stack.push(CallFrame {
+                    function: fun.name.name.clone(),
+                    line: call.line,
+                    position: call.position,
+                });
+                let mut ret_value = match self.run(&fun.body.statements) {
                     // napotkano Statement::Return podczas wykonywania funkcji
                     Ok(LoxResult::Return(value)) => Ok(value),
-                    // ciało funkcji nie zawierało instrukcji return, być może inne przypadki
-                    Ok(LoxResult::None) => Ok(LoxValue::Nil),
+                    Ok(LoxResult::Thrown {
+                        value,
+                        line,
+                        position,
+                    }) => Err(Error::Thrown {
+                        value,
+                        line,
+                        position,
+                    }),
+                    // ciało funkcji nie zawierało instrukcji return, być może inne przypadki
+                    // the resolver rejects `break` and `continue` outside of loops,
+                    // they cannot end a function
+                    Ok(LoxResult::None | LoxResult::Break | LoxResult::Continue) => {
+                        Ok(LoxValue::Nil)
+                    }
                     // RuntimeError
                     Err(e) => Err(e),
                 };
+                // `init` evaluates to the instance, also when called on it
+                // directly or when it ends with a bare `return`
+                if fun.initializer && ret_value.is_ok() {
+                    ret_value = Ok(fun
+                        .captured_scope
+                        .get(&"this".to_owned())
+                        .expect("initializers are bound"));
+                }
+                let frame = self.call_stack.pop().expect("the frame was pushed above");
+                if let Err(Error::RuntimeError { trace, .. }) = &mut ret_value {
+                    trace.push(frame);
+                }
+                self.environment.enter_module(caller);
                 self.environment.pop_closure();
 
                 ret_value
             }
             LoxValue::ForeinFun(fun) => {
-                if fun.arity() != args.len() {
-                    Err(self.error(format!(
-                        "Expected {} arguments, got {}.",
-                        fun.arity(),
-                        args.len()
-                    )))
-                } else {
-                    Ok((fun.fun)(self, arg_values.into_boxed_slice())?)
+                if !fun.arity().accepts(arg_count) {
+                    return Err(self.error(message!(
+                        ArityMismatch,
+                        expected = fun.arity(),
+                        got = arg_count
+                    )));
+                }
+
+                // the native gets a slice of the stack while also holding
+                // `&mut self`, so the stack is moved out for the duration
+                // of the call, nested calls start with a fresh one
+                let stack = std::mem::take(&mut self.arg_stack);
+                let result = (fun.fun)(&mut NativeContext {
+                    interpreter: self,
+                    args: &stack[base..],
+                    call,
+                    name: &fun.name,
+                    method: fun.method,
+                });
+                self.arg_stack = stack;
+                result
+            }
+            LoxValue::Class(class) => {
+                if !class.arity().accepts(arg_count) {
+                    return Err(self.error(message!(
+                        ArityMismatch,
+                        expected = class.arity(),
+                        got = arg_count
+                    )));
+                }
+
+                let object = LoxValue::Object(Rc::new(RefCell::new(LoxObject::new(class.clone()))));
+                if let Some(init) = class.find_method("init") {
+                    // whatever `init` returns, the call evaluates to the instance
+                    let init = LoxValue::LoxFun(Rc::new(init.bind(object.clone())));
+                    self.call_value(&init, base, call)?;
+                }
+                Ok(object)
+            }
+            LoxValue::Bound(fun) => {
+                // the receiver of a built-in method is not counted
+                let arity = calle.arity().expect("bound functions are callable");
+                let method = matches!(&fun.target, LoxValue::ForeinFun(target) if target.method);
+                if method && !arity.accepts(arg_count) {
+                    return Err(self.error(message!(
+                        ArityMismatch,
+                        expected = arity,
+                        got = arg_count
+                    )));
                 }
+                self.arg_stack.splice(base..base, fun.args.iter().cloned());
+                self.call_value(&fun.target, base, call)
             }
-            _ => Err(self.error("Expected a function")),
+            _ => Err(self.error(message!(NotCallable))),
         }
     }
 
-    fn error<S: Into<String>>(&self, message: S) -> Error {
+    fn error<S: Into<Message>>(&self, message: S) -> Error {
         Error::RuntimeError {
             line: self.line,
             position: self.position,
+            end: self.end,
             message: message.into(),
+            trace: Vec::new(),
         }
     }
 }
 
+/// Name of the method an instance can define to overload a binary
+/// operator, `!=` negates the result of `equals`. Floor division and the
+/// bitwise operators cannot be overloaded.
+fn operator_method(operator: &BinaryOperator) -> Option<(&'static str, &DebugInfo)> {
+    match operator {
+        BinaryOperator::Add(debug) => Some(("plus", debug)),
+        BinaryOperator::Subtract(debug) => Some(("minus", debug)),
+        BinaryOperator::Multiply(debug) => Some(("times", debug)),
+        BinaryOperator::Divide(debug) => Some(("divide", debug)),
+        BinaryOperator::Equal(debug) | BinaryOperator::NotEqual(debug) => Some(("equals", debug)),
+        BinaryOperator::Less(debug) => Some(("less", debug)),
+        BinaryOperator::LessEqual(debug) => Some(("lessEqual", debug)),
+        BinaryOperator::Greater(debug) => Some(("greater", debug)),
+        BinaryOperator::GreaterEqual(debug) => Some(("greaterEqual", debug)),
+        BinaryOperator::FloorDivide(_)
+        | BinaryOperator::BitAnd(_)
+        | BinaryOperator::BitOr(_)
+        | BinaryOperator::BitXor(_)
+        | BinaryOperator::ShiftLeft(_)
+        | BinaryOperator::ShiftRight(_) => None,
+    }
+}
+
+/// Runs `source` in a new interpreter, parsed in `edition`, evaluating to
+/// its global `result`, nil if it defines none.
+#[cfg(test)]
+fn run_result_in(source: &str, edition: Edition) -> Result<LoxValue, Error> {
+    let tokens = scanner::scan_tokens(source)?;
+    let program = Parser::with_edition(edition).parse(tokens)?;
+    let access_table = resolver::resolve(&program)?;
+    let mut interp = Interpreter::with_edition(edition);
+    interp.execute(&program, access_table)?;
+    let result = interp.environment.get_global(&"result".to_string());
+    Ok(result.unwrap_or(LoxValue::Nil))
+}
+
+/// `run_result_in` the default edition.
+#[cfg(test)]
+fn run_result(source: &str) -> Result<LoxValue, Error> {
+    run_result_in(source, Edition::default())
+}
+
 #[test]
 fn runtime_error_string_negation() {
     use crate::parser::Parser;
@@ -463,11 +2257,12 @@ fn runtime_error_string_negation() {
         line,
         position,
         message,
+        ..
     } = interp.run(&tree).unwrap_err()
     {
         assert_eq!(line, 1);
         assert_eq!(position, 1);
-        assert_eq!(message, "Cannot negate: String(\"asdf\")");
+        assert_eq!(message, "Operand of '-' must be a number, got string.");
     };
 }
 
@@ -543,15 +2338,12 @@ fn program_return() {
 
     let _v = LoxValue::Number(3.);
 
-    assert_eq!(
-        match val {
-            LoxResult::Return(LoxValue::Number(value)) => {
-                value == 3.
-            }
-            _ => false,
-        },
-        true
-    );
+    assert!(match val {
+        LoxResult::Return(LoxValue::Number(value)) => {
+            value == 3.
+        }
+        _ => false,
+    });
 }
 
 #[test]
@@ -578,13 +2370,1732 @@ fn func_loop_return() {
     let mut interp = Interpreter::new();
     let val = interp.execute(&program, access_table).unwrap();
 
+    assert!(match val {
+        LoxResult::Return(LoxValue::Number(value)) => {
+            value == 5.
+        }
+        _ => false,
+    });
+}
+
+#[test]
+fn argument_stack_is_balanced() {
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source = "fun fib(n) {
+            if n < 2 { return n; }
+            return fib(n - 1) + fib(n - 2);
+        }
+        fun add(a, b, c) { return a + b + c; }
+        var a = add(fib(10), toString(1) == \"1\", 0);
+        "
+    .to_string();
+    let mut parser = Parser::new();
+    let tokens = scanner::scan_tokens(&source).unwrap();
+    let program = parser.parse(tokens).unwrap();
+    let access_table = resolver::resolve(&program).unwrap();
+    let mut interp = Interpreter::new();
+    interp.execute(&program, access_table).unwrap_err();
+    // `55 + true` fails inside `add`, the stack must still be unwound
+    assert!(interp.arg_stack.is_empty());
+
+    let source = "var b = fib(add(1, 2, 3) - 1);".to_string();
+    let tokens = scanner::scan_tokens(&source).unwrap();
+    let program = parser.parse(tokens).unwrap();
+    let access_table = resolver::resolve(&program).unwrap();
+    interp.execute(&program, access_table).unwrap();
+    assert!(interp.arg_stack.is_empty());
     assert_eq!(
-        match val {
-            LoxResult::Return(LoxValue::Number(value)) => {
-                value == 5.
-            }
-            _ => false,
-        },
-        true
+        interp.environment.get_global(&"b".to_string()),
+        Some(LoxValue::Number(5.))
+    );
+}
+
+#[test]
+fn undefined_variable_suggests_similar_name() {
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let run = |source: &str| {
+        let tokens = scanner::scan_tokens(source).unwrap();
+        let program = Parser::new().parse(tokens).unwrap();
+        let access_table = resolver::resolve(&program).unwrap();
+        match Interpreter::new().execute(&program, access_table) {
+            Err(Error::RuntimeError { message, .. }) => message,
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    };
+
+    assert_eq!(
+        run("fun f(counter) { return countr + 1; } f(1);"),
+        "Undefined variable 'countr'. Did you mean 'counter'?"
+    );
+    assert_eq!(
+        run("var total = 0; totl = 1;"),
+        "Undefined variable 'totl'. Did you mean 'total'?"
+    );
+    assert_eq!(
+        run("print tostring(1);"),
+        "Undefined variable 'tostring'. Did you mean 'toString'?"
+    );
+    assert_eq!(run("print nothing;"), "Undefined variable 'nothing'.");
+}
+
+#[test]
+fn break_is_a_contextual_keyword() {
+    use crate::edition::Edition;
+
+    // a plain identifier in every edition, as long as it is not a statement on its own
+    let source = "var break = 1; break = break + 1; var result = break;";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2023).unwrap(),
+        LoxValue::Number(2.)
+    );
+    assert_eq!(
+        run_result_in(source, Edition::Lox2026).unwrap(),
+        LoxValue::Number(2.)
+    );
+
+    let source = "var break = 0;
+        while true {
+            while true { break; }
+            break = break + 1;
+            if break == 3 { break; }
+        }
+        var result = break;";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2026).unwrap(),
+        LoxValue::Number(3.)
+    );
+
+    // older editions read the same statement as an expression
+    let source = "var break = 1; if true { break; } var result = break;";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2023).unwrap(),
+        LoxValue::Number(1.)
+    );
+
+    assert!(matches!(
+        run_result_in("fun f() { break; } while true { f(); }", Edition::Lox2026),
+        Err(Error::ResolverError { .. })
+    ));
+}
+
+#[test]
+fn continue_runs_the_for_loop_increment() {
+    use crate::edition::Edition;
+
+    let source = "var result = 0;
+        for (var i = 0; i < 10; i = i + 1) {
+            if i == 3 or i == 5 { continue; }
+            result = result + i;
+        }";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2026).unwrap(),
+        LoxValue::Number(37.)
+    );
+
+    let source = "var result = 0; var i = 0;
+        while i < 5 {
+            i = i + 1;
+            if i == 3 { continue; }
+            result = result + i;
+        }";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2026).unwrap(),
+        LoxValue::Number(12.)
+    );
+
+    assert!(matches!(
+        run_result_in("var result = 0; continue;", Edition::Lox2026),
+        Err(Error::ResolverError { .. })
+    ));
+}
+
+#[test]
+fn constants() {
+    use crate::edition::Edition;
+
+    let source = "const limit = 10;
+        var const = 1;
+        {
+            const inner = limit + const;
+            var result = inner;
+            fun get() { return inner; }
+            const = get();
+        }
+        var result = limit + const;";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2026).unwrap(),
+        LoxValue::Number(21.)
+    );
+
+    // a constant can still be shadowed by a variable
+    let source = "const result = 1; { var result = 2; result = 3; }";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2026).unwrap(),
+        LoxValue::Number(1.)
+    );
+
+    for (source, name) in [
+        ("const limit = 1; limit = 2;", "limit"),
+        ("{ const limit = 1; fun f() { limit = 2; } }", "limit"),
+        ("fun f(x) { const y = x; y = 1; }", "y"),
+    ] {
+        match run_result_in(source, Edition::Lox2026) {
+            Err(Error::ResolverError { message, .. }) => {
+                assert_eq!(message, format!("Cannot assign to constant '{name}'."))
+            }
+            other => panic!("expected a resolver error, got {other:?}"),
+        }
+    }
+
+    // the resolver cannot know about a global declared after the assignment
+    let source = "fun reset() { limit = 0; }\nconst limit = 10;\nreset();";
+    match run_result_in(source, Edition::Lox2026) {
+        Err(Error::RuntimeError {
+            line,
+            position,
+            message,
+            ..
+        }) => assert_eq!(
+            (line, position, message.as_str()),
+            (1, 15, "Cannot assign to constant 'limit'.")
+        ),
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+
+    assert!(matches!(
+        run_result_in("const limit;", Edition::Lox2026),
+        Err(Error::ParsingError { .. })
+    ));
+}
+
+#[test]
+fn match_statement() {
+    use crate::edition::Edition;
+
+    let source = "fun describe(x) {
+            match x {
+                0 => { return \"zero\"; }
+                1, 2, -1 => { return \"small\"; }
+                \"a\", nil => { return \"other\"; }
+                _ => { return \"big\"; }
+            }
+        }
+        var result = \"\";
+        for (x in [0, 2, -1, \"a\", nil, 7, true]) {
+            result = result + describe(x) + \" \";
+            match x { true => { break; } }
+        }";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2026).unwrap(),
+        LoxValue::String("zero small small other other big big ".to_owned())
+    );
+
+    // without a default arm nothing runs
+    let source = "var result = 1; match result + 1 { 1 => { result = 0; } }";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2026).unwrap(),
+        LoxValue::Number(1.)
+    );
+
+    // the value may be any expression, also one in parentheses
+    let source = "var x = 2; var result = 0;
+        match (x) { 2 => { result = result + 1; } }
+        match -x { -2 => { result = result + 10; } }
+        match [x][0] { _ => { result = result + 100; } }";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2026).unwrap(),
+        LoxValue::Number(111.)
+    );
+
+    // `match` stays an identifier where it could not start a statement
+    let source = "fun match(x) { return x; } var result = match(2) - 1;";
+    for edition in [Edition::Lox2023, Edition::Lox2026] {
+        assert_eq!(
+            run_result_in(source, edition).unwrap(),
+            LoxValue::Number(1.)
+        );
+    }
+
+    for source in [
+        "match 1 { _ => {} 1 => {} }",
+        "match 1 { x => {} }",
+        "match 1 { 1 {} }",
+    ] {
+        assert!(matches!(
+            run_result_in(source, Edition::Lox2026),
+            Err(Error::ParsingError { .. })
+        ));
+    }
+}
+
+#[test]
+fn exceptions() {
+    use crate::edition::Edition;
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+
+    let source = "fun fail(x) { throw \"bad \" + toString(x); }
+        var log = \"\";
+        fun attempt(x) {
+            try {
+                if x > 1 { fail(x); }
+                log = log + \"ok \";
+                return x;
+            } catch e {
+                log = log + e + \" \";
+                return -1;
+            } finally {
+                log = log + \"done \";
+            }
+        }
+        var result = toString([attempt(1), attempt(2)]) + \" \" + log;";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2026).unwrap(),
+        LoxValue::String("[1, -1] ok done bad 2 done ".to_owned())
+    );
+
+    // rethrowing from a catch block, throwing out of loops and any value
+    // being throwable
+    let source = "var result = nil;
+        try {
+            try {
+                for (i in range(10)) { if i == 3 { throw {\"at\": i}; } }
+            } catch {
+                var error = [1];
+                throw error;
+            }
+        } catch e {
+            result = e;
+        }
+        try { while true { try { break; } finally { result[0] = 2; } } } catch {}";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2026).unwrap().to_string(),
+        "[2]"
+    );
+
+    // finally blocks also run when the block fails with a runtime error
+    let mut interp = Interpreter::new();
+    let source = "var result = 0;\ntry { result = nil + 1; } finally { result = 1; }";
+    let program = Parser::with_edition(Edition::Lox2026)
+        .parse(scanner::scan_tokens(source).unwrap())
+        .unwrap();
+    assert!(matches!(
+        interp.execute(&program, resolver::resolve(&program).unwrap()),
+        Err(Error::RuntimeError { line: 2, .. })
+    ));
+    assert_eq!(
+        interp.environment.get_global(&"result".to_string()),
+        Some(LoxValue::Number(1.))
+    );
+
+    match run_result_in("fun f() {\n  throw 42;\n}\nf();", Edition::Lox2026) {
+        Err(Error::RuntimeError {
+            line,
+            position,
+            message,
+            ..
+        }) => assert_eq!(
+            (line, position, message.as_str()),
+            (2, 3, "Uncaught exception: 42.")
+        ),
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+
+    // the thrown value may be any expression
+    for (thrown, expected) in [
+        ("[1, 2]", "[1, 2]"),
+        ("-1", "-1"),
+        ("(\"x\")", "x"),
+        ("!true", "false"),
+        ("{\"a\": 1}", "{\"a\": 1}"),
+    ] {
+        let source = format!("var result; try {{ throw {thrown}; }} catch e {{ result = e; }}");
+        assert_eq!(
+            run_result_in(&source, Edition::Lox2026)
+                .unwrap()
+                .to_string(),
+            expected,
+            "{thrown}"
+        );
+    }
+
+    assert!(matches!(
+        run_result_in("try {}", Edition::Lox2026),
+        Err(Error::ParsingError { .. })
+    ));
+}
+
+#[test]
+fn catching_runtime_errors() {
+    use crate::edition::Edition;
+
+    let source = "fun attempt(f) {
+            try { f(); } catch e {
+                return e.message + \" at \" + toString(e.line) + \":\" + toString(e.position);
+            }
+        }
+        fun add() { return nil + 1; }
+        fun undefined() { return missing; }
+        fun call() { return add(1); }
+        var result = [attempt(add), attempt(undefined), attempt(call)];
+        result = toString(result);";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2026).unwrap(),
+        LoxValue::String(
+            concat!(
+                "[\"Operands of '+' must be two numbers or two strings, got nil and number. at 6:32\", ",
+                "\"Undefined variable 'missing'. at 7:34\", ",
+                "\"Expected 0 arguments but got 1. at 8:32\"]"
+            )
+            .to_owned()
+        )
+    );
+
+    // a rethrown error ends the program the way it would have originally
+    match run_result_in(
+        "try {\n  [][0];\n} catch e {\n  throw e;\n}",
+        Edition::Lox2026,
+    ) {
+        Err(Error::RuntimeError {
+            line,
+            position,
+            message,
+            ..
+        }) => assert_eq!(
+            (line, position, message.as_str()),
+            (2, 5, "Index 0 is out of range for a list of length 0.")
+        ),
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+}
+
+#[test]
+fn nil_coalescing_and_safe_navigation() {
+    let source = "class Node {
+            init(value) { this.value = value; this.next = nil; }
+            describe() { return \"node \" + toString(this.value); }
+        }
+        var calls = 0;
+        fun count() { calls = calls + 1; return calls; }
+        var a = Node(1);
+        a.next = Node(false);
+        var none = nil;
+        var result = [
+            none ?? \"default\",
+            a.next.value ?? \"default\",
+            a.next?.next?.value,
+            a?.describe(),
+            none?.describe(count()),
+            none ?? nil ?? 3,
+            1 ?? count(),
+            calls,
+        ];
+        result = toString(result);";
+    assert_eq!(
+        run_result(source).unwrap(),
+        LoxValue::String("[\"default\", false, nil, \"node 1\", nil, 3, 1, 0]".to_owned())
+    );
+
+    // `??` binds looser than `or`
+    assert_eq!(
+        run_result("var result = nil ?? false or 2;").unwrap(),
+        LoxValue::Number(2.)
+    );
+
+    // a nil before `?.` skips the rest of the chain, but not past parentheses
+    let source = "var calls = 0;
+        fun count() { calls = calls + 1; return calls; }
+        var a = nil;
+        var result = toString([a?.b.c, a?.b.c(count())[0], a?.b[count()].c, a?.b[1:].c, calls]);";
+    assert_eq!(
+        run_result(source).unwrap(),
+        LoxValue::String("[nil, nil, nil, nil, 0]".to_owned())
+    );
+    assert!(matches!(
+        run_result("var a = nil; (a?.b).c;"),
+        Err(Error::RuntimeError { .. })
+    ));
+
+    match run_result("var a = true; a?.field;") {
+        Err(Error::RuntimeError { message, .. }) => {
+            assert_eq!(message, "Only instances and maps have properties.")
+        }
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+    assert!(matches!(
+        run_result("var a = nil; a?.field = 1;"),
+        Err(Error::ParsingError { .. })
+    ));
+}
+
+#[test]
+fn imports() {
+    use crate::edition::Edition;
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let dir = std::env::temp_dir().join(format!("rlox-imports-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("lib")).unwrap();
+    for (file, source) in [
+        (
+            "lib/math.lox",
+            "var calls = 0;
+            fun square(x) { calls = calls + 1; return x * x; }
+            fun count() { return calls; }
+            class Point {
+                init(x, y) { this.x = x; this.y = y; }
+                norm() { return square(this.x) + square(this.y); }
+            }",
+        ),
+        (
+            "lib/quad.lox",
+            "import square from \"math.lox\"; fun quad(x) { return square(square(x)); }",
+        ),
+        ("cycle.lox", "import \"lib/../cycle.lox\";"),
+        ("a.lox", "import \"lib/b.lox\";"),
+        ("lib/b.lox", "var b = 1;\nimport \"../a.lox\";"),
+        ("broken.lox", "var a = 1;\nprint a +;"),
+        ("throws.lox", "throw \"from module\";"),
+    ] {
+        std::fs::write(dir.join(file), source).unwrap();
+    }
+
+    let run = |source: &str| {
+        let tokens = scanner::scan_tokens(source).unwrap();
+        let program = Parser::with_edition(Edition::Lox2026).parse(tokens)?;
+        let access_table = resolver::resolve(&program)?;
+        let mut interp = Interpreter::with_edition(Edition::Lox2026);
+        interp.set_script(&dir.join("main.lox"));
+        interp.execute(&program, access_table)?;
+        Ok::<_, Error>(interp.environment.get_global(&"result".to_string()))
+    };
+
+    // module functions see the globals of their own module, a module
+    // imported twice, under any path, is executed once
+    let source = "import square, count, Point from \"lib/./math.lox\";
+        import quad from \"lib/quad.lox\";
+        var calls = 100;
+        var result = [square(3), Point(3, 4).norm(), quad(2), count(), calls];";
+    assert_eq!(
+        run(source).unwrap().unwrap().to_string(),
+        "[9, 25, 16, 5, 100]"
+    );
+
+    // importing a module again, whole or by name, binds nothing new
+    let source = "import \"lib/math.lox\";
+        import square from \"lib/math.lox\";
+        import \"lib/math.lox\";
+        import count, square from \"lib/math.lox\";
+        var result = [square(2), count()];";
+    assert_eq!(run(source).unwrap().unwrap().to_string(), "[4, 1]");
+
+    for (source, expected) in [
+        (
+            "import missing from \"lib/math.lox\";",
+            (1, 8, "Module 'lib/math.lox' does not define 'missing'."),
+        ),
+        (
+            "import \"cycle.lox\";",
+            (
+                1,
+                1,
+                "In module 'cycle.lox' at 1:1: Circular import: cycle.lox -> cycle.lox.",
+            ),
+        ),
+        (
+            "import \"a.lox\";",
+            (
+                1,
+                1,
+                "In module 'a.lox' at 1:1: In module 'lib/b.lox' at 2:1: \
+                 Circular import: a.lox -> lib/b.lox -> a.lox.",
+            ),
+        ),
+        (
+            "var x = 1;\nimport \"broken.lox\";",
+            (
+                2,
+                1,
+                "In module 'broken.lox' at 2:10: Expect expression, found ';'.",
+            ),
+        ),
+        (
+            "var x = 1;\n  import \"throws.lox\";",
+            (
+                2,
+                3,
+                "In module 'throws.lox' at 1:1: Uncaught exception: from module.",
+            ),
+        ),
+        (
+            "var square = 1;\nimport count, square from \"lib/math.lox\";",
+            (2, 15, "Variable 'square' already defined at 1:5."),
+        ),
+        (
+            "fun count() {}\nimport \"lib/math.lox\";",
+            (2, 1, "Variable 'count' already defined at 1:5."),
+        ),
+    ] {
+        match run(source) {
+            Err(Error::RuntimeError {
+                line,
+                position,
+                message,
+                ..
+            }) => assert_eq!((line, position, message.as_str()), expected),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+
+    let source = "{ import \"lib/math.lox\"; }";
+    assert!(matches!(run(source), Err(Error::ResolverError { .. })));
+    match run("import \"none.lox\";") {
+        Err(Error::RuntimeError { message, .. }) => {
+            assert!(message.starts_with("Could not import 'none.lox': "))
+        }
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn eval_runs_code_at_the_top_level() {
+    use crate::edition::Edition;
+
+    // definitions made by evaluated code are globals, even when `eval` is
+    // called from a function, and later calls can use them
+    let source = "var base = 10;
+        fun define() {
+            var base = 1;
+            return eval(\"var offset = base + 1; fun add(x) { return x + offset; } add(1);\");
+        }
+        var first = define();
+        var result = [first, eval(\"add(base);\"), eval(\"if true { 1; }\"), eval(\"\")];";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2026).unwrap().to_string(),
+        "[12, 21, nil, nil]"
+    );
+
+    let source = "var result; try { eval(\"throw 5;\"); } catch e { result = e; }";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2026).unwrap(),
+        LoxValue::Number(5.)
+    );
+
+    for (source, expected) in [
+        (
+            "var x = 1;\n  eval(\"1 +\");",
+            (
+                2,
+                7,
+                "In evaluated code at 1:4: Expect expression, found end of input.",
+            ),
+        ),
+        (
+            "eval(\"var a = 1;\\nprint a + nil;\");",
+            (
+                1,
+                5,
+                "In evaluated code at 2:9: \
+                 Operands of '+' must be two numbers or two strings, got number and nil.",
+            ),
+        ),
+        (
+            "eval(1);",
+            (1, 5, "Argument 1 of eval() must be a string, got number."),
+        ),
+    ] {
+        match run_result_in(source, Edition::Lox2026) {
+            Err(Error::RuntimeError {
+                line,
+                position,
+                message,
+                ..
+            }) => assert_eq!((line, position, message.as_str()), expected),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn stack_trace_lists_active_calls() {
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source = "fun inner() { return stackTrace(); }
+fun outer() {
+    return inner();
+}
+var trace = outer();
+var after = stackTrace();";
+    let tokens = scanner::scan_tokens(source).unwrap();
+    let program = Parser::new().parse(tokens).unwrap();
+    let access_table = resolver::resolve(&program).unwrap();
+    let mut interp = Interpreter::new();
+    interp.execute(&program, access_table).unwrap();
+
+    let global = |name: &str| {
+        interp
+            .environment
+            .get_global(&name.to_string())
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(
+        global("trace"),
+        "[{\"function\": \"inner\", \"line\": 3, \"position\": 17}, \
+        {\"function\": \"outer\", \"line\": 5, \"position\": 18}]"
+    );
+    assert_eq!(global("after"), "[]");
+    assert!(interp.call_stack.is_empty());
+}
+
+#[test]
+fn runtime_errors_carry_a_backtrace() {
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source = "fun fib(n) {
+    if (n < 1) { return -\"a\"; }
+    return fib(n - 1);
+}
+fun main() {
+    fib(1);
+}
+var caught;
+try { main(); } catch e { caught = e; }
+main();";
+    let tokens = scanner::scan_tokens(source).unwrap();
+    let program = Parser::with_edition(Edition::Lox2026)
+        .parse(tokens)
+        .unwrap();
+    let access_table = resolver::resolve(&program).unwrap();
+    let mut interp = Interpreter::new();
+    let error = interp.execute(&program, access_table).unwrap_err();
+
+    match &error {
+        Error::RuntimeError { trace, .. } => {
+            let frames: Vec<_> = trace
+                .iter()
+                .map(|frame| (frame.function.as_str(), frame.line, frame.position))
+                .collect();
+            assert_eq!(frames, [("fib", 3, 15), ("fib", 6, 8), ("main", 10, 5)]);
+        }
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+    assert_eq!(
+        error.into_diagnostic().unwrap().notes,
+        [
+            "in fib at 2:25",
+            "called from fib at 3:15",
+            "called from main at 6:8",
+            "called from <script> at 10:5"
+        ]
+    );
+    assert!(interp.call_stack.is_empty());
+}
+
+#[test]
+fn function_introspection() {
+    let source = "fun add(a, b) { return a + b; }
+        var f = add;
+        var result = nameOf(f) + toString(arity(f)) + nameOf(toString) + toString(arity(toString));";
+    assert_eq!(
+        run_result(source).unwrap(),
+        LoxValue::String("add2toString1".to_owned())
+    );
+
+    // reported at the call, not at the last evaluated operator
+    match run_result("var result = 1 -\n  -arity(\"add\");") {
+        Err(Error::RuntimeError {
+            line,
+            position,
+            message,
+            ..
+        }) => {
+            assert_eq!((line, position), (2, 9));
+            assert_eq!(
+                message,
+                "Argument 1 of arity() must be a function, got string."
+            )
+        }
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+}
+
+#[test]
+fn reflection_and_shadowing_builtins() {
+    let source = "class A { m() {} }
+        var values = [1, \"s\", true, nil, [], {}, range(1), A, A(), A().m, toString, bind(toString, 1)];
+        var result = \"\";
+        for (value in values) { result = result + type(value) + \" \"; }
+        result = result + name(A().m) + \" \" + name(type) + \" \" + toString(arity(name));";
+    assert_eq!(
+        run_result(source).unwrap(),
+        LoxValue::String(
+            "number string boolean nil list map range class instance function function \
+             function m type 1"
+                .to_owned()
+        )
+    );
+
+    // programs may define globals named like built-ins, once
+    let source = "fun len(x) { return 0; } var type = \"t\"; var result = len([1]) + arity(len);";
+    assert_eq!(run_result(source).unwrap(), LoxValue::Number(1.));
+    match run_result("var name = 1;\nvar name = 2;") {
+        Err(Error::RuntimeError { message, .. }) => {
+            assert_eq!(message, "Variable 'name' already defined at 1:5.")
+        }
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+}
+
+#[test]
+fn clocks() {
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source = "var start = monotonicMillis();
+        var epoch = clock();
+        var date = formatTime(parseTime(\"2026-03-08 14:05\", \"%F %H:%M\") + 59, \"%d %b %Y %T\");
+        var seconds = now() - parseTime(formatTime(now(), \"%F %T\"), \"%F %T\");
+        var i = 0;
+        while i < 1000 { i = i + 1; }
+        var elapsed = monotonicMillis() - start;";
+    let program = Parser::new()
+        .parse(scanner::scan_tokens(source).unwrap())
+        .unwrap();
+    let mut interp = Interpreter::new();
+    interp
+        .execute(&program, resolver::resolve(&program).unwrap())
+        .unwrap();
+
+    let number = |name: &str| match interp.environment.get_global(&name.to_string()) {
+        Some(LoxValue::Number(n)) => n,
+        other => panic!("expected a number, got {other:?}"),
+    };
+    // 2020-01-01
+    assert!(number("epoch") > 1_577_836_800.);
+    assert!(number("seconds").abs() <= 1.);
+    assert!(number("start") >= 0.);
+    assert!(number("elapsed") >= 0.);
+    assert_eq!(
+        interp.environment.get_global(&"date".to_string()),
+        Some(LoxValue::String("08 Mar 2026 14:05:59".to_owned()))
+    );
+}
+
+#[test]
+fn seeded_random_numbers() {
+    let numbers = |source: &str| match run_result(source) {
+        Ok(LoxValue::List(list)) => list
+            .borrow()
+            .iter()
+            .map(|value| match value {
+                LoxValue::Number(n) => *n,
+                other => panic!("expected a number, got {other:?}"),
+            })
+            .collect::<Vec<_>>(),
+        other => panic!("expected a list, got {other:?}"),
+    };
+
+    let source = "seedRandom(42);
+        var result = [];
+        for (i in range(100)) { result.push(random()); result.push(randomInt(-3, 3)); }";
+    let first = numbers(source);
+    assert_eq!(first, numbers(source));
+    for pair in first.chunks(2) {
+        assert!((0. ..1.).contains(&pair[0]));
+        assert!((-3. ..=3.).contains(&pair[1]) && pair[1].fract() == 0.);
+    }
+
+    for (source, expected) in [
+        (
+            "randomInt(1, 0.5);",
+            (
+                1,
+                10,
+                "Argument 2 of randomInt() must be an integer, got 0.5.",
+            ),
+        ),
+        (
+            "randomInt(2, 1);",
+            (
+                1,
+                10,
+                "Cannot pick a number between 2 and 1, the lower bound is greater.",
+            ),
+        ),
+        (
+            "seedRandom(\"a\");",
+            (
+                1,
+                11,
+                "Argument 1 of seedRandom() must be a number, got string.",
+            ),
+        ),
+    ] {
+        match run_result(source) {
+            Err(Error::RuntimeError {
+                line,
+                position,
+                message,
+                ..
+            }) => assert_eq!((line, position, message.as_str()), expected),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn env_natives_need_the_capability() {
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let run = |source: &str, capabilities: &[Capability]| {
+        let program = Parser::new()
+            .parse(scanner::scan_tokens(source).unwrap())
+            .unwrap();
+        let mut interp = Interpreter::new();
+        for &capability in capabilities {
+            interp.allow(capability);
+        }
+        interp.execute(&program, resolver::resolve(&program).unwrap())?;
+        Ok::<_, Error>(interp.environment.get_global(&"result".to_string()))
+    };
+
+    let source = "setEnv(\"RLOX_TEST_ENV\", \"a=b\");
+        var result = getEnv(\"RLOX_TEST_ENV\") + \" \" + platform();
+        setEnv(\"RLOX_TEST_ENV\", nil);
+        result = result + \" \" + toString(getEnv(\"RLOX_TEST_ENV\"));";
+    assert_eq!(
+        run(source, &[Capability::Env, Capability::Env]).unwrap(),
+        Some(LoxValue::String(format!(
+            "a=b {} nil",
+            std::env::consts::OS
+        )))
+    );
+    assert!(run("var result = platform();", &[]).is_err());
+
+    match run("setEnv(\"A=B\", \"c\");", &[Capability::Env]) {
+        Err(Error::RuntimeError { message, .. }) => {
+            assert_eq!(message, "Invalid environment variable name 'A=B'.")
+        }
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+}
+
+#[test]
+fn exit_is_not_caught() {
+    use crate::edition::Edition;
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let run = |source: &str| {
+        let program = Parser::with_edition(Edition::Lox2026)
+            .parse(scanner::scan_tokens(source).unwrap())
+            .unwrap();
+        let mut interp = Interpreter::with_edition(Edition::Lox2026);
+        interp.execute(&program, resolver::resolve(&program).unwrap())
+    };
+
+    let source = "fun f() { exit(3); }
+        var result = 0;
+        try { f(); } catch { result = 1; }
+        result = 2;";
+    assert!(matches!(run(source), Err(Error::Exit { code: 3 })));
+    assert!(matches!(
+        run("eval(\"exit();\");"),
+        Err(Error::Exit { code: 0 })
+    ));
+    match run("exit(256);") {
+        Err(Error::RuntimeError { message, .. }) => {
+            assert_eq!(message, "Exit code must be between 0 and 255, got 256.")
+        }
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+}
+
+#[test]
+fn assertions() {
+    use crate::edition::Edition;
+
+    let source = "assert(true); assert(1 < 2, \"math\");
+        var result;
+        try { assert(nil, \"x is \" + toString(1)); } catch e { result = e.message; }";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2026).unwrap(),
+        LoxValue::String("Assertion failed: x is 1".to_owned())
+    );
+
+    for (source, expected) in [
+        ("\n  assert(false);", (2, 9, "Assertion failed.")),
+        (
+            "fun f(x) {\n  panic(\"bad \" + toString(x));\n}\nf(3);",
+            (2, 8, "Panic: bad 3"),
+        ),
+    ] {
+        match run_result_in(source, Edition::Lox2026) {
+            Err(Error::RuntimeError {
+                line,
+                position,
+                message,
+                ..
+            }) => assert_eq!((line, position, message.as_str()), expected),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn exec_runs_commands() {
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let run = |source: &str, capabilities: &[Capability]| {
+        let program = Parser::new()
+            .parse(scanner::scan_tokens(source).unwrap())
+            .unwrap();
+        let mut interp = Interpreter::new();
+        for &capability in capabilities {
+            interp.allow(capability);
+        }
+        interp.execute(&program, resolver::resolve(&program).unwrap())?;
+        Ok::<_, Error>(interp.environment.get_global(&"result".to_string()))
+    };
+
+    let source = "var output = exec(\"sh\", [\"-c\", \"echo out; echo err >&2; exit 3\"]);
+        var result = toString(output[\"status\"]) + \" \" + output[\"stdout\"] + output[\"stderr\"];";
+    assert_eq!(
+        run(source, &[Capability::Process]).unwrap(),
+        Some(LoxValue::String("3 out\nerr\n".to_owned()))
+    );
+    assert!(run(source, &[Capability::Env]).is_err());
+
+    for (source, expected) in [
+        (
+            "exec(\"sh\", [\"-c\", 1]);",
+            "Argument 2 of exec() must be a list of strings, got list.",
+        ),
+        (
+            "exec(\"rlox-no-such-command\");",
+            "Could not run 'rlox-no-such-command': No such file or directory (os error 2)",
+        ),
+    ] {
+        match run(source, &[Capability::Process]) {
+            Err(Error::RuntimeError { message, .. }) => assert_eq!(message, expected),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn http_get() {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        for response in [
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello",
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n",
+        ] {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    assert_eq!(
+        run_result(&format!("var result = httpGet(\"http://{address}/\");")).unwrap(),
+        LoxValue::String("hello".to_owned())
+    );
+    match run_result(&format!("httpGet(\"http://{address}/missing\");")) {
+        Err(Error::RuntimeError { message, .. }) => assert_eq!(
+            message,
+            format!("GET http://{address}/missing failed with status 404.")
+        ),
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn native_modules() {
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let run = |source: &str| {
+        let program = Parser::new()
+            .parse(scanner::scan_tokens(source).unwrap())
+            .unwrap();
+        let mut interp = Interpreter::new();
+        fn answer(_ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+            Ok(LoxValue::Number(42.))
+        }
+        interp
+            .install(NativeModule::namespace("host").function("answer", 0, answer))
+            .unwrap();
+        interp.execute(&program, resolver::resolve(&program).unwrap())?;
+        Ok::<_, Error>(interp.environment.get_global(&"result".to_string()))
+    };
+
+    assert_eq!(
+        run("var result = [math.sqrt(16), math.pow(2, 10), math.abs(-1.5), math.floor(-1.5), math.ceil(1.2), math.round(2.5), math.min(1, 2), math.max(1, 2), host.answer()];")
+            .unwrap()
+            .map(|list| list.to_string()),
+        Some("[4, 1024, 1.5, -2, 2, 3, 1, 2, 42]".to_owned())
+    );
+    match run("math.sqrt(\"a\");") {
+        Err(Error::RuntimeError { message, .. }) => assert_eq!(
+            message,
+            "Argument 1 of math.sqrt() must be a number, got string."
+        ),
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+}
+
+#[test]
+fn extension_natives() {
+    use crate::extension::{RloxDefine, RloxValue, RLOX_NUMBER, RLOX_STRING};
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    use std::ffi::{c_int, c_void};
+
+    unsafe extern "C" fn twice(
+        args: *const RloxValue,
+        count: usize,
+        result: *mut RloxValue,
+    ) -> c_int {
+        let args = unsafe { std::slice::from_raw_parts(args, count) };
+        let result = unsafe { &mut *result };
+        if args[0].kind != RLOX_NUMBER {
+            result.kind = RLOX_STRING;
+            result.string = c"expected a number".as_ptr();
+            return 1;
+        }
+        result.kind = RLOX_NUMBER;
+        result.number = args[0].number * 2.;
+        0
+    }
+
+    unsafe extern "C" fn register(registry: *mut c_void, define: RloxDefine) {
+        unsafe { define(registry, c"twice".as_ptr(), 1, twice) };
+    }
+
+    let run = |source: &str| {
+        let program = Parser::new()
+            .parse(scanner::scan_tokens(source).unwrap())
+            .unwrap();
+        let mut interp = Interpreter::new();
+        interp.register_extension(register)?;
+        assert!(interp.register_extension(register).is_err());
+        interp.execute(&program, resolver::resolve(&program).unwrap())?;
+        Ok::<_, Error>(interp.environment.get_global(&"result".to_string()))
+    };
+
+    assert_eq!(
+        run("var result = twice(21);").unwrap(),
+        Some(LoxValue::Number(42.))
+    );
+    for (source, expected) in [
+        ("twice(\"a\");", "twice() failed: expected a number"),
+        (
+            "twice([]);",
+            "Argument 1 of twice() cannot be passed to a native extension, got list.",
+        ),
+    ] {
+        match run(source) {
+            Err(Error::RuntimeError { message, .. }) => assert_eq!(message, expected),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn output_sinks() {
+    use crate::output::Captured;
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+
+    let source = "print 1; eprint(\"oops\"); print [true, nil];";
+    let program = Parser::new()
+        .parse(scanner::scan_tokens(source).unwrap())
+        .unwrap();
+    let (output, errors) = (Captured::new(), Captured::new());
+    let mut interp = Interpreter::new();
+    interp.set_output(output.clone());
+    interp.set_error_output(errors.clone());
+    interp
+        .execute(&program, resolver::resolve(&program).unwrap())
+        .unwrap();
+
+    assert_eq!(output.contents(), "1\n[true, nil]\n");
+    assert_eq!(errors.contents(), "oops\n");
+}
+
+#[test]
+fn closures_as_natives() {
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+
+    let calls = Rc::new(RefCell::new(0.));
+    let mut interp = Interpreter::new();
+    let counter = calls.clone();
+    interp
+        .register_native("count", 1, move |ctx| {
+            *counter.borrow_mut() += ctx.arg_number(0)?;
+            Ok(LoxValue::Number(*counter.borrow()))
+        })
+        .unwrap();
+    assert!(interp
+        .register_native("count", 0, |_| Ok(LoxValue::Nil))
+        .is_err());
+
+    let source = "count(1); count(2); var result = count(3);";
+    let program = Parser::new()
+        .parse(scanner::scan_tokens(source).unwrap())
+        .unwrap();
+    interp
+        .execute(&program, resolver::resolve(&program).unwrap())
+        .unwrap();
+    assert_eq!(
+        interp.environment.get_global(&"result".to_string()),
+        Some(LoxValue::Number(6.))
+    );
+    assert_eq!(*calls.borrow(), 6.);
+}
+
+#[test]
+fn call_function_from_the_host() {
+    use crate::edition::Edition;
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+
+    let source = "fun add(a, b) { return a + b; }
+        fun fail(message) {
+          throw message;
+        }
+        class Point { init(x) { this.x = x; } }
+        var notAFunction = 1;";
+    let program = Parser::with_edition(Edition::Lox2026)
+        .parse(scanner::scan_tokens(source).unwrap())
+        .unwrap();
+    let mut interp = Interpreter::with_edition(Edition::Lox2026);
+    interp
+        .execute(&program, resolver::resolve(&program).unwrap())
+        .unwrap();
+
+    let number = |n| LoxValue::Number(n);
+    assert_eq!(
+        interp
+            .call_function("add", &[number(1.), number(2.)])
+            .unwrap(),
+        number(3.)
+    );
+    assert_eq!(
+        interp
+            .call_function("len", &[LoxValue::String("abc".to_owned())])
+            .unwrap(),
+        number(3.)
+    );
+    let point = interp.call_function("Point", &[number(5.)]).unwrap();
+    assert_eq!(point.to_string(), "<Point instance>");
+
+    for (name, args, expected) in [
+        (
+            "add",
+            vec![number(1.)],
+            (0, 0, "Expected 2 arguments but got 1."),
+        ),
+        ("missing", vec![], (0, 0, "Undefined variable 'missing'.")),
+        ("notAFunction", vec![], (0, 0, "Can only call functions.")),
+        (
+            "fail",
+            vec![LoxValue::String("boom".to_owned())],
+            (3, 11, "Uncaught exception: boom."),
+        ),
+    ] {
+        match interp.call_function(name, &args) {
+            Err(Error::RuntimeError {
+                line,
+                position,
+                message,
+                ..
+            }) => assert_eq!((line, position, message.as_str()), expected),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+    assert!(interp.arg_stack.is_empty());
+}
+
+#[test]
+fn globals_from_the_host() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_global("width", 3.).unwrap();
+    interpreter.set_global("name", "box").unwrap();
+    interpreter.set_global("verbose", Some(true)).unwrap();
+
+    let source = "const unit = \"cm\";
+        var result = name + \": \" + toString(width * 2) + unit;
+        verbose = !verbose;";
+    let program = Parser::with_edition(Edition::Lox2026)
+        .parse(scanner::scan_tokens(source).unwrap())
+        .unwrap();
+    interpreter
+        .execute(&program, resolver::resolve(&program).unwrap())
+        .unwrap();
+
+    assert_eq!(
+        interpreter
+            .get_global_value("result")
+            .map(|v| v.to_string()),
+        Some("box: 6cm".to_owned())
+    );
+    assert!(matches!(
+        interpreter.get_global_value("verbose"),
+        Some(LoxValue::Bool(false))
+    ));
+    assert!(interpreter.get_global_value("missing").is_none());
+
+    interpreter.set_global("width", 4_i64).unwrap();
+    assert!(matches!(
+        interpreter.get_global_value("width"),
+        Some(LoxValue::Number(n)) if n == 4.
+    ));
+    match interpreter.set_global("unit", "m") {
+        Err(Error::RuntimeError { message, .. }) => {
+            assert_eq!(message, "Cannot assign to constant 'unit'.")
+        }
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+}
+
+#[test]
+fn bind_prepends_arguments() {
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let run = |source: &str| {
+        let tokens = scanner::scan_tokens(source).unwrap();
+        let program = Parser::new().parse(tokens).unwrap();
+        let access_table = resolver::resolve(&program).unwrap();
+        let mut interp = Interpreter::new();
+        let result = interp.execute(&program, access_table);
+        assert!(interp.arg_stack.is_empty());
+        result?;
+        Ok::<_, Error>(interp.environment.get_global(&"result".to_string()))
+    };
+
+    let source = "fun join(a, b, c) { return a + b + c; }
+        var ab = bind(join, \"a\", \"b\");
+        var a = bind(join, \"a\");
+        var result = ab(\"c\") + bind(a, \"x\")(\"y\") + toString(arity(a)) + nameOf(ab) + toString(ab);";
+    assert_eq!(
+        run(source).unwrap(),
+        Some(LoxValue::String("abcaxy2join<bound fn join>".to_owned()))
+    );
+
+    match run("fun f(a, b) {} var result = bind(f, 1)();") {
+        Err(Error::RuntimeError { message, .. }) => {
+            assert_eq!(message, "Expected 2 arguments but got 1.")
+        }
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+    assert!(matches!(
+        run("var result = bind(1, 2);"),
+        Err(Error::RuntimeError { .. })
+    ));
+}
+
+#[test]
+fn string_interpolation() {
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source = "var name = \"world\";
+fun twice(x) { return x * 2; }
+var result = \"hello ${name}, ${twice(2) + 1} ${\"<${name}>\"} \\${name} ${nil}\";";
+    let tokens = scanner::scan_tokens(source).unwrap();
+    let program = Parser::new().parse(tokens).unwrap();
+    let access_table = resolver::resolve(&program).unwrap();
+    let mut interp = Interpreter::new();
+    interp.execute(&program, access_table).unwrap();
+    assert_eq!(
+        interp.environment.get_global(&"result".to_string()),
+        Some(LoxValue::String(
+            "hello world, 5 <world> ${name} nil".to_owned()
+        ))
+    );
+
+    let tokens = scanner::scan_tokens("print \"a ${1 2}\";").unwrap();
+    match Parser::new().parse(tokens).unwrap_err().as_slice() {
+        [Error::ParsingError {
+            line,
+            position,
+            message,
+            ..
+        }] => assert_eq!(
+            (*line, *position, message.as_str()),
+            (
+                1,
+                14,
+                "Expect '}' after interpolated expression, found '2'."
+            )
+        ),
+        other => panic!("expected a parsing error, got {other:?}"),
+    }
+}
+
+#[test]
+fn lists() {
+    let source = "var xs = [1, \"two\", [3, nil],];
+xs[0] = xs[0] + 10;
+xs[2][1] = len(xs) + len(\"żółw\");
+var result = toString(xs);";
+    assert_eq!(
+        run_result(source).unwrap(),
+        LoxValue::String("[11, \"two\", [3, 7]]".to_owned())
+    );
+    assert_eq!(
+        run_result("var result = len([]);").unwrap(),
+        LoxValue::Number(0.)
+    );
+
+    for (source, expected) in [
+        (
+            "[1, 2][2];",
+            (1, 7, "Index 2 is out of range for a list of length 2."),
+        ),
+        (
+            "var xs = [1];\nxs[0.5] = 1;",
+            (2, 3, "List index must be an integer, got 0.5."),
+        ),
+        (
+            "[1][\"0\"];",
+            (1, 4, "List index must be an integer, got \"0\"."),
+        ),
+        (
+            "var s = \"abc\"; s[0];",
+            (1, 17, "Values of type string cannot be indexed."),
+        ),
+        (
+            "len(1);",
+            (
+                1,
+                4,
+                "Argument 1 of len() must be a list, map or string, got number.",
+            ),
+        ),
+    ] {
+        match run_result(source) {
+            Err(Error::RuntimeError {
+                line,
+                position,
+                message,
+                ..
+            }) => assert_eq!((line, position, message.as_str()), expected),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn negative_indexes_and_slices() {
+    let source = "var xs = [1, 2, 3, 4];
+xs[-1] = xs[-1] + xs[-4];
+var tail = xs[1:];
+tail[0] = 0;
+var result = toString([xs[1:3], xs[:-2], xs[-2:], tail, xs[:], xs[2:2]]);";
+    assert_eq!(
+        run_result(source).unwrap(),
+        LoxValue::String("[[2, 3], [1, 2], [3, 5], [0, 3, 5], [1, 2, 3, 5], []]".to_owned())
+    );
+
+    for (source, expected) in [
+        (
+            "var xs = [1, 2];\nxs[-3];",
+            (2, 3, "Index -3 is out of range for a list of length 2."),
+        ),
+        (
+            "[1, 2][1:4];",
+            (1, 7, "Index 4 is out of range for a list of length 2."),
+        ),
+        (
+            "[1, 2][0.5:];",
+            (1, 7, "List index must be an integer, got 0.5."),
+        ),
+        (
+            "\"abc\"[1:];",
+            (1, 6, "Values of type string cannot be indexed."),
+        ),
+    ] {
+        match run_result(source) {
+            Err(Error::RuntimeError {
+                line,
+                position,
+                message,
+                ..
+            }) => assert_eq!((line, position, message.as_str()), expected),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn maps() {
+    let source = "var m = {\"a\": 1, 2: [true], nil: \"none\",};
+m[\"a\"] = m[\"a\"] + 1;
+m[\"new\"] = len(m);
+var removed = remove(m, 2);
+var result = toString([m, removed, has(m, \"a\"), has(m, 2), remove(m, 2), keys(m), values({})]);";
+    assert_eq!(
+        run_result(source).unwrap(),
+        LoxValue::String(
+            "[{\"a\": 2, nil: \"none\", \"new\": 3}, [true], true, false, nil, [\"a\", nil, \"new\"], []]"
+                .to_owned()
+        )
+    );
+
+    for (source, expected) in [
+        ("var m = {1: 2};\nm[2];", (2, 2, "Key 2 not found in map.")),
+        (
+            "var m = {[]: 1};",
+            (1, 9, "Values of type list cannot be used as keys."),
+        ),
+        (
+            "var m = {}; m[0.5] = 1;",
+            (1, 14, "Only integer numbers can be used as keys, got 0.5."),
+        ),
+        (
+            "has(1, 2);",
+            (1, 4, "Argument 1 of has() must be a map, got number."),
+        ),
+        ("remove({}, 0 / 0);", (1, 7, "NaN cannot be used as a key.")),
+    ] {
+        match run_result(source) {
+            Err(Error::RuntimeError {
+                line,
+                position,
+                message,
+                ..
+            }) => assert_eq!((line, position, message.as_str()), expected),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn map_properties() {
+    let source =
+        "var config = {\"name\": \"lox\", \"nested\": {\"depth\": 2}, \"unset\": nil, 1: 0};
+var result = config.name + toString(config.nested.depth) + toString(config.unset ?? 3);";
+    assert_eq!(
+        run_result(source).unwrap(),
+        LoxValue::String("lox23".to_owned())
+    );
+
+    let source = "var m = {\"a\": 1};
+m.a = m.a + 1;
+m.b = \"new\";
+var result = toString(m);";
+    assert_eq!(
+        run_result(source).unwrap(),
+        LoxValue::String("{\"a\": 2, \"b\": \"new\"}".to_owned())
+    );
+
+    for (source, expected) in [
+        (
+            "var m = {\"a\": 1};\nm.b;",
+            (2, 3, "Undefined property 'b'."),
+        ),
+        ("var m = {1: 1};\nm.b;", (2, 3, "Undefined property 'b'.")),
+        (
+            "var b = true;\nb.length;",
+            (2, 3, "Only instances and maps have properties."),
+        ),
+    ] {
+        match run_result(source) {
+            Err(Error::RuntimeError {
+                line,
+                position,
+                message,
+                ..
+            }) => assert_eq!((line, position, message.as_str()), expected),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn builtin_methods() {
+    let source = "var xs = [1];
+xs.push(\"two\");
+var push = xs.push;
+push(3);
+var popped = [xs.pop(), [].pop()];
+var words = \"  Zażółć gęślą  \".trim().split(\" \");
+var result = [
+    \"żółw\".length(), \"Abc\".upper(), \"Abc\".lower(), \"abc\".contains(\"bc\"), \"ab\".split(\"\"),
+    (-1.5).abs(), 1.5.floor(), 1.2.ceil(), 2.5.round(),
+    xs, popped, xs.length(), xs.contains(\"two\"), words.join(\"-\"), arity(push),
+];
+result = toString(result);";
+    assert_eq!(
+        run_result(source).unwrap(),
+        LoxValue::String(
+            concat!(
+                "[4, \"ABC\", \"abc\", true, [\"a\", \"b\"], ",
+                "1.5, 1, 2, 3, ",
+                "[1, \"two\"], [3, nil], 2, true, \"Zażółć-gęślą\", 1]"
+            )
+            .to_owned()
+        )
+    );
+
+    for (source, expected) in [
+        (
+            "var s = \"abc\";\ns.reverse();",
+            (2, 3, "Undefined property 'reverse'."),
+        ),
+        (
+            "\"abc\".split(1);",
+            (1, 12, "Argument 1 of split() must be a string, got number."),
+        ),
+        ("[].push();", (1, 8, "Expected 1 arguments but got 0.")),
+    ] {
+        match run_result(source) {
+            Err(Error::RuntimeError {
+                line,
+                position,
+                message,
+                ..
+            }) => assert_eq!((line, position, message.as_str()), expected),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn for_in_loops() {
+    use crate::edition::Edition;
+
+    let source = "var result = \"\";
+var xs = [1, 2, 3, 4];
+for (x in xs) {
+    if x == 2 { continue; }
+    if x == 4 { break; }
+    xs[3] = 10;
+    result = result + toString(x);
+}
+for (key in {\"a\": 1, \"b\": 2}) { result = result + key; }
+for (c in \"żó\") { result = result + \"[\" + c + \"]\"; }";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2026).unwrap(),
+        LoxValue::String("13ab[ż][ó]".to_owned())
+    );
+
+    // every iteration binds a fresh variable
+    let source = "var closures = [];
+for (x in [1, 2]) {
+    fun get() { return x; }
+    closures = [get, closures];
+}
+var result = closures[0]() * 10 + closures[1][0]();";
+    assert_eq!(
+        run_result_in(source, Edition::Lox2026).unwrap(),
+        LoxValue::Number(21.)
+    );
+
+    match run_result_in("for (x in 12) {}", Edition::Lox2026) {
+        Err(Error::RuntimeError {
+            line,
+            position,
+            message,
+            ..
+        }) => assert_eq!(
+            (line, position, message.as_str()),
+            (1, 8, "Values of type number cannot be iterated.")
+        ),
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+}
+
+#[test]
+fn ranges() {
+    let source = "var result = [];
+for (i in range(3)) { result = [result, i]; }
+for (i in range(10, 4, -3)) { result = [result, i]; }
+result = toString([result, range(1, 2)]);";
+    assert_eq!(
+        run_result(source).unwrap(),
+        LoxValue::String("[[[[[[[], 0], 1], 2], 10], 7], range(1, 2, 1)]".to_owned())
+    );
+
+    for (source, expected) in [
+        ("range(1, 2, 0);", "Range step must not be zero."),
+        (
+            "range(\"a\");",
+            "Argument 1 of range() must be a number, got string.",
+        ),
+        ("range();", "Expected 1 to 3 arguments but got 0."),
+    ] {
+        match run_result(source) {
+            Err(Error::RuntimeError { message, .. }) => assert_eq!(message, expected),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn comma_operator() {
+    let source = "var a; var b;
+var result = (a = 1, b = 2, a + b);
+var j;
+for (var i = (j = 10, 0); i < j; i = i + 1, j = j - 1) { result = [result, i, j]; }
+result = toString([result, len([a, b]), {a: b}[1]]);";
+    assert_eq!(
+        run_result(source).unwrap(),
+        LoxValue::String("[[[[[[3, 0, 10], 1, 9], 2, 8], 3, 7], 4, 6], 2, 2]".to_owned())
+    );
+}
+
+#[test]
+fn repl_lines() {
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let mut parser = Parser::with_edition(Edition::Lox2026);
+    let mut interp = Interpreter::new();
+    let mut run = |source: &str| {
+        let tokens = scanner::scan_tokens(source).unwrap();
+        let program = parser.parse(tokens).unwrap();
+        let access_table = resolver::resolve(&program).unwrap();
+        interp.execute_line(&program, access_table)
+    };
+
+    assert_eq!(run("var a = 2;").unwrap(), None);
+    assert_eq!(
+        run("a = a + 1; a * 2;").unwrap(),
+        Some(LoxValue::Number(6.))
     );
+    assert_eq!(run("a * 2; print a;").unwrap(), None);
+    assert!(matches!(
+        run("fun f() { throw 1; } f();"),
+        Err(Error::RuntimeError { line: 1, .. })
+    ));
 }
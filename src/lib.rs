@@ -0,0 +1,52 @@
+//! The rlox interpreter as a library, for Rust programs embedding it.
+//!
+//! A program is run by scanning it with `scanner::scan_tokens`, parsing
+//! the tokens with `parser::Parser`, resolving the tree with
+//! `resolver::resolve` and executing it with `interpreter::Interpreter`,
+//! which also takes natives, plugins and the output of `print`. With the
+//! `ffi` feature the library also exports the C API of `ffi`.
+
+pub mod ast_builder;
+pub mod capability;
+pub mod datetime;
+pub mod diagnostic;
+pub mod edition;
+pub mod environment;
+pub mod error;
+pub mod expression;
+pub mod extension;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod format;
+pub mod formatter;
+pub mod interpreter;
+pub mod lint;
+pub mod lower;
+pub mod lox_function;
+pub mod lox_key;
+pub mod lox_map;
+pub mod lox_object;
+pub mod lox_range;
+pub mod lox_value;
+pub mod messages;
+pub mod module_loader;
+pub mod native_module;
+pub mod output;
+pub mod parser;
+pub mod plugin;
+pub mod pretty;
+pub mod project;
+pub mod random;
+pub mod reporter;
+pub mod resolver;
+pub mod scanner;
+pub mod sexpr;
+pub mod shared;
+pub mod source_map;
+pub mod statement;
+pub mod suggestions;
+pub mod tokens;
+pub mod typed_native;
+
+use error::*;
+use tokens::*;
@@ -0,0 +1,151 @@
+//! `rlox lint`, warnings about code which runs, but likely not the way it
+//! was meant to.
+//!
+//! The checks are made by the resolver while it walks the program, so they
+//! see the scopes the way the interpreter will. Every warning belongs to a
+//! rule, which can be turned off with `--disable`.
+
+use std::str::FromStr;
+
+use crate::diagnostic::Diagnostic;
+use crate::error::Error;
+use crate::expression::{DebugInfo, Expression, LiteralValue};
+use crate::resolver::Resolver;
+use crate::statement::Statement;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rule {
+    /// A local variable or function which is never read.
+    UnusedVariable,
+    /// A local declared with the name of a local from an enclosing scope.
+    Shadowing,
+    /// A block without statements, other than the body of a function, a
+    /// `catch` or an arm of a `match`.
+    EmptyBlock,
+    /// A literal as the condition of an `if` or a `while`, except for the
+    /// `while (true)` of a loop ended with `break`.
+    ConstantCondition,
+}
+
+impl Rule {
+    pub const ALL: &'static [Rule] = &[
+        Rule::UnusedVariable,
+        Rule::Shadowing,
+        Rule::EmptyBlock,
+        Rule::ConstantCondition,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::UnusedVariable => "unused-variable",
+            Rule::Shadowing => "shadowing",
+            Rule::EmptyBlock => "empty-block",
+            Rule::ConstantCondition => "constant-condition",
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Rule::ALL
+            .iter()
+            .find(|rule| rule.name() == name)
+            .copied()
+            .ok_or(())
+    }
+}
+
+/// A diagnostic of `rule`, always a warning.
+#[derive(Debug)]
+pub struct Warning {
+    pub rule: Rule,
+    pub diagnostic: Diagnostic,
+}
+
+/// Returns the warnings about `program` of the rules not in `disabled`,
+/// in the order of their positions.
+pub fn lint(program: &[Statement], disabled: &[Rule]) -> Result<Vec<Warning>, Error> {
+    let mut resolver = Resolver::new();
+    resolver.warnings = Some(Vec::new());
+    resolver.resolve(program)?;
+
+    let mut warnings = resolver.warnings.unwrap_or_default();
+    warnings.retain(|warning| !disabled.contains(&warning.rule));
+    warnings.sort_by_key(|warning| {
+        (
+            warning.diagnostic.span.line,
+            warning.diagnostic.span.position,
+        )
+    });
+    Ok(warnings)
+}
+
+/// The literal `condition` consists of, looking through parentheses, and
+/// where it is.
+pub fn constant(condition: &Expression) -> Option<(&LiteralValue, &DebugInfo)> {
+    match condition {
+        Expression::Grouping(grouping) => constant(&grouping.expression),
+        Expression::Literal(literal) => match &literal.value {
+            value @ (LiteralValue::Number(_, debug_info)
+            | LiteralValue::String(_, debug_info)
+            | LiteralValue::True(debug_info)
+            | LiteralValue::False(debug_info)
+            | LiteralValue::Nil(debug_info)) => Some((value, debug_info)),
+        },
+        _ => None,
+    }
+}
+
+#[test]
+fn test_lint() {
+    use crate::edition::Edition;
+    use crate::parser::Parser;
+    use crate::scanner;
+
+    let source = "var global = 1;
+fun f(parameter) {
+  var unused = 1;
+  var _ignored = 2;
+  var global = 3;
+  {
+    var global = 4;
+    print global;
+  }
+  if (false) {}
+  while (true) { break; }
+  for (;;) { break; }
+  try { throw 1; } catch {}
+  fun helper() {}
+}
+f(1);
+";
+    let tokens = scanner::scan_tokens(source).unwrap();
+    let program = Parser::with_edition(Edition::Lox2026)
+        .parse(tokens)
+        .unwrap();
+
+    let warnings = |disabled| {
+        lint(&program, disabled)
+            .unwrap()
+            .into_iter()
+            .map(|w| (w.rule, w.diagnostic.span.line, w.diagnostic.span.position))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        warnings(&[]),
+        [
+            (Rule::UnusedVariable, 3, 7),
+            (Rule::UnusedVariable, 5, 7),
+            (Rule::Shadowing, 7, 9),
+            (Rule::ConstantCondition, 10, 7),
+            (Rule::EmptyBlock, 10, 14),
+            (Rule::UnusedVariable, 14, 7),
+        ]
+    );
+    assert_eq!(
+        warnings(&[Rule::UnusedVariable, Rule::EmptyBlock]),
+        [(Rule::Shadowing, 7, 9), (Rule::ConstantCondition, 10, 7)]
+    );
+}
@@ -0,0 +1,186 @@
+//! Lowering of syntax sugar, run on parsed programs before they are
+//! resolved.
+//!
+//! The parser keeps every construct as it was written, so tools such as
+//! `--print-ast` can show it. The resolver and the interpreter only know
+//! the statements the sugar stands for:
+//!
+//! - `for (initializer; condition; increment) body` becomes
+//!   `{ initializer; while condition body }`, with `increment` evaluated
+//!   after every iteration of the `while`.
+
+use crate::expression::{Expression, Literal, LiteralValue};
+use crate::statement::{Block, Catch, FunctionDeclaration, MatchArm, Statement};
+
+pub fn program(program: Vec<Statement>) -> Vec<Statement> {
+    program.into_iter().map(statement).collect()
+}
+
+fn block(block: Block) -> Block {
+    Block {
+        statements: program(block.statements),
+        debug_info: block.debug_info,
+    }
+}
+
+fn statement(statement: Statement) -> Statement {
+    match statement {
+        Statement::For {
+            initializer,
+            condition,
+            increment,
+            body,
+            debug_info,
+        } => {
+            // a loop without a condition runs until `break`
+            let condition = condition.unwrap_or_else(|| {
+                Expression::from(Literal {
+                    value: LiteralValue::True(debug_info.clone()),
+                })
+            });
+            let mut statements: Vec<_> = initializer
+                .map(|initializer| self::statement(*initializer))
+                .into_iter()
+                .collect();
+            statements.push(Statement::While {
+                condition,
+                body: block(body),
+                increment,
+            });
+            Statement::Block(Block {
+                statements,
+                debug_info,
+            })
+        }
+
+        Statement::Block(body) => Statement::Block(block(body)),
+        Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => Statement::If {
+            condition,
+            then_branch: block(then_branch),
+            else_branch: else_branch.map(block),
+        },
+        Statement::While {
+            condition,
+            body,
+            increment,
+        } => Statement::While {
+            condition,
+            body: block(body),
+            increment,
+        },
+        Statement::ForIn {
+            variable,
+            iterable,
+            body,
+            debug_info,
+        } => Statement::ForIn {
+            variable,
+            iterable,
+            body: block(body),
+            debug_info,
+        },
+        Statement::Match {
+            value,
+            arms,
+            default,
+        } => Statement::Match {
+            value,
+            arms: arms
+                .into_iter()
+                .map(|MatchArm { patterns, body }| MatchArm {
+                    patterns,
+                    body: block(body),
+                })
+                .collect(),
+            default: default.map(block),
+        },
+        Statement::Try {
+            body,
+            catch,
+            finally,
+        } => Statement::Try {
+            body: block(body),
+            catch: catch.map(|Catch { variable, body }| Catch {
+                variable,
+                body: block(body),
+            }),
+            finally: finally.map(block),
+        },
+        Statement::Function {
+            name,
+            args,
+            body,
+            variadic,
+        } => Statement::Function {
+            name,
+            args,
+            body: block(body),
+            variadic,
+        },
+        Statement::Class {
+            name,
+            superclass,
+            methods,
+        } => Statement::Class {
+            name,
+            superclass,
+            methods: methods
+                .into_iter()
+                .map(|method| FunctionDeclaration {
+                    body: block(method.body),
+                    ..method
+                })
+                .collect(),
+        },
+
+        statement @ (Statement::Expression(_)
+        | Statement::Print(_)
+        | Statement::Variable { .. }
+        | Statement::Throw { .. }
+        | Statement::Import { .. }
+        | Statement::Return { .. }
+        | Statement::Break { .. }
+        | Statement::Continue { .. }
+        | Statement::Error { .. }) => statement,
+    }
+}
+
+#[test]
+fn test_lower() {
+    use crate::edition::Edition;
+    use crate::parser::Parser;
+    use crate::scanner;
+    use crate::sexpr;
+
+    let tokens = scanner::scan_tokens(
+        "fun f() { for (var i = 0; i < 3; i = i + 1) { for (;;) { break; } } }",
+    )
+    .unwrap();
+    let program = Parser::with_edition(Edition::Lox2026)
+        .parse_syntax(tokens)
+        .unwrap();
+    assert_eq!(
+        sexpr::program(&program),
+        "(fun f ()
+  (for (var i 0) (< i 3) (= i (+ i 1))
+    (for _ _ _
+      (break))))
+"
+    );
+    assert_eq!(
+        sexpr::program(&self::program(program)),
+        "(fun f ()
+  (block
+    (var i 0)
+    (while (< i 3)
+      (increment (= i (+ i 1)))
+      (block
+        (while true
+          (break))))))
+"
+    );
+}
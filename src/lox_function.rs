@@ -1,35 +1,240 @@
 use core::fmt;
 
 use crate::{
-    environment::FrameRef, expression::Identifier, interpreter::Interpreter, lox_value::LoxValue,
-    statement::Block, Error,
+    environment::{FrameRef, Module},
+    expression::{DebugInfo, Identifier},
+    interpreter::Interpreter,
+    lox_key::{LoxKey, MAX_SAFE_INTEGER},
+    lox_map::LoxMap,
+    lox_value::LoxValue,
+    messages::{message, Message},
+    shared::{Rc, RefCell, Shareable, Weak},
+    statement::Block,
+    Error,
 };
 
-#[derive(PartialEq, Clone, Debug)]
+/// Native functions receive their arguments as a slice borrowed from the
+/// interpreter's argument stack, so calling them does not allocate.
+/// A native function, closures can keep state of the host, e.g.
+/// `move |_| Ok(LoxValue::Number(counter.get()))`.
+pub type NativeFn = Rc<dyn Native>;
+
+/// The function behind a [`NativeFn`], `Send + Sync` in the `sync` build.
+pub trait Native: Fn(&mut NativeContext) -> Result<LoxValue, Error> + Shareable {}
+
+impl<F: Fn(&mut NativeContext) -> Result<LoxValue, Error> + Shareable> Native for F {}
+
+/// Everything a native function gets to see about its call.
+pub struct NativeContext<'a> {
+    pub interpreter: &'a mut Interpreter,
+    pub args: &'a [LoxValue],
+    /// the call expression, errors are reported at its location
+    pub call: &'a DebugInfo,
+    /// name the native was defined with
+    pub name: &'a str,
+    /// the native is a method of a built-in type, the receiver is passed
+    /// as the first argument and does not count in error messages
+    pub method: bool,
+}
+
+impl NativeContext<'_> {
+    pub fn arg(&self, index: usize) -> &LoxValue {
+        &self.args[index]
+    }
+
+    /// A runtime error located at the call site.
+    pub fn error<S: Into<Message>>(&self, message: S) -> Error {
+        Error::RuntimeError {
+            line: self.call.line,
+            position: self.call.position,
+            end: self.call.span().end,
+            message: message.into(),
+            trace: Vec::new(),
+        }
+    }
+
+    /// Error for an argument that is not of the `expected` type.
+    pub fn argument_error(&self, index: usize, expected: &str) -> Error {
+        self.error(message!(
+            ArgumentType,
+            index = index + 1 - usize::from(self.method),
+            function = self.name,
+            expected = expected,
+            kind = self.args[index].type_name()
+        ))
+    }
+
+    pub fn arg_number(&self, index: usize) -> Result<f64, Error> {
+        match self.arg(index) {
+            LoxValue::Number(n) => Ok(*n),
+            _ => Err(self.argument_error(index, "number")),
+        }
+    }
+
+    /// Accepts numbers without a fractional part.
+    pub fn arg_integer(&self, index: usize) -> Result<i64, Error> {
+        match self.arg_number(index)? {
+            n if n.fract() == 0. && n.abs() <= MAX_SAFE_INTEGER => Ok(n as i64),
+            n => Err(self.error(message!(
+                ArgumentMustBeInteger,
+                index = index + 1 - usize::from(self.method),
+                function = self.name,
+                value = n
+            ))),
+        }
+    }
+
+    pub fn arg_string(&self, index: usize) -> Result<&str, Error> {
+        match self.arg(index) {
+            LoxValue::String(s) => Ok(s),
+            _ => Err(self.argument_error(index, "string")),
+        }
+    }
+
+    pub fn arg_list(&self, index: usize) -> Result<&Rc<RefCell<Vec<LoxValue>>>, Error> {
+        match self.arg(index) {
+            LoxValue::List(list) => Ok(list),
+            _ => Err(self.argument_error(index, "list")),
+        }
+    }
+
+    pub fn arg_map(&self, index: usize) -> Result<&Rc<RefCell<LoxMap>>, Error> {
+        match self.arg(index) {
+            LoxValue::Map(map) => Ok(map),
+            _ => Err(self.argument_error(index, "map")),
+        }
+    }
+
+    /// Converts the argument to a map key.
+    pub fn arg_key(&self, index: usize) -> Result<LoxKey, Error> {
+        LoxKey::try_from(self.arg(index)).map_err(|error| match error {
+            Error::InternalRuntimeError { message } => self.error(message),
+            error => error,
+        })
+    }
+
+    /// Returns the argument if it can be called.
+    pub fn arg_callable(&self, index: usize) -> Result<&LoxValue, Error> {
+        match self.arg(index) {
+            value if value.arity().is_some() => Ok(value),
+            _ => Err(self.argument_error(index, "function")),
+        }
+    }
+}
+
+/// Number of arguments a function accepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Arity {
+    pub min: usize,
+    /// `None` for variadic functions
+    pub max: Option<usize>,
+}
+
+impl Arity {
+    pub fn exactly(count: usize) -> Self {
+        Arity {
+            min: count,
+            max: Some(count),
+        }
+    }
+
+    pub fn at_least(count: usize) -> Self {
+        Arity {
+            min: count,
+            max: None,
+        }
+    }
+
+    pub fn accepts(&self, count: usize) -> bool {
+        self.min <= count && self.max.is_none_or(|max| count <= max)
+    }
+
+    /// Arity left after the first `count` arguments have been supplied.
+    pub fn after_binding(&self, count: usize) -> Self {
+        Arity {
+            min: self.min.saturating_sub(count),
+            max: self.max.map(|max| max.saturating_sub(count)),
+        }
+    }
+}
+
+impl From<usize> for Arity {
+    fn from(count: usize) -> Self {
+        Arity::exactly(count)
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{max}"),
+            Some(max) => write!(f, "{} to {max}", self.min),
+            None => write!(f, "at least {}", self.min),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct ForeinFun {
     pub name: String,
-    arity: usize,
-    pub fun: fn(&mut Interpreter, Box<[LoxValue]>) -> Result<LoxValue, Error>,
+    arity: Arity,
+    pub fun: NativeFn,
+    /// a method of a built-in type, see [`ForeinFun::method`]
+    pub method: bool,
 }
 
 impl ForeinFun {
     pub fn new(
         name: String,
-        arity: usize,
-        fun: fn(&mut Interpreter, Box<[LoxValue]>) -> Result<LoxValue, Error>,
+        arity: impl Into<Arity>,
+        fun: impl Fn(&mut NativeContext) -> Result<LoxValue, Error> + Shareable + 'static,
     ) -> Self {
-        Self { name, arity, fun }
+        Self {
+            name,
+            arity: arity.into(),
+            fun: Rc::new(fun),
+            method: false,
+        }
+    }
+
+    /// A method of a built-in type, it receives the value it was looked
+    /// up on as its first argument, which `arity` does not count.
+    pub fn method(
+        name: String,
+        arity: impl Into<Arity>,
+        fun: impl Fn(&mut NativeContext) -> Result<LoxValue, Error> + Shareable + 'static,
+    ) -> Self {
+        let arity = arity.into();
+        Self {
+            name,
+            arity: Arity {
+                min: arity.min + 1,
+                max: arity.max.map(|max| max + 1),
+            },
+            fun: Rc::new(fun),
+            method: true,
+        }
+    }
+}
+
+impl fmt::Debug for ForeinFun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ForeinFun")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .field("method", &self.method)
+            .finish_non_exhaustive()
     }
 }
 
 impl core::fmt::Display for ForeinFun {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Debug::fmt(self, f)
+        write!(f, "<native fn {}>", self.name)
     }
 }
 
 impl ForeinFun {
-    pub fn arity(&self) -> usize {
+    pub fn arity(&self) -> Arity {
         self.arity
     }
 }
@@ -37,20 +242,50 @@ impl ForeinFun {
 #[derive(Debug)]
 pub struct LoxFun {
     pub name: Identifier,
-    pub args: Box<[Identifier]>,
-    pub body: Block,
+    pub args: Rc<[Identifier]>,
+    pub body: Rc<Block>,
     pub captured_scope: FrameRef,
+    /// module the function was defined in, its globals are the ones
+    /// the body sees. The module holds the function in turn, so it is
+    /// referenced weakly, the interpreter keeps every module alive.
+    pub module: Weak<RefCell<Module>>,
+    /// getters are called as soon as they are looked up on an instance
+    pub getter: bool,
+    /// `init` of a class, calls evaluate to the instance it is bound to
+    pub initializer: bool,
+    /// the last parameter collects any remaining arguments into a list
+    pub variadic: bool,
 }
 
 impl fmt::Display for LoxFun {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Debug::fmt(self, f)
+        write!(f, "<fn {}>", self.name.name)
     }
 }
 
 impl LoxFun {
-    pub fn arity(&self) -> usize {
-        self.args.len()
+    pub fn arity(&self) -> Arity {
+        match self.variadic {
+            true => Arity::at_least(self.args.len() - 1),
+            false => Arity::exactly(self.args.len()),
+        }
+    }
+}
+
+/// A callable with its leading arguments already supplied, created by `bind`.
+#[derive(Debug)]
+pub struct BoundFun {
+    pub target: LoxValue,
+    pub args: Box<[LoxValue]>,
+}
+
+impl fmt::Display for BoundFun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "<bound fn {}>",
+            self.target.callable_name().unwrap_or_default()
+        )
     }
 }
 
@@ -58,14 +293,33 @@ impl LoxFun {
     pub(crate) fn new(
         name: Identifier,
         frame: FrameRef,
-        args: Box<[Identifier]>,
-        body: Block,
+        module: Weak<RefCell<Module>>,
+        args: Rc<[Identifier]>,
+        body: Rc<Block>,
     ) -> Self {
         LoxFun {
             name,
             args,
             body,
             captured_scope: frame,
+            module,
+            getter: false,
+            initializer: false,
+            variadic: false,
+        }
+    }
+
+    /// Returns a copy of the method with `this` bound to `object`.
+    pub fn bind(&self, object: LoxValue) -> LoxFun {
+        LoxFun {
+            name: self.name.clone(),
+            args: self.args.clone(),
+            body: self.body.clone(),
+            captured_scope: self.captured_scope.rebind("this", object),
+            module: self.module.clone(),
+            getter: self.getter,
+            initializer: self.initializer,
+            variadic: self.variadic,
         }
     }
 }
@@ -90,3 +344,131 @@ fn test_fun_stmt() {
     assert_eq!(val, LoxValue::Number(123.));
     // assert_eq!(val, LoxValue::Nil);
 }
+
+#[test]
+fn test_recursive_closure_is_freed() {
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source = "
+        fun make() {
+            fun count_down(n) {
+                if n == 0 { return 0; }
+                return count_down(n - 1);
+            }
+            return count_down;
+        }
+        var count_down = make();
+        var result = count_down(3);
+    ";
+    let tokens = scanner::scan_tokens(source).unwrap();
+    let tree = Parser::new().parse(tokens).unwrap();
+    let access_table = resolver::resolve(&tree).unwrap();
+    let mut interp = Interpreter::new();
+    interp.execute(&tree, access_table).unwrap();
+
+    assert_eq!(
+        interp.environment.get_global(&"result".to_string()),
+        Some(LoxValue::Number(0.))
+    );
+    let count_down = match interp.environment.get_global(&"count_down".to_string()) {
+        Some(LoxValue::LoxFun(count_down)) => Rc::downgrade(&count_down),
+        _ => panic!("Expected variable `count_down` to be a function."),
+    };
+
+    drop(interp);
+    assert!(count_down.upgrade().is_none(), "recursive function leaked");
+}
+
+#[test]
+fn test_reassigned_closure_name_is_shared() {
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source = "
+        var outside;
+        var inside;
+        {
+            fun f() { return f; }
+            var g = f;
+            f = nil;
+            outside = g();
+        }
+        {
+            fun f() { f = \"replaced\"; }
+            f();
+            inside = f;
+        }
+    ";
+    let tokens = scanner::scan_tokens(source).unwrap();
+    let tree = Parser::new().parse(tokens).unwrap();
+    let access_table = resolver::resolve(&tree).unwrap();
+    let mut interp = Interpreter::new();
+    interp.execute(&tree, access_table).unwrap();
+
+    assert_eq!(
+        interp.environment.get_global(&"outside".to_string()),
+        Some(LoxValue::Nil)
+    );
+    assert_eq!(
+        interp.environment.get_global(&"inside".to_string()),
+        Some(LoxValue::String("replaced".into()))
+    );
+}
+
+#[test]
+fn test_variadic_functions() {
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source = "
+        fun log(level, ...rest) {
+            return level + \": \" + toString(rest);
+        }
+        class Sum {
+            of(...numbers) {
+                var total = 0;
+                for (n in numbers) { total = total + n; }
+                return total;
+            }
+        }
+        var none = log(\"info\");
+        var some = log(\"warn\", 1, \"two\");
+        var total = Sum().of(1, 2, 3) + Sum().of();
+        var params = arity(log);
+    ";
+    let tokens = scanner::scan_tokens(source).unwrap();
+    let tree = Parser::new().parse(tokens).unwrap();
+    let access_table = resolver::resolve(&tree).unwrap();
+    let mut interp = Interpreter::new();
+    interp.execute(&tree, access_table).unwrap();
+
+    let global = |name: &str| interp.environment.get_global(&name.to_string()).unwrap();
+    assert_eq!(global("none"), LoxValue::String("info: []".to_owned()));
+    assert_eq!(
+        global("some"),
+        LoxValue::String("warn: [1, \"two\"]".to_owned())
+    );
+    assert_eq!(global("total"), LoxValue::Number(6.));
+    assert_eq!(global("params"), LoxValue::Number(1.));
+
+    let tree = Parser::new()
+        .parse(scanner::scan_tokens("fun log(level, ...rest) {} log();").unwrap())
+        .unwrap();
+    match Interpreter::new().execute(&tree, resolver::resolve(&tree).unwrap()) {
+        Err(Error::RuntimeError { message, .. }) => {
+            assert_eq!(message, "Expected at least 1 arguments but got 0.")
+        }
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+
+    for source in ["fun f(...rest, last) {}", "fun f(...) {}"] {
+        assert!(matches!(
+            Parser::new()
+                .parse(scanner::scan_tokens(source).unwrap())
+                .unwrap_err()
+                .as_slice(),
+            [Error::ParsingError { .. }]
+        ));
+    }
+}
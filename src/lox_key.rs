@@ -0,0 +1,108 @@
+use std::fmt;
+
+use crate::error::Error;
+use crate::lox_value::LoxValue;
+use crate::messages::message;
+
+/// Largest magnitude below which every integer is exactly representable as an `f64`.
+pub const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.;
+
+/// A [`LoxValue`] that can be hashed and used as a map key.
+///
+/// Numbers are only accepted when they hold an integer, so that two keys are
+/// equal exactly when the values they came from are `==`. `0` and `-0`
+/// become the same key, `NaN` is rejected as it is not even equal to itself.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LoxKey {
+    Nil,
+    Bool(bool),
+    Integer(i64),
+    String(String),
+}
+
+impl TryFrom<&LoxValue> for LoxKey {
+    type Error = Error;
+
+    fn try_from(value: &LoxValue) -> Result<Self, Self::Error> {
+        let error = |message| Err(Error::InternalRuntimeError { message });
+
+        match value {
+            LoxValue::Nil => Ok(LoxKey::Nil),
+            LoxValue::Bool(b) => Ok(LoxKey::Bool(*b)),
+            LoxValue::String(s) => Ok(LoxKey::String(s.clone())),
+            LoxValue::Number(n) if n.is_nan() => error(message!(NanKey)),
+            LoxValue::Number(n) if n.fract() != 0. || n.abs() > MAX_SAFE_INTEGER => {
+                error(message!(FractionalKey, value = n))
+            }
+            LoxValue::Number(n) => Ok(LoxKey::Integer(*n as i64)),
+            value => error(message!(UnhashableKey, kind = value.type_name())),
+        }
+    }
+}
+
+impl From<LoxKey> for LoxValue {
+    fn from(key: LoxKey) -> Self {
+        match key {
+            LoxKey::Nil => LoxValue::Nil,
+            LoxKey::Bool(b) => LoxValue::Bool(b),
+            LoxKey::Integer(i) => LoxValue::Number(i as f64),
+            LoxKey::String(s) => LoxValue::String(s),
+        }
+    }
+}
+
+impl fmt::Display for LoxKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoxKey::Nil => write!(f, "nil"),
+            LoxKey::Bool(b) => write!(f, "{b}"),
+            LoxKey::Integer(i) => write!(f, "{i}"),
+            LoxKey::String(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+#[test]
+fn test_lox_keys() {
+    use std::collections::HashSet;
+
+    let key = |value: LoxValue| LoxKey::try_from(&value);
+
+    let keys: HashSet<LoxKey> = [
+        LoxValue::Nil,
+        LoxValue::Bool(true),
+        LoxValue::Number(1.),
+        LoxValue::Number(0.),
+        LoxValue::Number(-0.),
+        LoxValue::String("1".to_owned()),
+    ]
+    .iter()
+    .map(|value| LoxKey::try_from(value).unwrap())
+    .collect();
+    // `0` and `-0` are the same key, `1` and `"1"` are not
+    assert_eq!(keys.len(), 5);
+
+    assert_eq!(
+        LoxValue::from(key(LoxValue::Number(-3.)).unwrap()),
+        LoxValue::Number(-3.)
+    );
+    for value in [
+        LoxValue::Number(f64::NAN),
+        LoxValue::Number(0.5),
+        LoxValue::Number(f64::INFINITY),
+        LoxValue::Number(1e300),
+    ] {
+        assert!(matches!(
+            key(value),
+            Err(Error::InternalRuntimeError { .. })
+        ));
+    }
+
+    let function = crate::lox_function::ForeinFun::new("f".to_owned(), 0, |_| Ok(LoxValue::Nil));
+    match key(LoxValue::ForeinFun(function.into())) {
+        Err(Error::InternalRuntimeError { message }) => {
+            assert_eq!(message, "Values of type function cannot be used as keys.")
+        }
+        other => panic!("expected an error, got {other:?}"),
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use crate::lox_key::LoxKey;
+use crate::lox_value::LoxValue;
+
+/// Map value keeping its entries in insertion order, so that printing and
+/// iterating a map is deterministic.
+#[derive(Clone, Debug, Default)]
+pub struct LoxMap {
+    entries: Vec<(LoxKey, LoxValue)>,
+    index: HashMap<LoxKey, usize>,
+}
+
+impl LoxMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts `value` under `key`, an existing key keeps its position.
+    pub fn insert(&mut self, key: LoxKey, value: LoxValue) -> Option<LoxValue> {
+        match self.index.get(&key) {
+            Some(&i) => Some(std::mem::replace(&mut self.entries[i].1, value)),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    pub fn get(&self, key: &LoxKey) -> Option<&LoxValue> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn contains_key(&self, key: &LoxKey) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Removes `key`, the remaining entries keep their order.
+    pub fn remove(&mut self, key: &LoxKey) -> Option<LoxValue> {
+        let i = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(i);
+        for (key, _) in &self.entries[i..] {
+            *self.index.get_mut(key).expect("indexed key") -= 1;
+        }
+        Some(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&LoxKey, &LoxValue)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+}
+
+impl FromIterator<(LoxKey, LoxValue)> for LoxMap {
+    fn from_iter<T: IntoIterator<Item = (LoxKey, LoxValue)>>(iter: T) -> Self {
+        let mut map = LoxMap::new();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+#[test]
+fn test_map_order() {
+    let key = |name: &str| LoxKey::String(name.to_owned());
+    let mut map: LoxMap = [("b", 1.), ("a", 2.), ("c", 3.)]
+        .into_iter()
+        .map(|(name, n)| (key(name), LoxValue::Number(n)))
+        .collect();
+
+    assert_eq!(
+        map.insert(key("a"), LoxValue::Nil),
+        Some(LoxValue::Number(2.))
+    );
+
+    let entries: Vec<_> = map
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
+    assert_eq!(entries, ["b=1", "a=nil", "c=3"]);
+
+    assert_eq!(map.remove(&key("b")), Some(LoxValue::Number(1.)));
+    assert_eq!(map.remove(&key("b")), None);
+    assert_eq!(map.get(&key("c")), Some(&LoxValue::Number(3.)));
+    assert!(map.contains_key(&key("a")));
+    map.insert(key("b"), LoxValue::Bool(true));
+    let entries: Vec<_> = map
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
+    assert_eq!(entries, ["a=nil", "c=3", "b=true"]);
+    assert_eq!(map.len(), 3);
+}
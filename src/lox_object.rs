@@ -0,0 +1,404 @@
+use core::fmt;
+use std::collections::HashMap;
+
+use crate::lox_function::{Arity, LoxFun};
+use crate::lox_value::LoxValue;
+use crate::shared::Rc;
+
+/// A class, calling it creates a new instance.
+#[derive(Debug)]
+pub struct LoxClass {
+    pub name: String,
+    pub superclass: Option<Rc<LoxClass>>,
+    /// methods with an unbound `this`
+    pub methods: HashMap<String, Rc<LoxFun>>,
+}
+
+impl LoxClass {
+    /// Looks `name` up in the class and then in its superclasses.
+    pub fn find_method(&self, name: &str) -> Option<&Rc<LoxFun>> {
+        self.methods.get(name).or_else(|| {
+            self.superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find_method(name))
+        })
+    }
+
+    /// Calling the class passes its arguments to `init`, if there is one.
+    pub fn arity(&self) -> Arity {
+        self.find_method("init")
+            .map_or(Arity::exactly(0), |init| init.arity())
+    }
+}
+
+impl fmt::Display for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<class {}>", self.name)
+    }
+}
+
+/// An instance of a class.
+#[derive(Debug)]
+pub struct LoxObject {
+    pub class: Rc<LoxClass>,
+    pub fields: HashMap<String, LoxValue>,
+}
+
+impl LoxObject {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        LoxObject {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<LoxValue> {
+        self.fields.get(name).cloned()
+    }
+
+    pub fn set(&mut self, name: &str, value: LoxValue) {
+        self.fields.insert(name.to_owned(), value);
+    }
+
+    /// Fields sorted by name.
+    pub fn fields(&self) -> Vec<(String, LoxValue)> {
+        let mut fields: Vec<_> = self
+            .fields
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+        fields
+    }
+}
+
+impl fmt::Display for LoxObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{} instance>", self.class.name)
+    }
+}
+
+#[test]
+fn test_instances() {
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source = "
+        class Point {}
+        var p = Point();
+        p.x = 1;
+        p.y = p.x + 1;
+        var q = Point();
+        q.next = p;
+        q.next.x = 10;
+        var sum = p.x + p.y;
+    ";
+    let tree = Parser::new()
+        .parse(scanner::scan_tokens(source).unwrap())
+        .unwrap();
+    let mut interp = Interpreter::new();
+    interp
+        .execute(&tree, resolver::resolve(&tree).unwrap())
+        .unwrap();
+
+    let global = |name: &str| interp.environment.get_global(&name.to_string()).unwrap();
+    assert_eq!(global("sum"), LoxValue::Number(12.));
+    assert_eq!(global("Point").to_string(), "<class Point>");
+    assert_eq!(global("q").to_string(), "<Point instance>");
+
+    for (source, expected) in [
+        (
+            "class A {} var a = A(); print a.missing;",
+            "Undefined property 'missing'.",
+        ),
+        (
+            "var a = true; print a.field;",
+            "Only instances and maps have properties.",
+        ),
+        (
+            "var a = \"s\"; a.field = 1;",
+            "Only instances and maps have fields.",
+        ),
+        ("class A {} A(1);", "Expected 0 arguments but got 1."),
+    ] {
+        let tree = Parser::new()
+            .parse(scanner::scan_tokens(source).unwrap())
+            .unwrap();
+        match Interpreter::new().execute(&tree, resolver::resolve(&tree).unwrap()) {
+            Err(crate::Error::RuntimeError { message, .. }) => assert_eq!(message, expected),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_methods_and_this() {
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source = "
+        class Counter {
+            increment() { this.count = this.count + 1; return this; }
+            getter() {
+                fun get() { return this.count; }
+                return get;
+            }
+        }
+        var a = Counter();
+        var b = Counter();
+        a.count = 0;
+        b.count = 10;
+        var increment = a.increment;
+        increment().increment();
+        b.increment();
+        var get = a.getter();
+        a.count = a.count + 100;
+        var result = get() + b.getter()();
+    ";
+    let tree = Parser::new()
+        .parse(scanner::scan_tokens(source).unwrap())
+        .unwrap();
+    let mut interp = Interpreter::new();
+    interp
+        .execute(&tree, resolver::resolve(&tree).unwrap())
+        .unwrap();
+    assert_eq!(
+        interp.environment.get_global(&"result".to_string()),
+        Some(LoxValue::Number(113.))
+    );
+
+    for source in ["print this;", "fun f() { return this; }"] {
+        let tree = Parser::new()
+            .parse(scanner::scan_tokens(source).unwrap())
+            .unwrap();
+        assert!(matches!(
+            resolver::resolve(&tree),
+            Err(crate::Error::ResolverError { .. })
+        ));
+    }
+}
+
+#[test]
+fn test_inheritance_and_super() {
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source = "
+        class A {
+            name() { return \"A\"; }
+            describe() { return this.name(); }
+        }
+        class B < A {
+            name() { return \"B\" + super.name(); }
+        }
+        class C < B {
+            name() { return \"C\" + super.name(); }
+        }
+        var result = C().describe();
+    ";
+    let tree = Parser::new()
+        .parse(scanner::scan_tokens(source).unwrap())
+        .unwrap();
+    let mut interp = Interpreter::new();
+    interp
+        .execute(&tree, resolver::resolve(&tree).unwrap())
+        .unwrap();
+    assert_eq!(
+        interp.environment.get_global(&"result".to_string()),
+        Some(LoxValue::String("CBA".to_owned()))
+    );
+
+    for source in [
+        "class A < A {}",
+        "print super.x;",
+        "class A { f() { super.f(); } }",
+    ] {
+        let tree = Parser::new()
+            .parse(scanner::scan_tokens(source).unwrap())
+            .unwrap();
+        assert!(matches!(
+            resolver::resolve(&tree),
+            Err(crate::Error::ResolverError { .. })
+        ));
+    }
+
+    for (source, expected) in [
+        ("var x = 1; class A < x {}", "Superclass must be a class."),
+        (
+            "class A {} class B < A { f() { return super.g; } } B().f();",
+            "Undefined property 'g'.",
+        ),
+    ] {
+        let tree = Parser::new()
+            .parse(scanner::scan_tokens(source).unwrap())
+            .unwrap();
+        match Interpreter::new().execute(&tree, resolver::resolve(&tree).unwrap()) {
+            Err(crate::Error::RuntimeError { message, .. }) => assert_eq!(message, expected),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_initializers() {
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source = "
+        class Point {
+            init(x, y) {
+                this.x = x;
+                this.y = y;
+                if x == 0 { return; }
+                this.y = y + 1;
+            }
+        }
+        class Named < Point {
+            init(name) { super.init(1, 2); this.name = name; }
+        }
+        var a = Point(0, 1);
+        var b = Point(1, 1);
+        var n = Named(\"n\");
+        var sum = a.y + b.y + n.x + n.y;
+        var params = arity(Point);
+        var again = a.init(1, 1);
+        var same = again == a and a.y == 2;
+    ";
+    let tree = Parser::new()
+        .parse(scanner::scan_tokens(source).unwrap())
+        .unwrap();
+    let mut interp = Interpreter::new();
+    interp
+        .execute(&tree, resolver::resolve(&tree).unwrap())
+        .unwrap();
+    let global = |name: &str| interp.environment.get_global(&name.to_string()).unwrap();
+    assert_eq!(global("sum"), LoxValue::Number(7.));
+    assert_eq!(global("params"), LoxValue::Number(2.));
+    assert_eq!(global("n").to_string(), "<Named instance>");
+    assert_eq!(global("same"), LoxValue::Bool(true));
+
+    let tree = Parser::new()
+        .parse(scanner::scan_tokens("class A {\n  init() {\n    return 1;\n  }\n}").unwrap())
+        .unwrap();
+    assert!(matches!(
+        resolver::resolve(&tree),
+        Err(crate::Error::ResolverError {
+            line: 3,
+            position: 5,
+            ..
+        })
+    ));
+
+    let tree = Parser::new()
+        .parse(scanner::scan_tokens("class A { init(a) {} } A();").unwrap())
+        .unwrap();
+    match Interpreter::new().execute(&tree, resolver::resolve(&tree).unwrap()) {
+        Err(crate::Error::RuntimeError { message, .. }) => {
+            assert_eq!(message, "Expected 1 arguments but got 0.")
+        }
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_getters() {
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source = "
+        class Rect {
+            init(w, h) { this.w = w; this.h = h; }
+            area { return this.w * this.h; }
+        }
+        class Square < Rect {
+            init(side) { super.init(side, side); }
+            area { return super.area + 1; }
+        }
+        var r = Rect(2, 3);
+        var before = r.area;
+        r.w = 10;
+        var after = r.area;
+        var square = Square(4).area;
+    ";
+    let tree = Parser::new()
+        .parse(scanner::scan_tokens(source).unwrap())
+        .unwrap();
+    let mut interp = Interpreter::new();
+    interp
+        .execute(&tree, resolver::resolve(&tree).unwrap())
+        .unwrap();
+    let global = |name: &str| interp.environment.get_global(&name.to_string()).unwrap();
+    assert_eq!(global("before"), LoxValue::Number(6.));
+    assert_eq!(global("after"), LoxValue::Number(30.));
+    assert_eq!(global("square"), LoxValue::Number(17.));
+}
+
+#[test]
+fn test_operator_overloading() {
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source = "
+        class Vector {
+            init(x, y) { this.x = x; this.y = y; }
+            plus(other) { return Vector(this.x + other.x, this.y + other.y); }
+            equals(other) { return this.x == other.x and this.y == other.y; }
+            less(other) { return this.x * this.x + this.y * this.y < other.x * other.x + other.y * other.y; }
+            get(i) { if i == 0 { return this.x; } return this.y; }
+            set(i, value) { if i == 0 { this.x = value; } else { this.y = value; } }
+        }
+        class Named < Vector {}
+        var v = Vector(1, 2) + Named(3, 4);
+        var sum = v.x * 10 + v.y;
+        var same = Vector(1, 2) == Vector(1, 2);
+        var different = Vector(1, 2) != Vector(1, 2);
+        var shorter = Vector(1, 1) < Vector(2, 2);
+        var assigned = v[1] = 7;
+        var indexed = v[0] + v[1];
+        var plain = 1 + 2 == 3 and \"a\" + \"b\" == \"ab\";
+    ";
+    let tree = Parser::new()
+        .parse(scanner::scan_tokens(source).unwrap())
+        .unwrap();
+    let mut interp = Interpreter::new();
+    interp
+        .execute(&tree, resolver::resolve(&tree).unwrap())
+        .unwrap();
+    let global = |name: &str| interp.environment.get_global(&name.to_string()).unwrap();
+    assert_eq!(global("sum"), LoxValue::Number(46.));
+    assert_eq!(global("same"), LoxValue::Bool(true));
+    assert_eq!(global("different"), LoxValue::Bool(false));
+    assert_eq!(global("shorter"), LoxValue::Bool(true));
+    assert_eq!(global("assigned"), LoxValue::Number(7.));
+    assert_eq!(global("indexed"), LoxValue::Number(11.));
+    assert_eq!(global("plain"), LoxValue::Bool(true));
+
+    for (source, expected) in [
+        (
+            "class A {} A() + 1;",
+            "Operands of '+' must be two numbers or two strings, got instance and number.",
+        ),
+        (
+            "class A {} print A()[0];",
+            "Values of type instance cannot be indexed.",
+        ),
+        (
+            "class A { plus() {} } A() + 1;",
+            "Expected 0 arguments but got 1.",
+        ),
+    ] {
+        let tree = Parser::new()
+            .parse(scanner::scan_tokens(source).unwrap())
+            .unwrap();
+        match Interpreter::new().execute(&tree, resolver::resolve(&tree).unwrap()) {
+            Err(crate::Error::RuntimeError { message, .. }) => assert_eq!(message, expected),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+}
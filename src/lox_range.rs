@@ -0,0 +1,53 @@
+use std::fmt;
+
+/// Numbers from `start` up to, but excluding, `end`, spaced by `step`.
+///
+/// Ranges are lazy, iterating one does not allocate its values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoxRange {
+    pub start: f64,
+    pub end: f64,
+    /// never zero, a negative step counts down
+    pub step: f64,
+}
+
+impl LoxRange {
+    /// Returns `None` when `step` is zero or NaN.
+    pub fn new(start: f64, end: f64, step: f64) -> Option<Self> {
+        (step != 0. && !step.is_nan()).then_some(LoxRange { start, end, step })
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = f64> {
+        // computing every value from `start` keeps fractional steps from drifting
+        (0..)
+            .map(move |i| self.start + i as f64 * self.step)
+            .take_while(move |&n| {
+                if self.step > 0. {
+                    n < self.end
+                } else {
+                    n > self.end
+                }
+            })
+    }
+}
+
+impl fmt::Display for LoxRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "range({}, {}, {})", self.start, self.end, self.step)
+    }
+}
+
+#[test]
+fn test_ranges() {
+    let values = |range: LoxRange| range.iter().collect::<Vec<_>>();
+
+    assert_eq!(values(LoxRange::new(0., 4., 1.).unwrap()), [0., 1., 2., 3.]);
+    assert_eq!(values(LoxRange::new(5., 0., -2.).unwrap()), [5., 3., 1.]);
+    assert_eq!(values(LoxRange::new(0., 0.3, 0.1).unwrap()).len(), 3);
+    assert!(values(LoxRange::new(3., 0., 1.).unwrap()).is_empty());
+    assert!(LoxRange::new(0., 1., 0.).is_none());
+
+    // infinite ranges are fine as long as nobody collects them
+    let endless = LoxRange::new(0., f64::INFINITY, 1.).unwrap();
+    assert_eq!(endless.iter().nth(1000), Some(1000.));
+}
@@ -1,8 +1,13 @@
+use crate::lox_map::LoxMap;
+use crate::lox_object::{LoxClass, LoxObject};
+use crate::lox_range::LoxRange;
+use crate::messages::message;
+use crate::shared::{Rc, RefCell};
 use crate::{
     error::Error,
-    lox_function::{ForeinFun, LoxFun},
+    lox_function::{Arity, BoundFun, ForeinFun, LoxFun},
 };
-use std::rc::Rc;
+use std::io::{self, Write};
 
 #[derive(Clone, Debug)]
 pub enum LoxValue {
@@ -11,6 +16,12 @@ pub enum LoxValue {
     String(String),
     LoxFun(Rc<LoxFun>),
     ForeinFun(Rc<ForeinFun>),
+    Bound(Rc<BoundFun>),
+    Class(Rc<LoxClass>),
+    Object(Rc<RefCell<LoxObject>>),
+    List(Rc<RefCell<Vec<LoxValue>>>),
+    Map(Rc<RefCell<LoxMap>>),
+    Range(LoxRange),
     Nil,
 }
 
@@ -27,6 +38,18 @@ impl PartialEq for LoxValue {
             (LoxValue::LoxFun(_), _) => false,
             (LoxValue::ForeinFun(a), LoxValue::ForeinFun(b)) => Rc::ptr_eq(a, b),
             (LoxValue::ForeinFun(_), _) => false,
+            (LoxValue::Bound(a), LoxValue::Bound(b)) => Rc::ptr_eq(a, b),
+            (LoxValue::Bound(_), _) => false,
+            (LoxValue::Class(a), LoxValue::Class(b)) => Rc::ptr_eq(a, b),
+            (LoxValue::Class(_), _) => false,
+            (LoxValue::Object(a), LoxValue::Object(b)) => Rc::ptr_eq(a, b),
+            (LoxValue::Object(_), _) => false,
+            (LoxValue::List(a), LoxValue::List(b)) => Rc::ptr_eq(a, b),
+            (LoxValue::List(_), _) => false,
+            (LoxValue::Map(a), LoxValue::Map(b)) => Rc::ptr_eq(a, b),
+            (LoxValue::Map(_), _) => false,
+            (LoxValue::Range(a), LoxValue::Range(b)) => a == b,
+            (LoxValue::Range(_), _) => false,
             (LoxValue::Nil, LoxValue::Nil) => true,
             (LoxValue::Nil, _) => false,
         }
@@ -39,19 +62,76 @@ impl core::fmt::Display for LoxValue {
             LoxValue::Number(n) => write!(f, "{}", n),
             LoxValue::Bool(b) => write!(f, "{}", b),
             LoxValue::String(s) => write!(f, "{}", s),
-            // LoxValue::Object(o) => write!(f, "{}", o.to_string()),
+            LoxValue::Class(class) => write!(f, "{}", class),
+            LoxValue::Object(o) => write!(f, "{}", o.borrow()),
             LoxValue::Nil => write!(f, "nil"),
             LoxValue::LoxFun(fun) => write!(f, "{}", fun),
             LoxValue::ForeinFun(fun) => write!(f, "{}", fun),
+            LoxValue::Bound(fun) => write!(f, "{}", fun),
+            LoxValue::Range(range) => write!(f, "{}", range),
+            LoxValue::List(list) => display_once(f, Rc::as_ptr(list).cast(), "[...]", |f| {
+                write!(f, "[")?;
+                for (i, value) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value.repr())?;
+                }
+                write!(f, "]")
+            }),
+            LoxValue::Map(map) => display_once(f, Rc::as_ptr(map).cast(), "{...}", |f| {
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    let key = LoxValue::from(key.clone());
+                    write!(f, "{}: {}", key.repr(), value.repr())?;
+                }
+                write!(f, "}}")
+            }),
+        }
+    }
+}
+
+thread_local! {
+    /// containers whose `Display` is currently running
+    static DISPLAYING: std::cell::RefCell<Vec<*const ()>> =
+        const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Displays the container at `address` with `display`, unless it contains
+/// itself and is already being displayed, then `placeholder` is written.
+fn display_once(
+    f: &mut std::fmt::Formatter<'_>,
+    address: *const (),
+    placeholder: &str,
+    display: impl FnOnce(&mut std::fmt::Formatter<'_>) -> std::fmt::Result,
+) -> std::fmt::Result {
+    let entered = DISPLAYING.with_borrow_mut(|displaying| {
+        let entered = !displaying.contains(&address);
+        if entered {
+            displaying.push(address);
         }
+        entered
+    });
+    if !entered {
+        return write!(f, "{placeholder}");
     }
+
+    let result = display(f);
+    DISPLAYING.with_borrow_mut(|displaying| displaying.pop());
+    result
 }
 
 impl LoxValue {
-    pub fn print(value: &LoxValue) {
-        println!("{}", LoxValue::to_string(&value));
+    /// Writes the value the way `print` shows it, followed by a newline.
+    pub fn print(value: &LoxValue, output: &mut dyn Write) -> io::Result<()> {
+        writeln!(output, "{}", LoxValue::to_string(value))
     }
 
+    // Lox `+`, which can fail unlike `std::ops::Add`
+    #[allow(clippy::should_implement_trait)]
     pub fn add(left: LoxValue, right: LoxValue) -> Result<LoxValue, Error> {
         match (left, right) {
             (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Number(l + r)),
@@ -59,7 +139,11 @@ impl LoxValue {
                 Ok(LoxValue::String(format!("{}{}", l, r)))
             }
             (left, right) => Err(Error::InternalRuntimeError {
-                message: format!("Cannot add: {:?} and {:?}", left, right),
+                message: message!(
+                    OperandsMustBeNumbersOrStrings,
+                    left = left.type_name(),
+                    right = right.type_name()
+                ),
             }),
         }
     }
@@ -68,7 +152,12 @@ impl LoxValue {
         match (left, right) {
             (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Number(l - r)),
             (left, right) => Err(Error::InternalRuntimeError {
-                message: format!("Cannot subtract: {:?} from {:?}", left, right),
+                message: message!(
+                    OperandsMustBeNumbers,
+                    operator = "-",
+                    left = left.type_name(),
+                    right = right.type_name()
+                ),
             }),
         }
     }
@@ -77,7 +166,12 @@ impl LoxValue {
         match (left, right) {
             (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Number(l * r)),
             (left, right) => Err(Error::InternalRuntimeError {
-                message: format!("Cannot multiply: {:?} by {:?}", left, right),
+                message: message!(
+                    OperandsMustBeNumbers,
+                    operator = "*",
+                    left = left.type_name(),
+                    right = right.type_name()
+                ),
             }),
         }
     }
@@ -86,11 +180,90 @@ impl LoxValue {
         match (left, right) {
             (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Number(l / r)),
             (left, right) => Err(Error::InternalRuntimeError {
-                message: format!("Cannot divide: {:?} over {:?}", left, right),
+                message: message!(
+                    OperandsMustBeNumbers,
+                    operator = "/",
+                    left = left.type_name(),
+                    right = right.type_name()
+                ),
+            }),
+        }
+    }
+
+    /// Divides and rounds the quotient towards negative infinity.
+    pub fn floor_divide(left: LoxValue, right: LoxValue) -> Result<LoxValue, Error> {
+        match (left, right) {
+            (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Number((l / r).floor())),
+            (left, right) => Err(Error::InternalRuntimeError {
+                message: message!(
+                    OperandsMustBeNumbers,
+                    operator = "~/",
+                    left = left.type_name(),
+                    right = right.type_name()
+                ),
+            }),
+        }
+    }
+
+    /// Truncates both operands of a bitwise operator to integers.
+    fn integer_operands(
+        operator: &str,
+        left: LoxValue,
+        right: LoxValue,
+    ) -> Result<(i64, i64), Error> {
+        match (left, right) {
+            (LoxValue::Number(l), LoxValue::Number(r)) if l.is_finite() && r.is_finite() => {
+                Ok((l as i64, r as i64))
+            }
+            (LoxValue::Number(_), LoxValue::Number(_)) => Err(Error::InternalRuntimeError {
+                message: message!(OperandsMustBeFinite, operator = operator),
+            }),
+            (left, right) => Err(Error::InternalRuntimeError {
+                message: message!(
+                    OperandsMustBeNumbers,
+                    operator = operator,
+                    left = left.type_name(),
+                    right = right.type_name()
+                ),
             }),
         }
     }
 
+    pub fn bit_and(left: LoxValue, right: LoxValue) -> Result<LoxValue, Error> {
+        let (l, r) = LoxValue::integer_operands("&", left, right)?;
+        Ok(LoxValue::Number((l & r) as f64))
+    }
+
+    pub fn bit_or(left: LoxValue, right: LoxValue) -> Result<LoxValue, Error> {
+        let (l, r) = LoxValue::integer_operands("|", left, right)?;
+        Ok(LoxValue::Number((l | r) as f64))
+    }
+
+    pub fn bit_xor(left: LoxValue, right: LoxValue) -> Result<LoxValue, Error> {
+        let (l, r) = LoxValue::integer_operands("^", left, right)?;
+        Ok(LoxValue::Number((l ^ r) as f64))
+    }
+
+    pub fn shift_left(left: LoxValue, right: LoxValue) -> Result<LoxValue, Error> {
+        let (l, r) = LoxValue::integer_operands("<<", left, right)?;
+        Ok(LoxValue::Number((l << LoxValue::shift_amount(r)?) as f64))
+    }
+
+    /// Arithmetic shift, the sign is preserved.
+    pub fn shift_right(left: LoxValue, right: LoxValue) -> Result<LoxValue, Error> {
+        let (l, r) = LoxValue::integer_operands(">>", left, right)?;
+        Ok(LoxValue::Number((l >> LoxValue::shift_amount(r)?) as f64))
+    }
+
+    fn shift_amount(amount: i64) -> Result<u32, Error> {
+        u32::try_from(amount)
+            .ok()
+            .filter(|amount| *amount < i64::BITS)
+            .ok_or_else(|| Error::InternalRuntimeError {
+                message: message!(ShiftOutOfRange, amount = amount),
+            })
+    }
+
     // Follows IEEE 754, ie: (NaN == NaN) -> False
     pub fn equal(left: LoxValue, right: LoxValue) -> Result<LoxValue, Error> {
         Ok(LoxValue::Bool(left == right))
@@ -104,7 +277,12 @@ impl LoxValue {
         match (left, right) {
             (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Bool(l > r)),
             (left, right) => Err(Error::InternalRuntimeError {
-                message: format!("Cannot check if: {:?} > {:?}", left, right),
+                message: message!(
+                    OperandsMustBeNumbers,
+                    operator = ">",
+                    left = left.type_name(),
+                    right = right.type_name()
+                ),
             }),
         }
     }
@@ -113,7 +291,12 @@ impl LoxValue {
         match (left, right) {
             (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Bool(l >= r)),
             (left, right) => Err(Error::InternalRuntimeError {
-                message: format!("Cannot check if: {:?} >= {:?}", left, right),
+                message: message!(
+                    OperandsMustBeNumbers,
+                    operator = ">=",
+                    left = left.type_name(),
+                    right = right.type_name()
+                ),
             }),
         }
     }
@@ -122,7 +305,12 @@ impl LoxValue {
         match (left, right) {
             (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Bool(l < r)),
             (left, right) => Err(Error::InternalRuntimeError {
-                message: format!("Cannot check if: {:?} < {:?}", left, right),
+                message: message!(
+                    OperandsMustBeNumbers,
+                    operator = "<",
+                    left = left.type_name(),
+                    right = right.type_name()
+                ),
             }),
         }
     }
@@ -131,7 +319,12 @@ impl LoxValue {
         match (left, right) {
             (LoxValue::Number(l), LoxValue::Number(r)) => Ok(LoxValue::Bool(l <= r)),
             (left, right) => Err(Error::InternalRuntimeError {
-                message: format!("Cannot check if: {:?} <= {:?}", left, right),
+                message: message!(
+                    OperandsMustBeNumbers,
+                    operator = "<=",
+                    left = left.type_name(),
+                    right = right.type_name()
+                ),
             }),
         }
     }
@@ -140,11 +333,53 @@ impl LoxValue {
         match value {
             LoxValue::Number(value) => Ok(LoxValue::Number(-value)),
             _ => Err(Error::InternalRuntimeError {
-                message: format!("Cannot negate: {:?}", value),
+                message: message!(
+                    OperandMustBeNumber,
+                    operator = "-",
+                    value = value.type_name()
+                ),
             }),
         }
     }
 
+    /// Name of the value's type as used in diagnostics.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            LoxValue::Number(_) => "number",
+            LoxValue::Bool(_) => "boolean",
+            LoxValue::String(_) => "string",
+            LoxValue::LoxFun(_) | LoxValue::ForeinFun(_) | LoxValue::Bound(_) => "function",
+            LoxValue::List(_) => "list",
+            LoxValue::Map(_) => "map",
+            LoxValue::Range(_) => "range",
+            LoxValue::Class(_) => "class",
+            LoxValue::Object(_) => "instance",
+            LoxValue::Nil => "nil",
+        }
+    }
+
+    /// Number of arguments accepted by a callable value.
+    pub fn arity(&self) -> Option<Arity> {
+        match self {
+            LoxValue::LoxFun(fun) => Some(fun.arity()),
+            LoxValue::ForeinFun(fun) => Some(fun.arity()),
+            LoxValue::Bound(fun) => Some(fun.target.arity()?.after_binding(fun.args.len())),
+            LoxValue::Class(class) => Some(class.arity()),
+            _ => None,
+        }
+    }
+
+    /// Name of a callable value.
+    pub fn callable_name(&self) -> Option<&str> {
+        match self {
+            LoxValue::LoxFun(fun) => Some(&fun.name.name),
+            LoxValue::ForeinFun(fun) => Some(&fun.name),
+            LoxValue::Bound(fun) => fun.target.callable_name(),
+            LoxValue::Class(class) => Some(&class.name),
+            _ => None,
+        }
+    }
+
     pub fn is_truthy(value: &LoxValue) -> bool {
         match value {
             LoxValue::Bool(b) => *b,
@@ -153,6 +388,31 @@ impl LoxValue {
         }
     }
 
+    /// Renders the value unambiguously, unlike `to_string` strings are
+    /// quoted and escaped, so `"nil"` and `nil` can be told apart.
+    pub fn repr(&self) -> String {
+        match self {
+            LoxValue::String(s) => {
+                let mut repr = String::with_capacity(s.len() + 2);
+                repr.push('"');
+                for c in s.chars() {
+                    match c {
+                        '"' => repr.push_str("\\\""),
+                        '\\' => repr.push_str("\\\\"),
+                        '\n' => repr.push_str("\\n"),
+                        '\r' => repr.push_str("\\r"),
+                        '\t' => repr.push_str("\\t"),
+                        c if c.is_control() => repr.push_str(&format!("\\u{{{:x}}}", c as u32)),
+                        c => repr.push(c),
+                    }
+                }
+                repr.push('"');
+                repr
+            }
+            value => value.to_string(),
+        }
+    }
+
     pub fn to_string(value: &LoxValue) -> String {
         match value {
             LoxValue::Number(n) => n.to_string(),
@@ -161,6 +421,9 @@ impl LoxValue {
             LoxValue::Nil => "nil".to_owned(),
             LoxValue::LoxFun(f) => f.to_string(),
             LoxValue::ForeinFun(f) => f.to_string(),
+            LoxValue::Bound(f) => f.to_string(),
+            LoxValue::Class(_) | LoxValue::Object(_) => value.to_string(),
+            LoxValue::List(_) | LoxValue::Map(_) | LoxValue::Range(_) => value.to_string(),
         }
     }
 }
@@ -168,7 +431,7 @@ impl LoxValue {
 #[test]
 fn comparison_tests() {
     use crate::interpreter::Interpreter;
-    use crate::parser::Parser;
+    use crate::parser;
     use crate::resolver;
     use crate::scanner;
     for (source, _expected) in [
@@ -184,10 +447,110 @@ fn comparison_tests() {
         ("!(\"asdf\"==\"asdf\");", false),
         ("!!(\"asdf\"==\"asdf\");", true),
     ] {
-        let tokens = scanner::scan_tokens(&source.to_string()).unwrap();
-        let tree = Parser::new().parse(tokens).unwrap();
+        let tokens = scanner::scan_tokens(source).unwrap();
+        let tree = parser::parse(tokens).unwrap();
         let access_table = resolver::resolve(&tree).unwrap();
         let mut interp = Interpreter::new();
         interp.execute(&tree, access_table).unwrap();
     }
 }
+
+#[test]
+fn repr_tests() {
+    use crate::interpreter::Interpreter;
+    use crate::parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source =
+        "var a = repr(\"nil\"); var b = repr(nil); var c = repr(toString); var d = repr(1.5);";
+    let tree = parser::parse(scanner::scan_tokens(source).unwrap()).unwrap();
+    let access_table = resolver::resolve(&tree).unwrap();
+    let mut interp = Interpreter::new();
+    interp.execute(&tree, access_table).unwrap();
+    for (name, expected) in [
+        ("a", "\"nil\""),
+        ("b", "nil"),
+        ("c", "<native fn toString>"),
+        ("d", "1.5"),
+    ] {
+        assert_eq!(
+            interp.environment.get_global(&name.to_string()),
+            Some(LoxValue::String(expected.to_owned()))
+        );
+    }
+
+    let list = LoxValue::List(Rc::new(RefCell::new(vec![LoxValue::Nil])));
+    let LoxValue::List(elements) = &list else {
+        unreachable!()
+    };
+    elements.borrow_mut().push(list.clone());
+    assert_eq!(list.to_string(), "[nil, [...]]");
+    // break the cycle so the list is freed
+    elements.borrow_mut().clear();
+
+    let tricky = LoxValue::String("say \"hi\"\\\n\t\u{7}".to_owned());
+    assert_eq!(tricky.repr(), "\"say \\\"hi\\\"\\\\\\n\\t\\u{7}\"");
+}
+
+#[test]
+fn arithmetic_tests() {
+    use crate::interpreter::Interpreter;
+    use crate::parser;
+    use crate::resolver;
+    use crate::scanner;
+    for (expression, expected) in [
+        ("7 ~/ 2", 3.),
+        ("-7 ~/ 2", -4.),
+        ("7.5 ~/ 0.5", 15.),
+        ("1 + 9 ~/ 4 * 2", 5.),
+        ("6 & 3", 2.),
+        ("6 | 3", 7.),
+        ("6 ^ 3", 5.),
+        ("1 << 4", 16.),
+        ("-16 >> 2", -4.),
+        ("7.9 & 3.2", 3.),
+        ("1 + 1 << 2", 8.),
+        ("3 & 1 + 1", 2.),
+        ("12 & 10 ^ 1 | 16", 25.),
+    ] {
+        let source = format!("var result = {expression};");
+        let tree = parser::parse(scanner::scan_tokens(&source).unwrap()).unwrap();
+        let access_table = resolver::resolve(&tree).unwrap();
+        let mut interp = Interpreter::new();
+        interp.execute(&tree, access_table).unwrap();
+        assert_eq!(
+            interp.environment.get_global(&"result".to_string()),
+            Some(LoxValue::Number(expected)),
+            "{expression}"
+        );
+    }
+
+    match LoxValue::floor_divide(LoxValue::String("a".to_owned()), LoxValue::Number(1.)) {
+        Err(Error::InternalRuntimeError { message }) => {
+            assert_eq!(
+                message,
+                "Operands of '~/' must be numbers, got string and number."
+            )
+        }
+        other => panic!("expected an error, got {other:?}"),
+    }
+    for (result, expected) in [
+        (
+            LoxValue::bit_or(LoxValue::Number(f64::NAN), LoxValue::Number(1.)),
+            "Operands of '|' must be finite numbers.",
+        ),
+        (
+            LoxValue::shift_left(LoxValue::Number(1.), LoxValue::Number(64.)),
+            "Shift amount must be between 0 and 63, got 64.",
+        ),
+        (
+            LoxValue::shift_right(LoxValue::Number(1.), LoxValue::Number(-1.)),
+            "Shift amount must be between 0 and 63, got -1.",
+        ),
+    ] {
+        match result {
+            Err(Error::InternalRuntimeError { message }) => assert_eq!(message, expected),
+            other => panic!("expected an error, got {other:?}"),
+        }
+    }
+}
@@ -1,119 +1,458 @@
-mod environment;
-mod error;
-mod expression;
-mod interpreter;
-mod lox_function;
-mod lox_value;
-mod parser;
-mod resolver;
-mod scanner;
-mod statement;
-mod tokens;
-
-use error::*;
-use tokens::*;
+use rlox::error::*;
+use rlox::tokens::*;
 
 use std::env;
 use std::fs;
 use std::io;
-use std::io::Write;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-use crate::interpreter::Interpreter;
-use crate::parser::Parser;
-use crate::resolver::resolve;
+use rlox::{formatter, lint, messages, pretty, scanner, sexpr};
 
-fn run(source: String) -> Result<(), Error> {
-    let tokens = scanner::scan_tokens(&source)?;
+use rlox::capability::Capability;
+use rlox::edition::Edition;
+use rlox::interpreter::Interpreter;
+use rlox::lint::{Rule, Warning};
+use rlox::lox_value::LoxValue;
+use rlox::messages::{message, Locale};
+use rlox::parser::Parser;
+use rlox::project::Project;
+use rlox::reporter::ErrorFormat;
+use rlox::resolver::resolve;
+use rlox::statement::Statement;
+
+fn run(
+    source: &str,
+    path: &Path,
+    roots: Vec<PathBuf>,
+    edition: Edition,
+    capabilities: &[Capability],
+    extensions: &[PathBuf],
+) -> Result<(), Vec<Error>> {
+    let tokens = scanner::scan_tokens(source)?;
     // println!("tokens: {:#?}", tokens);
-    let mut parser = Parser::new();
-    let program = parser.parse(tokens)?;
+    let program = Parser::with_edition(edition).parse(tokens)?;
     let access_table = resolve(&program)?;
     // println!("tree: {:#?}", tree);
-    let mut interpreter = Interpreter::new();
-    let _result = interpreter.execute(&program, access_table);
-    // println!("result: {:#?}", result);
+    let mut interpreter = Interpreter::with_edition(edition);
+    interpreter.set_script(path);
+    interpreter.set_source_roots(roots);
+    add_natives(&mut interpreter, capabilities, extensions)?;
+    interpreter.execute(&program, access_table)?;
+
+    Ok(())
+}
 
+/// Grants the capabilities and loads the extensions given on the command
+/// line.
+fn add_natives(
+    interpreter: &mut Interpreter,
+    capabilities: &[Capability],
+    extensions: &[PathBuf],
+) -> Result<(), Error> {
+    for &capability in capabilities {
+        interpreter.allow(capability);
+    }
+    for path in extensions {
+        interpreter.load_extension(path)?;
+    }
     Ok(())
 }
 
-fn print_ast(source: &String) -> Result<(), Error> {
-    let tokens = scanner::scan_tokens(&source)?;
+/// Status the process ends with after `error`, following the BSD
+/// `sysexits.h` conventions.
+fn exit_code(error: &Error) -> u8 {
+    match error {
+        Error::Exit { code } => *code,
+        Error::SyntaxError { .. }
+        | Error::ParsingError { .. }
+        | Error::UnknownBinaryOperator { .. }
+        | Error::UnknownUnaryOperator { .. }
+        | Error::UnknownLiteral { .. }
+        | Error::ResolverError { .. } => EX_DATAERR,
+        Error::InternalRuntimeError { .. } | Error::RuntimeError { .. } | Error::Thrown { .. } => {
+            EX_SOFTWARE
+        }
+    }
+}
+
+/// The command line was used incorrectly.
+const EX_USAGE: u8 = 64;
+/// The program could not be parsed.
+const EX_DATAERR: u8 = 65;
+/// A file could not be read.
+const EX_NOINPUT: u8 = 66;
+/// The program failed while running.
+const EX_SOFTWARE: u8 = 70;
+/// A file could not be written.
+const EX_CANTCREAT: u8 = 73;
+/// The project manifest is invalid.
+const EX_CONFIG: u8 = 78;
+
+/// Reports `errors` of `source` in `error_format`, returns the status to
+/// end with, the one of the first error.
+fn report_errors(errors: Vec<Error>, source: &str, error_format: ErrorFormat) -> ExitCode {
+    let code = errors.first().map_or(EX_SOFTWARE, exit_code);
+    let mut reporter = error_format.reporter(source);
+    for error in errors {
+        match error.into_diagnostic() {
+            Ok(diagnostic) => reporter.report(&diagnostic),
+            Err(Error::Exit { .. }) => {}
+            Err(_) if error_format == ErrorFormat::Silent => {}
+            Err(error) => println!("{} {:#?}", message!(ErrorHeader), error),
+        }
+    }
+    ExitCode::from(code)
+}
+
+/// Prints the syntax tree of `source` as S-expressions, or with `debug`
+/// as the `Debug` output of its nodes.
+fn print_ast(source: &str, edition: Edition, debug: bool) -> Result<(), Vec<Error>> {
+    let tokens = scanner::scan_tokens(source)?;
     // println!("tokens: {:#?}", tokens);
-    let mut parser = Parser::new();
-    let program = parser.parse(tokens)?;
+    // statements which failed to parse are printed as errors
+    let (program, errors) = Parser::with_edition(edition).parse_recovering(tokens);
 
-    for stmt in program {
-        println!("{stmt:#?}");
+    if !debug {
+        print!("{}", sexpr::program(&program));
+    } else {
+        for stmt in program {
+            println!("{stmt:#?}");
+        }
     }
 
-    Ok(())
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors),
+    }
+}
+
+/// Parses a line typed into the REPL, which may also be a bare expression
+/// without the `;` ending it.
+fn parse_line(parser: &mut Parser, tokens: Vec<Token>) -> Result<Vec<Statement>, Vec<Error>> {
+    parser.parse(tokens.clone()).or_else(|errors| {
+        parser
+            .parse_expression(tokens)
+            .map(|expression| vec![Statement::Expression(expression)])
+            .map_err(|_| errors)
+    })
+}
+
+/// Rewrites the file at `path` in the canonical layout, with `check` only
+/// reports whether it already is in it.
+fn format_file(path: &Path, edition: Edition, check: bool, error_format: ErrorFormat) -> ExitCode {
+    let code = match read_source(path, error_format) {
+        Ok(code) => code,
+        Err(status) => return status,
+    };
+    let formatted = match formatter::format(&code, edition) {
+        Ok(formatted) => formatted,
+        Err(errors) => return report_errors(errors, &code, error_format),
+    };
+
+    if formatted == code {
+        return ExitCode::SUCCESS;
+    }
+    if check {
+        eprintln!("{}", message!(NotFormatted, path = path.display()));
+        return ExitCode::FAILURE;
+    }
+    match fs::write(path, formatted) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!(
+                "{}",
+                message!(CannotWriteFile, path = path.display(), error = error)
+            );
+            ExitCode::from(EX_CANTCREAT)
+        }
+    }
+}
+
+/// Reports the warnings about the file at `path` of the rules not in
+/// `disabled`, failing if there are any.
+fn lint_file(
+    path: &Path,
+    edition: Edition,
+    disabled: &[Rule],
+    error_format: ErrorFormat,
+) -> ExitCode {
+    let code = match read_source(path, error_format) {
+        Ok(code) => code,
+        Err(status) => return status,
+    };
+    let mut reporter = error_format.reporter(&code);
+    let warnings = scanner::scan_tokens(&code)
+        .and_then(|tokens| Parser::with_edition(edition).parse(tokens))
+        .and_then(|program| lint::lint(&program, disabled).map_err(Vec::from));
+
+    match warnings {
+        Ok(warnings) if warnings.is_empty() => ExitCode::SUCCESS,
+        Ok(warnings) => {
+            for Warning { rule, diagnostic } in warnings {
+                let diagnostic = diagnostic.with_note(message!(LintRule, rule = rule.name()));
+                reporter.report(&diagnostic);
+            }
+            ExitCode::FAILURE
+        }
+        Err(errors) => report_errors(errors, &code, error_format),
+    }
+}
+
+/// Reads the source file at `path`, reporting invalid UTF-8 in it like
+/// any other syntax error. Returns the status to exit with on failure.
+fn read_source(path: &Path, error_format: ErrorFormat) -> Result<String, ExitCode> {
+    let bytes = fs::read(path).map_err(|error| {
+        eprintln!(
+            "{}",
+            message!(CannotReadFile, path = path.display(), error = error)
+        );
+        ExitCode::from(EX_NOINPUT)
+    })?;
+    match scanner::decode(&bytes) {
+        Ok(source) => Ok(source.to_owned()),
+        Err(error) => Err(report_errors(
+            vec![error],
+            &String::from_utf8_lossy(&bytes),
+            error_format,
+        )),
+    }
 }
 
-fn main() {
-    let args: Vec<&'static mut str> = env::args().map(|arg| arg.leak()).collect();
+fn main() -> ExitCode {
+    messages::set_locale(Locale::from_env());
+    // `--flag=value` is the same as `--flag value`
+    let mut args: Vec<&'static mut str> = env::args()
+        .flat_map(|arg| match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                vec![flag.to_owned(), value.to_owned()]
+            }
+            _ => vec![arg],
+        })
+        .map(|arg| arg.leak())
+        .collect();
+
+    let mut edition = None;
+    if let Some(flag) = args.iter().position(|arg| *arg == "--edition") {
+        let year = args.get(flag + 1).map_or("", |year| &**year);
+        let Ok(selected) = year.parse() else {
+            let editions: Vec<_> = Edition::ALL.iter().map(|edition| edition.year()).collect();
+            eprintln!(
+                "{}",
+                message!(
+                    UnknownEdition,
+                    edition = year,
+                    editions = editions.join(", ")
+                )
+            );
+            return ExitCode::from(EX_USAGE);
+        };
+        edition = Some(selected);
+        args.drain(flag..(flag + 2).min(args.len()));
+    }
+
+    let mut capabilities = Vec::new();
+    if let Some(flag) = args.iter().position(|arg| *arg == "--allow") {
+        let names = args.get(flag + 1).map_or("", |names| &**names);
+        for name in names.split(',') {
+            let Ok(capability) = name.trim().parse() else {
+                let names: Vec<_> = Capability::ALL.iter().map(|c| c.name()).collect();
+                eprintln!(
+                    "{}",
+                    message!(
+                        UnknownCapability,
+                        capability = name,
+                        capabilities = names.join(", ")
+                    )
+                );
+                return ExitCode::from(EX_USAGE);
+            };
+            capabilities.push(capability);
+        }
+        args.drain(flag..(flag + 2).min(args.len()));
+    }
+
+    let mut error_format = ErrorFormat::default();
+    if let Some(flag) = args.iter().position(|arg| *arg == "--error-format") {
+        let name = args.get(flag + 1).map_or("", |name| &**name);
+        let Ok(format) = name.parse() else {
+            let names: Vec<_> = ErrorFormat::ALL.iter().map(|f| f.name()).collect();
+            eprintln!(
+                "{}",
+                message!(
+                    UnknownErrorFormat,
+                    format = name,
+                    formats = names.join(", ")
+                )
+            );
+            return ExitCode::from(EX_USAGE);
+        };
+        error_format = format;
+        args.drain(flag..(flag + 2).min(args.len()));
+    }
+
+    let mut disabled_rules = Vec::new();
+    if let Some(flag) = args.iter().position(|arg| *arg == "--disable") {
+        let names = args.get(flag + 1).map_or("", |names| &**names);
+        for name in names.split(',') {
+            let Ok(rule) = name.trim().parse() else {
+                let names: Vec<_> = Rule::ALL.iter().map(|rule| rule.name()).collect();
+                eprintln!(
+                    "{}",
+                    message!(UnknownLintRule, rule = name, rules = names.join(", "))
+                );
+                return ExitCode::from(EX_USAGE);
+            };
+            disabled_rules.push(rule);
+        }
+        args.drain(flag..(flag + 2).min(args.len()));
+    }
+
+    let mut extensions = Vec::new();
+    while let Some(flag) = args.iter().position(|arg| *arg == "--load") {
+        let Some(path) = args.get(flag + 1) else {
+            println!("{}", message!(Usage));
+            return ExitCode::from(EX_USAGE);
+        };
+        extensions.push(PathBuf::from(&**path));
+        args.drain(flag..flag + 2);
+    }
 
     match args.as_slice() {
         [_] => {
-            let mut line = String::new();
+            let mut bytes = Vec::new();
             print!(" >> ");
             io::stdout().flush().unwrap();
-            let mut interpreter = Interpreter::new();
-            let mut parser = Parser::new();
+            let edition = edition.unwrap_or_default();
+            let mut interpreter = Interpreter::with_edition(edition);
+            if let Err(error) = add_natives(&mut interpreter, &capabilities, &extensions) {
+                return report_errors(vec![error], "", error_format);
+            }
+            let mut parser = Parser::with_edition(edition);
+
+            while io::stdin()
+                .lock()
+                .read_until(b'\n', &mut bytes)
+                .is_ok_and(|read| read > 0)
+            {
+                let line = match scanner::decode(&bytes) {
+                    Ok(line) => line.to_owned(),
+                    Err(error) => {
+                        report_errors(vec![error], &String::from_utf8_lossy(&bytes), error_format);
+                        bytes.clear();
+                        print!(" >> ");
+                        io::stdout().flush().unwrap();
+                        continue;
+                    }
+                };
+                bytes.clear();
+                if line.trim() == ":env" {
+                    for (name, value) in interpreter.environment.globals() {
+                        println!("{name} = {}", pretty::value(&value, pretty::DEFAULT_DEPTH));
+                    }
+                    print!(" >> ");
+                    io::stdout().flush().unwrap();
+                    continue;
+                }
 
-            while let Ok(_) = io::stdin().read_line(&mut line) {
                 match scanner::scan_tokens(&line)
-                    .and_then(|tokens| parser.parse(tokens))
+                    .and_then(|tokens| parse_line(&mut parser, tokens))
                     .and_then(|program| Ok((resolve(&program)?, program)))
-                    .and_then(|(access_table, program)| interpreter.execute(&program, access_table))
-                {
-                    Ok(_result) => {
-                        // println!("{:?}", result);
-                    }
-                    Err(Error::ParsingError {
-                        line,
-                        position,
-                        message: _,
-                    }) => {
-                        println!(
-                            "Encountered error while parsing program, at line {} position {}",
-                            line, position
-                        );
+                    .and_then(|(access_table, program)| {
+                        Ok(interpreter.execute_line(&program, access_table)?)
+                    }) {
+                    // like in most REPLs, `nil` is not worth a line
+                    Ok(None | Some(LoxValue::Nil)) => {}
+                    Ok(Some(value)) => {
+                        println!("{}", pretty::value(&value, pretty::DEFAULT_DEPTH));
                     }
-                    Err(error) => {
-                        println!("Encountered Error:");
-                        println!("{:#?}", error);
+                    Err(errors) => {
+                        if let [Error::Exit { code }] = errors[..] {
+                            return ExitCode::from(code);
+                        }
+                        // errors point into the line they were found in
+                        report_errors(errors, &line, error_format);
                     }
                 };
 
                 print!(" >> ");
-                line.clear();
                 io::stdout().flush().unwrap();
             }
+            ExitCode::SUCCESS
         }
         [_, path] if *path != "--help" => {
-            let code = fs::read_to_string(path).unwrap();
+            let path = Path::new(path);
+            let project = match path.is_dir() {
+                true => match Project::open(path) {
+                    Ok(project) => project,
+                    Err(message) => {
+                        eprintln!("{message}");
+                        return ExitCode::from(EX_CONFIG);
+                    }
+                },
+                false => Project {
+                    entry: path.to_owned(),
+                    roots: Vec::new(),
+                    edition: None,
+                },
+            };
+            let code = match read_source(&project.entry, error_format) {
+                Ok(code) => code,
+                Err(status) => return status,
+            };
 
-            match run(code.clone()) {
-                Ok(_) => {}
-                Err(e) => {
-                    println!("Error: {:#?}", e);
-                }
+            // the command line overrides the edition of the manifest
+            let edition = edition.or(project.edition).unwrap_or_default();
+            match run(
+                &code,
+                &project.entry,
+                project.roots,
+                edition,
+                &capabilities,
+                &extensions,
+            ) {
+                Ok(_) => ExitCode::SUCCESS,
+                Err(errors) => report_errors(errors, &code, error_format),
             }
         }
-        [_, flag, path] if *flag == "--print-ast" => {
-            let code = fs::read_to_string(path).unwrap();
+        [_, flag, path] if *flag == "--print-ast" || *flag == "--dump-ast" => {
+            let code = match read_source(Path::new(path), error_format) {
+                Ok(code) => code,
+                Err(status) => return status,
+            };
 
-            match print_ast(&code) {
-                Ok(_) => {}
-                Err(e) => {
-                    println!("Error: {:#?}", e);
-                }
+            let debug = *flag == "--dump-ast";
+            match print_ast(&code, edition.unwrap_or_default(), debug) {
+                Ok(_) => ExitCode::SUCCESS,
+                Err(errors) => report_errors(errors, &code, error_format),
             }
         }
+        [_, command, path] if *command == "fmt" => format_file(
+            Path::new(path),
+            edition.unwrap_or_default(),
+            false,
+            error_format,
+        ),
+        [_, command, flag, path] if *command == "fmt" && *flag == "--check" => format_file(
+            Path::new(path),
+            edition.unwrap_or_default(),
+            true,
+            error_format,
+        ),
+        [_, command, path] if *command == "lint" => lint_file(
+            Path::new(path),
+            edition.unwrap_or_default(),
+            &disabled_rules,
+            error_format,
+        ),
+        [_, flag] if *flag == "--help" => {
+            println!("{}", message!(Usage));
+            ExitCode::SUCCESS
+        }
         _ => {
-            println!("usage: rlox                              ; uruchamia repl");
-            println!("       rlox [filename.lox]               ; wykonuje kod podany w pliku");
-            println!("       rlox --print-ast [filename.lox]   ; wypisuje ast kodu z pliku");
+            println!("{}", message!(Usage));
+            ExitCode::from(EX_USAGE)
         }
     }
 }
@@ -0,0 +1,847 @@
+//! Catalog of user-facing messages.
+//!
+//! Every diagnostic and CLI text is looked up here by a [`MessageId`], whose
+//! [`key`](MessageId::key) is stable across releases and wording changes.
+//! Templates name their arguments in braces, e.g. `{name}`, and are filled
+//! in with the [`message!`] macro.
+//!
+//! Messages of errors also have a [`code`](MessageId::code), e.g. `E0001`,
+//! shown next to them for looking the error up. The hundreds of a code
+//! tell the stage which reports it: `E00xx` the scanner, `E01xx` the
+//! parser, `E02xx` the resolver and `E1xxx` the interpreter.
+
+use std::cell::Cell;
+use std::fmt::Display;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Locale {
+    English,
+    Polish,
+}
+
+impl Locale {
+    /// Picks the locale named by `RLOX_LANG`, falling back to English.
+    pub fn from_env() -> Locale {
+        match std::env::var("RLOX_LANG") {
+            Ok(lang) if lang.starts_with("pl") => Locale::Polish,
+            _ => Locale::English,
+        }
+    }
+}
+
+thread_local! {
+    static LOCALE: Cell<Locale> = const { Cell::new(Locale::English) };
+}
+
+pub fn set_locale(locale: Locale) {
+    LOCALE.with(|current| current.set(locale));
+}
+
+pub fn locale() -> Locale {
+    LOCALE.with(Cell::get)
+}
+
+macro_rules! catalog {
+    (@code $code:ident) => { Some(stringify!($code)) };
+    (@code) => { None };
+    ($($id:ident => $key:literal $(, $code:ident)? { en: $en:literal, pl: $pl:literal $(,)? }),* $(,)?) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum MessageId {
+            $($id),*
+        }
+
+        impl MessageId {
+            #[allow(dead_code)]
+            pub const ALL: &'static [MessageId] = &[$(MessageId::$id),*];
+
+            /// Stable identifier of the message, independent of its wording.
+            #[allow(dead_code)]
+            pub fn key(self) -> &'static str {
+                match self {
+                    $(MessageId::$id => $key),*
+                }
+            }
+
+            /// Stable code of an error message, e.g. `E0001`.
+            pub fn code(self) -> Option<&'static str> {
+                match self {
+                    $(MessageId::$id => catalog!(@code $($code)?)),*
+                }
+            }
+
+            pub fn template(self, locale: Locale) -> &'static str {
+                match (self, locale) {
+                    $(
+                        (MessageId::$id, Locale::English) => $en,
+                        (MessageId::$id, Locale::Polish) => $pl,
+                    )*
+                }
+            }
+        }
+    };
+}
+
+catalog! {
+    // scanner
+    UnterminatedString => "scan.unterminated-string", E0001 {
+        en: "Unterminated string starting at {line}:{position}.",
+        pl: "Niezakończony łańcuch znaków rozpoczęty w {line}:{position}.",
+    },
+    MalformedExponent => "scan.malformed-exponent", E0002 {
+        en: "Malformed exponent in number literal '{lexeme}', expected digits after it.",
+        pl: "Niepoprawny wykładnik w literale liczbowym '{lexeme}', oczekiwano cyfr.",
+    },
+    MisplacedSeparator => "scan.misplaced-separator", E0003 {
+        en: "Misplaced '_' in number literal '{lexeme}', separators go between single digits.",
+        pl: "Niepoprawnie umieszczony '_' w literale liczbowym '{lexeme}', separatory muszą stać pojedynczo między cyframi.",
+    },
+    InvalidDigit => "scan.invalid-digit", E0004 {
+        en: "Invalid digit '{digit}' in {base} literal '{lexeme}'.",
+        pl: "Niepoprawna cyfra '{digit}' w literale '{lexeme}' o podstawie {radix}.",
+    },
+    MissingDigits => "scan.missing-digits", E0005 {
+        en: "Expected digits after '{prefix}'.",
+        pl: "Oczekiwano cyfr po '{prefix}'.",
+    },
+    InvalidEscape => "scan.invalid-escape", E0006 {
+        en: "Invalid escape sequence '{sequence}'.",
+        pl: "Niepoprawna sekwencja ucieczki '{sequence}'.",
+    },
+    InvalidUnicodeEscape => "scan.invalid-unicode-escape", E0007 {
+        en: "Invalid unicode escape '{sequence}', expected 1 to 6 hex digits in braces.",
+        pl: "Niepoprawna sekwencja unicode '{sequence}', oczekiwano od 1 do 6 cyfr szesnastkowych w nawiasach.",
+    },
+    InvalidNumber => "scan.invalid-number", E0008 {
+        en: "Invalid number literal '{lexeme}'.",
+        pl: "Niepoprawny literał liczbowy '{lexeme}'.",
+    },
+    UnexpectedCharacter => "scan.unexpected-character", E0009 {
+        en: "Unexpected character '{character}'.",
+        pl: "Nieoczekiwany znak '{character}'.",
+    },
+    InvalidUtf8 => "scan.invalid-utf8", E0010 {
+        en: "Invalid UTF-8, found byte {byte}.",
+        pl: "Niepoprawny UTF-8, znaleziono bajt {byte}.",
+    },
+
+    // parser
+    UnknownBinaryOperator => "parse.unknown-binary-operator", E0101 {
+        en: "Unknown binary operator '{lexeme}'.",
+        pl: "Nieznany operator dwuargumentowy '{lexeme}'.",
+    },
+    UnknownUnaryOperator => "parse.unknown-unary-operator", E0102 {
+        en: "Unknown unary operator '{lexeme}'.",
+        pl: "Nieznany operator jednoargumentowy '{lexeme}'.",
+    },
+    UnknownLogicalOperator => "parse.unknown-logical-operator", E0103 {
+        en: "Unknown logical operator '{lexeme}'.",
+        pl: "Nieznany operator logiczny '{lexeme}'.",
+    },
+    UnknownLiteral => "parse.unknown-literal", E0104 {
+        en: "Unknown literal '{lexeme}'.",
+        pl: "Nieznany literał '{lexeme}'.",
+    },
+    UnexpectedEnd => "parse.unexpected-end", E0105 {
+        en: "Unexpected end of input.",
+        pl: "Nieoczekiwany koniec danych wejściowych.",
+    },
+    ExpectedToken => "parse.expected-token", E0106 {
+        en: "Expect {expected}, found {found}.",
+        pl: "Oczekiwano {expected}, znaleziono {found}.",
+    },
+    ExpectedExpression => "parse.expected-expression", E0107 {
+        en: "Expect expression, found {found}.",
+        pl: "Oczekiwano wyrażenia, znaleziono {found}.",
+    },
+    ExpectedFunctionName => "parse.expected-function-name", E0108 {
+        en: "Expect function name.",
+        pl: "Oczekiwano nazwy funkcji.",
+    },
+    ExpectedClassName => "parse.expected-class-name", E0109 {
+        en: "Expect class name.",
+        pl: "Oczekiwano nazwy klasy.",
+    },
+    ExpectedSuperclassName => "parse.expected-superclass-name", E0110 {
+        en: "Expect superclass name.",
+        pl: "Oczekiwano nazwy nadklasy.",
+    },
+    ExpectedDotAfterSuper => "parse.expected-dot-after-super", E0111 {
+        en: "Expect '.' after 'super'.",
+        pl: "Oczekiwano '.' po 'super'.",
+    },
+    ExpectedSuperclassMethodName => "parse.expected-superclass-method-name", E0112 {
+        en: "Expect superclass method name.",
+        pl: "Oczekiwano nazwy metody nadklasy.",
+    },
+    ExpectedInterpolationEnd => "parse.expected-interpolation-end", E0113 {
+        en: "Expect '}' after interpolated expression, found {found}.",
+        pl: "Oczekiwano '}' po wyrażeniu w łańcuchu, znaleziono {found}.",
+    },
+    ExpectedPropertyName => "parse.expected-property-name", E0114 {
+        en: "Expect property name after '.'.",
+        pl: "Oczekiwano nazwy właściwości po '.'.",
+    },
+    ExpectedParameterName => "parse.expected-parameter-name", E0115 {
+        en: "Expect parameter name.",
+        pl: "Oczekiwano nazwy parametru.",
+    },
+    ExpectedVariableName => "parse.expected-variable-name", E0116 {
+        en: "Expect variable name.",
+        pl: "Oczekiwano nazwy zmiennej.",
+    },
+    ExpectedMatchArms => "parse.expected-match-arms", E0117 {
+        en: "Expect '{' after match value.",
+        pl: "Oczekiwano '{' po wartości instrukcji match.",
+    },
+    ExpectedPattern => "parse.expected-pattern", E0118 {
+        en: "Expect a literal or '_' as a match pattern.",
+        pl: "Oczekiwano literału lub '_' jako wzorca instrukcji match.",
+    },
+    DefaultArmNotLast => "parse.default-arm-not-last", E0119 {
+        en: "The '_' arm must be the last one in a match.",
+        pl: "Gałąź '_' musi być ostatnią gałęzią instrukcji match.",
+    },
+    ExpectedCatchOrFinally => "parse.expected-catch-or-finally", E0120 {
+        en: "Expect 'catch' or 'finally' after try block.",
+        pl: "Oczekiwano 'catch' lub 'finally' po bloku try.",
+    },
+    ExpectedImportName => "parse.expected-import-name", E0121 {
+        en: "Expect a name or a module path after 'import'.",
+        pl: "Oczekiwano nazwy lub ścieżki modułu po 'import'.",
+    },
+    ExpectedFrom => "parse.expected-from", E0122 {
+        en: "Expect 'from' after imported names.",
+        pl: "Oczekiwano 'from' po importowanych nazwach.",
+    },
+    ExpectedModulePath => "parse.expected-module-path", E0123 {
+        en: "Expect a string with the module path.",
+        pl: "Oczekiwano napisu ze ścieżką modułu.",
+    },
+    ExpectedConstantInitializer => "parse.expected-constant-initializer", E0124 {
+        en: "Constant '{name}' must be initialized.",
+        pl: "Stała '{name}' musi zostać zainicjalizowana.",
+    },
+    ExpectedBlock => "parse.expected-block", E0125 {
+        en: "Expect '{' after {statement} condition.",
+        pl: "Oczekiwano '{' po warunku instrukcji {statement}.",
+    },
+    ExpectedBlockEnd => "parse.expected-block-end", E0126 {
+        en: "Expect '}' after block.",
+        pl: "Oczekiwano '}' na końcu bloku.",
+    },
+    ExpectedSemicolonAfterValue => "parse.expected-semicolon-after-value", E0127 {
+        en: "Expect ';' after value.",
+        pl: "Oczekiwano ';' po wartości.",
+    },
+    ExpectedSemicolonAfterExpression => "parse.expected-semicolon-after-expression", E0128 {
+        en: "Expect ';' after expression.",
+        pl: "Oczekiwano ';' po wyrażeniu.",
+    },
+    ExpectedSemicolonAfterReturn => "parse.expected-semicolon-after-return", E0129 {
+        en: "Expect ';' after return value.",
+        pl: "Oczekiwano ';' po zwracanej wartości.",
+    },
+    InvalidAssignmentTarget => "parse.invalid-assignment-target", E0130 {
+        en: "Invalid assignment target.",
+        pl: "Niepoprawny cel przypisania.",
+    },
+    TooManyParameters => "parse.too-many-parameters", E0131 {
+        en: "Can't have more than {max} parameters.",
+        pl: "Funkcja nie może mieć więcej niż {max} parametrów.",
+    },
+    TooManyArguments => "parse.too-many-arguments", E0132 {
+        en: "Can't have more than {max} arguments.",
+        pl: "Wywołanie nie może mieć więcej niż {max} argumentów.",
+    },
+
+    // resolver
+    ReadInInitializer => "resolve.read-in-initializer", E0201 {
+        en: "Can't read local variable in its own initializer.",
+        pl: "Nie można odczytać zmiennej lokalnej w jej własnym inicjalizatorze.",
+    },
+    BreakOutsideLoop => "resolve.break-outside-loop", E0202 {
+        en: "Can't use 'break' outside of a loop.",
+        pl: "Nie można użyć 'break' poza pętlą.",
+    },
+    ContinueOutsideLoop => "resolve.continue-outside-loop", E0203 {
+        en: "Can't use 'continue' outside of a loop.",
+        pl: "Nie można użyć 'continue' poza pętlą.",
+    },
+    AssignToConstant => "resolve.assign-to-constant", E0204 {
+        en: "Cannot assign to constant '{name}'.",
+        pl: "Nie można przypisać wartości do stałej '{name}'.",
+    },
+    ThisOutsideClass => "resolve.this-outside-class", E0205 {
+        en: "Can't use 'this' outside of a class.",
+        pl: "Nie można użyć 'this' poza klasą.",
+    },
+    ReturnFromInitializer => "resolve.return-from-initializer", E0206 {
+        en: "Can't return a value from an initializer.",
+        pl: "Nie można zwrócić wartości z konstruktora.",
+    },
+    InheritFromItself => "resolve.inherit-from-itself", E0207 {
+        en: "A class can't inherit from itself.",
+        pl: "Klasa nie może dziedziczyć po sobie samej.",
+    },
+    SuperOutsideClass => "resolve.super-outside-class", E0208 {
+        en: "Can't use 'super' outside of a class.",
+        pl: "Nie można użyć 'super' poza klasą.",
+    },
+    SuperWithoutSuperclass => "resolve.super-without-superclass", E0209 {
+        en: "Can't use 'super' in a class with no superclass.",
+        pl: "Nie można użyć 'super' w klasie bez nadklasy.",
+    },
+    ImportOutsideTopLevel => "resolve.import-outside-top-level", E0210 {
+        en: "Can only import at the top level.",
+        pl: "Importować można tylko na najwyższym poziomie.",
+    },
+    ResolvedTwice => "resolve.resolved-twice", E0211 {
+        en: "Identifier '{name}' was resolved twice.",
+        pl: "Identyfikator '{name}' został rozwiązany dwukrotnie.",
+    },
+    UnparsedStatement => "resolve.unparsed-statement", E0212 {
+        en: "Cannot run a statement which failed to parse.",
+        pl: "Nie można wykonać instrukcji, której nie udało się sparsować.",
+    },
+
+    // lint
+    LintUnusedVariable => "lint.unused-variable" {
+        en: "'{name}' is never read.",
+        pl: "'{name}' nie jest nigdzie odczytywana.",
+    },
+    LintShadowing => "lint.shadowing" {
+        en: "'{name}' shadows a variable of an enclosing scope.",
+        pl: "'{name}' przesłania zmienną z zewnętrznego zasięgu.",
+    },
+    LintDeclaredAt => "lint.declared-at" {
+        en: "'{name}' of the enclosing scope is declared at line {line}.",
+        pl: "'{name}' z zewnętrznego zasięgu jest zadeklarowana w linii {line}.",
+    },
+    LintEmptyBlock => "lint.empty-block" {
+        en: "Empty block.",
+        pl: "Pusty blok.",
+    },
+    LintRule => "lint.rule" {
+        en: "Reported by the '{rule}' rule, '--disable {rule}' turns it off.",
+        pl: "Zgłoszone przez regułę '{rule}', '--disable {rule}' ją wyłącza.",
+    },
+    LintConstantCondition => "lint.constant-condition" {
+        en: "The condition is always '{value}'.",
+        pl: "Warunek ma zawsze wartość '{value}'.",
+    },
+
+    // runtime
+    UndefinedVariable => "runtime.undefined-variable", E1001 {
+        en: "Undefined variable '{name}'.",
+        pl: "Niezdefiniowana zmienna '{name}'.",
+    },
+    DidYouMean => "runtime.did-you-mean" {
+        en: "Did you mean '{name}'?",
+        pl: "Czy chodziło o '{name}'?",
+    },
+    UncaughtException => "runtime.uncaught-exception", E1002 {
+        en: "Uncaught exception: {value}.",
+        pl: "Nieprzechwycony wyjątek: {value}.",
+    },
+    AlreadyDefined => "runtime.already-defined", E1003 {
+        en: "Variable '{name}' already defined at {line}:{position}.",
+        pl: "Zmienna '{name}' została już zdefiniowana w {line}:{position}.",
+    },
+    CapturedUndefined => "runtime.captured-undefined", E1004 {
+        en: "Captured variable '{name}' is not defined.",
+        pl: "Przechwycona zmienna '{name}' nie jest zdefiniowana.",
+    },
+    ArityMismatch => "runtime.arity-mismatch", E1005 {
+        en: "Expected {expected} arguments but got {got}.",
+        pl: "Oczekiwano {expected} argumentów, otrzymano {got}.",
+    },
+    NotCallable => "runtime.not-callable", E1006 {
+        en: "Can only call functions.",
+        pl: "Można wywoływać tylko funkcje.",
+    },
+    OperandsMustBeNumbers => "runtime.operands-must-be-numbers", E1007 {
+        en: "Operands of '{operator}' must be numbers, got {left} and {right}.",
+        pl: "Argumenty '{operator}' muszą być liczbami, otrzymano {left} i {right}.",
+    },
+    OperandsMustBeFinite => "runtime.operands-must-be-finite", E1008 {
+        en: "Operands of '{operator}' must be finite numbers.",
+        pl: "Argumenty '{operator}' muszą być skończonymi liczbami.",
+    },
+    ShiftOutOfRange => "runtime.shift-out-of-range", E1009 {
+        en: "Shift amount must be between 0 and 63, got {amount}.",
+        pl: "Przesunięcie musi mieścić się w zakresie od 0 do 63, otrzymano {amount}.",
+    },
+    OperandsMustBeNumbersOrStrings => "runtime.operands-must-be-numbers-or-strings", E1010 {
+        en: "Operands of '+' must be two numbers or two strings, got {left} and {right}.",
+        pl: "Argumenty '+' muszą być dwiema liczbami lub dwoma łańcuchami, otrzymano {left} i {right}.",
+    },
+    OperandMustBeNumber => "runtime.operand-must-be-number", E1011 {
+        en: "Operand of '{operator}' must be a number, got {value}.",
+        pl: "Argument '{operator}' musi być liczbą, otrzymano {value}.",
+    },
+    ArgumentType => "runtime.argument-type", E1012 {
+        en: "Argument {index} of {function}() must be a {expected}, got {kind}.",
+        pl: "Argument {index} funkcji {function}() musi być typu {expected}, otrzymano {kind}.",
+    },
+    UndefinedProperty => "runtime.undefined-property", E1013 {
+        en: "Undefined property '{name}'.",
+        pl: "Niezdefiniowana właściwość '{name}'.",
+    },
+    OnlyInstancesHaveProperties => "runtime.only-instances-have-properties", E1014 {
+        en: "Only instances and maps have properties.",
+        pl: "Tylko instancje i mapy mają właściwości.",
+    },
+    SuperclassMustBeClass => "runtime.superclass-must-be-class", E1015 {
+        en: "Superclass must be a class.",
+        pl: "Nadklasa musi być klasą.",
+    },
+    UndefinedKey => "runtime.undefined-key", E1016 {
+        en: "Key {key} not found in map.",
+        pl: "Klucza {key} nie ma w mapie.",
+    },
+    ZeroRangeStep => "runtime.zero-range-step", E1017 {
+        en: "Range step must not be zero.",
+        pl: "Krok zakresu nie może być zerem.",
+    },
+    NotIterable => "runtime.not-iterable", E1018 {
+        en: "Values of type {kind} cannot be iterated.",
+        pl: "Po wartościach typu {kind} nie można iterować.",
+    },
+    NotIndexable => "runtime.not-indexable", E1019 {
+        en: "Values of type {kind} cannot be indexed.",
+        pl: "Wartości typu {kind} nie można indeksować.",
+    },
+    ArgumentMustBeInteger => "runtime.argument-must-be-integer", E1020 {
+        en: "Argument {index} of {function}() must be an integer, got {value}.",
+        pl: "Argument {index} funkcji {function}() musi być liczbą całkowitą, otrzymano {value}.",
+    },
+    EmptyRandomRange => "runtime.empty-random-range", E1021 {
+        en: "Cannot pick a number between {low} and {high}, the lower bound is greater.",
+        pl: "Nie można wylosować liczby między {low} a {high}, dolna granica jest większa.",
+    },
+    UnmatchedFormatBrace => "runtime.unmatched-format-brace", E1022 {
+        en: "Unmatched '}' in format string.",
+        pl: "Niesparowany '}' w formacie.",
+    },
+    UnclosedFormatPlaceholder => "runtime.unclosed-format-placeholder", E1023 {
+        en: "Unclosed '{' in format string.",
+        pl: "Niezamknięty '{' w formacie.",
+    },
+    InvalidFormatPlaceholder => "runtime.invalid-format-placeholder", E1024 {
+        en: "Invalid placeholder '{{placeholder}}' in format string.",
+        pl: "Niepoprawne miejsce na wartość '{{placeholder}}' w formacie.",
+    },
+    InvalidFormatSpec => "runtime.invalid-format-spec", E1025 {
+        en: "Invalid format specifier '{spec}'.",
+        pl: "Niepoprawny specyfikator formatu '{spec}'.",
+    },
+    MissingFormatArgument => "runtime.missing-format-argument", E1026 {
+        en: "Format string refers to argument {index}, but only {count} arguments were given.",
+        pl: "Format odwołuje się do argumentu {index}, a podano tylko {count} argumentów.",
+    },
+    UnknownTimeDirective => "runtime.unknown-time-directive", E1027 {
+        en: "Unknown directive '%{directive}' in time format.",
+        pl: "Nieznana dyrektywa '%{directive}' w formacie czasu.",
+    },
+    TimeDoesNotMatch => "runtime.time-does-not-match", E1028 {
+        en: "'{input}' does not match the time format '{format}'.",
+        pl: "'{input}' nie pasuje do formatu czasu '{format}'.",
+    },
+    InvalidDate => "runtime.invalid-date", E1029 {
+        en: "Invalid date '{input}'.",
+        pl: "Niepoprawna data '{input}'.",
+    },
+    AssertionFailed => "runtime.assertion-failed", E1030 {
+        en: "Assertion failed.",
+        pl: "Asercja nie powiodła się.",
+    },
+    AssertionFailedWith => "runtime.assertion-failed-with", E1031 {
+        en: "Assertion failed: {message}",
+        pl: "Asercja nie powiodła się: {message}",
+    },
+    Panic => "runtime.panic", E1032 {
+        en: "Panic: {message}",
+        pl: "Panika: {message}",
+    },
+    HttpStatus => "runtime.http-status", E1033 {
+        en: "GET {url} failed with status {status}.",
+        pl: "GET {url} zakończył się statusem {status}.",
+    },
+    HttpRequestFailed => "runtime.http-request-failed", E1034 {
+        en: "GET {url} failed: {error}",
+        pl: "GET {url} nie powiódł się: {error}",
+    },
+    NativeAlreadyDefined => "runtime.native-already-defined", E1035 {
+        en: "Cannot install the native '{name}', the name is already defined.",
+        pl: "Nie można zainstalować funkcji '{name}', nazwa jest już zdefiniowana.",
+    },
+    CannotLoadExtension => "runtime.cannot-load-extension", E1036 {
+        en: "Could not load the extension '{path}': {error}",
+        pl: "Nie można załadować rozszerzenia '{path}': {error}",
+    },
+    ExtensionInvalidName => "runtime.extension-invalid-name", E1037 {
+        en: "An extension defined a native without a valid UTF-8 name.",
+        pl: "Rozszerzenie zdefiniowało funkcję bez poprawnej nazwy w UTF-8.",
+    },
+    ExtensionArgument => "runtime.extension-argument", E1038 {
+        en: "Argument {index} of {function}() cannot be passed to a native extension, got {value}.",
+        pl: "Argumentu {index} funkcji {function}() nie można przekazać do rozszerzenia, otrzymano {value}.",
+    },
+    ExtensionFailed => "runtime.extension-failed", E1039 {
+        en: "{function}() failed: {message}",
+        pl: "{function}() nie powiodła się: {message}",
+    },
+    ExtensionInvalidResult => "runtime.extension-invalid-result", E1040 {
+        en: "{function}() returned an invalid value.",
+        pl: "{function}() zwróciła niepoprawną wartość.",
+    },
+    CannotWriteOutput => "runtime.cannot-write-output", E1041 {
+        en: "Could not write the output: {error}",
+        pl: "Nie można zapisać wyjścia: {error}",
+    },
+    CannotRunCommand => "runtime.cannot-run-command", E1042 {
+        en: "Could not run '{command}': {error}",
+        pl: "Nie można uruchomić '{command}': {error}",
+    },
+    ExitCodeOutOfRange => "runtime.exit-code-out-of-range", E1043 {
+        en: "Exit code must be between 0 and 255, got {code}.",
+        pl: "Kod wyjścia musi należeć do przedziału od 0 do 255, otrzymano {code}.",
+    },
+    InvalidEnvName => "runtime.invalid-env-name", E1044 {
+        en: "Invalid environment variable name '{name}'.",
+        pl: "Niepoprawna nazwa zmiennej środowiskowej '{name}'.",
+    },
+    InvalidEnvValue => "runtime.invalid-env-value", E1045 {
+        en: "Value of environment variable '{name}' cannot contain a NUL character.",
+        pl: "Wartość zmiennej środowiskowej '{name}' nie może zawierać znaku NUL.",
+    },
+    IndexMustBeInteger => "runtime.index-must-be-integer", E1046 {
+        en: "List index must be an integer, got {value}.",
+        pl: "Indeks listy musi być liczbą całkowitą, otrzymano {value}.",
+    },
+    IndexOutOfRange => "runtime.index-out-of-range", E1047 {
+        en: "Index {index} is out of range for a list of length {len}.",
+        pl: "Indeks {index} jest poza zakresem listy o długości {len}.",
+    },
+    OnlyInstancesHaveFields => "runtime.only-instances-have-fields", E1048 {
+        en: "Only instances and maps have fields.",
+        pl: "Tylko instancje i mapy mają pola.",
+    },
+    NanKey => "runtime.nan-key", E1049 {
+        en: "NaN cannot be used as a key.",
+        pl: "NaN nie może być użyte jako klucz.",
+    },
+    FractionalKey => "runtime.fractional-key", E1050 {
+        en: "Only integer numbers can be used as keys, got {value}.",
+        pl: "Tylko liczby całkowite mogą być użyte jako klucze, otrzymano {value}.",
+    },
+    UnhashableKey => "runtime.unhashable-key", E1051 {
+        en: "Values of type {kind} cannot be used as keys.",
+        pl: "Wartości typu {kind} nie mogą być użyte jako klucze.",
+    },
+    CannotImport => "runtime.cannot-import", E1052 {
+        en: "Could not import '{path}': {error}",
+        pl: "Nie można zaimportować '{path}': {error}",
+    },
+    CircularImport => "runtime.circular-import", E1053 {
+        en: "Circular import: {cycle}.",
+        pl: "Cykliczny import: {cycle}.",
+    },
+    ErrorInModule => "runtime.error-in-module", E1054 {
+        en: "In module '{path}' at {line}:{position}: {message}",
+        pl: "W module '{path}' w {line}:{position}: {message}",
+    },
+    ErrorInEval => "runtime.error-in-eval", E1055 {
+        en: "In evaluated code at {line}:{position}: {message}",
+        pl: "W wykonywanym kodzie w {line}:{position}: {message}",
+    },
+    NotExported => "runtime.not-exported", E1056 {
+        en: "Module '{path}' does not define '{name}'.",
+        pl: "Moduł '{path}' nie definiuje '{name}'.",
+    },
+    TraceIn => "runtime.trace-in" {
+        en: "in {function} at {line}:{position}",
+        pl: "w {function} w {line}:{position}",
+    },
+    TraceCalledFrom => "runtime.trace-called-from" {
+        en: "called from {function} at {line}:{position}",
+        pl: "wywołane z {function} w {line}:{position}",
+    },
+    TraceTopLevel => "runtime.trace-top-level" {
+        en: "<script>",
+        pl: "<skrypt>",
+    },
+    AccessTableConflict => "runtime.access-table-conflict", E1057 {
+        en: "Program was resolved against identifiers that are already in use.",
+        pl: "Program został rozwiązany z użyciem identyfikatorów, które są już zajęte.",
+    },
+
+    // command line
+    Usage => "cli.usage" {
+        en: "usage: rlox                              ; starts the REPL\n       rlox [filename.lox]               ; runs the code in the file\n       rlox [directory]                  ; runs the project in the directory\n       rlox --print-ast [filename.lox]   ; prints the AST of the code in the file\n       rlox --dump-ast [filename.lox]    ; prints the AST with every field of its nodes\n       rlox fmt [--check] [filename.lox] ; formats the file, with --check only reports if it is not formatted\n       rlox lint [filename.lox]          ; warns about likely mistakes in the code of the file\n\n       --edition <year>                  ; selects the language edition (2023, 2026)\n       --allow <capability>,...          ; lets scripts use the natives of the capabilities (env, process)\n       --load <library>                  ; loads natives from a shared library, can be repeated\n       --disable <rule>,...              ; turns off lint rules (unused-variable, shadowing, empty-block, constant-condition)\n       --error-format <format>           ; how errors are printed (console, json, silent)",
+        pl: "usage: rlox                              ; uruchamia repl\n       rlox [filename.lox]               ; wykonuje kod podany w pliku\n       rlox [katalog]                    ; wykonuje projekt z katalogu\n       rlox --print-ast [filename.lox]   ; wypisuje ast kodu z pliku\n       rlox --dump-ast [filename.lox]    ; wypisuje ast ze wszystkimi polami węzłów\n       rlox fmt [--check] [filename.lox] ; formatuje plik, z --check tylko zgłasza brak formatowania\n       rlox lint [filename.lox]          ; ostrzega o prawdopodobnych błędach w kodzie z pliku\n\n       --edition <rok>                   ; wybiera edycję języka (2023, 2026)\n       --allow <uprawnienie>,...         ; pozwala skryptom używać funkcji z uprawnień (env, process)\n       --load <biblioteka>               ; ładuje funkcje z biblioteki współdzielonej, można powtarzać\n       --disable <reguła>,...            ; wyłącza reguły lintera (unused-variable, shadowing, empty-block, constant-condition)\n       --error-format <format>           ; sposób wypisywania błędów (console, json, silent)",
+    },
+    UnknownEdition => "cli.unknown-edition" {
+        en: "Unknown edition '{edition}', expected one of: {editions}.",
+        pl: "Nieznana edycja '{edition}', oczekiwano jednej z: {editions}.",
+    },
+    UnknownCapability => "cli.unknown-capability" {
+        en: "Unknown capability '{capability}', expected one of: {capabilities}.",
+        pl: "Nieznane uprawnienie '{capability}', oczekiwano jednego z: {capabilities}.",
+    },
+    CannotReadFile => "cli.cannot-read-file" {
+        en: "Could not read '{path}': {error}",
+        pl: "Nie można odczytać '{path}': {error}",
+    },
+    ManifestSyntax => "cli.manifest-syntax" {
+        en: "{location}: Expect 'key = value'.",
+        pl: "{location}: Oczekiwano 'klucz = wartość'.",
+    },
+    ManifestUnknownKey => "cli.manifest-unknown-key" {
+        en: "{location}: Unknown key '{key}', expected entry, roots or edition.",
+        pl: "{location}: Nieznany klucz '{key}', oczekiwano entry, roots lub edition.",
+    },
+    CannotWriteFile => "cli.cannot-write-file" {
+        en: "Could not write '{path}': {error}",
+        pl: "Nie można zapisać '{path}': {error}",
+    },
+    NotFormatted => "cli.not-formatted" {
+        en: "'{path}' is not formatted.",
+        pl: "'{path}' nie jest sformatowany.",
+    },
+    UnknownLintRule => "cli.unknown-lint-rule" {
+        en: "Unknown lint rule '{rule}', expected one of: {rules}.",
+        pl: "Nieznana reguła lintera '{rule}', oczekiwano jednej z: {rules}.",
+    },
+    UnknownErrorFormat => "cli.unknown-error-format" {
+        en: "Unknown error format '{format}', expected one of: {formats}.",
+        pl: "Nieznany format błędów '{format}', oczekiwano jednego z: {formats}.",
+    },
+    ErrorAt => "cli.error-at" {
+        en: "Error at line {line}, position {position}: {message}",
+        pl: "Błąd w linii {line}, pozycja {position}: {message}",
+    },
+    ErrorWithCodeAt => "cli.error-with-code-at" {
+        en: "Error[{code}] at line {line}, position {position}: {message}",
+        pl: "Błąd[{code}] w linii {line}, pozycja {position}: {message}",
+    },
+    WarningAt => "cli.warning-at" {
+        en: "Warning at line {line}, position {position}: {message}",
+        pl: "Ostrzeżenie w linii {line}, pozycja {position}: {message}",
+    },
+    NoteLine => "cli.note" {
+        en: "  note: {note}",
+        pl: "  uwaga: {note}",
+    },
+    ErrorHeader => "cli.error" {
+        en: "Error:",
+        pl: "Błąd:",
+    },
+    FfiErrorAt => "ffi.error-at" {
+        en: "Error at line {line}, position {position}: {message}",
+        pl: "Błąd w linii {line}, pozycja {position}: {message}",
+    },
+    FfiInvalidSource => "ffi.invalid-source" {
+        en: "The source is not valid UTF-8.",
+        pl: "Kod źródłowy nie jest poprawnym UTF-8.",
+    },
+}
+
+impl MessageId {
+    /// The message whose code is `code`, e.g. `E0001`.
+    pub fn of_code(code: &str) -> Option<MessageId> {
+        MessageId::ALL
+            .iter()
+            .copied()
+            .find(|id| id.code() == Some(code))
+    }
+}
+
+/// Fills in the template of `id` in the current locale.
+///
+/// Placeholders without a matching argument are left as they are, which
+/// keeps literal braces such as `'{'` intact.
+pub fn render(id: MessageId, args: &[(&str, &dyn Display)]) -> String {
+    let template = id.template(locale());
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let argument = rest.find('}').and_then(|end| {
+            let name = &rest[1..end];
+            args.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| (end, value))
+        });
+
+        match argument {
+            Some((end, value)) => {
+                rendered.push_str(&value.to_string());
+                rest = &rest[end + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+
+    rendered
+}
+
+/// A rendered message, along with the catalog entry it was rendered
+/// from. Errors keep it to report the code of the entry, whatever the
+/// text turned out to be.
+#[derive(Clone, PartialEq)]
+pub struct Message {
+    pub id: Option<MessageId>,
+    pub text: String,
+}
+
+impl Message {
+    /// The code of the entry the message was rendered from, if it has one.
+    pub fn code(&self) -> Option<&'static str> {
+        self.id.and_then(MessageId::code)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+impl Display for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+// shown as its text, errors print the same as with a plain string
+impl std::fmt::Debug for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&self.text, f)
+    }
+}
+
+impl std::ops::Deref for Message {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+/// Text from outside the catalog, e.g. a message passed to `assert()`.
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Message { id: None, text }
+    }
+}
+
+impl From<&str> for Message {
+    fn from(text: &str) -> Self {
+        Message::from(text.to_owned())
+    }
+}
+
+impl From<Message> for String {
+    fn from(message: Message) -> Self {
+        message.text
+    }
+}
+
+impl PartialEq<String> for Message {
+    fn eq(&self, other: &String) -> bool {
+        self.text == *other
+    }
+}
+
+impl PartialEq<str> for Message {
+    fn eq(&self, other: &str) -> bool {
+        self.text == other
+    }
+}
+
+impl PartialEq<&str> for Message {
+    fn eq(&self, other: &&str) -> bool {
+        self.text == *other
+    }
+}
+
+/// Renders a catalog message, e.g. `message!(UndefinedVariable, name = "x")`.
+#[macro_export]
+macro_rules! message {
+    ($id:ident $(, $key:ident = $value:expr)* $(,)?) => {
+        $crate::messages::Message {
+            id: Some($crate::messages::MessageId::$id),
+            text: $crate::messages::render(
+                $crate::messages::MessageId::$id,
+                &[$((stringify!($key), &$value as &dyn std::fmt::Display)),*],
+            ),
+        }
+    };
+}
+
+pub use message;
+
+#[test]
+fn test_message_catalog() {
+    use std::collections::HashSet;
+
+    let keys: HashSet<_> = MessageId::ALL.iter().map(|id| id.key()).collect();
+    assert_eq!(
+        keys.len(),
+        MessageId::ALL.len(),
+        "message keys must be unique"
+    );
+
+    assert_eq!(
+        message!(UndefinedVariable, name = "x"),
+        "Undefined variable 'x'."
+    );
+    assert_eq!(
+        message!(ExpectedBlock, statement = "if"),
+        "Expect '{' after if condition."
+    );
+    assert_eq!(
+        message!(ArityMismatch, expected = 1),
+        "Expected 1 arguments but got {got}."
+    );
+
+    let codes: HashSet<_> = MessageId::ALL.iter().filter_map(|id| id.code()).collect();
+    assert_eq!(
+        codes.len(),
+        MessageId::ALL
+            .iter()
+            .filter(|id| id.code().is_some())
+            .count(),
+        "error codes must be unique"
+    );
+    assert_eq!(MessageId::UnterminatedString.code(), Some("E0001"));
+    assert_eq!(MessageId::ErrorAt.code(), None);
+
+    assert_eq!(
+        message!(ExpectedBlock, statement = "if").code(),
+        Some("E0125")
+    );
+    assert_eq!(Message::from("Something else.").code(), None);
+    assert_eq!(
+        MessageId::of_code("E0001"),
+        Some(MessageId::UnterminatedString)
+    );
+    assert_eq!(MessageId::of_code("E9999"), None);
+
+    set_locale(Locale::Polish);
+    assert_eq!(
+        message!(UndefinedVariable, name = "x"),
+        "Niezdefiniowana zmienna 'x'."
+    );
+    assert_eq!(
+        message!(UndefinedVariable, name = "x").id,
+        Some(MessageId::UndefinedVariable)
+    );
+    set_locale(Locale::English);
+}
@@ -0,0 +1,119 @@
+//! Loading of files imported with `import`.
+//!
+//! Each file is executed once, in a [`Module`](crate::environment::Module)
+//! of its own, the first time it is imported. Modules are cached by their
+//! canonical path, importing a file again, under any path, reuses the
+//! globals it defined. A file importing itself, directly or through other
+//! modules, is reported along with the chain of imports forming the cycle.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::edition::Edition;
+use crate::environment::ModuleRef;
+use crate::error::Error;
+use crate::messages::message;
+use crate::parser::Parser;
+use crate::resolver::{self, AccessTable};
+use crate::scanner;
+use crate::statement::Statement;
+
+pub struct ModuleLoader {
+    /// edition modules are parsed with, the same as the main program's
+    pub edition: Edition,
+    /// directory of the file being executed, imports are relative to it
+    pub base_dir: PathBuf,
+    /// directories searched for imports not found in `base_dir`
+    pub roots: Vec<PathBuf>,
+    /// canonical directory of the main program, cycles are described
+    /// with paths relative to it
+    root: PathBuf,
+    /// executed modules by canonical path
+    modules: HashMap<PathBuf, ModuleRef>,
+    /// modules whose top level is executing, outermost first
+    loading: Vec<PathBuf>,
+}
+
+impl ModuleLoader {
+    pub fn new(edition: Edition) -> Self {
+        ModuleLoader {
+            edition,
+            base_dir: PathBuf::new(),
+            roots: Vec::new(),
+            root: std::env::current_dir().unwrap_or_default(),
+            modules: HashMap::new(),
+            loading: Vec::new(),
+        }
+    }
+
+    /// Makes imports of the main program relative to `dir`.
+    pub fn set_base_dir(&mut self, dir: &Path) {
+        self.base_dir = dir.to_owned();
+        // the directory of a script given by its bare file name is empty
+        let root = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        self.root = fs::canonicalize(root).unwrap_or_else(|_| root.to_owned());
+    }
+
+    /// Canonical path of the file `path` refers to from `base_dir`, or
+    /// else from the first source root containing it.
+    pub fn locate(&self, path: &str) -> io::Result<PathBuf> {
+        fs::canonicalize(self.base_dir.join(path)).or_else(|error| {
+            self.roots
+                .iter()
+                .find_map(|root| fs::canonicalize(root.join(path)).ok())
+                .ok_or(error)
+        })
+    }
+
+    /// The module at `path`, if it was executed already.
+    pub fn cached(&self, path: &Path) -> Option<ModuleRef> {
+        self.modules.get(path).cloned()
+    }
+
+    /// Describes the chain of imports leading back to `path`, e.g.
+    /// `a.lox -> b.lox -> a.lox`, if its top level is still executing.
+    pub fn cycle(&self, path: &Path) -> Option<String> {
+        let start = self.loading.iter().position(|loading| loading == path)?;
+        let cycle: Vec<_> = self.loading[start..]
+            .iter()
+            .chain([&self.loading[start]])
+            .map(|path| {
+                path.strip_prefix(&self.root)
+                    .unwrap_or(path)
+                    .display()
+                    .to_string()
+            })
+            .collect();
+        Some(cycle.join(" -> "))
+    }
+
+    /// Reads, scans, parses and resolves the file at `path`, which is
+    /// marked as loading until [`finish`](Self::finish) is called.
+    pub fn load(&mut self, path: &Path) -> Result<(Vec<Statement>, AccessTable), Error> {
+        self.loading.push(path.to_owned());
+
+        let bytes = fs::read(path).map_err(|error| Error::InternalRuntimeError {
+            message: message!(CannotImport, path = path.display(), error = error),
+        })?;
+        let tokens = scanner::scan_tokens(scanner::decode(&bytes)?)?;
+        let program = Parser::with_edition(self.edition).parse(tokens)?;
+        let access_table = resolver::resolve(&program)?;
+        Ok((program, access_table))
+    }
+
+    /// Records the outcome of executing the module at `path`, a module
+    /// that failed is not cached and can be imported again.
+    pub fn finish(&mut self, path: &Path, module: Option<ModuleRef>) {
+        let loaded = self.loading.pop();
+        debug_assert_eq!(loaded.as_deref(), Some(path), "modules finish in order");
+        if let Some(module) = module {
+            self.modules.insert(path.to_owned(), module);
+        }
+    }
+}
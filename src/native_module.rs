@@ -0,0 +1,92 @@
+//! Groups of natives installed into an interpreter together.
+//!
+//! A module either defines its natives as globals, or as entries of a
+//! single global map named after its namespace, so that scripts call
+//! them as `math.sqrt(2)`:
+//!
+//! ```ignore
+//! interpreter.install(
+//!     NativeModule::namespace("math")
+//!         .function("sqrt", 1, sqrt)
+//!         .function("pow", 2, pow),
+//! );
+//! ```
+
+use crate::error::Error;
+use crate::lox_function::{Arity, ForeinFun, NativeContext};
+use crate::lox_key::LoxKey;
+use crate::lox_map::LoxMap;
+use crate::lox_value::LoxValue;
+use crate::shared::{Rc, RefCell, Shareable};
+use crate::typed_native::TypedNative;
+
+#[derive(Clone)]
+pub struct NativeModule {
+    /// name of the global map holding the natives, `None` for natives
+    /// defined as globals
+    namespace: Option<String>,
+    functions: Vec<(String, ForeinFun)>,
+}
+
+impl NativeModule {
+    /// A module defining its natives as globals.
+    pub fn global() -> Self {
+        NativeModule {
+            namespace: None,
+            functions: Vec::new(),
+        }
+    }
+
+    /// A module whose natives are reached through the global `name`.
+    pub fn namespace(name: &str) -> Self {
+        NativeModule {
+            namespace: Some(name.to_owned()),
+            functions: Vec::new(),
+        }
+    }
+
+    /// Adds the native `name`, in errors it is called by its name
+    /// qualified with the namespace, e.g. `math.sqrt()`.
+    pub fn function(
+        mut self,
+        name: &str,
+        arity: impl Into<Arity>,
+        fun: impl Fn(&mut NativeContext) -> Result<LoxValue, Error> + Shareable + 'static,
+    ) -> Self {
+        let qualified = match &self.namespace {
+            Some(namespace) => format!("{namespace}.{name}"),
+            None => name.to_owned(),
+        };
+        self.functions
+            .push((name.to_owned(), ForeinFun::new(qualified, arity, fun)));
+        self
+    }
+
+    /// Adds the native `name` written as a plain function, see
+    /// [`typed_native`](crate::typed_native).
+    pub fn typed<Args>(self, name: &str, fun: impl TypedNative<Args>) -> Self {
+        fn arity<Args, F: TypedNative<Args>>(_: &F) -> usize {
+            F::ARITY
+        }
+        let arity = arity(&fun);
+        self.function(name, arity, move |ctx| fun.call(ctx))
+    }
+
+    /// Globals the module defines, by name.
+    pub fn into_globals(self) -> Vec<(String, LoxValue)> {
+        let functions = self
+            .functions
+            .into_iter()
+            .map(|(name, fun)| (name, LoxValue::ForeinFun(fun.into())));
+        match self.namespace {
+            Some(namespace) => {
+                let mut map = LoxMap::new();
+                for (name, fun) in functions {
+                    map.insert(LoxKey::String(name), fun);
+                }
+                vec![(namespace, LoxValue::Map(Rc::new(RefCell::new(map))))]
+            }
+            None => functions.collect(),
+        }
+    }
+}
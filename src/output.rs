@@ -0,0 +1,43 @@
+//! Where `print` and `eprint()` write to.
+//!
+//! The interpreter writes to the standard output and error by default,
+//! embedders can replace either with any [`Write`], e.g. a [`Captured`]
+//! buffer to read the output of a program back.
+
+use std::io::{self, Write};
+
+use crate::shared::{Rc, RefCell, Shareable};
+
+/// Anything `print` and `eprint()` can write to, see `shared` for when
+/// it has to be `Send + Sync`.
+pub trait Sink: Write + Shareable {}
+
+impl<T: Write + Shareable> Sink for T {}
+
+/// An output sink keeping what was written to it. Clones share the same
+/// buffer, so one clone can be given to the interpreter and another kept
+/// to read the output.
+#[derive(Clone, Debug, Default)]
+pub struct Captured(Rc<RefCell<Vec<u8>>>);
+
+impl Captured {
+    pub fn new() -> Self {
+        Captured::default()
+    }
+
+    /// Everything written so far, invalid UTF-8 replaced.
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+}
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,14 +1,99 @@
-use crate::statement::{Block, Statement};
+use std::cell::Cell;
+
+use crate::edition::{ContextualKeyword, Edition};
+use crate::lower;
+use crate::messages::{message, Message};
+use crate::statement::{Block, Catch, FunctionDeclaration, MatchArm, Statement};
+use crate::tokens::StringPart;
 use crate::{error::Error, expression::*, Token, TokenType};
 
 pub struct Parser {
     tokens: Vec<Token>,
+    edition: Edition,
 
     identifier_counter: usize,
 
     current_index: usize,
     line: usize,
     position: usize,
+    end: usize,
+    /// errors found so far, also ones parsing goes on after
+    errors: Vec<Error>,
+}
+
+/// Most parameters a function and arguments a call may have.
+const MAX_ARGUMENTS: usize = 255;
+
+/// How tightly binary operators bind, from the loosest.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Coalesce,
+    Or,
+    And,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Equality,
+    Comparison,
+    Shift,
+    Term,
+    Factor,
+}
+
+impl Precedence {
+    /// The precedence of `token_type` as a binary operator, the operator
+    /// table of the expression grammar.
+    fn of(token_type: &TokenType) -> Option<Precedence> {
+        use TokenType as T;
+        let precedence = match token_type {
+            T::QuestionQuestion => Precedence::Coalesce,
+            T::Or => Precedence::Or,
+            T::And => Precedence::And,
+            // bitwise operators bind looser than equality, as in C
+            T::Pipe => Precedence::BitOr,
+            T::Caret => Precedence::BitXor,
+            T::Ampersand => Precedence::BitAnd,
+            T::BangEqual | T::EqualEqual => Precedence::Equality,
+            T::Greater | T::GreaterEqual | T::Less | T::LessEqual => Precedence::Comparison,
+            T::LessLess | T::GreaterGreater => Precedence::Shift,
+            T::Minus | T::Plus => Precedence::Term,
+            T::Slash | T::TildeSlash | T::Star => Precedence::Factor,
+            _ => return None,
+        };
+        Some(precedence)
+    }
+}
+
+thread_local! {
+    /// Last identifier id handed out by `parse` and `parse_expression`.
+    static IDENTIFIERS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Parses a program for the resolver and the interpreter, like
+/// `Parser::parse` in the default edition.
+///
+/// Every identifier in a tree gets an id, which has to be unique among
+/// all code run by one interpreter. This function and [`parse_expression`]
+/// take the ids from a counter kept per thread, which every call goes on
+/// with, so all trees they return on one thread can be run one after
+/// another by the same interpreter. Trees parsed on another thread, or by
+/// a `Parser` of your own, which numbers identifiers from 0, may reuse
+/// those ids and must not be run by the same interpreter.
+pub fn parse(tokens: Vec<Token>) -> Result<Vec<Statement>, Vec<Error>> {
+    with_shared_identifiers(|parser| parser.parse(tokens))
+}
+
+/// Parses a single expression, numbering its identifiers from the same
+/// per-thread counter as [`parse`].
+pub fn parse_expression(tokens: Vec<Token>) -> Result<Expression, Vec<Error>> {
+    with_shared_identifiers(|parser| parser.parse_expression(tokens))
+}
+
+fn with_shared_identifiers<T>(parse: impl FnOnce(&mut Parser) -> T) -> T {
+    let mut parser = Parser::with_first_identifier(Edition::default(), IDENTIFIERS.get());
+    let result = parse(&mut parser);
+    IDENTIFIERS.set(parser.identifier_counter());
+    result
 }
 
 macro_rules! check_m {
@@ -24,57 +109,172 @@ macro_rules! check_m {
 }
 
 impl Parser {
+    #[allow(dead_code)]
     pub(crate) fn new() -> Self {
+        Parser::with_edition(Edition::default())
+    }
+
+    pub fn with_edition(edition: Edition) -> Self {
+        Parser::with_first_identifier(edition, 0)
+    }
+
+    /// A parser numbering identifiers after `counter`, so that they do not
+    /// clash with ones of code parsed by another parser.
+    pub(crate) fn with_first_identifier(edition: Edition, counter: usize) -> Self {
         Parser {
             tokens: Vec::new(),
-            identifier_counter: 0,
+            edition,
+            identifier_counter: counter,
             current_index: 0,
             line: 0,
             position: 0,
+            end: 0,
+            errors: Vec::new(),
         }
     }
 
-    pub fn parse(&mut self, tokens: Vec<Token>) -> Result<Vec<Statement>, Error> {
-        self.tokens = tokens;
-        self.current_index = 0;
-        self.line = 0;
-        self.position = 0;
+    /// Last identifier id handed out so far.
+    pub(crate) fn identifier_counter(&self) -> usize {
+        self.identifier_counter
+    }
+
+    /// Parses a program for the resolver and the interpreter, with syntax
+    /// sugar lowered to the statements it stands for.
+    pub fn parse(&mut self, tokens: Vec<Token>) -> Result<Vec<Statement>, Vec<Error>> {
+        self.parse_syntax(tokens).map(lower::program)
+    }
+
+    /// Parses a program keeping every construct as it was written, e.g.
+    /// for printing it. Parsing goes on after an error from the next
+    /// statement, a failed parse returns every error found in the order
+    /// of the source.
+    pub fn parse_syntax(&mut self, tokens: Vec<Token>) -> Result<Vec<Statement>, Vec<Error>> {
+        match self.parse_recovering(tokens) {
+            (program, errors) if errors.is_empty() => Ok(program),
+            (_, errors) => Err(errors),
+        }
+    }
+
+    /// Parses a program like [`parse_syntax`](Self::parse_syntax), also
+    /// returning the part of it which did parse if there are errors. Every
+    /// statement which failed is a `Statement::Error` in its place.
+    pub fn parse_recovering(&mut self, tokens: Vec<Token>) -> (Vec<Statement>, Vec<Error>) {
+        self.start(tokens);
         let mut program = Vec::new();
-        let mut failed = None;
 
         while !self.is_at_end() {
+            let start = self.current_token().cloned().expect("statement token");
             match self.declaration() {
                 Ok(statement) => {
                     program.push(statement);
                 }
                 Err(error) => {
-                    println!("{:#?}", error);
-                    if failed.is_none() {
-                        failed = Some(error);
-                    }
+                    self.errors.push(error);
                     self.synchronize();
+                    let end = self.tokens[..self.current_index]
+                        .last()
+                        .map_or(start.offset, |last| last.offset + last.lexeme.len());
+                    program.push(Statement::Error {
+                        debug_info: DebugInfo::from(start),
+                        end,
+                    });
                 }
             }
         }
 
-        if let Some(error) = failed {
-            Err(error)
-        } else {
-            Ok(program)
+        (program, std::mem::take(&mut self.errors))
+    }
+
+    /// Parses `tokens` as a single expression, which has to end the input.
+    pub fn parse_expression(&mut self, tokens: Vec<Token>) -> Result<Expression, Vec<Error>> {
+        self.start(tokens);
+        let expression = self
+            .expression()
+            .and_then(|expression| match self.is_at_end() {
+                true => Ok(expression),
+                false => Err(self.error(message!(
+                    ExpectedToken,
+                    expected = TokenType::Eof,
+                    found = self.found()
+                ))),
+            });
+
+        let mut errors = std::mem::take(&mut self.errors);
+        match expression {
+            Ok(expression) if errors.is_empty() => Ok(expression),
+            Ok(_) => Err(errors),
+            Err(error) => {
+                errors.push(error);
+                Err(errors)
+            }
         }
     }
 
+    fn start(&mut self, tokens: Vec<Token>) {
+        // errors on the first token are reported at it, like after `advance`
+        let span = tokens.first().map(Token::span).unwrap_or_default();
+        (self.line, self.position, self.end) = (span.line, span.position, span.end);
+        self.tokens = tokens;
+        self.current_index = 0;
+    }
+
     fn current_token(&self) -> Option<&Token> {
         self.tokens.get(self.current_index)
     }
 
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.current_index + 1)
+    }
+
+    /// Returns the contextual keyword spelled by the current token, if the
+    /// edition recognizes it. Callers still have to check that the keyword is
+    /// in a position where it is meaningful.
+    fn contextual_keyword(&self) -> Option<ContextualKeyword> {
+        match self.current_token() {
+            Some(Token {
+                token_type: TokenType::Identifier(name),
+                ..
+            }) => ContextualKeyword::from_name(name, self.edition),
+            _ => None,
+        }
+    }
+
+    /// Whether the next token can start an expression. A contextual keyword
+    /// starting a statement is taken as the keyword when it is followed by
+    /// such a token, also one which could continue an expression, so that
+    /// `throw (x);` and `match -x {` are statements rather than a call of
+    /// or a subtraction from a variable named like the keyword.
+    fn peek_starts_expression(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(Token {
+                token_type: TokenType::Identifier(_)
+                    | TokenType::Number(_)
+                    | TokenType::String(_)
+                    | TokenType::Interpolation(_)
+                    | TokenType::True
+                    | TokenType::False
+                    | TokenType::Nil
+                    | TokenType::This
+                    | TokenType::Super
+                    | TokenType::Bang
+                    | TokenType::Minus
+                    | TokenType::LeftParen
+                    | TokenType::LeftBracket
+                    | TokenType::LeftBrace,
+                ..
+            })
+        )
+    }
+
     fn advance(&mut self) -> Result<(), Error> {
         if self.check(&TokenType::Eof) {
-            return Err(self.error("Tried to advance after Eof"));
+            return Err(self.error(message!(UnexpectedEnd)));
         }
         self.current_index += 1;
         let current = self.current_token().unwrap();
-        (self.line, self.position) = (current.line, current.position);
+        let span = current.span();
+        (self.line, self.position, self.end) = (span.line, span.position, span.end);
         Ok(())
     }
 
@@ -101,11 +301,7 @@ impl Parser {
             self.advance()?;
             Ok(())
         } else {
-            Err(self.error(format!(
-                "Expected {:?}, found: {:?}",
-                t,
-                self.current_token()
-            )))
+            Err(self.error(message!(ExpectedToken, expected = t, found = self.found())))
         }
     }
 
@@ -119,36 +315,134 @@ impl Parser {
                 token_type: TokenType::Fun,
                 ..
             }) => self.function_declaration(),
+            Some(Token {
+                token_type: TokenType::Class,
+                ..
+            }) => self.class_declaration(),
+            _ if self.contextual_keyword() == Some(ContextualKeyword::Const)
+                && matches!(
+                    self.peek(),
+                    Some(Token {
+                        token_type: TokenType::Identifier(_),
+                        ..
+                    })
+                ) =>
+            {
+                self.variable_declaration()
+            }
+            _ if self.contextual_keyword() == Some(ContextualKeyword::Import)
+                && matches!(
+                    self.peek(),
+                    Some(Token {
+                        token_type: TokenType::String(_) | TokenType::Identifier(_),
+                        ..
+                    })
+                ) =>
+            {
+                self.import_declaration()
+            }
             _ => self.statement(),
         }
     }
 
+    fn class_declaration(&mut self) -> Result<Statement, Error> {
+        self.consume(TokenType::Class)?;
+
+        let name = self
+            .identifier()
+            .ok_or_else(|| self.error(message!(ExpectedClassName)))?;
+
+        let superclass = if self.check(&TokenType::Less) {
+            self.consume(TokenType::Less)?;
+            let superclass = self
+                .identifier()
+                .ok_or_else(|| self.error(message!(ExpectedSuperclassName)))?;
+            Some(superclass)
+        } else {
+            None
+        };
+
+        self.consume(TokenType::LeftBrace)?;
+
+        let mut methods = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function(true)?);
+        }
+
+        self.consume(TokenType::RightBrace)?;
+
+        Ok(Statement::Class {
+            name,
+            superclass,
+            methods,
+        })
+    }
+
     fn function_declaration(&mut self) -> Result<Statement, Error> {
         self.consume(TokenType::Fun)?;
 
+        let FunctionDeclaration {
+            name,
+            args,
+            body,
+            variadic,
+            ..
+        } = self.function(false)?;
+
+        Ok(Statement::Function {
+            name,
+            args,
+            body,
+            variadic,
+        })
+    }
+
+    /// Parses the name, parameters and body of a function or method,
+    /// methods may leave out the parameter list to declare a getter.
+    /// The last parameter may be written as `...rest`.
+    fn function(&mut self, allow_getter: bool) -> Result<FunctionDeclaration, Error> {
         let name = self
             .identifier()
-            .ok_or_else(|| self.error("Expected function identifier."))?;
+            .ok_or_else(|| self.error(message!(ExpectedFunctionName)))?;
+
+        if allow_getter && self.check(&TokenType::LeftBrace) {
+            let body = self.block_statement()?;
+            return Ok(FunctionDeclaration {
+                name,
+                args: Vec::new(),
+                body,
+                getter: true,
+                variadic: false,
+            });
+        }
 
         self.consume(TokenType::LeftParen)?;
 
         let mut args = Vec::new();
+        let mut variadic = false;
 
         if !self.check(&TokenType::RightParen) {
-            let identifier = self
-                .identifier()
-                .ok_or_else(|| self.error("Expected argument Identifier"))?;
-
-            args.push(identifier);
-
-            while self.check(&TokenType::Comma) {
-                self.consume(TokenType::Comma)?;
+            loop {
+                if args.len() == MAX_ARGUMENTS {
+                    let error = self.error(message!(TooManyParameters, max = MAX_ARGUMENTS));
+                    self.errors.push(error);
+                }
+                if self.check(&TokenType::DotDotDot) {
+                    self.consume(TokenType::DotDotDot)?;
+                    variadic = true;
+                }
 
                 let identifier = self
                     .identifier()
-                    .ok_or_else(|| self.error("Expected argument Identifier"))?;
+                    .ok_or_else(|| self.error(message!(ExpectedParameterName)))?;
 
                 args.push(identifier);
+
+                // nothing may follow the rest parameter
+                if variadic || !self.check(&TokenType::Comma) {
+                    break;
+                }
+                self.consume(TokenType::Comma)?;
             }
         }
 
@@ -158,22 +452,39 @@ impl Parser {
         //  czyt NativeFun::call
         let body = self.block_statement()?;
 
-        Ok(Statement::Function { name, args, body })
+        Ok(FunctionDeclaration {
+            name,
+            args,
+            body,
+            getter: false,
+            variadic,
+        })
     }
 
+    /// Parses `var name = value;` or `const name = value;`, only
+    /// variables may leave out the initializer.
     fn variable_declaration(&mut self) -> Result<Statement, Error> {
-        self.consume(TokenType::Var)?;
+        let constant = !self.check(&TokenType::Var);
+        // either `var` or the contextual `const`
+        self.advance()?;
 
         let identifier = self
             .identifier()
-            .ok_or_else(|| self.error("Expected varaible Identifier"))?;
+            .ok_or_else(|| self.error(message!(ExpectedVariableName)))?;
 
         // self.advance()?;
 
         let mut initializer = None;
         if self.check(&TokenType::Equal) {
             self.advance()?;
-            initializer = Some(self.expression()?);
+            // `var a = 1, b = 2;` does not declare `b`, so it is an error
+            // rather than a sequence
+            initializer = Some(self.assignment()?);
+        } else if constant {
+            return Err(self.error(message!(
+                ExpectedConstantInitializer,
+                name = identifier.name
+            )));
         }
         // dbg!(self.current_token());
         self.consume(TokenType::Semicolon)?;
@@ -181,6 +492,50 @@ impl Parser {
         Ok(Statement::Variable {
             name: identifier,
             initializer,
+            constant,
+        })
+    }
+
+    /// Parses `import "path";` or `import a, b from "path";`.
+    fn import_declaration(&mut self) -> Result<Statement, Error> {
+        let keyword = self.current_token().cloned().expect("import token");
+        self.advance()?;
+
+        let mut names = Vec::new();
+        if !check_m!(self, TokenType::String(_)) {
+            loop {
+                let name = self
+                    .identifier()
+                    .ok_or_else(|| self.error(message!(ExpectedImportName)))?;
+                names.push(name);
+
+                if !self.check(&TokenType::Comma) {
+                    break;
+                }
+                self.consume(TokenType::Comma)?;
+            }
+
+            if self.contextual_keyword() != Some(ContextualKeyword::From) {
+                return Err(self.error(message!(ExpectedFrom)));
+            }
+            self.advance()?;
+        }
+
+        let path = match self.current_token() {
+            Some(Token {
+                token_type: TokenType::String(path),
+                ..
+            }) => path.clone(),
+            _ => return Err(self.error(message!(ExpectedModulePath))),
+        };
+        self.advance()?;
+        self.consume(TokenType::Semicolon)
+            .map_err(|_| self.error(message!(ExpectedSemicolonAfterValue)))?;
+
+        Ok(Statement::Import {
+            path,
+            names,
+            debug_info: DebugInfo::from(keyword),
         })
     }
 
@@ -209,6 +564,40 @@ impl Parser {
                 token_type: T::Return,
                 ..
             }) => self.return_statement(),
+            _ if self.contextual_keyword() == Some(ContextualKeyword::Match)
+                && self.peek_starts_expression() =>
+            {
+                self.match_statement()
+            }
+            _ if self.contextual_keyword() == Some(ContextualKeyword::Throw)
+                && self.peek_starts_expression() =>
+            {
+                self.throw_statement()
+            }
+            _ if self.contextual_keyword() == Some(ContextualKeyword::Try)
+                && matches!(
+                    self.peek(),
+                    Some(Token {
+                        token_type: T::LeftBrace,
+                        ..
+                    })
+                ) =>
+            {
+                self.try_statement()
+            }
+            _ if matches!(
+                self.contextual_keyword(),
+                Some(ContextualKeyword::Break | ContextualKeyword::Continue)
+            ) && matches!(
+                self.peek(),
+                Some(Token {
+                    token_type: T::Semicolon,
+                    ..
+                })
+            ) =>
+            {
+                self.break_statement()
+            }
             _ => self.expression_statement(),
         }
     }
@@ -216,15 +605,15 @@ impl Parser {
     fn if_statement(&mut self) -> Result<Statement, Error> {
         self.consume(TokenType::If)?;
         let condition = self.expression()?;
-        if !self.check(&TokenType::LeftBrace) {
-            return Err(self.error("Expected the beginning of a block after an if ()."));
-        }
-
-        let then_branch = self.block_statement()?;
+        let parenthesized = matches!(condition, Expression::Grouping(_));
+        let then_branch = self.body("if", parenthesized)?;
 
         let else_branch = if self.check(&TokenType::Else) {
             self.consume(TokenType::Else)?;
-            Some(self.block_statement()?)
+            match parenthesized {
+                true => Some(self.body("if", true)?),
+                false => Some(self.block_statement()?),
+            }
         } else {
             None
         };
@@ -239,76 +628,222 @@ impl Parser {
     fn while_statement(&mut self) -> Result<Statement, Error> {
         self.consume(TokenType::While)?;
         let condition = self.expression()?;
-        if !self.check(&TokenType::LeftBrace) {
-            return Err(self.error("Expected the beginning of a block after an while ()."));
-        }
-
-        let body = self.block_statement()?;
+        let parenthesized = matches!(condition, Expression::Grouping(_));
+        let body = self.body("while", parenthesized)?;
 
-        Ok(Statement::While { condition, body })
+        Ok(Statement::While {
+            condition,
+            body,
+            increment: None,
+        })
     }
 
     fn for_statement(&mut self) -> Result<Statement, Error> {
+        let keyword = self.current_token().cloned().expect("for keyword");
         self.consume(TokenType::For)?;
         self.consume(TokenType::LeftParen)?;
 
-        let initialization = match self.current_token() {
+        // `in` is not reserved, but cannot follow an identifier in any other statement
+        if let (
+            Some(Token {
+                token_type: TokenType::Identifier(_),
+                ..
+            }),
+            Some(Token {
+                token_type: TokenType::Identifier(keyword),
+                ..
+            }),
+        ) = (self.current_token(), self.peek())
+        {
+            if &**keyword == "in" {
+                return self.for_in_statement();
+            }
+        }
+
+        let initializer = match self.current_token() {
             Some(Token {
                 token_type: TokenType::Semicolon,
                 ..
             }) => {
                 self.consume(TokenType::Semicolon)?;
-                Statement::Nop
+                None
             }
             Some(Token {
                 token_type: TokenType::Var,
                 ..
-            }) => self.variable_declaration()?,
-            _ => self.expression_statement()?,
+            }) => Some(self.variable_declaration()?),
+            _ => Some(self.expression_statement()?),
         };
 
         let condition = if !self.check(&TokenType::Semicolon) {
-            self.expression()?
+            Some(self.expression()?)
         } else {
-            Expression::Literal(Box::new(Literal {
-                value: LiteralValue::True(DebugInfo {
-                    lexeme: "GENERATED_VALUE".to_owned(),
-                    position: self.position,
-                    line: self.line,
-                }),
-            }))
+            None
         };
 
         self.consume(TokenType::Semicolon)?;
 
-        let expression = if !self.check(&TokenType::RightParen) {
-            Statement::Expression(self.expression()?)
+        let increment = if !self.check(&TokenType::RightParen) {
+            Some(self.expression()?)
         } else {
-            Statement::Nop
+            None
         };
 
         self.consume(TokenType::RightParen)?;
+        let body = self.body("for", true)?;
+
+        Ok(Statement::For {
+            initializer: initializer.map(Box::new),
+            condition,
+            increment,
+            body,
+            debug_info: DebugInfo::from(keyword),
+        })
+    }
+
+    /// Parses the rest of `for (variable in iterable) { ... }`.
+    fn for_in_statement(&mut self) -> Result<Statement, Error> {
+        let variable = self
+            .identifier()
+            .ok_or_else(|| self.error(message!(ExpectedVariableName)))?;
+        let keyword = self.current_token().cloned().expect("in keyword");
+        self.advance()?;
+        let iterable = self.expression()?;
+        self.consume(TokenType::RightParen)?;
+        let body = self.body("for", true)?;
+
+        Ok(Statement::ForIn {
+            variable,
+            iterable,
+            body,
+            debug_info: DebugInfo::from(keyword),
+        })
+    }
+
+    /// Parses `match value { 1, 2 => { ... } _ => { ... } }`.
+    fn match_statement(&mut self) -> Result<Statement, Error> {
+        // the contextual `match` keyword
+        self.advance()?;
+        let value = self.expression()?;
+        self.consume(TokenType::LeftBrace)
+            .map_err(|_| self.error(message!(ExpectedMatchArms)))?;
+
+        let mut arms = Vec::new();
+        let mut default = None;
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            if default.is_some() {
+                return Err(self.error(message!(DefaultArmNotLast)));
+            }
+
+            if matches!(self.current_token(), Some(Token { token_type: TokenType::Identifier(name), .. }) if &**name == "_")
+            {
+                self.advance()?;
+                self.consume(TokenType::FatArrow)?;
+                default = Some(self.block_statement()?);
+                continue;
+            }
+
+            let mut patterns = vec![self.pattern()?];
+            while self.check(&TokenType::Comma) {
+                self.consume(TokenType::Comma)?;
+                patterns.push(self.pattern()?);
+            }
+            self.consume(TokenType::FatArrow)?;
+            let body = self.block_statement()?;
+            arms.push(MatchArm { patterns, body });
+        }
+        self.consume(TokenType::RightBrace)
+            .map_err(|_| self.error(message!(ExpectedBlockEnd)))?;
+
+        Ok(Statement::Match {
+            value,
+            arms,
+            default,
+        })
+    }
+
+    /// A literal, numbers may be negated.
+    fn pattern(&mut self) -> Result<LiteralValue, Error> {
+        let negative = self.check(&TokenType::Minus);
+        if negative {
+            self.advance()?;
+        }
+
+        let token = self.current_token().cloned().expect("pattern token");
+        match token.token_type {
+            TokenType::Number(n) if negative => {
+                self.advance()?;
+                Ok(LiteralValue::Number(-n, DebugInfo::from(token)))
+            }
+            TokenType::False
+            | TokenType::True
+            | TokenType::Nil
+            | TokenType::Number(_)
+            | TokenType::String(_)
+                if !negative =>
+            {
+                self.advance()?;
+                LiteralValue::new(token)
+            }
+            _ => Err(self.error(message!(ExpectedPattern))),
+        }
+    }
+
+    /// Parses `try { ... } catch error { ... } finally { ... }`.
+    fn try_statement(&mut self) -> Result<Statement, Error> {
+        // the contextual `try` keyword
+        self.advance()?;
+        let body = self.block_statement()?;
+
+        let mut catch = None;
+        if self.contextual_keyword() == Some(ContextualKeyword::Catch) {
+            self.advance()?;
+            let variable = self.identifier();
+            catch = Some(Catch {
+                variable,
+                body: self.block_statement()?,
+            });
+        }
+
+        let mut finally = None;
+        if self.contextual_keyword() == Some(ContextualKeyword::Finally) {
+            self.advance()?;
+            finally = Some(self.block_statement()?);
+        }
 
-        if !self.check(&TokenType::LeftBrace) {
-            return Err(self.error("Expected the beginning of a block after an for (;;)."));
+        if catch.is_none() && finally.is_none() {
+            return Err(self.error(message!(ExpectedCatchOrFinally)));
         }
 
-        let mut body = self.block_statement()?;
-        body.statements.push(expression);
+        Ok(Statement::Try {
+            body,
+            catch,
+            finally,
+        })
+    }
+
+    fn throw_statement(&mut self) -> Result<Statement, Error> {
+        let keyword = self.current_token().cloned().expect("throw token");
+        self.advance()?;
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon)
+            .map_err(|_| self.error(message!(ExpectedSemicolonAfterValue)))?;
 
-        Ok(Statement::Block(Block {
-            statements: vec![initialization, Statement::While { condition, body }],
-        }))
+        Ok(Statement::Throw {
+            value,
+            debug_info: DebugInfo::from(keyword),
+        })
     }
 
     fn expression_statement(&mut self) -> Result<Statement, Error> {
         let expr = self.expression()?;
         self.consume(TokenType::Semicolon)
-            .or_else(|_| Err(self.error("Expected ';' after expression")))?;
+            .map_err(|_| self.error(message!(ExpectedSemicolonAfterExpression)))?;
         Ok(Statement::Expression(expr))
     }
 
     fn return_statement(&mut self) -> Result<Statement, Error> {
+        let token = self.current_token().cloned().expect("return token");
         self.consume(TokenType::Return).expect("return token");
 
         let expr = if !self.check(&TokenType::Semicolon) {
@@ -318,12 +853,48 @@ impl Parser {
         };
 
         self.consume(TokenType::Semicolon)
-            .or_else(|_| Err(self.error("Expected ';' at the end of return statement")))?;
+            .map_err(|_| self.error(message!(ExpectedSemicolonAfterReturn)))?;
 
-        Ok(Statement::Return { value: expr })
+        Ok(Statement::Return {
+            value: expr,
+            debug_info: DebugInfo::from(token),
+        })
+    }
+
+    /// Parses `break;` or `continue;`.
+    fn break_statement(&mut self) -> Result<Statement, Error> {
+        let keyword = self.contextual_keyword().expect("loop control keyword");
+        let token = self.current_token().cloned().expect("break token");
+        self.advance()?;
+        self.consume(TokenType::Semicolon)?;
+
+        let debug_info = DebugInfo::from(token);
+        Ok(match keyword {
+            ContextualKeyword::Break => Statement::Break { debug_info },
+            ContextualKeyword::Continue => Statement::Continue { debug_info },
+            _ => unreachable!("not a loop control keyword"),
+        })
+    }
+
+    /// Parses the body of an `if`, `while` or `for` statement. A body is a
+    /// block, but after a condition in parentheses it may be any statement,
+    /// as in classic Lox.
+    fn body(&mut self, statement: &str, parenthesized: bool) -> Result<Block, Error> {
+        if self.check(&TokenType::LeftBrace) {
+            return self.block_statement();
+        }
+        if !parenthesized {
+            return Err(self.error(message!(ExpectedBlock, statement = statement)));
+        }
+        let start = self.current_token().cloned().expect("statement token");
+        Ok(Block {
+            statements: vec![self.statement()?],
+            debug_info: DebugInfo::from(start),
+        })
     }
 
     fn block_statement(&mut self) -> Result<Block, Error> {
+        let brace = self.current_token().cloned().expect("block token");
         self.consume(TokenType::LeftBrace)?;
 
         let mut statements = Vec::new();
@@ -332,153 +903,114 @@ impl Parser {
             statements.push(self.declaration()?);
         }
 
-        self.consume(TokenType::RightBrace).or_else(|_| {
-            Err(Error::ParsingError {
-                line: self.line,
-                position: self.position,
-                message: "Expected '}' after block".to_string(),
-            })
-        })?;
+        self.consume(TokenType::RightBrace)
+            .map_err(|_| self.error(message!(ExpectedBlockEnd)))?;
 
-        Ok(Block { statements })
+        Ok(Block {
+            statements,
+            debug_info: DebugInfo::from(brace),
+        })
     }
 
     fn print_statement(&mut self) -> Result<Statement, Error> {
         self.consume(TokenType::Print)?;
         let expr = self.expression()?;
-        self.consume(TokenType::Semicolon).or_else(|_| {
-            Err(Error::ParsingError {
-                line: self.line,
-                position: self.position,
-                message: "Expected ';' after value".to_string(),
-            })
-        })?;
+        self.consume(TokenType::Semicolon)
+            .map_err(|_| self.error(message!(ExpectedSemicolonAfterValue)))?;
         Ok(Statement::Print(expr))
     }
 
+    /// Parses assignments separated by the comma operator. Lists of
+    /// arguments, elements and entries are separated by commas themselves,
+    /// their items are parsed with [`assignment`](Self::assignment).
     fn expression(&mut self) -> Result<Expression, Error> {
-        self.assignment()
+        let mut expr = self.assignment()?;
+
+        while self.check(&TokenType::Comma) {
+            self.advance()?;
+            let right = self.assignment()?;
+            expr = Expression::from(Sequence { left: expr, right });
+        }
+
+        Ok(expr)
     }
 
     fn assignment(&mut self) -> Result<Expression, Error> {
-        let expr = self.or()?;
+        let expr = self.binary(None)?;
 
         if self.check(&TokenType::Equal) {
             self.advance()?;
             let value = self.assignment()?;
             match expr {
-                Expression::Identifier(target) => {
+                Expression::Identifier(target) if target.name != "this" => {
                     return Ok(Expression::from(Assignment {
                         target: *target,
                         value,
                     }));
                 }
+                Expression::Get(get) if !get.optional => {
+                    let Get {
+                        object,
+                        name,
+                        debug_info,
+                        ..
+                    } = *get;
+                    return Ok(Expression::from(Set {
+                        object,
+                        name,
+                        value,
+                        debug_info,
+                    }));
+                }
+                Expression::Index(index) => {
+                    let Index {
+                        object,
+                        index,
+                        debug_info,
+                    } = *index;
+                    return Ok(Expression::from(IndexSet {
+                        object,
+                        index,
+                        value,
+                        debug_info,
+                    }));
+                }
                 _ => {
-                    todo!("Assingment to non-identifier is not yet suported.")
+                    return Err(self.error(message!(InvalidAssignmentTarget)));
                 }
             }
         }
         Ok(expr)
     }
 
-    fn or(&mut self) -> Result<Expression, Error> {
-        let mut expr = self.and()?;
-
-        while let Some(operator) = self.match_token_type(&[TokenType::Or]) {
-            self.advance()?;
-            let right = self.and()?;
-            expr = Expression::from(Logical {
-                left: expr,
-                operator: LogicalOperator::new(operator)?,
-                right,
-            });
-        }
-
-        Ok(expr)
-    }
-
-    fn and(&mut self) -> Result<Expression, Error> {
-        let mut expr = self.equality()?;
-
-        while let Some(operator) = self.match_token_type(&[TokenType::And]) {
-            self.advance()?;
-            let right = self.equality()?;
-            expr = Expression::from(Logical {
-                left: expr,
-                operator: LogicalOperator::new(operator)?,
-                right,
-            });
-        }
-
-        Ok(expr)
-    }
-
-    fn equality(&mut self) -> Result<Expression, Error> {
-        let mut left = self.comparison()?;
-
-        while let Some(operator) =
-            self.match_token_type(&[TokenType::BangEqual, TokenType::EqualEqual])
-        {
-            self.advance()?;
-            let right = self.comparison()?;
-            left = Expression::from(Binary {
-                left,
-                operator: BinaryOperator::new(operator)?,
-                right,
-            });
-        }
-
-        Ok(left)
-    }
-
-    fn comparison(&mut self) -> Result<Expression, Error> {
-        let mut left = self.term()?;
-
-        while let Some(operator) = self.match_token_type(&[
-            TokenType::Greater,
-            TokenType::GreaterEqual,
-            TokenType::Less,
-            TokenType::LessEqual,
-        ]) {
-            self.advance()?;
-            let right = self.term()?;
-            left = Expression::from(Binary {
-                left,
-                operator: BinaryOperator::new(operator)?,
-                right,
-            });
-        }
-
-        Ok(left)
-    }
-
-    fn term(&mut self) -> Result<Expression, Error> {
-        let mut left = self.factor()?;
-
-        while let Some(operator) = self.match_token_type(&[TokenType::Minus, TokenType::Plus]) {
-            self.advance()?;
-            let right = self.factor()?;
-            left = Expression::from(Binary {
-                left,
-                operator: BinaryOperator::new(operator)?,
-                right,
-            });
-        }
-
-        Ok(left)
-    }
-
-    fn factor(&mut self) -> Result<Expression, Error> {
+    /// Parses a chain of binary operators binding tighter than `above`,
+    /// `None` taking all of them, see [`Precedence::of`].
+    fn binary(&mut self, above: Option<Precedence>) -> Result<Expression, Error> {
         let mut left = self.unary()?;
 
-        while let Some(operator) = self.match_token_type(&[TokenType::Slash, TokenType::Star]) {
+        while let Some((operator, precedence)) = self.current_token().and_then(|token| {
+            Precedence::of(&token.token_type)
+                .filter(|precedence| Some(*precedence) > above)
+                .map(|precedence| (token.clone(), precedence))
+        }) {
             self.advance()?;
-            let right = self.unary()?;
-            left = Expression::from(Binary {
-                left,
-                operator: BinaryOperator::new(operator)?,
-                right,
-            });
+            // all operators are left associative, the right operand only
+            // takes the ones binding tighter
+            let right = self.binary(Some(precedence))?;
+            left = match precedence {
+                Precedence::Coalesce | Precedence::Or | Precedence::And => {
+                    Expression::from(Logical {
+                        left,
+                        operator: LogicalOperator::new(operator)?,
+                        right,
+                    })
+                }
+                _ => Expression::from(Binary {
+                    left,
+                    operator: BinaryOperator::new(operator)?,
+                    right,
+                }),
+            };
         }
 
         Ok(left)
@@ -500,22 +1032,72 @@ impl Parser {
     fn call(&mut self) -> Result<Expression, Error> {
         let mut calle = self.primary()?;
 
-        while self.check(&TokenType::LeftParen) {
-            let debug_info = DebugInfo {
-                line: self.line,
-                position: self.position,
-                lexeme: "(".to_owned(),
-            };
+        loop {
+            if self.check(&TokenType::Dot) || self.check(&TokenType::QuestionDot) {
+                let optional = self.check(&TokenType::QuestionDot);
+                self.advance()?;
+                let name = self
+                    .identifier()
+                    .ok_or_else(|| self.error(message!(ExpectedPropertyName)))?;
+                calle = Expression::from(Get {
+                    object: calle,
+                    name: name.name,
+                    debug_info: name.debug_info,
+                    optional,
+                });
+                continue;
+            }
+            if self.check(&TokenType::LeftBracket) {
+                let debug_info = DebugInfo::from(self.current_token().unwrap().clone());
+                self.consume(TokenType::LeftBracket)?;
+                let start = match self.check(&TokenType::Colon) {
+                    true => None,
+                    false => Some(self.expression()?),
+                };
+                calle = match (start, self.check(&TokenType::Colon)) {
+                    (Some(index), false) => Expression::from(Index {
+                        object: calle,
+                        index,
+                        debug_info,
+                    }),
+                    (start, _) => {
+                        self.consume(TokenType::Colon)?;
+                        let end = match self.check(&TokenType::RightBracket) {
+                            true => None,
+                            false => Some(self.expression()?),
+                        };
+                        Expression::from(Slice {
+                            object: calle,
+                            start,
+                            end,
+                            debug_info,
+                        })
+                    }
+                };
+                self.consume(TokenType::RightBracket)?;
+                continue;
+            }
+            if !self.check(&TokenType::LeftParen) {
+                break;
+            }
+
+            let debug_info = DebugInfo::from(self.current_token().unwrap().clone());
             self.consume(TokenType::LeftParen)?;
 
             let mut args = Vec::new();
 
             if !check_m!(self, TokenType::RightParen) {
-                args.push(self.expression()?);
+                loop {
+                    if args.len() == MAX_ARGUMENTS {
+                        let error = self.error(message!(TooManyArguments, max = MAX_ARGUMENTS));
+                        self.errors.push(error);
+                    }
+                    args.push(self.assignment()?);
 
-                while self.check(&TokenType::Comma) {
+                    if !self.check(&TokenType::Comma) {
+                        break;
+                    }
                     self.consume(TokenType::Comma)?;
-                    args.push(self.expression()?);
                 }
             }
 
@@ -531,10 +1113,29 @@ impl Parser {
         Ok(calle)
     }
 
+    /// Parses the tokens of an expression embedded in a string literal,
+    /// they end with `Eof` in place of the closing brace.
+    fn embedded_expression(&mut self, tokens: Vec<Token>) -> Result<Expression, Error> {
+        let tokens = std::mem::replace(&mut self.tokens, tokens);
+        let index = std::mem::replace(&mut self.current_index, 0);
+
+        let result = self.expression().and_then(|expression| {
+            if self.is_at_end() {
+                Ok(expression)
+            } else {
+                Err(self.error(message!(ExpectedInterpolationEnd, found = self.found())))
+            }
+        });
+
+        self.tokens = tokens;
+        self.current_index = index;
+        result
+    }
+
     fn primary(&mut self) -> Result<Expression, Error> {
         if let Some(pat) = self.current_token() {
             let token = pat.clone();
-            return match token.token_type {
+            match token.token_type.clone() {
                 TokenType::False
                 | TokenType::True
                 | TokenType::Nil
@@ -548,52 +1149,113 @@ impl Parser {
                 TokenType::Identifier(name) => {
                     self.advance()?;
                     Ok(Expression::from(self.create_identifier(
-                        name.clone(),
-                        DebugInfo {
-                            line: token.line,
-                            position: token.position,
-                            lexeme: token.lexeme,
-                        },
+                        name.to_string(),
+                        DebugInfo::from(token),
+                    )))
+                }
+                TokenType::This => {
+                    self.advance()?;
+                    Ok(Expression::from(self.create_identifier(
+                        "this".to_owned(),
+                        DebugInfo::from(token),
                     )))
                 }
+                TokenType::Super => {
+                    self.advance()?;
+                    let debug_info = DebugInfo::from(token);
+                    self.consume(TokenType::Dot)
+                        .map_err(|_| self.error(message!(ExpectedDotAfterSuper)))?;
+                    let method = self
+                        .identifier()
+                        .ok_or_else(|| self.error(message!(ExpectedSuperclassMethodName)))?;
+                    Ok(Expression::from(Super {
+                        keyword: self.create_identifier("super".to_owned(), debug_info.clone()),
+                        this: self.create_identifier("this".to_owned(), debug_info),
+                        method: method.name,
+                        debug_info: method.debug_info,
+                    }))
+                }
+                TokenType::Interpolation(parts) => {
+                    self.advance()?;
+                    let debug_info = DebugInfo::from(token);
+                    let parts = parts
+                        .into_iter()
+                        .map(|part| match part {
+                            StringPart::Text(text) => Ok(Expression::from(Literal {
+                                value: LiteralValue::String(text, debug_info.clone()),
+                            })),
+                            StringPart::Expression(tokens) => self.embedded_expression(tokens),
+                        })
+                        .collect::<Result<_, _>>()?;
+                    Ok(Expression::from(Interpolation { parts }))
+                }
+                TokenType::LeftBracket => {
+                    self.advance()?;
+                    let mut elements = Vec::new();
+                    // a trailing comma is allowed
+                    while !self.check(&TokenType::RightBracket) {
+                        elements.push(self.assignment()?);
+                        if !self.check(&TokenType::Comma) {
+                            break;
+                        }
+                        self.consume(TokenType::Comma)?;
+                    }
+                    self.consume(TokenType::RightBracket)?;
+                    Ok(Expression::from(List { elements }))
+                }
+                TokenType::LeftBrace => {
+                    self.advance()?;
+                    let mut entries = Vec::new();
+                    while !self.check(&TokenType::RightBrace) {
+                        let key = self.assignment()?;
+                        self.consume(TokenType::Colon)?;
+                        entries.push((key, self.assignment()?));
+                        if !self.check(&TokenType::Comma) {
+                            break;
+                        }
+                        self.consume(TokenType::Comma)?;
+                    }
+                    self.consume(TokenType::RightBrace)?;
+                    Ok(Expression::from(Map {
+                        entries,
+                        debug_info: DebugInfo::from(token),
+                    }))
+                }
                 TokenType::LeftParen => {
                     self.advance()?;
                     let e = self.expression()?;
                     self.consume(TokenType::RightParen)?;
                     Ok(Expression::from(Grouping { expression: e }))
                 }
-                token_type => {
-                    let message = format!(
-                        "Expected Literal, Identifier or start of expression, found: {:?}",
-                        token_type
-                    );
-                    Err(self.error(message))
-                }
-            };
+                _ => Err(self.error(message!(ExpectedExpression, found = self.found()))),
+            }
         } else {
-            Err(self.error("Expected Token"))
+            Err(self.error(message!(UnexpectedEnd)))
         }
     }
 
-    pub fn is_at_end(self: &Self) -> bool {
+    pub fn is_at_end(&self) -> bool {
         self.check(&TokenType::Eof)
     }
 
     pub fn synchronize(&mut self) {
-        while let Ok(_) = self.advance() {
-            if let Some(_) = self.match_token_type(&[
-                TokenType::Class,
-                TokenType::Fun,
-                TokenType::Var,
-                TokenType::For,
-                TokenType::If,
-                TokenType::While,
-                TokenType::Print,
-                TokenType::Return,
-            ]) {
+        while self.advance().is_ok() {
+            if self
+                .match_token_type(&[
+                    TokenType::Class,
+                    TokenType::Fun,
+                    TokenType::Var,
+                    TokenType::For,
+                    TokenType::If,
+                    TokenType::While,
+                    TokenType::Print,
+                    TokenType::Return,
+                ])
+                .is_some()
+            {
                 return;
             };
-            if let Ok(_) = self.consume(TokenType::Semicolon) {
+            if self.consume(TokenType::Semicolon).is_ok() {
                 return;
             }
         }
@@ -612,13 +1274,15 @@ impl Parser {
                 lexeme,
                 line,
                 position,
+                offset,
             }) => {
                 let identifier = self.create_identifier(
-                    name.clone(),
+                    name.to_string(),
                     DebugInfo {
                         line: *line,
                         position: *position,
-                        lexeme: lexeme.clone(),
+                        lexeme: lexeme.to_string(),
+                        offset: *offset,
                     },
                 );
                 self.advance().unwrap();
@@ -627,10 +1291,23 @@ impl Parser {
             _ => None,
         }
     }
-    fn error<S: Into<String>>(&self, message: S) -> Error {
+    /// Describes the current token for "found ..." parts of messages.
+    fn found(&self) -> String {
+        match self.current_token() {
+            Some(Token {
+                token_type: TokenType::Eof,
+                ..
+            })
+            | None => TokenType::Eof.to_string(),
+            Some(token) => format!("'{}'", token.lexeme),
+        }
+    }
+
+    fn error<S: Into<Message>>(&self, message: S) -> Error {
         Error::ParsingError {
             line: self.line,
             position: self.position,
+            end: self.end,
             message: message.into(),
         }
     }
@@ -649,9 +1326,9 @@ fn test_statements() {
         var a = 1;
     ";
 
-    let expr = scanner::scan_tokens(&expr.to_string());
-    let prnt = scanner::scan_tokens(&prnt.to_string());
-    let varb = scanner::scan_tokens(&varb.to_string());
+    let expr = scanner::scan_tokens(expr);
+    let prnt = scanner::scan_tokens(prnt);
+    let varb = scanner::scan_tokens(varb);
 
     let mut parser = Parser::new();
     let _expr = parser.parse(expr.unwrap()).unwrap();
@@ -665,9 +1342,10 @@ fn test_parser() {
         ($type:expr, $line:expr) => {
             Token {
                 token_type: $type,
-                lexeme: String::new(),
+                lexeme: "".into(),
                 line: $line,
                 position: 0,
+                offset: 0,
             }
         };
     }
@@ -707,8 +1385,7 @@ fn test_parser() {
 fn test_fun_stmt() {
     use crate::scanner::scan_tokens;
 
-    let tokens =
-        scan_tokens(&"fun funkcja(arg) {print arg;}".to_owned()).expect("expected valid string");
+    let tokens = scan_tokens("fun funkcja(arg) {print arg;}").expect("expected valid string");
 
     let fun = Parser::new()
         .parse(tokens)
@@ -718,10 +1395,11 @@ fn test_fun_stmt() {
         name: identifier,
         args,
         body,
-    }) = fun.get(0)
+        ..
+    }) = fun.first()
     {
         assert_eq!(identifier.name, "funkcja");
-        assert_eq!(args.get(0).unwrap().name, "arg");
+        assert_eq!(args.first().unwrap().name, "arg");
         match body.statements[..] {
             [Statement::Print(_)] => Ok(()),
             _ => Err(()),
@@ -734,13 +1412,13 @@ fn test_fun_stmt() {
 fn test_call() {
     use crate::scanner::scan_tokens;
 
-    let tokens = scan_tokens(&"funkcja(arg);".to_owned()).expect("expected valid string");
+    let tokens = scan_tokens("funkcja(arg);").expect("expected valid string");
 
     let call = Parser::new()
         .parse(tokens)
         .expect("expected valid tokens comprising valid function");
 
-    if let Some(Statement::Expression(expr)) = call.get(0) {
+    if let Some(Statement::Expression(expr)) = call.first() {
         match expr {
             Expression::Call(call) => match *call.to_owned() {
                 Call {
@@ -749,7 +1427,7 @@ fn test_call() {
                     args,
                 } => {
                     assert_eq!(identifier.name, "funkcja");
-                    if let Expression::Identifier(_) = args.get(0).unwrap() {
+                    if let Expression::Identifier(_) = args.first().unwrap() {
                         Ok(())
                     } else {
                         Err(())
@@ -762,3 +1440,146 @@ fn test_call() {
         .expect("expected valid call in expression stmt");
     }
 }
+
+#[test]
+fn test_operator_precedence() {
+    use crate::scanner::scan_tokens;
+    use crate::sexpr;
+
+    let print = |source: &str| {
+        let program = Parser::new().parse(scan_tokens(source).unwrap()).unwrap();
+        sexpr::program(&program)
+    };
+
+    assert_eq!(print("1 - 2 - 3 * 4 / 5;"), "(- (- 1 2) (/ (* 3 4) 5))\n");
+    assert_eq!(
+        print("a ?? b or c and d == e | f & g < h << i;"),
+        "(?? a (or b (and c (| (== d e) (& f (< g (<< h i)))))))\n"
+    );
+    assert_eq!(print("-a * !b ^ c;"), "(^ (* (- a) (! b)) c)\n");
+    assert_eq!(print("a = b = c or d;"), "(= a (= b (or c d)))\n");
+    assert_eq!(
+        print("a = 1, b = 2, f(a, [b, c], {d: e});"),
+        "(, (, (= a 1) (= b 2)) (call f a (list b c) (map (d e))))\n"
+    );
+    assert!(Parser::new()
+        .parse(scan_tokens("var a = 1, b = 2;").unwrap())
+        .is_err());
+}
+
+#[test]
+fn test_argument_limit() {
+    use crate::scanner::scan_tokens;
+
+    let list = |count: usize, name: &str| {
+        (0..count)
+            .map(|i| format!("{name}{i}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let parse = |source: String| Parser::new().parse(scan_tokens(&source).unwrap());
+
+    assert!(parse(format!(
+        "fun f({}) {{}} f({});",
+        list(255, "p"),
+        list(255, "a")
+    ))
+    .is_ok());
+
+    let source = format!(
+        "fun f({}) {{}}\nf({});\nprint (;",
+        list(257, "p"),
+        list(256, "a")
+    );
+    match parse(source).unwrap_err().as_slice() {
+        [Error::ParsingError {
+            line: 1,
+            message: parameters,
+            ..
+        }, Error::ParsingError {
+            line: 2,
+            message: arguments,
+            ..
+        }, Error::ParsingError { line: 3, .. }] => {
+            assert_eq!(parameters, "Can't have more than 255 parameters.");
+            assert_eq!(arguments, "Can't have more than 255 arguments.");
+        }
+        other => panic!("expected three errors, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_classic_bodies() {
+    use crate::scanner::scan_tokens;
+    use crate::sexpr;
+
+    let print = |source: &str| {
+        let program = Parser::new().parse(scan_tokens(source).unwrap()).unwrap();
+        sexpr::program(&program)
+    };
+
+    assert_eq!(
+        print("if (a) print 1; else if (b) print 2; else { print 3; }"),
+        print("if (a) { print 1; } else { if (b) { print 2; } else { print 3; } }")
+    );
+    assert_eq!(
+        print("while (a) a = a - 1;"),
+        print("while (a) { a = a - 1; }")
+    );
+
+    for source in ["if a print 1;", "while a - 1 print 1;", "if (a) var b;"] {
+        assert!(
+            Parser::new().parse(scan_tokens(source).unwrap()).is_err(),
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn test_partial_program() {
+    use crate::resolver;
+    use crate::scanner::scan_tokens;
+    use crate::sexpr;
+
+    let source = "print 1;\nvar = 2;\nprint 3;";
+    let (program, errors) = Parser::new().parse_recovering(scan_tokens(source).unwrap());
+    assert!(matches!(
+        errors.as_slice(),
+        [Error::ParsingError { line: 2, .. }]
+    ));
+    assert_eq!(sexpr::program(&program), "(print 1)\n(error)\n(print 3)\n");
+
+    match &program[1] {
+        Statement::Error { debug_info, end } => {
+            assert_eq!(&source[debug_info.offset..*end], "var = 2;")
+        }
+        statement => panic!("{statement:?}"),
+    }
+    assert!(matches!(
+        resolver::resolve(&program).unwrap_err(),
+        Error::ResolverError { line: 2, .. }
+    ));
+}
+
+#[test]
+fn test_stateless_parsing() {
+    use crate::interpreter::Interpreter;
+    use crate::resolver;
+    use crate::scanner::scan_tokens;
+    use crate::sexpr;
+
+    let mut interpreter = Interpreter::new();
+    for source in ["var a = 1;", "var b = a + 1;", "a = a + b;"] {
+        let program = parse(scan_tokens(source).unwrap()).unwrap();
+        let access_table = resolver::resolve(&program).unwrap();
+        interpreter.execute(&program, access_table).unwrap();
+    }
+
+    let expression = parse_expression(scan_tokens("a = 1, b + 2 * c").unwrap()).unwrap();
+    assert_eq!(
+        sexpr::program(&[Statement::Expression(expression)]),
+        "(, (= a 1) (+ b (* 2 c)))\n"
+    );
+    assert!(parse_expression(scan_tokens("1 + 2;").unwrap()).is_err());
+    assert!(parse_expression(scan_tokens("").unwrap()).is_err());
+}
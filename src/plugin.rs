@@ -0,0 +1,98 @@
+//! Bundles of natives provided by host programs.
+
+use crate::error::Error;
+use crate::interpreter::Interpreter;
+use crate::native_module::NativeModule;
+
+/// A reusable set of natives, installed with a single call:
+///
+/// ```
+/// # use rlox::error::Error;
+/// # use rlox::interpreter::Interpreter;
+/// # use rlox::lox_function::NativeContext;
+/// # use rlox::lox_value::LoxValue;
+/// # use rlox::native_module::NativeModule;
+/// # use rlox::plugin::LoxPlugin;
+/// # fn hello(_ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+/// #     Ok(LoxValue::Nil)
+/// # }
+/// struct Greetings;
+///
+/// impl LoxPlugin for Greetings {
+///     fn install(&self, interpreter: &mut Interpreter) -> Result<(), Error> {
+///         interpreter.install(NativeModule::global().function("hello", 0, hello))
+///     }
+/// }
+///
+/// # let mut interpreter = Interpreter::new();
+/// Greetings.install(&mut interpreter)?;
+/// # Ok::<(), Error>(())
+/// ```
+///
+/// Installing a native under a name that is already defined fails, so
+/// two plugins cannot silently replace each other's functions.
+pub trait LoxPlugin {
+    fn install(&self, interpreter: &mut Interpreter) -> Result<(), Error>;
+}
+
+impl LoxPlugin for NativeModule {
+    fn install(&self, interpreter: &mut Interpreter) -> Result<(), Error> {
+        interpreter.install(self.clone())
+    }
+}
+
+#[test]
+fn test_plugin() {
+    use crate::lox_function::NativeContext;
+    use crate::lox_value::LoxValue;
+
+    fn hello(_ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+        Ok(LoxValue::String("hello".to_owned()))
+    }
+
+    struct Greetings;
+
+    impl LoxPlugin for Greetings {
+        fn install(&self, interpreter: &mut Interpreter) -> Result<(), Error> {
+            interpreter.install(
+                NativeModule::global()
+                    .function("hello", 0, hello)
+                    .function("hi", 0, hello),
+            )
+        }
+    }
+
+    let mut interpreter = Interpreter::new();
+    Greetings.install(&mut interpreter).unwrap();
+    assert!(interpreter
+        .environment
+        .get_global(&"hi".to_string())
+        .is_some());
+
+    let message = |result: Result<(), Error>| match result {
+        Err(Error::RuntimeError { message, .. }) => message,
+        other => panic!("expected a runtime error, got {other:?}"),
+    };
+    assert_eq!(
+        message(Greetings.install(&mut interpreter)),
+        "Cannot install the native 'hello', the name is already defined."
+    );
+
+    // nothing is installed when one of the names is taken
+    let clashing = NativeModule::global()
+        .function("greet", 0, hello)
+        .function("len", 0, hello);
+    assert_eq!(
+        message(clashing.install(&mut interpreter)),
+        "Cannot install the native 'len', the name is already defined."
+    );
+    assert!(interpreter
+        .environment
+        .get_global(&"greet".to_string())
+        .is_none());
+    assert!(NativeModule::global()
+        .function("twice", 0, hello)
+        .function("twice", 0, hello)
+        .install(&mut interpreter)
+        .is_err());
+}
@@ -0,0 +1,218 @@
+//! Multi-line rendering of values for the REPL.
+//!
+//! Unlike `Display`, values are shown as by `repr`, nested values are
+//! laid out over several indented lines, nesting is cut off after a given
+//! depth and values that contain themselves are printed as `{...}`
+//! instead of recursing forever.
+
+use std::fmt::Write;
+
+use crate::lox_function::LoxFun;
+use crate::lox_map::LoxMap;
+use crate::lox_object::LoxObject;
+use crate::lox_value::LoxValue;
+use crate::shared::{Rc, RefCell};
+
+/// Nesting depth used by the REPL.
+pub const DEFAULT_DEPTH: usize = 3;
+
+const INDENT: &str = "    ";
+
+/// Renders `value`, expanding at most `depth` levels of nested values.
+pub fn value(value: &LoxValue, depth: usize) -> String {
+    let mut printer = Printer {
+        out: String::new(),
+        visiting: Vec::new(),
+    };
+    printer.value(value, depth, 0);
+    printer.out
+}
+
+struct Printer {
+    out: String,
+    /// values currently being printed, used to detect cycles
+    visiting: Vec<*const ()>,
+}
+
+impl Printer {
+    fn value(&mut self, value: &LoxValue, depth: usize, indent: usize) {
+        match value {
+            LoxValue::LoxFun(fun) => self.function(fun, depth, indent),
+            LoxValue::List(list) => self.list(list, depth, indent),
+            LoxValue::Map(map) => self.map(map, depth, indent),
+            LoxValue::Object(object) => self.object(object, depth, indent),
+            value => self.out.push_str(&value.repr()),
+        }
+    }
+
+    /// Functions are shown together with the variables they captured.
+    fn function(&mut self, fun: &Rc<LoxFun>, depth: usize, indent: usize) {
+        let _ = write!(self.out, "{fun}");
+
+        let captured: Vec<_> = fun
+            .captured_scope
+            .bindings()
+            .into_iter()
+            .map(|(name, value)| (format!("{name} = "), value))
+            .collect();
+        if captured.is_empty() {
+            return;
+        }
+
+        self.out.push(' ');
+        self.entries(Rc::as_ptr(fun) as *const (), captured, "{}", depth, indent);
+    }
+
+    fn map(&mut self, map: &Rc<RefCell<LoxMap>>, depth: usize, indent: usize) {
+        let entries: Vec<_> = map
+            .borrow()
+            .iter()
+            .map(|(key, value)| {
+                let key = LoxValue::from(key.clone()).repr();
+                (format!("{key}: "), value.clone())
+            })
+            .collect();
+        if entries.is_empty() {
+            self.out.push_str("{}");
+            return;
+        }
+
+        self.entries(Rc::as_ptr(map) as *const (), entries, "{}", depth, indent);
+    }
+
+    /// Instances are shown together with their fields.
+    fn object(&mut self, object: &Rc<RefCell<LoxObject>>, depth: usize, indent: usize) {
+        let _ = write!(self.out, "{}", object.borrow());
+
+        let fields: Vec<_> = object
+            .borrow()
+            .fields()
+            .into_iter()
+            .map(|(name, value)| (format!("{name} = "), value))
+            .collect();
+        if fields.is_empty() {
+            return;
+        }
+
+        self.out.push(' ');
+        self.entries(Rc::as_ptr(object) as *const (), fields, "{}", depth, indent);
+    }
+
+    fn list(&mut self, list: &Rc<RefCell<Vec<LoxValue>>>, depth: usize, indent: usize) {
+        let entries: Vec<_> = list
+            .borrow()
+            .iter()
+            .map(|value| (String::new(), value.clone()))
+            .collect();
+        if entries.is_empty() {
+            self.out.push_str("[]");
+            return;
+        }
+
+        self.entries(Rc::as_ptr(list) as *const (), entries, "[]", depth, indent);
+    }
+
+    /// Prints labelled values of the container at `address` as an indented
+    /// block, one per line, enclosed in the two characters of `brackets`.
+    fn entries(
+        &mut self,
+        address: *const (),
+        entries: Vec<(String, LoxValue)>,
+        brackets: &str,
+        depth: usize,
+        indent: usize,
+    ) {
+        let (open, close) = brackets.split_at(1);
+        if self.visiting.contains(&address) {
+            let _ = write!(self.out, "{open}...{close}");
+            return;
+        }
+        if depth == 0 {
+            let _ = write!(self.out, "{open} ... {close}");
+            return;
+        }
+
+        self.visiting.push(address);
+        self.out.push_str(open);
+        self.out.push('\n');
+        for (label, value) in entries {
+            self.indent(indent + 1);
+            self.out.push_str(&label);
+            self.value(&value, depth - 1, indent + 1);
+            self.out.push_str(",\n");
+        }
+        self.indent(indent);
+        self.out.push_str(close);
+        self.visiting.pop();
+    }
+
+    fn indent(&mut self, indent: usize) {
+        for _ in 0..indent {
+            self.out.push_str(INDENT);
+        }
+    }
+}
+
+#[test]
+fn test_pretty_closures() {
+    use crate::interpreter::Interpreter;
+    use crate::lox_key::LoxKey;
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+    let source = "
+        fun counter(start) {
+            var step = 1;
+            fun next() { start = start + step; return next; }
+            return next;
+        }
+        var c = counter(10);
+    ";
+    let tree = Parser::new()
+        .parse(scanner::scan_tokens(source).unwrap())
+        .unwrap();
+    let mut interp = Interpreter::new();
+    interp
+        .execute(&tree, resolver::resolve(&tree).unwrap())
+        .unwrap();
+    let c = interp
+        .environment
+        .get_global(&"c".to_string())
+        .expect("Expected variable `c` to be defined.");
+
+    assert_eq!(
+        value(&c, DEFAULT_DEPTH),
+        [
+            "<fn next> {",
+            "    next = <fn next> {...},",
+            "    start = 10,",
+            "    step = 1,",
+            "}",
+        ]
+        .join("\n")
+    );
+    assert_eq!(value(&c, 0), "<fn next> { ... }");
+    assert_eq!(value(&LoxValue::Number(1.5), 0), "1.5");
+    assert_eq!(value(&LoxValue::String("nil".to_owned()), 0), "\"nil\"");
+
+    let map: LoxMap = [
+        (LoxKey::String("c".to_owned()), c),
+        (LoxKey::Integer(1), LoxValue::Nil),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(
+        value(&LoxValue::Map(Rc::new(RefCell::new(map))), 2),
+        [
+            "{",
+            "    \"c\": <fn next> {",
+            "        next = <fn next> {...},",
+            "        start = 10,",
+            "        step = 1,",
+            "    },",
+            "    1: nil,",
+            "}",
+        ]
+        .join("\n")
+    );
+}
@@ -0,0 +1,131 @@
+//! Programs split over several files, run by passing their directory.
+//!
+//! A project directory runs its `main.lox`, unless it has a manifest,
+//! `rlox.project`, made of `key = value` lines:
+//!
+//! ```text
+//! # comments and blank lines are skipped
+//! entry = src/app.lox
+//! roots = src, vendor
+//! edition = 2026
+//! ```
+//!
+//! Paths are relative to the project directory. Imports not found next to
+//! the importing file are looked up in the source roots, in order.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::edition::Edition;
+use crate::messages::{message, Message};
+
+pub const MANIFEST: &str = "rlox.project";
+pub const DEFAULT_ENTRY: &str = "main.lox";
+
+#[derive(Debug, PartialEq)]
+pub struct Project {
+    /// file the program starts in
+    pub entry: PathBuf,
+    /// directories searched for imports
+    pub roots: Vec<PathBuf>,
+    /// edition the project is written in, if it names one
+    pub edition: Option<Edition>,
+}
+
+impl Project {
+    /// Reads the manifest of the project in `dir`, if there is one.
+    pub fn open(dir: &Path) -> Result<Project, Message> {
+        let manifest = dir.join(MANIFEST);
+        match fs::read_to_string(&manifest) {
+            Ok(source) => Project::parse(dir, &manifest, &source),
+            Err(error) if manifest.exists() => Err(message!(
+                CannotReadFile,
+                path = manifest.display(),
+                error = error
+            )),
+            Err(_) => Ok(Project {
+                entry: dir.join(DEFAULT_ENTRY),
+                roots: Vec::new(),
+                edition: None,
+            }),
+        }
+    }
+
+    /// Parses the `source` of the manifest at `path`, describing the
+    /// project in `dir`.
+    fn parse(dir: &Path, path: &Path, source: &str) -> Result<Project, Message> {
+        let mut project = Project {
+            entry: dir.join(DEFAULT_ENTRY),
+            roots: Vec::new(),
+            edition: None,
+        };
+
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let location = format!("{}:{}", path.display(), index + 1);
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(message!(ManifestSyntax, location = location));
+            };
+            let value = value.trim();
+            match key.trim() {
+                "entry" => project.entry = dir.join(value),
+                "roots" => {
+                    project.roots = value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|root| !root.is_empty())
+                        .map(|root| dir.join(root))
+                        .collect()
+                }
+                "edition" => {
+                    let edition = value.parse().map_err(|_| {
+                        let editions: Vec<_> =
+                            Edition::ALL.iter().map(|edition| edition.year()).collect();
+                        message!(
+                            UnknownEdition,
+                            edition = value,
+                            editions = editions.join(", ")
+                        )
+                    })?;
+                    project.edition = Some(edition);
+                }
+                key => return Err(message!(ManifestUnknownKey, location = location, key = key)),
+            }
+        }
+
+        Ok(project)
+    }
+}
+
+#[test]
+fn test_manifest() {
+    let dir = Path::new("game");
+    let manifest = dir.join(MANIFEST);
+    let parse = |source: &str| Project::parse(dir, &manifest, source);
+
+    assert_eq!(
+        parse("# a game\n\nentry = src/app.lox\nroots = src, vendor ,\nedition=2026\n"),
+        Ok(Project {
+            entry: dir.join("src/app.lox"),
+            roots: vec![dir.join("src"), dir.join("vendor")],
+            edition: Some(Edition::Lox2026),
+        })
+    );
+    assert_eq!(parse("").unwrap().entry, dir.join("main.lox"));
+
+    assert_eq!(
+        parse("roots = src\nentry src/app.lox").map_err(String::from),
+        Err("game/rlox.project:2: Expect 'key = value'.".to_owned())
+    );
+    assert_eq!(
+        parse("main = app.lox").map_err(String::from),
+        Err(
+            "game/rlox.project:1: Unknown key 'main', expected entry, roots or edition.".to_owned()
+        )
+    );
+    assert!(parse("edition = 1999").is_err());
+}
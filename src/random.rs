@@ -0,0 +1,81 @@
+//! Pseudo-random numbers behind `random()` and `randomInt()`.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// SplitMix64, small and fast. The same seed always gives the same
+/// sequence, which makes seeded scripts reproducible. Not suitable for
+/// anything security related.
+#[derive(Clone, Debug)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random { state: seed }
+    }
+
+    /// A generator seeded with the current time.
+    pub fn from_time() -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Random::new(now.as_nanos() as u64)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniformly distributed in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        // the top 53 bits fill the mantissa exactly
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniformly distributed in `low..=high`, `low` must not exceed `high`.
+    pub fn next_in(&mut self, low: i64, high: i64) -> i64 {
+        let span = high.abs_diff(low).wrapping_add(1);
+        if span == 0 {
+            // the whole range of i64
+            return self.next_u64() as i64;
+        }
+        // values past the last whole multiple of `span` would favour
+        // the lower part of the range
+        let limit = u64::MAX - u64::MAX % span;
+        loop {
+            let n = self.next_u64();
+            if n < limit {
+                return low.wrapping_add((n % span) as i64);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_random() {
+    let sequence = |seed| {
+        let mut random = Random::new(seed);
+        [random.next_u64(), random.next_u64(), random.next_u64()]
+    };
+    assert_eq!(sequence(7), sequence(7));
+    assert_ne!(sequence(7), sequence(8));
+
+    let mut random = Random::new(1);
+    let mut seen = [false; 5];
+    for _ in 0..1000 {
+        let n = random.next_in(-2, 2);
+        assert!((-2..=2).contains(&n));
+        seen[(n + 2) as usize] = true;
+
+        let x = random.next_f64();
+        assert!((0. ..1.).contains(&x));
+    }
+    assert_eq!(seen, [true; 5]);
+    assert_eq!(random.next_in(3, 3), 3);
+    random.next_in(i64::MIN, i64::MAX);
+}
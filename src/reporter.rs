@@ -0,0 +1,313 @@
+//! Where diagnostics go once a stage has found them.
+//!
+//! The command line picks a reporter with `--error-format` and hands it
+//! every error a run ends with, e.g. all the errors a parse failed with,
+//! and the warnings of `rlox lint`.
+
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::str::FromStr;
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::messages::message;
+use crate::output::Sink;
+use crate::source_map::{self, SourceMap};
+
+pub trait DiagnosticReporter {
+    fn report(&mut self, diagnostic: &Diagnostic);
+}
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const RESET: &str = "\x1b[0m";
+
+/// Reports diagnostics as text for people to read, showing the line of
+/// the source they point at with carets under their span:
+///
+/// ```text
+/// Error[E1001] at line 2, position 7: Undefined variable 'count'.
+///  2 | print count;
+///    |       ^^^^^
+/// ```
+pub struct ConsoleReporter {
+    output: Box<dyn Sink>,
+    /// the source the diagnostics point into
+    source: Option<String>,
+    /// highlight with ANSI escape codes
+    color: bool,
+}
+
+impl ConsoleReporter {
+    pub fn new(output: impl Sink + 'static) -> Self {
+        ConsoleReporter {
+            output: Box::new(output),
+            source: None,
+            color: false,
+        }
+    }
+
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_owned());
+        self
+    }
+
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// `text` in `color` if colors are used.
+    fn paint(&self, color: &str, text: &str) -> String {
+        match self.color {
+            true => format!("{color}{text}{RESET}"),
+            false => text.to_owned(),
+        }
+    }
+
+    fn write(&mut self, diagnostic: &Diagnostic) -> io::Result<()> {
+        let span = diagnostic.span;
+        let (header, color) = match (diagnostic.severity, diagnostic.code) {
+            (Severity::Error, Some(code)) => (
+                message!(
+                    ErrorWithCodeAt,
+                    code = code,
+                    line = span.line,
+                    position = span.position,
+                    message = diagnostic.message
+                ),
+                RED,
+            ),
+            (Severity::Error, None) => (
+                message!(
+                    ErrorAt,
+                    line = span.line,
+                    position = span.position,
+                    message = diagnostic.message
+                ),
+                RED,
+            ),
+            (Severity::Warning, _) => (
+                message!(
+                    WarningAt,
+                    line = span.line,
+                    position = span.position,
+                    message = diagnostic.message
+                ),
+                YELLOW,
+            ),
+        };
+        writeln!(self.output, "{}", self.paint(color, &header))?;
+
+        let line = self
+            .source
+            .as_deref()
+            .and_then(|source| SourceMap::new(source).line(span.line));
+        if let Some(line) = line {
+            let number = span.line.to_string();
+            let gutter = " ".repeat(number.len());
+            let underline = "^".repeat(span.end.saturating_sub(span.position).max(1));
+            let caret = " ".repeat(span.position.saturating_sub(1)) + &underline;
+            let text = source_map::expand_tabs(line);
+            let bar = self.paint(BLUE, "|");
+            writeln!(self.output, " {} {bar} {text}", self.paint(BLUE, &number))?;
+            writeln!(self.output, " {gutter} {bar} {}", self.paint(color, &caret))?;
+        }
+
+        for note in &diagnostic.notes {
+            writeln!(self.output, "{}", message!(NoteLine, note = note))?;
+        }
+        Ok(())
+    }
+}
+
+impl DiagnosticReporter for ConsoleReporter {
+    fn report(&mut self, diagnostic: &Diagnostic) {
+        // nothing is left to report a failing output to
+        let _ = self.write(diagnostic);
+    }
+}
+
+/// Reports every diagnostic as a JSON object on a line of its own, for
+/// editors and CI:
+///
+/// ```json
+/// {"severity":"error","code":"E0107","line":2,"position":10,"end":11,"message":"...","notes":[]}
+/// ```
+pub struct JsonLinesReporter {
+    output: Box<dyn Sink>,
+}
+
+impl JsonLinesReporter {
+    pub fn new(output: impl Sink + 'static) -> Self {
+        JsonLinesReporter {
+            output: Box::new(output),
+        }
+    }
+}
+
+impl DiagnosticReporter for JsonLinesReporter {
+    fn report(&mut self, diagnostic: &Diagnostic) {
+        let severity = match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let code = diagnostic.code.map_or("null".to_owned(), json_string);
+        let notes: Vec<_> = diagnostic.notes.iter().map(|n| json_string(n)).collect();
+        let _ = writeln!(
+            self.output,
+            r#"{{"severity":"{severity}","code":{code},"line":{},"position":{},"end":{},"message":{},"notes":[{}]}}"#,
+            diagnostic.span.line,
+            diagnostic.span.position,
+            diagnostic.span.end,
+            json_string(&diagnostic.message),
+            notes.join(",")
+        );
+    }
+}
+
+/// Drops every diagnostic.
+pub struct SilentReporter;
+
+impl DiagnosticReporter for SilentReporter {
+    fn report(&mut self, _: &Diagnostic) {}
+}
+
+/// `text` as a JSON string literal.
+fn json_string(text: &str) -> String {
+    let mut literal = String::with_capacity(text.len() + 2);
+    literal.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            c if c.is_control() => literal.push_str(&format!("\\u{:04x}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// The reporters `--error-format` selects from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    #[default]
+    Console,
+    Json,
+    Silent,
+}
+
+impl ErrorFormat {
+    pub const ALL: &'static [ErrorFormat] =
+        &[ErrorFormat::Console, ErrorFormat::Json, ErrorFormat::Silent];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorFormat::Console => "console",
+            ErrorFormat::Json => "json",
+            ErrorFormat::Silent => "silent",
+        }
+    }
+
+    /// A reporter of the format writing to the standard output, about
+    /// diagnostics of `source`. Colors are used on a terminal, unless
+    /// turned off with the `NO_COLOR` environment variable.
+    pub fn reporter(self, source: &str) -> Box<dyn DiagnosticReporter> {
+        match self {
+            ErrorFormat::Console => {
+                let color = io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+                let reporter = ConsoleReporter::new(io::stdout())
+                    .with_source(source)
+                    .with_color(color);
+                Box::new(reporter)
+            }
+            ErrorFormat::Json => Box::new(JsonLinesReporter::new(io::stdout())),
+            ErrorFormat::Silent => Box::new(SilentReporter),
+        }
+    }
+}
+
+impl FromStr for ErrorFormat {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        ErrorFormat::ALL
+            .iter()
+            .find(|format| format.name() == name)
+            .copied()
+            .ok_or(())
+    }
+}
+
+#[test]
+fn test_reporters() {
+    use crate::diagnostic::Span;
+    use crate::output::Captured;
+    use crate::parser::Parser;
+    use crate::scanner;
+
+    let tokens = scanner::scan_tokens("print 1 +;\nvar = 2;\nprint \"ok\";\nprint );").unwrap();
+    let output = Captured::new();
+    let mut reporter = JsonLinesReporter::new(output.clone());
+    for error in Parser::new().parse(tokens).unwrap_err() {
+        reporter.report(&error.into_diagnostic().unwrap());
+    }
+    assert_eq!(
+        output.contents(),
+        r#"{"severity":"error","code":"E0107","line":1,"position":10,"end":11,"message":"Expect expression, found ';'.","notes":[]}
+{"severity":"error","code":"E0116","line":2,"position":5,"end":6,"message":"Expect variable name.","notes":[]}
+{"severity":"error","code":"E0107","line":4,"position":7,"end":8,"message":"Expect expression, found ')'.","notes":[]}
+"#
+    );
+
+    let output = Captured::new();
+    let warning = Diagnostic::warning(Span::new(3, 1), "Tab\there \"quoted\".".to_owned())
+        .with_note("A note.".to_owned());
+    ConsoleReporter::new(output.clone()).report(&warning);
+    JsonLinesReporter::new(output.clone()).report(&warning);
+    assert_eq!(
+        output.contents(),
+        r#"Warning at line 3, position 1: Tab	here "quoted".
+  note: A note.
+{"severity":"warning","code":null,"line":3,"position":1,"end":2,"message":"Tab\there \"quoted\".","notes":["A note."]}
+"#
+    );
+
+    let output = Captured::new();
+    let mut error = Diagnostic::error(Span::with_end(2, 5, 10), "Bad.".to_owned());
+    let source = "var a = 1;\n\tprint a +;\n";
+    let mut reporter = ConsoleReporter::new(output.clone()).with_source(source);
+    reporter.report(&error);
+    reporter.color = true;
+    error.code = Some("E0107");
+    reporter.report(&error);
+    assert_eq!(
+        output.contents(),
+        "Error at line 2, position 5: Bad.
+ 2 |     print a +;
+   |     ^^^^^
+\x1b[1;31mError[E0107] at line 2, position 5: Bad.\x1b[0m
+ \x1b[1;34m2\x1b[0m \x1b[1;34m|\x1b[0m     print a +;
+   \x1b[1;34m|\x1b[0m \x1b[1;31m    ^^^^^\x1b[0m
+"
+    );
+    // an error on the first token is shown at it, not at line 0
+    let source = ");\n";
+    let tokens = scanner::scan_tokens(source).unwrap();
+    let output = Captured::new();
+    let mut reporter = ConsoleReporter::new(output.clone()).with_source(source);
+    for error in Parser::new().parse(tokens).unwrap_err() {
+        reporter.report(&error.into_diagnostic().unwrap());
+    }
+    assert_eq!(
+        output.contents(),
+        "Error[E0107] at line 1, position 1: Expect expression, found ')'.
+ 1 | );
+   | ^
+"
+    );
+}
@@ -1,8 +1,15 @@
-use std::{collections::HashMap, num::NonZeroUsize};
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZeroUsize,
+};
 
 use crate::{
+    diagnostic::{Diagnostic, Span},
     error::Error,
-    expression::{DebugInfo, Expression, Identifier, IdentifierId},
+    expression::{DebugInfo, Expression, Identifier, IdentifierId, LiteralValue},
+    lint::{self, Rule, Warning},
+    messages::{message, Message},
+    shared::Rc,
     statement::{Block, Statement},
 };
 
@@ -21,28 +28,54 @@ impl ScopeDepth {
         self.0.get() - 1
     }
 
-    fn from(depth: usize, number_of_parent_scopes: usize) -> Option<ScopeDepth> {
-        NonZeroUsize::new(if depth == number_of_parent_scopes {
-            0
-        } else {
-            depth + 1
-        })
-        .and_then(|v| Some(ScopeDepth(v)))
+    fn new(depth: usize) -> ScopeDepth {
+        ScopeDepth(NonZeroUsize::new(depth + 1).expect("scope depth overflow"))
     }
 }
 
+/// A variable from an enclosing scope used by a function.
+///
+/// `depth` is the distance to the variable's scope counted from the
+/// scope in which the function is defined.
+#[derive(Clone, Debug)]
+pub struct Capture {
+    pub name: String,
+    pub depth: usize,
+}
+
 #[derive(Debug)]
 pub struct AccessTable {
     access_table: HashMap<IdentifierId, ScopeDepth>,
+    /// variables captured by each function, keyed by the id of its name
+    captures: HashMap<IdentifierId, Rc<[Capture]>>,
+    /// local functions whose name is never assigned, they may refer to
+    /// themselves without capturing the variable holding them
+    self_captures: HashSet<IdentifierId>,
 }
 
 impl AccessTable {
     pub fn empty() -> Self {
         Self {
             access_table: HashMap::new(),
+            captures: HashMap::new(),
+            self_captures: HashSet::new(),
         }
     }
 
+    /// variables captured by the function declared with identifier `id`
+    pub fn captures(&self, id: &IdentifierId) -> Rc<[Capture]> {
+        self.captures
+            .get(id)
+            .cloned()
+            .unwrap_or_else(|| Rc::new([]))
+    }
+
+    /// whether the function declared with identifier `id` can refer to
+    /// itself weakly, as its name always holds it
+    pub fn captures_itself(&self, id: &IdentifierId) -> bool {
+        self.self_captures.contains(id)
+    }
+
     /// returned value represents a depth at which to
     /// look for a value of given IdentifierId,
     /// if id is not found, then identifier
@@ -67,23 +100,101 @@ impl AccessTable {
         }
     }
 
-    pub fn add_all(&mut self, other: AccessTable) -> Result<(), ()> {
+    pub(crate) fn add_all(&mut self, other: AccessTable) -> Result<(), ()> {
         for (id, depth) in other.access_table {
             self.put(id, Some(depth))?;
         }
+        self.captures.extend(other.captures);
+        self.self_captures.extend(other.self_captures);
         Ok(())
     }
 }
 
+/// A function whose body is being resolved.
+pub struct FunctionScope {
+    /// index of the scope holding the function's parameters
+    scope_index: usize,
+    captures: Vec<Capture>,
+    /// `init` method of a class, which cannot return a value
+    initializer: bool,
+}
+
+/// A variable declared in a local scope.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Binding {
+    /// the initializer has been resolved, the variable can be read
+    pub defined: bool,
+    pub constant: bool,
+    /// the variable is read somewhere in its scope
+    pub used: bool,
+    /// the variable is assigned somewhere in its scope
+    pub assigned: bool,
+    /// id of the name of the function declared by it
+    pub function: Option<IdentifierId>,
+    /// where the variable is declared
+    pub line: usize,
+    pub position: usize,
+}
+
+impl Binding {
+    const DEFINED: Binding = Binding {
+        defined: true,
+        constant: false,
+        used: true,
+        assigned: false,
+        function: None,
+        line: 0,
+        position: 0,
+    };
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ClassKind {
+    Class,
+    Subclass,
+}
+
 pub struct Resolver {
     pub access_table: AccessTable,
-    pub scopes: Vec<HashMap<String, bool>>,
+    pub scopes: Vec<HashMap<String, Binding>>,
+    /// constants declared at the top level so far, assignments to them
+    /// made before they are declared are only caught at runtime
+    pub global_constants: HashSet<String>,
+    pub functions: Vec<FunctionScope>,
+    /// number of loops enclosing the current statement within its function
+    pub loops: usize,
+    /// classes enclosing the current statement, innermost last
+    pub classes: Vec<ClassKind>,
     pub line: usize,
     pub position: usize,
+    pub end: usize,
+    /// warnings of the rules in `lint`, only collected when `Some`
+    pub warnings: Option<Vec<Warning>>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Resolver::new()
+    }
 }
 
 impl Resolver {
-    pub fn resolve(&mut self, statements: &Vec<Statement>) -> Result<(), Error> {
+    pub fn new() -> Self {
+        Resolver {
+            line: 0,
+            position: 0,
+            end: 0,
+            access_table: AccessTable::empty(),
+            scopes: Vec::new(),
+            global_constants: HashSet::new(),
+            functions: Vec::new(),
+            loops: 0,
+            classes: Vec::new(),
+            warnings: None,
+        }
+    }
+
+    pub fn resolve(&mut self, statements: &[Statement]) -> Result<(), Error> {
         statements
             .iter()
             .try_for_each(|statement| self.visit_statement(statement))
@@ -91,27 +202,97 @@ impl Resolver {
 
     fn visit_statement(&mut self, statement: &Statement) -> Result<(), Error> {
         match statement {
-            Statement::Nop => Ok(()),
             Statement::Expression(e) => self.visit_expression(e),
             Statement::Print(e) => self.visit_expression(e),
-            Statement::Block(block) => self.visit_block(block),
-            Statement::Return { value: Some(value) } => self.visit_expression(value),
-            Statement::Return { value: None } => Ok(()),
-            Statement::Variable {
-                name: identifier,
-                initializer: Some(initializer),
+            Statement::Block(block) => {
+                self.check_empty(block);
+                self.visit_block(block)
+            }
+            Statement::Return {
+                value: Some(value),
+                debug_info,
             } => {
-                self.declare(&identifier.name)?;
-                self.visit_expression(initializer)?;
-                self.define(&identifier.name)?;
+                self.visit_expression(value)?;
+                if self
+                    .functions
+                    .last()
+                    .is_some_and(|function| function.initializer)
+                {
+                    self.set_location(debug_info);
+                    return Err(self.error(message!(ReturnFromInitializer)));
+                }
                 Ok(())
             }
+            Statement::Return { value: None, .. } => Ok(()),
+            Statement::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                self.set_location(&name.debug_info);
+                self.declare(&name.name)?;
+                self.define(&name.name)?;
+
+                let mut kind = ClassKind::Class;
+                if let Some(superclass) = superclass {
+                    if superclass.name == name.name {
+                        self.set_location(&superclass.debug_info);
+                        return Err(self.error(message!(InheritFromItself)));
+                    }
+                    self.visit_identifier(superclass)?;
+                    kind = ClassKind::Subclass;
+                    // `super` lives in its own scope, just outside the one holding `this`
+                    self.scopes
+                        .push(HashMap::from([("super".to_owned(), Binding::DEFINED)]));
+                }
+
+                // methods capture `this` from a scope wrapping the class body
+                self.scopes
+                    .push(HashMap::from([("this".to_owned(), Binding::DEFINED)]));
+                self.classes.push(kind);
+                let result = methods.iter().try_for_each(|method| {
+                    let initializer = method.name.name == "init";
+                    self.resolve_function(&method.name, &method.args, &method.body, initializer)
+                });
+                self.classes.pop();
+                self.end_scope();
+                if kind == ClassKind::Subclass {
+                    self.end_scope();
+                }
+                result
+            }
+            Statement::Break { debug_info } => {
+                self.set_location(debug_info);
+                if self.loops == 0 {
+                    return Err(self.error(message!(BreakOutsideLoop)));
+                }
+                Ok(())
+            }
+            Statement::Continue { debug_info } => {
+                self.set_location(debug_info);
+                if self.loops == 0 {
+                    return Err(self.error(message!(ContinueOutsideLoop)));
+                }
+                Ok(())
+            }
+            Statement::Error { debug_info, .. } => {
+                self.set_location(debug_info);
+                Err(self.error(message!(UnparsedStatement)))
+            }
             Statement::Variable {
                 name: identifier,
-                initializer: None,
+                initializer,
+                constant,
             } => {
+                self.set_location(&identifier.debug_info);
                 self.declare(&identifier.name)?;
+                if let Some(initializer) = initializer {
+                    self.visit_expression(initializer)?;
+                }
                 self.define(&identifier.name)?;
+                if *constant {
+                    self.declare_constant(&identifier.name);
+                }
                 Ok(())
             }
             Statement::If {
@@ -119,54 +300,220 @@ impl Resolver {
                 then_branch,
                 else_branch,
             } => {
+                self.check_constant(condition, false);
                 self.visit_expression(condition)?;
+                self.check_empty(then_branch);
                 self.visit_block(then_branch)?;
                 if let Some(else_branch) = else_branch.as_ref() {
+                    self.check_empty(else_branch);
                     self.visit_block(else_branch)?;
                 }
                 Ok(())
             }
-            Statement::While { condition, body } => {
+            Statement::While {
+                condition,
+                body,
+                increment,
+            } => {
+                self.check_constant(condition, true);
                 self.visit_expression(condition)?;
+                self.check_empty(body);
+                self.loops += 1;
+                let result = self.visit_block(body);
+                self.loops -= 1;
+                result?;
+                match increment {
+                    Some(increment) => self.visit_expression(increment),
+                    None => Ok(()),
+                }
+            }
+            Statement::For { .. } => unreachable!("`for` loops are lowered before resolution"),
+            Statement::ForIn {
+                variable,
+                iterable,
+                body,
+                ..
+            } => {
+                self.visit_expression(iterable)?;
+                self.scopes.push(HashMap::new());
+                self.set_location(&variable.debug_info);
+                self.declare(&variable.name)?;
+                self.define(&variable.name)?;
+                self.check_empty(body);
+                self.loops += 1;
+                let result = self.visit_block(body);
+                self.loops -= 1;
+                self.end_scope();
+                result
+            }
+            Statement::Match {
+                value,
+                arms,
+                default,
+            } => {
+                self.visit_expression(value)?;
+                arms.iter()
+                    .map(|arm| &arm.body)
+                    .chain(default)
+                    .try_for_each(|body| self.visit_block(body))
+            }
+            Statement::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.check_empty(body);
                 self.visit_block(body)?;
+                if let Some(catch) = catch {
+                    self.scopes.push(HashMap::new());
+                    if let Some(variable) = &catch.variable {
+                        self.set_location(&variable.debug_info);
+                        self.declare(&variable.name)?;
+                        self.define(&variable.name)?;
+                    }
+                    let result = self.visit_block(&catch.body);
+                    self.end_scope();
+                    result?;
+                }
+                match finally {
+                    Some(finally) => {
+                        self.check_empty(finally);
+                        self.visit_block(finally)
+                    }
+                    None => Ok(()),
+                }
+            }
+            Statement::Throw { value, .. } => self.visit_expression(value),
+            Statement::Import { debug_info, .. } => {
+                // imported names become globals of the importing module
+                if !self.scopes.is_empty() || !self.functions.is_empty() {
+                    self.set_location(debug_info);
+                    return Err(self.error(message!(ImportOutsideTopLevel)));
+                }
                 Ok(())
             }
             Statement::Function {
                 name: identifier,
                 args,
                 body,
+                ..
             } => {
+                self.set_location(&identifier.debug_info);
                 self.declare(&identifier.name)?;
                 self.define(&identifier.name)?;
-
-                self.scopes.push(HashMap::new());
-                for arg in args {
-                    self.set_location(&arg.debug_info);
-                    self.declare(&arg.name)?;
-                    self.define(&arg.name)?;
+                if let Some(binding) = self
+                    .scopes
+                    .last_mut()
+                    .and_then(|scope| scope.get_mut(&identifier.name))
+                {
+                    binding.function = Some(identifier.id);
                 }
-                self.resolve(&body.statements)?;
-                self.scopes.pop();
-                Ok(())
+                self.resolve_function(identifier, args, body, false)
             }
         }
     }
 
-    fn resolve_local_identifier(&mut self, id: IdentifierId, name: String) -> Result<(), Error> {
-        for (i, scope) in self.scopes.iter().rev().enumerate() {
-            if scope.contains_key(&name) {
-                return self
-                    .access_table
-                    .put(id, ScopeDepth::from(i, self.scopes.len()))
-                    .map_err(|_| self.error("Tried to resolve the same identifier twice."));
-            }
+    fn resolve_function(
+        &mut self,
+        name: &Identifier,
+        args: &[Identifier],
+        body: &Block,
+        initializer: bool,
+    ) -> Result<(), Error> {
+        self.scopes.push(HashMap::new());
+        self.functions.push(FunctionScope {
+            scope_index: self.scopes.len() - 1,
+            captures: Vec::new(),
+            initializer,
+        });
+        for arg in args {
+            self.set_location(&arg.debug_info);
+            self.declare(&arg.name)?;
+            self.define(&arg.name)?;
+            // callbacks often ignore some of their parameters
+            self.mark_used(&arg.name);
+        }
+        let loops = std::mem::take(&mut self.loops);
+        self.resolve(&body.statements)?;
+        self.loops = loops;
+        let function = self.functions.pop().expect("function scope");
+        self.end_scope();
+
+        if !function.captures.is_empty() {
+            self.access_table
+                .captures
+                .insert(name.id, function.captures.into());
         }
         Ok(())
     }
 
-    fn declare(&mut self, name: &String) -> Result<(), Error> {
+    fn resolve_local_identifier(&mut self, id: IdentifierId, name: String) -> Result<(), Error> {
+        let depth = self.lookup(&name, self.scopes.len()).map(ScopeDepth::new);
+        self.access_table
+            .put(id, depth)
+            .map_err(|_| self.error(message!(ResolvedTwice, name = name)))
+    }
+
+    /// Finds the scope declaring `name` among the first `top` scopes and
+    /// returns its distance from scope `top - 1`, `None` means a global.
+    ///
+    /// A variable declared outside of the innermost function is captured
+    /// by that function, the returned depth then points at the function's
+    /// capture frame, which sits right above its parameters. The function
+    /// itself looks the variable up in its defining scope, capturing it
+    /// through any enclosing functions as well.
+    fn lookup(&mut self, name: &str, top: usize) -> Option<usize> {
+        let index = (0..top)
+            .rev()
+            .find(|i| self.scopes[*i].contains_key(name))?;
+
+        let function = self
+            .functions
+            .iter()
+            .rposition(|function| function.scope_index < top)
+            .filter(|f| self.functions[*f].scope_index > index);
+
+        let Some(function) = function else {
+            return Some(top - 1 - index);
+        };
+
+        let scope_index = self.functions[function].scope_index;
+        let captured = self.functions[function]
+            .captures
+            .iter()
+            .any(|capture| capture.name == name);
+        if !captured {
+            let depth = self
+                .lookup(name, scope_index)
+                .expect("variable is declared in an enclosing scope");
+            self.functions[function].captures.push(Capture {
+                name: name.to_owned(),
+                depth,
+            });
+        }
+
+        Some(top - scope_index)
+    }
+
+    fn declare(&mut self, name: &str) -> Result<(), Error> {
+        let outer = match self.scopes.split_last() {
+            Some((_, outer)) => outer.iter().rev().find_map(|scope| scope.get(name)),
+            None => None,
+        };
+        if let Some(shadowed) = outer.copied() {
+            let span = Span::with_end(self.line, self.position, self.end);
+            let warning = Diagnostic::warning(span, message!(LintShadowing, name = name))
+                .with_note(message!(LintDeclaredAt, name = name, line = shadowed.line));
+            self.warn(Rule::Shadowing, warning);
+        }
+
+        let binding = Binding {
+            line: self.line,
+            position: self.position,
+            ..Binding::default()
+        };
         if let Some(scope) = self.scopes.last_mut() {
-            scope.insert(name.clone(), false);
+            scope.insert(name.to_owned(), binding);
         } else {
             // identifier is declared in global scope
         }
@@ -175,23 +522,115 @@ impl Resolver {
 
     fn define(&mut self, name: &String) -> Result<(), Error> {
         if let Some(scope) = self.scopes.last_mut() {
-            *scope
+            scope
                 .get_mut(name)
-                .expect("Variable or should be declared before definition") = true;
+                .expect("Variable or should be declared before definition")
+                .defined = true;
         } else {
             // identifier is defined in global scope
         }
         Ok(())
     }
 
+    fn declare_constant(&mut self, name: &str) {
+        match self.scopes.last_mut() {
+            Some(scope) => {
+                if let Some(binding) = scope.get_mut(name) {
+                    binding.constant = true;
+                }
+            }
+            None => {
+                self.global_constants.insert(name.to_owned());
+            }
+        }
+    }
+
+    /// Whether `name` refers to a constant, as far as can be told before
+    /// running the program.
+    fn is_constant(&self, name: &str) -> bool {
+        match self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            Some(binding) => binding.constant,
+            None => self.global_constants.contains(name),
+        }
+    }
+
     fn visit_block(&mut self, block: &Block) -> Result<(), Error> {
         self.scopes.push(HashMap::new());
         self.resolve(&block.statements)?;
-        self.scopes.pop();
+        self.end_scope();
 
         Ok(())
     }
 
+    /// Leaves the innermost scope, warning about its unused variables.
+    fn end_scope(&mut self) {
+        let scope = self.scopes.pop().expect("scope to end");
+        for binding in scope.values() {
+            if let (Some(id), false) = (binding.function, binding.assigned) {
+                self.access_table.self_captures.insert(id);
+            }
+        }
+        if self.warnings.is_none() {
+            return;
+        }
+        // a leading `_` marks a variable as unused on purpose
+        for (name, binding) in scope {
+            if !binding.used && !name.starts_with('_') {
+                let span = Span::of_text(binding.line, binding.position, &name);
+                let message = message!(LintUnusedVariable, name = name);
+                self.warn(Rule::UnusedVariable, Diagnostic::warning(span, message));
+            }
+        }
+    }
+
+    fn mark_used(&mut self, name: &str) {
+        let binding = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name));
+        if let Some(binding) = binding {
+            binding.used = true;
+        }
+    }
+
+    fn mark_assigned(&mut self, name: &str) {
+        let binding = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name));
+        if let Some(binding) = binding {
+            binding.assigned = true;
+        }
+    }
+
+    fn check_empty(&mut self, block: &Block) {
+        if block.statements.is_empty() {
+            let span = block.debug_info.span();
+            let warning = Diagnostic::warning(span, message!(LintEmptyBlock));
+            self.warn(Rule::EmptyBlock, warning);
+        }
+    }
+
+    /// Warns about a literal `condition`, of a loop one other than `true`.
+    fn check_constant(&mut self, condition: &Expression, looping: bool) {
+        let Some((value, literal)) = lint::constant(condition) else {
+            return;
+        };
+        if !(looping && matches!(value, LiteralValue::True(_))) {
+            let span = literal.span();
+            let message = message!(LintConstantCondition, value = literal.lexeme);
+            self.warn(Rule::ConstantCondition, Diagnostic::warning(span, message));
+        }
+    }
+
+    fn warn(&mut self, rule: Rule, diagnostic: Diagnostic) {
+        if let Some(warnings) = &mut self.warnings {
+            warnings.push(Warning { rule, diagnostic });
+        }
+    }
+
     fn visit_expression(&mut self, expression: &Expression) -> Result<(), Error> {
         match expression {
             Expression::Binary(op) => {
@@ -203,6 +642,10 @@ impl Resolver {
                 self.visit_expression(&grouping.expression)?;
                 Ok(())
             }
+            Expression::Interpolation(interpolation) => interpolation
+                .parts
+                .iter()
+                .try_for_each(|part| self.visit_expression(part)),
             Expression::Literal(_) => Ok(()),
             Expression::Unary(op) => {
                 self.visit_expression(&op.right)?;
@@ -216,6 +659,10 @@ impl Resolver {
                 self.visit_expression(&assignment.value)?;
                 let target = &assignment.target;
                 self.set_location(&target.debug_info);
+                if self.is_constant(&target.name) {
+                    return Err(self.error(message!(AssignToConstant, name = target.name)));
+                }
+                self.mark_assigned(&target.name);
                 self.resolve_local_identifier(target.id, target.name.clone())?;
                 Ok(())
             }
@@ -224,36 +671,88 @@ impl Resolver {
                 self.visit_expression(&op.right)?;
                 Ok(())
             }
+            Expression::Sequence(sequence) => {
+                self.visit_expression(&sequence.left)?;
+                self.visit_expression(&sequence.right)?;
+                Ok(())
+            }
             Expression::Call(call) => {
                 self.visit_expression(&call.calle)?;
                 for arg in &call.args {
-                    self.visit_expression(&arg)?;
+                    self.visit_expression(arg)?;
                 }
                 Ok(())
             }
+            Expression::Get(get) => self.visit_expression(&get.object),
+            Expression::Set(set) => {
+                self.visit_expression(&set.value)?;
+                self.visit_expression(&set.object)
+            }
+            Expression::List(list) => list
+                .elements
+                .iter()
+                .try_for_each(|element| self.visit_expression(element)),
+            Expression::Map(map) => map.entries.iter().try_for_each(|(key, value)| {
+                self.visit_expression(key)?;
+                self.visit_expression(value)
+            }),
+            Expression::Index(index) => {
+                self.visit_expression(&index.object)?;
+                self.visit_expression(&index.index)
+            }
+            Expression::Slice(slice) => {
+                self.visit_expression(&slice.object)?;
+                [&slice.start, &slice.end]
+                    .into_iter()
+                    .flatten()
+                    .try_for_each(|bound| self.visit_expression(bound))
+            }
+            Expression::IndexSet(set) => {
+                self.visit_expression(&set.object)?;
+                self.visit_expression(&set.index)?;
+                self.visit_expression(&set.value)
+            }
+            Expression::Super(sup) => {
+                self.set_location(&sup.keyword.debug_info);
+                match self.classes.last() {
+                    None => return Err(self.error(message!(SuperOutsideClass))),
+                    Some(ClassKind::Class) => {
+                        return Err(self.error(message!(SuperWithoutSuperclass)))
+                    }
+                    Some(ClassKind::Subclass) => {}
+                }
+                self.visit_identifier(&sup.keyword)?;
+                self.visit_identifier(&sup.this)
+            }
         }
     }
 
     fn visit_identifier(&mut self, identifier: &Identifier) -> Result<(), Error> {
         self.set_location(&identifier.debug_info);
 
+        if identifier.name == "this" && self.classes.is_empty() {
+            return Err(self.error(message!(ThisOutsideClass)));
+        }
+
         if self
             .scopes
             .last()
             .and_then(|scope| scope.get(&identifier.name))
-            .is_some_and(|defined| *defined == false)
+            .is_some_and(|binding| !binding.defined)
         {
-            return Err(self.error("Can't read local variable in its initializer."));
+            return Err(self.error(message!(ReadInInitializer)));
         }
 
+        self.mark_used(&identifier.name);
         self.resolve_local_identifier(identifier.id, identifier.name.clone())?;
         Ok(())
     }
 
-    fn error<S: Into<String>>(&self, message: S) -> Error {
+    fn error<S: Into<Message>>(&self, message: S) -> Error {
         Error::ResolverError {
             line: self.line,
             position: self.position,
+            end: self.end,
             message: message.into(),
         }
     }
@@ -261,26 +760,21 @@ impl Resolver {
     fn set_location(&mut self, debug_info: &DebugInfo) {
         self.line = debug_info.line;
         self.position = debug_info.position;
+        self.end = debug_info.span().end;
     }
 }
 
-pub fn resolve(statements: &Vec<Statement>) -> Result<AccessTable, Error> {
-    let mut resolver = Resolver {
-        line: 0,
-        position: 0,
-        access_table: AccessTable::empty(),
-        scopes: Vec::new(),
-    };
-
+pub fn resolve(statements: &[Statement]) -> Result<AccessTable, Error> {
+    let mut resolver = Resolver::new();
     resolver.resolve(statements)?;
 
-    return Ok(resolver.access_table);
+    Ok(resolver.access_table)
 }
 
 #[test]
 fn test_resolver() {
     use crate::interpreter::Interpreter;
-    use crate::lox_function::ForeinFun;
+    use crate::lox_function::{ForeinFun, NativeContext};
     use crate::lox_value::LoxValue;
     use crate::parser::Parser;
     use crate::resolver;
@@ -308,14 +802,15 @@ fn test_resolver() {
             line: 0,
             position: 0,
             lexeme: "<native test>".to_owned(),
+            offset: 0,
         },
     };
 
-    static mut VALUES_OF_A: Vec<String> = Vec::new();
-    fn test(_env: &mut Interpreter, args: Box<[LoxValue]>) -> Result<LoxValue, Error> {
-        unsafe {
-            VALUES_OF_A.push(args[0].to_string());
-        }
+    thread_local! {
+        static VALUES_OF_A: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(Vec::new()) };
+    }
+    fn test(ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+        VALUES_OF_A.with_borrow_mut(|values| values.push(ctx.arg(0).to_string()));
         Ok(LoxValue::Nil)
     }
 
@@ -328,7 +823,5 @@ fn test_resolver() {
 
     interp.execute(&tree, access_table).unwrap();
 
-    unsafe {
-        assert_eq!(VALUES_OF_A, ["global", "global"]);
-    }
+    VALUES_OF_A.with_borrow(|values| assert_eq!(values, &["global", "global"]));
 }
@@ -1,76 +1,96 @@
-use crate::{Error, Token, TokenType};
+use std::collections::HashSet;
 
-pub fn from_slice<'a, 'b>(
-    source: &'a str,
-    line: &'b mut usize,
-    line_position: &'b mut usize,
-) -> Result<(Token, &'a str), Error> {
-    let characters_skipped = skip_whitespace_characters(source, line, line_position);
-    let line = *line;
-    let position = *line_position;
+use crate::diagnostic::Span;
+use crate::messages::{message, Message};
+use crate::shared::Rc;
+use crate::source_map::{SourceMap, BYTE_ORDER_MARK};
+use crate::tokens::StringPart;
+use crate::{Error, LosslessToken, Token, TokenType, Trivia};
+
+/// Scans a single token from `source` starting at byte `offset`,
+/// returns the token and the offset just past it.
+pub fn from_slice(
+    source: &str,
+    offset: usize,
+    source_map: &SourceMap,
+    interner: &mut Interner,
+) -> Result<(Token, usize), Error> {
+    let start = offset + skip_whitespace_characters(&source[offset..]);
+    let (line, position) = source_map.location(start);
 
-    let source = &source[characters_skipped..];
+    let whole_source = source;
+    let source = &source[start..];
 
     // rozpoznanie lekseme
     let (token_type, token_len) = match source.as_bytes() {
         [b'(', ..] => (TokenType::LeftParen, 1),
         [b')', ..] => (TokenType::RightParen, 1),
         [b'{', ..] => (TokenType::LeftBrace, 1),
+        [b'[', ..] => (TokenType::LeftBracket, 1),
+        [b']', ..] => (TokenType::RightBracket, 1),
         [b'}', ..] => (TokenType::RightBrace, 1),
         [b',', ..] => (TokenType::Comma, 1),
+        [b'.', b'.', b'.', ..] => (TokenType::DotDotDot, 3),
         [b'.', ..] => (TokenType::Dot, 1),
         [b'-', ..] => (TokenType::Minus, 1),
         [b'+', ..] => (TokenType::Plus, 1),
         [b';', ..] => (TokenType::Semicolon, 1),
+        [b':', ..] => (TokenType::Colon, 1),
         [b'/', ..] => (TokenType::Slash, 1),
         [b'*', ..] => (TokenType::Star, 1),
+        [b'&', ..] => (TokenType::Ampersand, 1),
+        [b'|', ..] => (TokenType::Pipe, 1),
+        [b'^', ..] => (TokenType::Caret, 1),
+        [b'?', b'?', ..] => (TokenType::QuestionQuestion, 2),
+        [b'?', b'.', ..] => (TokenType::QuestionDot, 2),
         [b'!', b'=', ..] => (TokenType::BangEqual, 2),
         [b'!', ..] => (TokenType::Bang, 1),
         [b'=', b'=', ..] => (TokenType::EqualEqual, 2),
+        [b'=', b'>', ..] => (TokenType::FatArrow, 2),
         [b'=', ..] => (TokenType::Equal, 1),
+        [b'>', b'>', ..] => (TokenType::GreaterGreater, 2),
         [b'>', b'=', ..] => (TokenType::GreaterEqual, 2),
         [b'>', ..] => (TokenType::Greater, 1),
+        [b'<', b'<', ..] => (TokenType::LessLess, 2),
         [b'<', b'=', ..] => (TokenType::LessEqual, 2),
         [b'<', ..] => (TokenType::Less, 1),
-        [b'A'..=b'Z' | b'a'..=b'z' | b'_', ..] => {
+        [b'~', b'/', ..] => (TokenType::TildeSlash, 2),
+        _ if source.starts_with(is_identifier_start) => {
             let s = find_identifier(source);
 
             if let Some(token_type) = crate::tokens::parse_keyword(s) {
                 (token_type, s.len())
             } else {
-                (TokenType::Identifier(String::from(s)), s.len())
+                (TokenType::Identifier(interner.intern(s)), s.len())
             }
         }
-        [b'"', ..] => {
-            if let Ok(s) = find_string_literal(source) {
-                // println!("s: {}", s);
-                (TokenType::String(String::from(s)), s.len() + 2)
-            } else {
+        [b'"', ..] => string_literal(whole_source, start, source_map, interner)?,
+        [b'`', ..] => match source[1..].find('`') {
+            // raw strings may span lines, positions of the following
+            // tokens come from their byte offsets so they stay accurate
+            Some(len) => (TokenType::String(String::from(&source[1..=len])), len + 2),
+            None => {
                 return Err(Error::SyntaxError {
                     line,
                     position,
-                    message: String::from("Error while building a string."),
+                    end: Span::of_text(line, position, source).end,
+                    message: message!(UnterminatedString, line = line, position = position),
                 });
             }
+        },
+        [b'0', b'x' | b'X' | b'o' | b'O' | b'b' | b'B', ..] => {
+            let (n, len) = find_radix_numeric(source)
+                .map_err(|message| malformed_number(source, line, position, message))?;
+            (TokenType::Number(n), len)
         }
         [b'0'..=b'9', ..] => {
-            if let Ok(numeric) = find_numeric(source) {
-                if let Ok(n) = numeric.parse() {
-                    (TokenType::Number(n), numeric.len())
-                } else {
-                    println!("numeric: {}", numeric);
-                    return Err(Error::SyntaxError {
-                        line,
-                        position,
-                        message: String::from("Error while parsing a numeric"),
-                    });
-                }
+            let numeric = find_numeric(source)
+                .map_err(|message| malformed_number(source, line, position, message))?;
+            if let Ok(n) = numeric.replace('_', "").parse() {
+                (TokenType::Number(n), numeric.len())
             } else {
-                return Err(Error::SyntaxError {
-                    line,
-                    position,
-                    message: String::from("Error while building a numeric."),
-                });
+                let message = message!(InvalidNumber, lexeme = numeric);
+                return Err(malformed_number(source, line, position, message));
             }
         }
         [] => (TokenType::Eof, 0),
@@ -78,60 +98,135 @@ pub fn from_slice<'a, 'b>(
             return Err(Error::SyntaxError {
                 line,
                 position,
-                message: format!("Unexpected character: {}", &source[0..1]),
+                end: position + 1,
+                message: message!(
+                    UnexpectedCharacter,
+                    character = source.chars().next().unwrap_or_default()
+                ),
             });
         }
     };
-    *line_position += token_len;
 
-    return Ok((
+    Ok((
         Token {
             token_type,
-            lexeme: String::from(&source[0..token_len]),
+            lexeme: interner.intern(&source[0..token_len]),
             line,
             position,
+            offset: start,
         },
-        &source[token_len..],
-    ));
+        start + token_len,
+    ))
 }
 
-fn find_numeric(source: &str) -> Result<&str, ()> {
-    let mut len = 0;
-    for c in source.chars() {
-        match c {
-            '0'..='9' => {
-                len += 1;
-            }
-            '.' => {
-                break;
-            }
-            _ => {
-                return Ok(&source[0..len]);
-            }
+/// Lexemes scanned so far, every token with the same text shares one
+/// string instead of allocating its own.
+#[derive(Default)]
+pub struct Interner(HashSet<Rc<str>>);
+
+impl Interner {
+    pub fn intern(&mut self, text: &str) -> Rc<str> {
+        if let Some(interned) = self.0.get(text) {
+            return interned.clone();
         }
+        let interned = Rc::<str>::from(text);
+        self.0.insert(interned.clone());
+        interned
     }
-    if source.chars().nth(len + 1).unwrap_or(' ').is_digit(10) {
-        // if there is a digit after '.', then continue finidng digits
-        len += 1;
-        for c in source[len..].chars() {
-            match c {
-                '0'..='9' => {
-                    len += 1;
-                }
-                _ => {
-                    return Ok(&source[0..len]);
-                }
-            }
+}
+
+/// Error about the number literal `source` starts with, at
+/// `line:position`.
+fn malformed_number(source: &str, line: usize, position: usize, message: Message) -> Error {
+    let literal = &source[..number_end(source)];
+    Error::SyntaxError {
+        line,
+        position,
+        end: Span::of_text(line, position, literal).end,
+        message,
+    }
+}
+
+/// Returns the end of the run of digits and `_` separators starting at
+/// byte `from`, separators have to be single and surrounded by digits.
+fn digit_run(source: &str, from: usize, radix: u32) -> Result<usize, Message> {
+    let end = from
+        + source[from..]
+            .chars()
+            .take_while(|c| c.is_digit(radix) || *c == '_')
+            .count();
+
+    let run = &source[from..end];
+    if run.starts_with('_') || run.ends_with('_') || run.contains("__") {
+        let lexeme = &source[..end];
+        return Err(message!(MisplacedSeparator, lexeme = lexeme));
+    }
+    Ok(end)
+}
+
+/// Returns the number literal at the start of `source`, like `12`, `1.5`,
+/// `2.5e-3` or `1_000`, or a message describing why it is malformed.
+fn find_numeric(source: &str) -> Result<&str, Message> {
+    let bytes = source.as_bytes();
+    let digits = |from: usize| digit_run(source, from, 10);
+
+    let mut len = digits(0)?;
+    // without a digit after it the '.' is not part of the number
+    if bytes.get(len) == Some(&b'.') && bytes.get(len + 1).is_some_and(u8::is_ascii_digit) {
+        len = digits(len + 1)?;
+    }
+    if let Some(b'e' | b'E') = bytes.get(len) {
+        let mut exponent = len + 1;
+        if let Some(b'+' | b'-') = bytes.get(exponent) {
+            exponent += 1;
+        }
+        len = digits(exponent)?;
+        if len == exponent {
+            let lexeme = &source[..exponent];
+            return Err(message!(MalformedExponent, lexeme = lexeme));
         }
     }
+
     Ok(&source[0..len])
 }
 
-fn skip_whitespace_characters(source: &str, line: &mut usize, position: &mut usize) -> usize {
+/// Scans an integer literal with a `0x`, `0o` or `0b` prefix, returns its
+/// value and length or a message describing why it is malformed.
+fn find_radix_numeric(source: &str) -> Result<(f64, usize), Message> {
+    let (radix, base) = match source.as_bytes()[1] {
+        b'x' | b'X' => (16, "hexadecimal"),
+        b'o' | b'O' => (8, "octal"),
+        _ => (2, "binary"),
+    };
+    let prefix = &source[..2];
+
+    let len = digit_run(source, 2, radix)?;
+    // digits of a larger base would otherwise start a new token
+    if let Some(digit) = source[len..].chars().next().filter(|c| c.is_alphanumeric()) {
+        let lexeme = find_identifier(source);
+        return Err(message!(
+            InvalidDigit,
+            digit = digit,
+            base = base,
+            radix = radix,
+            lexeme = lexeme
+        ));
+    }
+    if len == 2 {
+        return Err(message!(MissingDigits, prefix = prefix));
+    }
+
+    let digits = source[2..len].replace('_', "");
+    u64::from_str_radix(&digits, radix)
+        .map(|n| (n as f64, len))
+        .map_err(|_| message!(InvalidNumber, lexeme = &source[..len]))
+}
+
+fn skip_whitespace_characters(source: &str) -> usize {
     let mut characters_skipped = 0;
     let mut handle = source;
 
-    while handle.len() > 0 {
+    while !handle.is_empty() {
         match handle.as_bytes() {
             [b'/', b'/', ..] => {
                 let i = handle.find('\n').unwrap_or(
@@ -139,18 +234,10 @@ fn skip_whitespace_characters(source: &str, line: &mut usize, position: &mut usi
                     // comment at the end of the source code
                     handle.len(),
                 );
-                *position = i;
                 handle = &handle[i..];
                 characters_skipped += i;
             }
-            [b' ' | b'\r' | b'\t', ..] => {
-                *position += 1;
-                handle = &handle[1..];
-                characters_skipped += 1;
-            }
-            [b'\n', ..] => {
-                *line += 1;
-                *position = 1;
+            [b' ' | b'\r' | b'\t' | b'\n', ..] => {
                 handle = &handle[1..];
                 characters_skipped += 1;
             }
@@ -162,57 +249,745 @@ fn skip_whitespace_characters(source: &str, line: &mut usize, position: &mut usi
 
     characters_skipped
 }
-fn find_string_literal(source: &str) -> Result<&str, ()> {
-    let mut len = 0;
-    for c in source.chars().skip(1) {
-        match c {
-            '\n' => {
-                return Err(());
+/// Scans the string literal starting at byte `start` of `source`, returns
+/// its token type and length. Expressions embedded with `${...}` are
+/// scanned as tokens, making the literal an interpolation.
+fn string_literal(
+    source: &str,
+    start: usize,
+    source_map: &SourceMap,
+    interner: &mut Interner,
+) -> Result<(TokenType, usize), Error> {
+    let unterminated = || {
+        let (line, position) = source_map.location(start);
+        Error::SyntaxError {
+            line,
+            position,
+            end: Span::of_text(line, position, &source[start..]).end,
+            message: message!(UnterminatedString, line = line, position = position),
+        }
+    };
+    let text = |from: usize, to: usize| {
+        unescape(&source[from..to]).map_err(|(offset, message)| {
+            // point at the escape sequence rather than the whole literal
+            let (line, position) = source_map.location(from + offset);
+            Error::SyntaxError {
+                line,
+                position,
+                end: position + 1,
+                message,
             }
-            '"' => {
-                break;
+        })
+    };
+
+    let mut parts = Vec::new();
+    let mut text_start = start + 1;
+    let mut offset = start + 1;
+    loop {
+        let rest = &source[offset..];
+        match rest.chars().next() {
+            None | Some('\n') => return Err(unterminated()),
+            Some('"') => break,
+            Some('\\') => {
+                // the escaped character can not end the literal
+                offset += 1 + rest[1..]
+                    .chars()
+                    .next()
+                    .filter(|c| *c != '\n')
+                    .map_or(0, char::len_utf8);
             }
-            _ => {
-                len += 1;
+            Some('$') if rest[1..].starts_with('{') => {
+                parts.push(StringPart::Text(text(text_start, offset)?));
+                offset += 2;
+
+                let mut tokens = Vec::new();
+                let mut depth = 0;
+                loop {
+                    let (mut token, next) = from_slice(source, offset, source_map, interner)?;
+                    offset = next;
+                    match token.token_type {
+                        TokenType::Eof => return Err(unterminated()),
+                        TokenType::LeftBrace => depth += 1,
+                        TokenType::RightBrace if depth == 0 => {
+                            token.token_type = TokenType::Eof;
+                            tokens.push(token);
+                            break;
+                        }
+                        TokenType::RightBrace => depth -= 1,
+                        _ => {}
+                    }
+                    tokens.push(token);
+                }
+                parts.push(StringPart::Expression(tokens));
+                text_start = offset;
             }
+            Some(c) => offset += c.len_utf8(),
         }
     }
+    let last = text(text_start, offset)?;
+    let len = offset + 1 - start;
 
-    Ok(&source[1..=len])
+    if parts.is_empty() {
+        return Ok((TokenType::String(last), len));
+    }
+    parts.push(StringPart::Text(last));
+    Ok((TokenType::Interpolation(parts), len))
 }
-fn find_identifier(source: &str) -> &str {
-    let mut len = 0;
-    while let [b'A'..=b'Z' | b'a'..=b'z' | b'_', ..] = source[len..].as_bytes() {
-        len += 1;
+
+/// Replaces escape sequences in the contents of a string literal, on
+/// failure returns the byte offset of the invalid sequence and a message.
+fn unescape(raw: &str) -> Result<String, (usize, Message)> {
+    let mut value = String::with_capacity(raw.len());
+    let mut rest = raw;
+
+    while let Some(i) = rest.find('\\') {
+        value.push_str(&rest[..i]);
+        let offset = raw.len() - rest.len() + i;
+        let sequence = &rest[i..];
+
+        let (c, len) = match sequence[1..].chars().next() {
+            Some('n') => ('\n', 2),
+            Some('t') => ('\t', 2),
+            Some('r') => ('\r', 2),
+            Some('0') => ('\0', 2),
+            Some('"') => ('"', 2),
+            Some('\\') => ('\\', 2),
+            Some('$') => ('$', 2),
+            Some('u') => {
+                let len = match sequence.find('}') {
+                    Some(end) if sequence[2..].starts_with('{') => end + 1,
+                    _ => 2,
+                };
+                let c = sequence[..len]
+                    .strip_prefix("\\u{")
+                    .and_then(|s| s.strip_suffix('}'))
+                    .filter(|hex| (1..=6).contains(&hex.len()))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| {
+                        let sequence = &sequence[..len];
+                        (offset, message!(InvalidUnicodeEscape, sequence = sequence))
+                    })?;
+                (c, len)
+            }
+            other => {
+                let len = 1 + other.map_or(0, char::len_utf8);
+                let sequence = &sequence[..len];
+                return Err((offset, message!(InvalidEscape, sequence = sequence)));
+            }
+        };
+        value.push(c);
+        rest = &sequence[len..];
     }
+    value.push_str(rest);
+
+    Ok(value)
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+/// Returns the identifier at the start of `source`, letters may come from
+/// any script, digits are allowed after the first character.
+fn find_identifier(source: &str) -> &str {
+    let len = source
+        .char_indices()
+        .find(|(_, c)| !(c.is_alphanumeric() || *c == '_'))
+        .map_or(source.len(), |(i, _)| i);
 
     &source[0..len]
 }
 
-pub fn scan_tokens(source: &String) -> Result<Vec<Token>, Error> {
-    let mut tokens = Vec::new();
+/// Splits `skipped`, a run of whitespace and comments, into trivia.
+fn trivia(skipped: &str) -> Vec<Trivia> {
+    let mut trivia = Vec::new();
+    let mut rest = skipped;
+    while !rest.is_empty() {
+        if rest.starts_with("//") {
+            // the line break and spaces before it are whitespace
+            let line_end = rest.find('\n').unwrap_or(rest.len());
+            let text = rest[..line_end].trim_end();
+            trivia.push(Trivia::Comment(text.to_owned()));
+            rest = &rest[text.len()..];
+        } else {
+            let len = rest.find("//").unwrap_or(rest.len());
+            trivia.push(Trivia::Whitespace(rest[..len].to_owned()));
+            rest = &rest[len..];
+        }
+    }
+    trivia
+}
 
-    let mut slice_handle = source.as_str();
-    let mut line_number = 1usize;
-    let mut line_position = 1usize;
+/// Decodes the bytes of a source file, invalid UTF-8 is reported as a
+/// syntax error at the first byte that is not part of a character.
+pub fn decode(bytes: &[u8]) -> Result<&str, Error> {
+    std::str::from_utf8(bytes).map_err(|error| {
+        let valid = error.valid_up_to();
+        let prefix = std::str::from_utf8(&bytes[..valid]).expect("checked by from_utf8");
+        let (line, position) = SourceMap::new(prefix).location(valid);
+        Error::SyntaxError {
+            line,
+            position,
+            end: position + 1,
+            message: message!(InvalidUtf8, byte = format!("{:#04x}", bytes[valid])),
+        }
+    })
+}
+
+/// Returns the tokens of `source`, or every error in it.
+pub fn scan_tokens(source: &str) -> Result<Vec<Token>, Vec<Error>> {
+    match scan_recovering(source) {
+        (tokens, errors) if errors.is_empty() => Ok(tokens),
+        (_, errors) => Err(errors),
+    }
+}
+
+/// Scans `source` without losing any of it, every token comes with the
+/// whitespace and comments in front of it, for tools which have to write
+/// the source back.
+pub fn scan_lossless(source: &str) -> Result<Vec<LosslessToken>, Vec<Error>> {
+    let tokens = scan_tokens(source)?;
+    let mut end = preamble_len(source);
+    let mut preamble = match end {
+        0 => Vec::new(),
+        len => vec![Trivia::Preamble(source[..len].to_owned())],
+    };
+
+    let tokens = tokens
+        .into_iter()
+        .map(|token| {
+            let mut trivia = std::mem::take(&mut preamble);
+            trivia.extend(self::trivia(&source[end..token.offset]));
+            end = token.offset + token.lexeme.len();
+            LosslessToken { trivia, token }
+        })
+        .collect();
+    Ok(tokens)
+}
+
+/// Scans all of `source` even if it has errors, returning the tokens
+/// found around them and the errors. Scanning goes on after the character
+/// which is not a token, the number literal or the line of the string
+/// literal an error is in, so a typo leaves the rest of the tokens intact.
+pub fn scan_recovering(source: &str) -> (Vec<Token>, Vec<Error>) {
+    collect(tokens(source))
+}
+
+/// Scans `source` lazily, one token at a time. Errors are recovered
+/// from like in [`scan_recovering`], the last item is the `Eof` token.
+pub fn tokens(source: &str) -> impl Iterator<Item = Result<Token, Error>> + '_ {
+    Tokens::new(source)
+}
 
-    while slice_handle.len() > 0 {
-        let token;
-        (token, slice_handle) = from_slice(slice_handle, &mut line_number, &mut line_position)?;
-        tokens.push(token);
+fn collect(tokens: impl Iterator<Item = Result<Token, Error>>) -> (Vec<Token>, Vec<Error>) {
+    let mut errors = Vec::new();
+    let tokens = tokens
+        .filter_map(|token| token.map_err(|error| errors.push(error)).ok())
+        .collect();
+    (tokens, errors)
+}
+
+struct Tokens<'a> {
+    source: &'a str,
+    source_map: SourceMap<'a>,
+    /// byte offset the next token is scanned from, `None` after `Eof`
+    offset: Option<usize>,
+    interner: Interner,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(source: &'a str) -> Self {
+        Tokens {
+            source,
+            source_map: SourceMap::new(source),
+            offset: Some(preamble_len(source)),
+            interner: Interner::default(),
+        }
     }
+}
+
+/// Length of the byte order mark and `#!/usr/bin/env rlox` line `source`
+/// may start with, the line lets scripts be made executable.
+fn preamble_len(source: &str) -> usize {
+    let bom = match source.starts_with(BYTE_ORDER_MARK) {
+        true => BYTE_ORDER_MARK.len_utf8(),
+        false => 0,
+    };
+    match source[bom..].starts_with("#!") {
+        true => source[bom..].find('\n').map_or(source.len(), |i| bom + i),
+        false => bom,
+    }
+}
 
-    let (line_number, line_position) = tokens
-        .last()
-        .map(|token| (token.line, token.position))
-        .unwrap_or((1usize, 1usize));
+impl Iterator for Tokens<'_> {
+    type Item = Result<Token, Error>;
 
-    tokens.push(Token {
-        token_type: TokenType::Eof,
-        lexeme: String::from(""),
-        line: line_number,
-        position: line_position + 1,
-    });
+    fn next(&mut self) -> Option<Self::Item> {
+        let (source, offset) = (self.source, self.offset?);
+        let start = offset + skip_whitespace_characters(&source[offset..]);
 
-    return Ok(tokens);
+        match from_slice(source, offset, &self.source_map, &mut self.interner) {
+            Ok((token, end)) => {
+                let is_eof = matches!(token.token_type, TokenType::Eof);
+                self.offset = (!is_eof).then_some(end);
+                Some(Ok(token))
+            }
+            Err(error) => {
+                self.offset = Some(recovery_point(source, start));
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// Byte offset to go on scanning from after an error in the token
+/// starting at byte `start`, always past `start`.
+fn recovery_point(source: &str, start: usize) -> usize {
+    let rest = &source[start..];
+    let len = match rest.as_bytes() {
+        [b'"', ..] => string_end(rest),
+        // nothing but the closing '`' ends a raw string
+        [b'`', ..] => rest.find('\n').unwrap_or(rest.len()),
+        [b'0'..=b'9', ..] => number_end(rest),
+        _ => rest.chars().next().map_or(1, char::len_utf8),
+    };
+    start + len
+}
+
+/// Length of the string literal `rest` starts with, up to its closing
+/// quote or the end of the line if it is unterminated.
+fn string_end(rest: &str) -> usize {
+    let mut chars = rest.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return i + 1,
+            '\n' => return i,
+            '\\' if !rest[i + 1..].starts_with('\n') => {
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+    rest.len()
+}
+
+/// Length of the malformed number literal `rest` starts with: its digits,
+/// letters, separators, fraction and exponent sign.
+fn number_end(rest: &str) -> usize {
+    let decimal = !rest
+        .get(..2)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("0x"));
+    let mut end = 0;
+    let mut previous = '0';
+    for (i, c) in rest.char_indices() {
+        let continues = c.is_alphanumeric()
+            || c == '_'
+            || (c == '.' && rest[i + 1..].starts_with(|c: char| c.is_ascii_digit()))
+            || (decimal && matches!(c, '+' | '-') && matches!(previous, 'e' | 'E'));
+        if !continues {
+            break;
+        }
+        end = i + c.len_utf8();
+        previous = c;
+    }
+    end
+}
+
+#[test]
+fn test_positions_with_tabs() {
+    let tokens = scan_tokens("\tvar a;\n  a\t= 1;").unwrap();
+    let positions: Vec<(usize, usize)> = tokens.iter().map(|t| (t.line, t.position)).collect();
+
+    assert_eq!(
+        positions[..7],
+        [(1, 5), (1, 9), (1, 10), (2, 3), (2, 5), (2, 7), (2, 8)]
+    );
+}
+
+#[test]
+fn test_positions_with_crlf() {
+    let tokens = scan_tokens("var a;\r\n\r\nprint a; // comment\r\nb;").unwrap();
+    let positions: Vec<(usize, usize)> = tokens.iter().map(|t| (t.line, t.position)).collect();
+
+    assert_eq!(
+        positions[..8],
+        [
+            (1, 1),
+            (1, 5),
+            (1, 6),
+            (3, 1),
+            (3, 7),
+            (3, 8),
+            (4, 1),
+            (4, 2)
+        ]
+    );
+}
+
+#[test]
+fn test_positions_with_utf8() {
+    let tokens = scan_tokens("print \"zażółć\" + \"🦀\" + x;").unwrap();
+    let positions: Vec<usize> = tokens.iter().map(|t| t.position).collect();
+
+    assert!(matches!(&tokens[1].token_type, TokenType::String(s) if s == "zażółć"));
+    assert_eq!(positions[..7], [1, 7, 16, 18, 22, 24, 25]);
+}
+
+#[test]
+fn test_eof_after_trailing_comment() {
+    let tokens = scan_tokens("a;\n// comment").unwrap();
+
+    assert_eq!(tokens.len(), 3);
+    assert!(matches!(tokens[2].token_type, TokenType::Eof));
+    assert_eq!((tokens[2].line, tokens[2].position), (2, 11));
+}
+
+#[test]
+fn test_string_escapes() {
+    let string = |source: &str| match scan_tokens(source).unwrap().remove(0).token_type {
+        TokenType::String(s) => s,
+        other => panic!("expected a string, got {other:?}"),
+    };
+    assert_eq!(string(r#""a\nb\t\"c\"\\""#), "a\nb\t\"c\"\\");
+    assert_eq!(string(r#""\u{41}\u{17c}\u{1F980}""#), "Aż🦀");
+    assert_eq!(string(r#""ends with \\""#), "ends with \\");
+
+    for (source, expected) in [
+        (
+            r#"var s = "ab\q";"#,
+            (1, 12, "Invalid escape sequence '\\q'."),
+        ),
+        (
+            "\n  \"ż\\u{110000}\";",
+            (
+                2,
+                5,
+                "Invalid unicode escape '\\u{110000}', expected 1 to 6 hex digits in braces.",
+            ),
+        ),
+        (
+            r#""\u41";"#,
+            (
+                1,
+                2,
+                "Invalid unicode escape '\\u', expected 1 to 6 hex digits in braces.",
+            ),
+        ),
+    ] {
+        match scan_tokens(source).unwrap_err().as_slice() {
+            [Error::SyntaxError {
+                line,
+                position,
+                message,
+                ..
+            }] => assert_eq!((*line, *position, message.as_str()), expected),
+            other => panic!("expected a syntax error, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_raw_strings() {
+    let tokens = scan_tokens("print `a\\n\"b\"\n  ż`;\nx;").unwrap();
+
+    assert!(matches!(&tokens[1].token_type, TokenType::String(s) if s == "a\\n\"b\"\n  ż"));
+    let positions: Vec<(usize, usize)> = tokens.iter().map(|t| (t.line, t.position)).collect();
+    assert_eq!(positions[..5], [(1, 1), (1, 7), (2, 5), (3, 1), (3, 2)]);
+
+    assert!(matches!(
+        scan_tokens("var s = `never\nclosed;")
+            .unwrap_err()
+            .as_slice(),
+        [Error::SyntaxError {
+            line: 1,
+            position: 9,
+            ..
+        }]
+    ));
+}
+
+#[test]
+fn test_shebang() {
+    let tokens = scan_tokens("#!/usr/bin/env rlox\nprint 1;").unwrap();
+    assert!(matches!(tokens[0].token_type, TokenType::Print));
+    assert_eq!((tokens[0].line, tokens[0].position), (2, 1));
+
+    assert_eq!(scan_tokens("#!/usr/bin/env rlox").unwrap().len(), 1);
+    assert!(scan_tokens("print 1;\n#!not a shebang").is_err());
+}
+
+#[test]
+fn test_byte_order_mark() {
+    let tokens = scan_tokens("\u{feff}print 1;\r\nprint 2;").unwrap();
+    assert!(matches!(tokens[0].token_type, TokenType::Print));
+    assert_eq!((tokens[0].line, tokens[0].position), (1, 1));
+    assert_eq!((tokens[3].line, tokens[3].position), (2, 1));
+
+    let tokens = scan_tokens("\u{feff}#!/usr/bin/env rlox\nprint 1;").unwrap();
+    assert_eq!((tokens[0].line, tokens[0].position), (2, 1));
+}
+
+#[test]
+fn test_invalid_utf8() {
+    assert_eq!(decode("żółw".as_bytes()).unwrap(), "żółw");
+
+    match decode(b"print 1;\nprint \"\xc5\xbc\xff\";") {
+        Err(Error::SyntaxError {
+            line,
+            position,
+            message,
+            ..
+        }) => {
+            assert_eq!((line, position), (2, 9));
+            assert!(message.contains("0xff"), "{message}");
+        }
+        other => panic!("expected a syntax error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_unicode_identifiers() {
+    let tokens = scan_tokens("var zażółć_2 = \"🦀\"; πr2 + zażółć_2;").unwrap();
+    let names: Vec<&str> = tokens
+        .iter()
+        .filter_map(|t| match &t.token_type {
+            TokenType::Identifier(name) => Some(&**name),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(names, ["zażółć_2", "πr2", "zażółć_2"]);
+
+    let positions: Vec<usize> = tokens.iter().map(|t| t.position).collect();
+    assert_eq!(positions[..8], [1, 5, 14, 16, 19, 21, 25, 27]);
+
+    assert!(matches!(
+        scan_tokens("var a = 1 § 2;").unwrap_err().as_slice(),
+        [Error::SyntaxError { position: 11, .. }]
+    ));
+}
+
+#[test]
+fn test_number_literals() {
+    let number = |source: &str| match scan_tokens(source).unwrap()[0].token_type {
+        TokenType::Number(n) => n,
+        ref other => panic!("expected a number, got {other:?}"),
+    };
+    assert_eq!(number("12"), 12.);
+    assert_eq!(number("1.5"), 1.5);
+    assert_eq!(number("1e9"), 1e9);
+    assert_eq!(number("2.5e-3"), 2.5e-3);
+    assert_eq!(number("4E+2"), 400.);
+    assert_eq!(number("0xff"), 255.);
+    assert_eq!(number("0XdeadBEEF"), 3735928559.);
+    assert_eq!(number("0o17"), 15.);
+    assert_eq!(number("0b1010"), 10.);
+    assert_eq!(number("1_000_000"), 1e6);
+    assert_eq!(number("1.234_5"), 1.2345);
+    assert_eq!(number("1e1_0"), 1e10);
+    assert_eq!(number("0b1111_0000"), 240.);
+
+    // a '.' without digits after it is not part of the number
+    let tokens = scan_tokens("1.foo").unwrap();
+    assert!(matches!(tokens[1].token_type, TokenType::Dot));
+
+    for (source, expected) in [
+        (
+            "1e",
+            "Malformed exponent in number literal '1e', expected digits after it.",
+        ),
+        (
+            "var x = 2.5e-;",
+            "Malformed exponent in number literal '2.5e-', expected digits after it.",
+        ),
+        ("0b102;", "Invalid digit '2' in binary literal '0b102'."),
+        ("0o9", "Invalid digit '9' in octal literal '0o9'."),
+        ("0xfg", "Invalid digit 'g' in hexadecimal literal '0xfg'."),
+        ("0x;", "Expected digits after '0x'."),
+        (
+            "1__000",
+            "Misplaced '_' in number literal '1__000', separators go between single digits.",
+        ),
+        (
+            "1000_;",
+            "Misplaced '_' in number literal '1000_', separators go between single digits.",
+        ),
+        (
+            "0x_ff",
+            "Misplaced '_' in number literal '0x_ff', separators go between single digits.",
+        ),
+        (
+            "1e_5",
+            "Misplaced '_' in number literal '1e_5', separators go between single digits.",
+        ),
+        (
+            "0x1ffffffffffffffff",
+            "Invalid number literal '0x1ffffffffffffffff'.",
+        ),
+    ] {
+        match scan_tokens(source).unwrap_err().as_slice() {
+            [Error::SyntaxError { message, .. }] => assert_eq!(message, expected),
+            other => panic!("expected a syntax error, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_error_recovery() {
+    let source =
+        "var a = 1 § 2;\nprint \"ab\\q\" + 0b12 + 2.5e-;\nvar s = \"open;\nprint `raw;\nprint s;";
+    let (tokens, errors) = scan_recovering(source);
+
+    let locations: Vec<(usize, usize)> = errors
+        .iter()
+        .map(|error| match error {
+            Error::SyntaxError { line, position, .. } => (*line, *position),
+            other => panic!("expected a syntax error, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        locations,
+        [(1, 11), (2, 10), (2, 16), (2, 23), (3, 9), (4, 7)]
+    );
+
+    let lexemes: Vec<&str> = tokens.iter().map(|t| &*t.lexeme).collect();
+    assert_eq!(
+        lexemes,
+        [
+            "var", "a", "=", "1", "2", ";", "print", "+", "+", ";", "var", "s", "=", "print",
+            "print", "s", ";", ""
+        ]
+    );
+}
+
+#[test]
+fn test_unterminated_strings() {
+    for (source, expected) in [
+        ("print \"open", "Unterminated string starting at 1:7."),
+        (
+            "var a = 1;\n  print \"a\" + \"b;\nprint a;",
+            "Unterminated string starting at 2:15.",
+        ),
+        (
+            "print `raw\nstill raw",
+            "Unterminated string starting at 1:7.",
+        ),
+        (
+            "var s = \"x ${\"y\"",
+            "Unterminated string starting at 1:9.",
+        ),
+    ] {
+        match &scan_tokens(source).unwrap_err()[0] {
+            Error::SyntaxError { message, .. } => assert_eq!(message, expected, "{source}"),
+            other => panic!("expected a syntax error, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_interned_lexemes() {
+    let tokens = scan_tokens("var total = 1; total = total + 1;").unwrap();
+    let TokenType::Identifier(name) = &tokens[1].token_type else {
+        panic!("expected an identifier, got {:?}", tokens[1]);
+    };
+    assert!(Rc::ptr_eq(name, &tokens[1].lexeme));
+    assert!(Rc::ptr_eq(&tokens[1].lexeme, &tokens[5].lexeme));
+    assert!(Rc::ptr_eq(&tokens[1].lexeme, &tokens[7].lexeme));
+    assert!(Rc::ptr_eq(&tokens[4].lexeme, &tokens[10].lexeme));
+}
+
+#[test]
+fn test_lazy_tokens() {
+    let mut tokens = tokens("print 1;\nprint § 2;");
+    let first: Vec<TokenType> = tokens
+        .by_ref()
+        .take(3)
+        .map(|token| token.unwrap().token_type)
+        .collect();
+    assert!(matches!(
+        first[..],
+        [TokenType::Print, TokenType::Number(_), TokenType::Semicolon]
+    ));
+
+    let rest: Vec<_> = tokens.collect();
+    assert_eq!(rest.len(), 5);
+    assert!(matches!(rest[1], Err(Error::SyntaxError { line: 2, .. })));
+    assert!(matches!(
+        rest.last(),
+        Some(Ok(Token {
+            token_type: TokenType::Eof,
+            ..
+        }))
+    ));
+}
+
+#[test]
+fn test_lossless_tokens() {
+    let source =
+        "\u{feff}#!/usr/bin/env rlox\r\nprint \"${1}\";  // one  \r\n\r\n// two\nprint `a\nb`;\n";
+    let tokens = scan_lossless(source).unwrap();
+
+    let mut text = String::new();
+    for LosslessToken { trivia, token } in &tokens {
+        for trivia in trivia {
+            match trivia {
+                Trivia::Whitespace(s) | Trivia::Comment(s) | Trivia::Preamble(s) => {
+                    text.push_str(s)
+                }
+            }
+        }
+        text.push_str(&token.lexeme);
+    }
+    assert_eq!(text, source);
+
+    assert!(matches!(
+        &tokens[0].trivia[..],
+        [Trivia::Preamble(_), Trivia::Whitespace(_)]
+    ));
+    assert!(matches!(
+        &tokens[3].trivia[..],
+        [
+            Trivia::Whitespace(_),
+            Trivia::Comment(one),
+            Trivia::Whitespace(_),
+            Trivia::Comment(two),
+            Trivia::Whitespace(_)
+        ] if one == "// one" && two == "// two"
+    ));
+}
+
+#[test]
+fn test_token_spans() {
+    let tokens = scan_tokens("var\tname = \"żółw\" + `a\nb`;").unwrap();
+    let spans: Vec<(usize, usize, usize)> = tokens
+        .iter()
+        .map(|t| {
+            let span = t.span();
+            (span.line, span.position, span.end)
+        })
+        .collect();
+    assert_eq!(
+        spans,
+        [
+            (1, 1, 4),
+            (1, 5, 9),
+            (1, 10, 11),
+            (1, 12, 18),
+            (1, 19, 20),
+            (1, 21, 23),
+            (2, 3, 4),
+            (2, 4, 5)
+        ]
+    );
+
+    let source = "var\tname = \"żółw\" + `a\nb`;";
+    for token in scan_tokens(source).unwrap() {
+        let text = &source[token.offset..token.offset + token.lexeme.len()];
+        assert_eq!(text, &*token.lexeme);
+    }
+
+    match scan_tokens("print 0b102 + 1;").unwrap_err().as_slice() {
+        [Error::SyntaxError { position, end, .. }] => assert_eq!((*position, *end), (7, 12)),
+        other => panic!("expected a syntax error, got {other:?}"),
+    }
 }
@@ -0,0 +1,450 @@
+//! Compact rendering of syntax trees as S-expressions, shown by
+//! `rlox --print-ast`:
+//!
+//! ```text
+//! (fun fib (n)
+//!   (if (< n 2)
+//!     (then
+//!       (return n)))
+//!   (return (+ (call fib (- n 1)) (call fib (- n 2)))))
+//! ```
+//!
+//! Expressions stay on one line, statements holding other statements put
+//! each of them on a line of its own, indented under the parent.
+
+use std::fmt::Write;
+
+use crate::expression::{BinaryOperator, Expression, LiteralValue, LogicalOperator, UnaryOperator};
+use crate::statement::{Block, FunctionDeclaration, Statement};
+
+const INDENT: &str = "  ";
+
+/// Renders `program`, one top-level statement per line.
+pub fn program(program: &[Statement]) -> String {
+    let mut printer = Printer { out: String::new() };
+    for statement in program {
+        printer.statement(statement, 0);
+        printer.out.push('\n');
+    }
+    printer.out
+}
+
+struct Printer {
+    out: String,
+}
+
+impl Printer {
+    fn statement(&mut self, statement: &Statement, indent: usize) {
+        match statement {
+            Statement::Expression(expression) => self.expression(expression),
+            Statement::Print(expression) => self.form("print", [expression]),
+            Statement::Variable {
+                name,
+                initializer,
+                constant,
+            } => {
+                let keyword = if *constant { "const" } else { "var" };
+                let _ = write!(self.out, "({keyword} {}", name.name);
+                if let Some(initializer) = initializer {
+                    self.out.push(' ');
+                    self.expression(initializer);
+                }
+                self.out.push(')');
+            }
+            Statement::Block(block) => {
+                self.out.push_str("(block");
+                self.body(block, indent + 1);
+                self.out.push(')');
+            }
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.out.push_str("(if ");
+                self.expression(condition);
+                self.branch("then", then_branch, indent + 1);
+                if let Some(else_branch) = else_branch {
+                    self.branch("else", else_branch, indent + 1);
+                }
+                self.out.push(')');
+            }
+            Statement::While {
+                condition,
+                body,
+                increment,
+            } => {
+                self.out.push_str("(while ");
+                self.expression(condition);
+                if let Some(increment) = increment {
+                    self.line(indent + 1);
+                    self.form("increment", [increment]);
+                }
+                self.body(body, indent + 1);
+                self.out.push(')');
+            }
+            Statement::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                self.out.push_str("(for ");
+                match initializer {
+                    Some(initializer) => self.statement(initializer, indent),
+                    None => self.out.push('_'),
+                }
+                for clause in [condition, increment] {
+                    self.out.push(' ');
+                    match clause {
+                        Some(clause) => self.expression(clause),
+                        None => self.out.push('_'),
+                    }
+                }
+                self.body(body, indent + 1);
+                self.out.push(')');
+            }
+            Statement::ForIn {
+                variable,
+                iterable,
+                body,
+                ..
+            } => {
+                let _ = write!(self.out, "(for-in {} ", variable.name);
+                self.expression(iterable);
+                self.body(body, indent + 1);
+                self.out.push(')');
+            }
+            Statement::Match {
+                value,
+                arms,
+                default,
+            } => {
+                self.out.push_str("(match ");
+                self.expression(value);
+                for arm in arms {
+                    self.line(indent + 1);
+                    self.out.push_str("(arm (");
+                    for (i, pattern) in arm.patterns.iter().enumerate() {
+                        if i > 0 {
+                            self.out.push(' ');
+                        }
+                        self.literal(pattern);
+                    }
+                    self.out.push(')');
+                    self.body(&arm.body, indent + 2);
+                    self.out.push(')');
+                }
+                if let Some(default) = default {
+                    self.branch("default", default, indent + 1);
+                }
+                self.out.push(')');
+            }
+            Statement::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.out.push_str("(try");
+                self.body(body, indent + 1);
+                if let Some(catch) = catch {
+                    self.line(indent + 1);
+                    self.out.push_str("(catch");
+                    if let Some(variable) = &catch.variable {
+                        let _ = write!(self.out, " {}", variable.name);
+                    }
+                    self.body(&catch.body, indent + 2);
+                    self.out.push(')');
+                }
+                if let Some(finally) = finally {
+                    self.branch("finally", finally, indent + 1);
+                }
+                self.out.push(')');
+            }
+            Statement::Throw { value, .. } => self.form("throw", [value]),
+            Statement::Import { path, names, .. } => {
+                let _ = write!(self.out, "(import {path:?}");
+                for name in names {
+                    let _ = write!(self.out, " {}", name.name);
+                }
+                self.out.push(')');
+            }
+            Statement::Function {
+                name,
+                args,
+                body,
+                variadic,
+            } => {
+                let args: Vec<_> = args.iter().map(|arg| arg.name.as_str()).collect();
+                self.function("fun", &name.name, Some(&args), *variadic, body, indent);
+            }
+            Statement::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                let _ = write!(self.out, "(class {}", name.name);
+                if let Some(superclass) = superclass {
+                    let _ = write!(self.out, " (< {})", superclass.name);
+                }
+                for FunctionDeclaration {
+                    name,
+                    args,
+                    body,
+                    getter,
+                    variadic,
+                } in methods
+                {
+                    self.line(indent + 1);
+                    let args: Vec<_> = args.iter().map(|arg| arg.name.as_str()).collect();
+                    let args = (!getter).then_some(&args[..]);
+                    self.function("method", &name.name, args, *variadic, body, indent + 1);
+                }
+                self.out.push(')');
+            }
+            Statement::Return {
+                value: Some(value), ..
+            } => self.form("return", [value]),
+            Statement::Return { value: None, .. } => self.out.push_str("(return)"),
+            Statement::Break { .. } => self.out.push_str("(break)"),
+            Statement::Continue { .. } => self.out.push_str("(continue)"),
+            Statement::Error { .. } => self.out.push_str("(error)"),
+        }
+    }
+
+    /// `(keyword name (args...) body...)`, getters have no `args`.
+    fn function(
+        &mut self,
+        keyword: &str,
+        name: &str,
+        args: Option<&[&str]>,
+        variadic: bool,
+        body: &Block,
+        indent: usize,
+    ) {
+        let _ = write!(self.out, "({keyword} {name}");
+        if let Some(args) = args {
+            self.out.push_str(" (");
+            for (i, arg) in args.iter().enumerate() {
+                if i > 0 {
+                    self.out.push(' ');
+                }
+                if variadic && i + 1 == args.len() {
+                    self.out.push_str("...");
+                }
+                self.out.push_str(arg);
+            }
+            self.out.push(')');
+        }
+        self.body(body, indent + 1);
+        self.out.push(')');
+    }
+
+    /// `(name body...)` on a line of its own.
+    fn branch(&mut self, name: &str, block: &Block, indent: usize) {
+        self.line(indent);
+        let _ = write!(self.out, "({name}");
+        self.body(block, indent + 1);
+        self.out.push(')');
+    }
+
+    fn body(&mut self, block: &Block, indent: usize) {
+        for statement in &block.statements {
+            self.line(indent);
+            self.statement(statement, indent);
+        }
+    }
+
+    fn line(&mut self, indent: usize) {
+        self.out.push('\n');
+        self.out.push_str(&INDENT.repeat(indent));
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Binary(binary) => {
+                let operator = match binary.operator {
+                    BinaryOperator::Add(_) => "+",
+                    BinaryOperator::Subtract(_) => "-",
+                    BinaryOperator::Multiply(_) => "*",
+                    BinaryOperator::Divide(_) => "/",
+                    BinaryOperator::FloorDivide(_) => "~/",
+                    BinaryOperator::BitAnd(_) => "&",
+                    BinaryOperator::BitOr(_) => "|",
+                    BinaryOperator::BitXor(_) => "^",
+                    BinaryOperator::ShiftLeft(_) => "<<",
+                    BinaryOperator::ShiftRight(_) => ">>",
+                    BinaryOperator::Equal(_) => "==",
+                    BinaryOperator::NotEqual(_) => "!=",
+                    BinaryOperator::Less(_) => "<",
+                    BinaryOperator::LessEqual(_) => "<=",
+                    BinaryOperator::Greater(_) => ">",
+                    BinaryOperator::GreaterEqual(_) => ">=",
+                };
+                self.form(operator, [&binary.left, &binary.right]);
+            }
+            Expression::Logical(logical) => {
+                let operator = match logical.operator {
+                    LogicalOperator::And(_) => "and",
+                    LogicalOperator::Or(_) => "or",
+                    LogicalOperator::Coalesce(_) => "??",
+                };
+                self.form(operator, [&logical.left, &logical.right]);
+            }
+            Expression::Sequence(sequence) => self.form(",", [&sequence.left, &sequence.right]),
+            Expression::Unary(unary) => {
+                let operator = match unary.operator {
+                    UnaryOperator::Not(_) => "!",
+                    UnaryOperator::Negative(_) => "-",
+                };
+                self.form(operator, [&unary.right]);
+            }
+            Expression::Grouping(grouping) => self.form("group", [&grouping.expression]),
+            Expression::Interpolation(interpolation) => {
+                self.form("interpolate", &interpolation.parts)
+            }
+            Expression::Literal(literal) => self.literal(&literal.value),
+            Expression::Identifier(identifier) => self.out.push_str(&identifier.name),
+            Expression::Assignment(assignment) => {
+                let _ = write!(self.out, "(= {} ", assignment.target.name);
+                self.expression(&assignment.value);
+                self.out.push(')');
+            }
+            Expression::Call(call) => {
+                self.out.push_str("(call ");
+                self.expression(&call.calle);
+                for arg in &call.args {
+                    self.out.push(' ');
+                    self.expression(arg);
+                }
+                self.out.push(')');
+            }
+            Expression::Get(get) => {
+                let operator = if get.optional { "?." } else { "." };
+                let _ = write!(self.out, "({operator} ");
+                self.expression(&get.object);
+                let _ = write!(self.out, " {})", get.name);
+            }
+            Expression::Set(set) => {
+                self.out.push_str("(set ");
+                self.expression(&set.object);
+                let _ = write!(self.out, " {} ", set.name);
+                self.expression(&set.value);
+                self.out.push(')');
+            }
+            Expression::Super(sup) => {
+                let _ = write!(self.out, "(super {})", sup.method);
+            }
+            Expression::List(list) => self.form("list", &list.elements),
+            Expression::Map(map) => {
+                self.out.push_str("(map");
+                for (key, value) in &map.entries {
+                    self.out.push_str(" (");
+                    self.expression(key);
+                    self.out.push(' ');
+                    self.expression(value);
+                    self.out.push(')');
+                }
+                self.out.push(')');
+            }
+            Expression::Index(index) => self.form("index", [&index.object, &index.index]),
+            Expression::Slice(slice) => {
+                self.out.push_str("(slice ");
+                self.expression(&slice.object);
+                for bound in [&slice.start, &slice.end] {
+                    match bound {
+                        Some(bound) => {
+                            self.out.push(' ');
+                            self.expression(bound);
+                        }
+                        None => self.out.push_str(" _"),
+                    }
+                }
+                self.out.push(')');
+            }
+            Expression::IndexSet(set) => {
+                self.form("set-index", [&set.object, &set.index, &set.value])
+            }
+        }
+    }
+
+    /// `(name operands...)`.
+    fn form<'e>(&mut self, name: &str, operands: impl IntoIterator<Item = &'e Expression>) {
+        let _ = write!(self.out, "({name}");
+        for operand in operands {
+            self.out.push(' ');
+            self.expression(operand);
+        }
+        self.out.push(')');
+    }
+
+    fn literal(&mut self, literal: &LiteralValue) {
+        let _ = match literal {
+            LiteralValue::String(string, _) => write!(self.out, "{string:?}"),
+            LiteralValue::Number(number, _) => write!(self.out, "{number}"),
+            LiteralValue::True(_) => write!(self.out, "true"),
+            LiteralValue::False(_) => write!(self.out, "false"),
+            LiteralValue::Nil(_) => write!(self.out, "nil"),
+        };
+    }
+}
+
+#[test]
+fn test_sexpr() {
+    use crate::edition::Edition;
+    use crate::parser::Parser;
+    use crate::scanner;
+
+    let print = |source: &str| {
+        let tokens = scanner::scan_tokens(source).unwrap();
+        program(
+            &Parser::with_edition(Edition::Lox2026)
+                .parse(tokens)
+                .unwrap(),
+        )
+    };
+
+    assert_eq!(print("1 + 2 * 3;"), "(+ 1 (* 2 3))\n");
+    assert_eq!(
+        print("fun fib(n) { if n < 2 { return n; } return fib(n - 1) + fib(n - 2); }"),
+        "(fun fib (n)
+  (if (< n 2)
+    (then
+      (return n)))
+  (return (+ (call fib (- n 1)) (call fib (- n 2)))))
+"
+    );
+    assert_eq!(
+        print("const a = [1, \"x\", nil]; var m = {\"k\": -a[0]}; print m?.k ?? a[1:];"),
+        "(const a (list 1 \"x\" nil))
+(var m (map (\"k\" (- (index a 0)))))
+(print (?? (?. m k) (slice a 1 _)))
+"
+    );
+    assert_eq!(
+        print(
+            "class B < A { init(x, ...rest) { this.x = x; } size { return super.size; } }
+            for (x in xs) { if !x { continue; } else { break; } }
+            try { throw 1; } catch e { print e; } finally { print \"done\"; }"
+        ),
+        "(class B (< A)
+  (method init (x ...rest)
+    (set this x x))
+  (method size
+    (return (super size))))
+(for-in x xs
+  (if (! x)
+    (then
+      (continue))
+    (else
+      (break))))
+(try
+  (throw 1)
+  (catch e
+    (print e))
+  (finally
+    (print \"done\")))
+"
+    );
+}
@@ -0,0 +1,101 @@
+//! Pointers values, scopes and functions are shared through.
+//!
+//! By default they are `Rc<RefCell<_>>`, tying an interpreter to the
+//! thread that created it. Built with the `sync` feature they become
+//! `Arc` and a lock instead, so an interpreter can be moved to another
+//! thread, e.g. by an async host. Natives and output sinks then have to
+//! be `Send + Sync` as well.
+//!
+//! Modules import `Rc`, `Weak` and `RefCell` from here rather than from
+//! `std`, the rest of the code is the same in both builds.
+
+#[cfg(not(feature = "sync"))]
+pub use std::cell::RefCell;
+#[cfg(not(feature = "sync"))]
+pub use std::rc::{Rc, Weak};
+
+#[cfg(feature = "sync")]
+pub use std::sync::{Arc as Rc, Weak};
+
+/// `Send + Sync` in the `sync` build, implemented by everything otherwise.
+#[cfg(not(feature = "sync"))]
+pub trait Shareable {}
+
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> Shareable for T {}
+
+/// `Send + Sync` in the `sync` build, implemented by everything otherwise.
+#[cfg(feature = "sync")]
+pub trait Shareable: Send + Sync {}
+
+#[cfg(feature = "sync")]
+impl<T: ?Sized + Send + Sync> Shareable for T {}
+
+#[cfg(feature = "sync")]
+pub use lock::RefCell;
+
+#[cfg(feature = "sync")]
+mod lock {
+    use std::fmt;
+    use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+    /// The part of the `RefCell` interface the interpreter uses, on top of
+    /// a read-write lock. A value borrowed mutably while it is borrowed
+    /// blocks instead of panicking.
+    #[derive(Default)]
+    pub struct RefCell<T: ?Sized>(RwLock<T>);
+
+    impl<T> RefCell<T> {
+        pub const fn new(value: T) -> Self {
+            RefCell(RwLock::new(value))
+        }
+    }
+
+    impl<T: ?Sized> RefCell<T> {
+        // a panic while the lock was held has already been reported, the
+        // value is still usable
+        pub fn borrow(&self) -> RwLockReadGuard<'_, T> {
+            self.0.read().unwrap_or_else(PoisonError::into_inner)
+        }
+
+        pub fn borrow_mut(&self) -> RwLockWriteGuard<'_, T> {
+            self.0.write().unwrap_or_else(PoisonError::into_inner)
+        }
+    }
+
+    impl<T: ?Sized + fmt::Debug> fmt::Debug for RefCell<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.0.try_read() {
+                Ok(value) => f.debug_struct("RefCell").field("value", &&*value).finish(),
+                Err(_) => f.write_str("RefCell { <borrowed> }"),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn interpreter_moves_between_threads() {
+    use crate::interpreter::Interpreter;
+    use crate::lox_value::LoxValue;
+    use crate::output::Captured;
+
+    let output = Captured::new();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(output.clone());
+    interpreter
+        .eval("var counter = 0; fun bump() { counter = counter + 1; return counter; }")
+        .unwrap();
+
+    let mut interpreter = std::thread::spawn(move || {
+        interpreter.eval("bump(); print [counter];").unwrap();
+        interpreter
+    })
+    .join()
+    .unwrap();
+    assert!(matches!(
+        interpreter.call_function("bump", &[]),
+        Ok(LoxValue::Number(n)) if n == 2.
+    ));
+    assert_eq!(output.contents(), "[1]\n");
+}
@@ -0,0 +1,133 @@
+use std::cell::Cell;
+
+/// Width of a tab stop, used so reported columns match what editors display.
+pub const TAB_WIDTH: usize = 4;
+
+/// Some editors, mostly on Windows, start UTF-8 files with it.
+pub const BYTE_ORDER_MARK: char = '\u{feff}';
+
+/// Converts byte offsets into a source text to line/column pairs.
+///
+/// Lines and columns are 1-based, columns are counted in characters with
+/// tabs expanded to the next tab stop, so that reported positions match
+/// what an editor displays.
+pub struct SourceMap<'a> {
+    source: &'a str,
+    /// byte offset at which every line starts
+    line_starts: Vec<usize>,
+    /// `(offset, line, column)` of the last lookup, a later offset on the
+    /// same line is counted from it instead of from the start of the line
+    last: Cell<(usize, usize, usize)>,
+}
+
+impl<'a> SourceMap<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        SourceMap {
+            source,
+            line_starts,
+            last: Cell::new((0, 1, 1)),
+        }
+    }
+
+    /// Returns the `(line, column)` of the character starting at `offset`,
+    /// `offset == source.len()` is allowed and points just past the end.
+    pub fn location(&self, offset: usize) -> (usize, usize) {
+        let (last_offset, last_line, last_column) = self.last.get();
+        let last_line_end = self
+            .line_starts
+            .get(last_line)
+            .map_or(usize::MAX, |next| *next);
+
+        let (line, column) = if (last_offset..last_line_end).contains(&offset) {
+            let text = &self.source[last_offset..offset];
+            (last_line, advance_column(last_column, text))
+        } else {
+            let line = self.line_starts.partition_point(|start| *start <= offset);
+            let line_start = self.line_starts[line - 1];
+            (line, advance_column(1, &self.source[line_start..offset]))
+        };
+
+        self.last.set((offset, line, column));
+        (line, column)
+    }
+
+    /// The text of the 1-based `line`, without its line break.
+    pub fn line(&self, line: usize) -> Option<&'a str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .map_or(self.source.len(), |next| next - 1);
+        Some(
+            self.source[start..end]
+                .trim_start_matches(BYTE_ORDER_MARK)
+                .trim_end_matches('\r'),
+        )
+    }
+}
+
+/// `text` with tabs replaced by spaces up to the same tab stops, so that
+/// columns of it are the ones of [`SourceMap::location`].
+pub fn expand_tabs(text: &str) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\t' {
+            let column = advance_column(1, &expanded);
+            let stop = advance_column(column, "\t");
+            expanded.extend(std::iter::repeat_n(' ', stop - column));
+        } else {
+            expanded.push(c);
+        }
+    }
+    expanded
+}
+
+/// Returns the column reached after displaying `text` starting at `column`.
+///
+/// Tabs jump to the next tab stop, carriage returns and byte order marks
+/// take no space. `text` must not contain `\n`.
+pub fn advance_column(column: usize, text: &str) -> usize {
+    text.chars().fold(column, |column, c| match c {
+        '\t' => (column - 1) / TAB_WIDTH * TAB_WIDTH + TAB_WIDTH + 1,
+        '\r' | BYTE_ORDER_MARK => column,
+        _ => column + 1,
+    })
+}
+
+#[test]
+fn test_source_map_locations() {
+    let source = "a\n\tb;\r\nżółw // c";
+    let map = SourceMap::new(source);
+
+    assert_eq!(map.location(0), (1, 1));
+    assert_eq!(map.location(1), (1, 2));
+    assert_eq!(map.location(2), (2, 1));
+    assert_eq!(map.location(3), (2, 5));
+    assert_eq!(map.location(source.find("// c").unwrap()), (3, 6));
+    assert_eq!(map.location(source.len()), (3, 10));
+
+    assert_eq!(map.line(2), Some("\tb;"));
+    assert_eq!(map.line(3), Some("żółw // c"));
+    assert_eq!(map.line(4), None);
+    assert_eq!(expand_tabs("\tb;\t."), "    b;  .");
+}
+
+#[test]
+fn test_source_map_repeated_locations() {
+    let source = "x\ty = [1, 2,\t\"żółw\"];\n\tprint y;".repeat(3);
+    let map = SourceMap::new(&source);
+    let offsets: Vec<usize> = source.char_indices().map(|(i, _)| i).collect();
+
+    // lookups resuming from the previous one, forwards and backwards,
+    // agree with a fresh map
+    for &offset in offsets.iter().chain(offsets.iter().rev()) {
+        assert_eq!(
+            map.location(offset),
+            SourceMap::new(&source).location(offset)
+        );
+    }
+}
@@ -1,18 +1,47 @@
-use crate::expression::{Expression, Identifier};
+use crate::expression::{DebugInfo, Expression, Identifier, LiteralValue};
 
 #[derive(Debug, Clone)]
 pub struct Block {
     pub statements: Vec<Statement>,
+    /// the opening `{`, or the first token of the statement a body
+    /// without braces consists of
+    pub debug_info: DebugInfo,
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionDeclaration {
+    pub name: Identifier,
+    pub args: Vec<Identifier>,
+    pub body: Block,
+    /// a method declared without a parameter list, called on property access
+    pub getter: bool,
+    /// the last parameter was declared as `...rest`
+    pub variadic: bool,
+}
+
+/// `pattern, pattern => { body }` inside of a `match`.
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub patterns: Vec<LiteralValue>,
+    pub body: Block,
+}
+
+/// `catch error { body }`, the variable is optional.
+#[derive(Debug, Clone)]
+pub struct Catch {
+    pub variable: Option<Identifier>,
+    pub body: Block,
 }
 
 #[derive(Debug, Clone)]
 pub enum Statement {
-    Nop,
     Expression(Expression),
     Print(Expression),
     Variable {
         name: Identifier,
         initializer: Option<Expression>,
+        /// declared with `const`, always has an initializer
+        constant: bool,
     },
     Block(Block),
     If {
@@ -23,13 +52,85 @@ pub enum Statement {
     While {
         condition: Expression,
         body: Block,
+        /// evaluated after every iteration, including ones ended by `continue`
+        increment: Option<Expression>,
+    },
+    /// `for (initializer; condition; increment) body`, as written. Lowered
+    /// to a `While` inside of a block before resolution, see `lower`.
+    For {
+        initializer: Option<Box<Statement>>,
+        /// a loop without a condition runs until `break`
+        condition: Option<Expression>,
+        increment: Option<Expression>,
+        body: Block,
+        /// the `for` keyword
+        debug_info: DebugInfo,
+    },
+    /// `for (variable in iterable) body`, the variable is bound in a
+    /// new scope on every iteration.
+    ForIn {
+        variable: Identifier,
+        iterable: Expression,
+        body: Block,
+        /// the `in` keyword
+        debug_info: DebugInfo,
+    },
+    /// Runs the first arm with a pattern equal to `value`, or the
+    /// `_ => { ... }` arm if none matches.
+    Match {
+        value: Expression,
+        arms: Vec<MatchArm>,
+        default: Option<Block>,
+    },
+    /// At least one of `catch` and `finally` is present.
+    Try {
+        body: Block,
+        catch: Option<Catch>,
+        finally: Option<Block>,
+    },
+    Throw {
+        value: Expression,
+        /// the `throw` keyword
+        debug_info: DebugInfo,
+    },
+    /// `import "path";` brings in every global of the file at `path`,
+    /// `import a, b from "path";` only the listed ones.
+    Import {
+        path: String,
+        /// empty when importing everything
+        names: Vec<Identifier>,
+        /// the `import` keyword
+        debug_info: DebugInfo,
     },
     Function {
         name: Identifier,
         args: Vec<Identifier>,
         body: Block,
+        /// the last parameter collects the remaining arguments into a list
+        variadic: bool,
+    },
+    Class {
+        name: Identifier,
+        superclass: Option<Identifier>,
+        methods: Vec<FunctionDeclaration>,
     },
     Return {
         value: Option<Expression>,
+        /// the `return` keyword
+        debug_info: DebugInfo,
+    },
+    Break {
+        debug_info: DebugInfo,
+    },
+    Continue {
+        debug_info: DebugInfo,
+    },
+    /// A statement which failed to parse, in programs returned by
+    /// `Parser::parse_recovering`. Such programs cannot be run.
+    Error {
+        /// the first token of the statement
+        debug_info: DebugInfo,
+        /// byte offset just past the last token of the statement
+        end: usize,
     },
 }
@@ -1,4 +1,5 @@
-use phf::phf_map;
+use crate::diagnostic::Span;
+use crate::shared::Rc;
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
@@ -6,16 +7,22 @@ use phf::phf_map;
 pub enum TokenType {
     // Single-character tokens.
     LeftParen, RightParen, LeftBrace, RightBrace,
-    Comma, Dot, Minus, Plus, Semicolon, Slash, Star,
+    LeftBracket, RightBracket,
+    Comma, Dot, Minus, Plus, Semicolon, Colon, Slash, Star,
+    Ampersand, Pipe, Caret,
 
     // One or two character tokens.
     Bang, BangEqual,
     Equal, EqualEqual,
     Greater, GreaterEqual,
     Less, LessEqual,
+    LessLess, GreaterGreater,
+    TildeSlash, DotDotDot, FatArrow,
+    QuestionQuestion, QuestionDot,
 
     // Literals.
-    Identifier(String), String(String), Number(f64),
+    Identifier(Rc<str>), String(String), Number(f64),
+    Interpolation(Vec<StringPart>),
 
     // Keywords.
     And, Class, Else, False, Fun, For, If, Nil, Or,
@@ -30,34 +37,102 @@ impl TokenType {
     }
 }
 
-/// These tokens do not store enouhg information
+#[rustfmt::skip]
+impl std::fmt::Display for TokenType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use TokenType as T;
+        let symbol = match self {
+            T::LeftParen => "(", T::RightParen => ")",
+            T::LeftBrace => "{", T::RightBrace => "}",
+            T::LeftBracket => "[", T::RightBracket => "]",
+            T::Comma => ",", T::Dot => ".", T::Minus => "-", T::Plus => "+",
+            T::Semicolon => ";", T::Colon => ":", T::Slash => "/", T::Star => "*",
+            T::Ampersand => "&", T::Pipe => "|", T::Caret => "^",
+            T::Bang => "!", T::BangEqual => "!=",
+            T::Equal => "=", T::EqualEqual => "==",
+            T::Greater => ">", T::GreaterEqual => ">=",
+            T::Less => "<", T::LessEqual => "<=",
+            T::LessLess => "<<", T::GreaterGreater => ">>",
+            T::TildeSlash => "~/", T::DotDotDot => "...", T::FatArrow => "=>",
+            T::QuestionQuestion => "??", T::QuestionDot => "?.",
+            T::Identifier(_) => return write!(f, "identifier"),
+            T::String(_) | T::Interpolation(_) => return write!(f, "string"),
+            T::Number(_) => return write!(f, "number"),
+            T::And => "and", T::Class => "class", T::Else => "else",
+            T::False => "false", T::Fun => "fun", T::For => "for", T::If => "if",
+            T::Nil => "nil", T::Or => "or", T::Print => "print", T::Return => "return",
+            T::Super => "super", T::This => "this", T::True => "true", T::Var => "var",
+            T::While => "while",
+            T::Eof => return write!(f, "end of input"),
+        };
+        write!(f, "'{symbol}'")
+    }
+}
+
+/// Piece of an interpolated string literal, `"a ${b} c"`.
+#[derive(Debug, Clone)]
+pub enum StringPart {
+    Text(String),
+    /// tokens of the embedded expression, ending with `Eof` in place of `}`
+    Expression(Vec<Token>),
+}
+
+/// These tokens do not store enough information
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
-    pub lexeme: String,
+    /// shared by every token of a scan with the same text
+    pub lexeme: Rc<str>,
     pub line: usize,
     pub position: usize,
+    /// byte offset of the lexeme in the source
+    pub offset: usize,
 }
 
-static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
-    "and" => TokenType::And,
-    "class" => TokenType::Class,
-    "else" => TokenType::Else,
-    "false" => TokenType::False,
-    "fun" => TokenType::Fun,
-    "for" => TokenType::For,
-    "if" => TokenType::If,
-    "nil" => TokenType::Nil,
-    "or" => TokenType::Or,
-    "print" => TokenType::Print,
-    "return" => TokenType::Return,
-    "super" => TokenType::Super,
-    "this" => TokenType::This,
-    "true" => TokenType::True,
-    "var" => TokenType::Var,
-    "while" => TokenType::While,
-};
+impl Token {
+    pub fn span(&self) -> Span {
+        Span::of_text(self.line, self.position, &self.lexeme)
+    }
+}
+
+/// Source text the parser skips, kept by lossless scanning.
+#[derive(Debug, Clone)]
+pub enum Trivia {
+    /// spaces, tabs and line breaks
+    Whitespace(String),
+    /// a `// comment`, without the line break ending it
+    Comment(String),
+    /// the byte order mark and `#!` line a script may start with
+    Preamble(String),
+}
+
+/// A token with the trivia in front of it, the text of all trivia and
+/// lexemes of a source is the source itself.
+#[derive(Debug, Clone)]
+pub struct LosslessToken {
+    pub trivia: Vec<Trivia>,
+    pub token: Token,
+}
 
 pub fn parse_keyword(keyword: &str) -> Option<TokenType> {
-    KEYWORDS.get(keyword).cloned()
+    let token_type = match keyword {
+        "and" => TokenType::And,
+        "class" => TokenType::Class,
+        "else" => TokenType::Else,
+        "false" => TokenType::False,
+        "fun" => TokenType::Fun,
+        "for" => TokenType::For,
+        "if" => TokenType::If,
+        "nil" => TokenType::Nil,
+        "or" => TokenType::Or,
+        "print" => TokenType::Print,
+        "return" => TokenType::Return,
+        "super" => TokenType::Super,
+        "this" => TokenType::This,
+        "true" => TokenType::True,
+        "var" => TokenType::Var,
+        "while" => TokenType::While,
+        _ => return None,
+    };
+    Some(token_type)
 }
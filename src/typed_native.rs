@@ -0,0 +1,242 @@
+//! Natives written as plain Rust functions.
+//!
+//! Any function or closure taking up to five arguments that implement
+//! [`FromLox`] and returning a value implementing [`IntoLox`], or a
+//! `Result` of one, can be registered with [`NativeModule::typed`]:
+//!
+//! ```ignore
+//! NativeModule::namespace("math").typed("hypot", |x: f64, y: f64| x.hypot(y))
+//! ```
+//!
+//! Its arity is the number of arguments, and arguments of the wrong type
+//! are reported with their index and the type that was expected.
+//!
+//! [`NativeModule::typed`]: crate::native_module::NativeModule::typed
+
+use crate::error::Error;
+use crate::lox_function::NativeContext;
+use crate::lox_value::LoxValue;
+use crate::shared::Shareable;
+
+/// A type arguments of natives can be converted to.
+pub trait FromLox: Sized {
+    fn from_lox(ctx: &NativeContext, index: usize) -> Result<Self, Error>;
+}
+
+impl FromLox for f64 {
+    fn from_lox(ctx: &NativeContext, index: usize) -> Result<Self, Error> {
+        ctx.arg_number(index)
+    }
+}
+
+/// Numbers without a fractional part.
+impl FromLox for i64 {
+    fn from_lox(ctx: &NativeContext, index: usize) -> Result<Self, Error> {
+        ctx.arg_integer(index)
+    }
+}
+
+impl FromLox for bool {
+    fn from_lox(ctx: &NativeContext, index: usize) -> Result<Self, Error> {
+        match ctx.arg(index) {
+            LoxValue::Bool(b) => Ok(*b),
+            _ => Err(ctx.argument_error(index, "bool")),
+        }
+    }
+}
+
+impl FromLox for String {
+    fn from_lox(ctx: &NativeContext, index: usize) -> Result<Self, Error> {
+        ctx.arg_string(index).map(str::to_owned)
+    }
+}
+
+/// Any value, as it is.
+impl FromLox for LoxValue {
+    fn from_lox(ctx: &NativeContext, index: usize) -> Result<Self, Error> {
+        Ok(ctx.arg(index).clone())
+    }
+}
+
+/// `nil`, or else a value of the type `T`.
+impl<T: FromLox> FromLox for Option<T> {
+    fn from_lox(ctx: &NativeContext, index: usize) -> Result<Self, Error> {
+        match ctx.arg(index) {
+            LoxValue::Nil => Ok(None),
+            _ => T::from_lox(ctx, index).map(Some),
+        }
+    }
+}
+
+/// A type natives can return.
+pub trait IntoLox {
+    fn into_lox(self) -> LoxValue;
+}
+
+impl IntoLox for f64 {
+    fn into_lox(self) -> LoxValue {
+        LoxValue::Number(self)
+    }
+}
+
+impl IntoLox for i64 {
+    fn into_lox(self) -> LoxValue {
+        LoxValue::Number(self as f64)
+    }
+}
+
+impl IntoLox for bool {
+    fn into_lox(self) -> LoxValue {
+        LoxValue::Bool(self)
+    }
+}
+
+impl IntoLox for String {
+    fn into_lox(self) -> LoxValue {
+        LoxValue::String(self)
+    }
+}
+
+impl IntoLox for &str {
+    fn into_lox(self) -> LoxValue {
+        LoxValue::String(self.to_owned())
+    }
+}
+
+impl IntoLox for () {
+    fn into_lox(self) -> LoxValue {
+        LoxValue::Nil
+    }
+}
+
+impl IntoLox for LoxValue {
+    fn into_lox(self) -> LoxValue {
+        self
+    }
+}
+
+/// `None` becomes `nil`.
+impl<T: IntoLox> IntoLox for Option<T> {
+    fn into_lox(self) -> LoxValue {
+        self.map_or(LoxValue::Nil, T::into_lox)
+    }
+}
+
+/// What a typed native returns, a value or a `Result` of one.
+pub trait NativeResult {
+    fn into_result(self) -> Result<LoxValue, Error>;
+}
+
+impl<T: IntoLox> NativeResult for T {
+    fn into_result(self) -> Result<LoxValue, Error> {
+        Ok(self.into_lox())
+    }
+}
+
+impl<T: IntoLox> NativeResult for Result<T, Error> {
+    fn into_result(self) -> Result<LoxValue, Error> {
+        self.map(T::into_lox)
+    }
+}
+
+/// A function callable as a native, `Args` is the tuple of its argument
+/// types.
+pub trait TypedNative<Args>: Shareable + 'static {
+    const ARITY: usize;
+
+    fn call(&self, ctx: &mut NativeContext) -> Result<LoxValue, Error>;
+}
+
+macro_rules! typed_native {
+    ($arity:literal; $($arg:ident: $index:literal),*) => {
+        impl<F, R, $($arg),*> TypedNative<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + Shareable + 'static,
+            R: NativeResult,
+            $($arg: FromLox,)*
+        {
+            const ARITY: usize = $arity;
+
+            #[allow(unused_variables)]
+            fn call(&self, ctx: &mut NativeContext) -> Result<LoxValue, Error> {
+                // errors without a location are reported at the call
+                self($($arg::from_lox(ctx, $index)?),*)
+                    .into_result()
+                    .map_err(|error| match error {
+                        Error::InternalRuntimeError { message } => ctx.error(message),
+                        error => error,
+                    })
+            }
+        }
+    };
+}
+
+typed_native!(0;);
+typed_native!(1; A: 0);
+typed_native!(2; A: 0, B: 1);
+typed_native!(3; A: 0, B: 1, C: 2);
+typed_native!(4; A: 0, B: 1, C: 2, D: 3);
+typed_native!(5; A: 0, B: 1, C: 2, D: 3, E: 4);
+
+#[test]
+fn test_typed_native() {
+    use crate::interpreter::Interpreter;
+    use crate::native_module::NativeModule;
+    use crate::parser::Parser;
+    use crate::resolver;
+    use crate::scanner;
+
+    let run = |source: &str| {
+        let mut interpreter = Interpreter::new();
+        let module = NativeModule::global()
+            .typed("repeat", |s: String, n: i64| s.repeat(n.max(0) as usize))
+            .typed("half", |n: f64| match n % 2. == 0. {
+                true => Ok(n / 2.),
+                false => Err(Error::InternalRuntimeError {
+                    message: format!("{n} is odd.").into(),
+                }),
+            })
+            .typed("orDefault", |value: Option<String>| {
+                value.unwrap_or_else(|| "default".to_owned())
+            })
+            .typed("find", |found: bool| found.then_some(1.))
+            .typed("nothing", || ());
+        interpreter.install(module).unwrap();
+
+        let program = Parser::new()
+            .parse(scanner::scan_tokens(source).unwrap())
+            .unwrap();
+        interpreter.execute(&program, resolver::resolve(&program).unwrap())?;
+        Ok::<_, Error>(interpreter.environment.get_global(&"result".to_string()))
+    };
+
+    let source = "var result = [repeat(\"ab\", 2), half(4), orDefault(nil), orDefault(\"x\"),
+        find(true), find(false), nothing()];";
+    assert_eq!(
+        run(source).unwrap().map(|list| list.to_string()),
+        Some(r#"["abab", 2, "default", "x", 1, nil, nil]"#.to_owned())
+    );
+
+    for (source, expected) in [
+        (
+            "repeat(\"ab\", \"c\");",
+            (1, 7, "Argument 2 of repeat() must be a number, got string."),
+        ),
+        ("repeat(\"ab\");", (1, 7, "Expected 2 arguments but got 1.")),
+        (
+            "find(1);",
+            (1, 5, "Argument 1 of find() must be a bool, got number."),
+        ),
+        ("\n half(3);", (2, 6, "3 is odd.")),
+    ] {
+        match run(source) {
+            Err(Error::RuntimeError {
+                line,
+                position,
+                message,
+                ..
+            }) => assert_eq!((line, position, message.as_str()), expected),
+            other => panic!("expected a runtime error, got {other:?}"),
+        }
+    }
+}